@@ -16,7 +16,8 @@ impl Tag for AstDefinitionId {
         // simple helper function, this is effectively a reimplementation
         // of java's String#hashCode function
         fn str_to_hash(s: &str) -> u128 {
-            s.chars().fold(0, |hash, ch| 31 * hash + ch as u128)
+            s.chars()
+                .fold(0, |hash: u128, ch| hash.wrapping_mul(31).wrapping_add(ch as u128))
         }
 
         AstDefinitionId(Uuid::from_u128(str_to_hash(input)))
@@ -49,8 +50,8 @@ impl Tagged for AstDefinition {
 pub enum AstDefinitionKind {
     /// Represents a function definition.
     Function {
-        /// The parameters that the function accepts.
-        parameters: Vec<AstType>,
+        /// The names and types of the parameters that the function accepts.
+        parameters: Vec<(String, AstType)>,
         /// The generic names of the function.
         generics: Vec<String>,
         /// The return type of the function.
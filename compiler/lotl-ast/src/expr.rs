@@ -143,6 +143,17 @@ pub enum AstExpr {
         /// The ID of the expression
         id: ExprId,
     },
+    /// Represents a generic function instantiated with type arguments, `id::[i32]`
+    Instantiation {
+        /// The generic function to instantiate
+        obj: ExprId,
+        /// The type arguments, in the order of the function's generics
+        types: Vec<AstType>,
+        /// The span of the type arguments
+        span: Span,
+        /// The ID of the expression
+        id: ExprId,
+    },
     /// Represents a subscript
     Subscript {
         /// The object to access the index of
@@ -152,6 +163,21 @@ pub enum AstExpr {
         /// The ID of the expression
         id: ExprId,
     },
+    /// Represents inline assembly, `asm[flags]("template", "constraints", operands...)`
+    InlineAsm {
+        /// The assembly template, referring to the operands as `$0`, `$1`, ...
+        template: String,
+        /// The constraints of the outputs, inputs and clobbers, separated by commas
+        constraints: String,
+        /// The input operands of the assembly
+        operands: Vec<ExprId>,
+        /// The flags of the assembly
+        flags: Vec<AsmFlag>,
+        /// The span of the `asm` intrinsic
+        span: Span,
+        /// The ID of the expression
+        id: ExprId,
+    },
 }
 
 impl Tagged for AstExpr {
@@ -167,12 +193,14 @@ impl Tagged for AstExpr {
             AstExpr::FieldAccess { id, .. } => id,
             AstExpr::NamespaceAccess { id, .. } => id,
             AstExpr::Subscript { id, .. } => id,
+            AstExpr::Instantiation { id, .. } => id,
             AstExpr::Block { id, .. } => id,
             AstExpr::If { id, .. } => id,
             AstExpr::For { id, .. } => id,
             AstExpr::While { id, .. } => id,
             AstExpr::Storage { id, .. } => id,
             AstExpr::Returns { id, .. } => id,
+            AstExpr::InlineAsm { id, .. } => id,
         }
     }
 }
@@ -198,3 +226,14 @@ pub enum UnaryOperationKind {
     /// `!` operator
     Not,
 }
+
+/// The possible flags of inline assembly
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsmFlag {
+    /// `sideeffect`, the assembly has effects beyond its outputs
+    SideEffect,
+    /// `alignstack`, the assembly needs an aligned stack
+    AlignStack,
+    /// `inteldialect`, the template is written in the Intel dialect
+    IntelDialect,
+}
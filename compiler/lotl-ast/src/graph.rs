@@ -38,6 +38,16 @@ impl<T: Tagged> IdGraph<T> {
         }
     }
 
+    /// Inserts a node into the graph, replacing any node with the same ID.
+    pub fn insert(&mut self, node: T) {
+        self.map.insert(node.id().clone(), node);
+    }
+
+    /// Returns the node associated with the ID, if present.
+    pub fn get(&self, tag: &T::TagType) -> Option<&T> {
+        self.map.get(tag)
+    }
+
     /// Exposes all keys in this ID graph as an iterator
     pub fn keys(&self) -> impl Iterator<Item = &T::TagType> {
         self.map.keys()
@@ -1,4 +1,5 @@
 use std::string::String;
+use std::vec::Vec;
 
 /// Represents Lotl's type system in the AST.
 #[derive(Debug, Clone, PartialEq)]
@@ -17,4 +18,6 @@ pub enum AstType {
     TypeVar(String),
     /// Represents a type that is not yet resolved.
     Unresolved(String),
+    /// Represents a generic type applied to type arguments, such as `Box[T]`.
+    Applied(String, Vec<AstType>),
 }
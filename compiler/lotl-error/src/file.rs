@@ -24,4 +24,48 @@ impl SourceFile {
             contents: Arc::new(String::from(contents)),
         }
     }
+
+    /// Returns the 1-based line and column of the character at the given index.
+    pub fn line_column(&self, index: usize) -> (usize, usize) {
+        let mut line = 1;
+        let mut column = 1;
+        for ch in self.contents.chars().take(index) {
+            if ch == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        (line, column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::SourceFile;
+
+    #[test]
+    fn line_column_of_multiline_source() {
+        let file = SourceFile::new("example.lotl", "func\nmain()\n\n-> i32");
+        assert_eq!(file.line_column(0), (1, 1));
+        assert_eq!(file.line_column(3), (1, 4));
+        // the newline itself is the last column of its line
+        assert_eq!(file.line_column(4), (1, 5));
+        assert_eq!(file.line_column(5), (2, 1));
+        assert_eq!(file.line_column(10), (2, 6));
+        assert_eq!(file.line_column(12), (3, 1));
+        assert_eq!(file.line_column(13), (4, 1));
+    }
+
+    #[test]
+    fn line_column_at_end_of_file() {
+        let file = SourceFile::new("example.lotl", "a\nbc");
+        assert_eq!(file.line_column(4), (2, 3));
+        assert_eq!(file.line_column(100), (2, 3));
+
+        let trailing = SourceFile::new("example.lotl", "a\n");
+        assert_eq!(trailing.line_column(2), (2, 1));
+        assert_eq!(SourceFile::new("example.lotl", "").line_column(0), (1, 1));
+    }
 }
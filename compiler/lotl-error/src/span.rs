@@ -20,4 +20,41 @@ impl Span {
     pub fn new(file: SourceFile, start: usize, end: usize) -> Span {
         Self { file, start, end }
     }
+
+    /// Returns the file this span is in.
+    pub fn file(&self) -> &SourceFile {
+        &self.file
+    }
+
+    /// Returns the character index this span starts at.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the character index this span ends at.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the 1-based line and column this span starts at.
+    // todo: attach these through `DIBuilder::span_location` once codegen and `--debug` exist
+    pub fn line_column(&self) -> (usize, usize) {
+        self.file.line_column(self.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::SourceFile;
+    use crate::span::Span;
+
+    #[test]
+    fn line_column_of_spans() {
+        let file = SourceFile::new("example.lotl", "func main()\n-> i32 { }\n");
+        assert_eq!(Span::new(file.clone(), 0, 4).line_column(), (1, 1));
+        assert_eq!(Span::new(file.clone(), 10, 11).line_column(), (1, 11));
+        assert_eq!(Span::new(file.clone(), 12, 14).line_column(), (2, 1));
+        assert_eq!(Span::new(file.clone(), 21, 22).line_column(), (2, 10));
+        assert_eq!(Span::new(file, 23, 23).line_column(), (3, 1));
+    }
 }
@@ -17,3 +17,10 @@ impl DiagnosticError for InvalidCharacter {
         format!("{:?} is not a valid character", self.0)
     }
 }
+
+pub struct UnknownEscape(pub char);
+impl DiagnosticError for UnknownEscape {
+    fn message(self) -> String {
+        format!("\\{} is not a valid escape, expected \\n, \\t, \\0, \\\\, \\\" or \\'", self.0)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::err::{InvalidCharacter, UnexpectedEOFWhileFinding};
+use crate::err::{InvalidCharacter, UnexpectedEOFWhileFinding, UnknownEscape};
 use lotl_error::diagnostic::Diagnostic;
 use lotl_error::file::SourceFile;
 use lotl_error::results::Results;
@@ -87,6 +87,40 @@ impl Lexer {
             }
             return Some(TokenTree::new(TokenKind::Numeric(str), self.create_span()));
         }
+        if self.peek() == '"' {
+            self.next();
+            let mut str = String::new();
+            loop {
+                match self.next() {
+                    '"' => break,
+                    '\0' => {
+                        self.diagnostics.push(Diagnostic::new(
+                            UnexpectedEOFWhileFinding('"'),
+                            self.create_span(),
+                        ));
+                        break;
+                    }
+                    '\\' => match self.next() {
+                        'n' => str.push('\n'),
+                        't' => str.push('\t'),
+                        '0' => str.push('\0'),
+                        ch @ ('\\' | '"' | '\'') => str.push(ch),
+                        // the end of the file is reported by the next iteration
+                        '\0' => {}
+                        ch => {
+                            self.diagnostics.push(Diagnostic::new(
+                                UnknownEscape(ch),
+                                Span::new(self.file.clone(), self.index - 2, self.index),
+                            ));
+                            str.push('\\');
+                            str.push(ch);
+                        }
+                    },
+                    ch => str.push(ch),
+                }
+            }
+            return Some(TokenTree::new(TokenKind::StringLiteral(str), self.create_span()));
+        }
         match self.next() {
             ',' => Some(TokenTree::new(TokenKind::Comma, self.create_span())),
             ':' => Some(TokenTree::new(TokenKind::Colon, self.create_span())),
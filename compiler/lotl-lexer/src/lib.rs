@@ -65,6 +65,32 @@ mod tests {
         assert_eq!(tokens.diagnostics.len(), 1);
     }
 
+    #[test]
+    pub fn string_literals() {
+        let source = SourceFile::new("hello.lotl", r#"asm("mov $1, $0\n", "=r,r\"")"#);
+        let tokens = lex(source);
+        let TokenKind::Parenthesis(inner) = &tokens.output[1].kind else {
+            panic!("expected parenthesis");
+        };
+        assert_eq!(inner[0].kind, TokenKind::StringLiteral("mov $1, $0\n".to_string()));
+        assert_eq!(inner[2].kind, TokenKind::StringLiteral("=r,r\"".to_string()));
+        assert_eq!(tokens.diagnostics.len(), 0);
+
+        let source = SourceFile::new("hello.lotl", "\"unterminated");
+        let tokens = lex(source);
+        assert_eq!(tokens.diagnostics.len(), 1);
+    }
+
+    #[test]
+    pub fn unknown_string_escapes() {
+        let source = SourceFile::new("hello.lotl", r#""\x41\q\\""#);
+        let tokens = lex(source);
+        assert_eq!(tokens.output[0].kind, TokenKind::StringLiteral(r"\x41\q\".to_string()));
+        assert_eq!(tokens.diagnostics.len(), 2);
+        assert_eq!(tokens.diagnostics[0].main.1.start(), 1);
+        assert_eq!(tokens.diagnostics[0].main.1.end(), 3);
+    }
+
     #[test]
     pub fn realistic_example() {
         let source = SourceFile::new(
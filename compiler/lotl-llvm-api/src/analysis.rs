@@ -0,0 +1,408 @@
+use crate::instruction::{BlockId, InstId};
+use crate::module::FunctionBody;
+use crate::value::Value;
+use std::vec;
+use std::vec::Vec;
+
+/// The control flow graph of a function body, holding the successors and predecessors
+/// of every block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ControlFlowGraph {
+    entry: BlockId,
+    successors: Vec<Vec<BlockId>>,
+    predecessors: Vec<Vec<BlockId>>,
+    reverse_postorder: Vec<BlockId>,
+}
+
+impl ControlFlowGraph {
+    /// Computes the control flow graph from the terminators of every block.
+    pub fn new(body: &FunctionBody) -> Self {
+        let count = body.blocks.len();
+        let mut successors = vec![Vec::new(); count];
+        let mut predecessors = vec![Vec::new(); count];
+        for block in body.blocks() {
+            for instruction in body.block(block).instructions() {
+                for successor in body.instruction(*instruction).kind().successors() {
+                    if !successors[block.0].contains(&successor) {
+                        successors[block.0].push(successor);
+                        predecessors[successor.0].push(block);
+                    }
+                }
+            }
+        }
+
+        // an iterative depth-first search, finishing a block once all of its successors are done
+        let entry = body.entry();
+        let mut visited = vec![false; count];
+        let mut postorder = Vec::new();
+        let mut pending = vec![(entry, 0)];
+        visited[entry.0] = true;
+        while let Some((block, next)) = pending.pop() {
+            match successors[block.0].get(next) {
+                Some(successor) => {
+                    pending.push((block, next + 1));
+                    if !visited[successor.0] {
+                        visited[successor.0] = true;
+                        pending.push((*successor, 0));
+                    }
+                }
+                None => postorder.push(block),
+            }
+        }
+        postorder.reverse();
+
+        ControlFlowGraph {
+            entry,
+            successors,
+            predecessors,
+            reverse_postorder: postorder,
+        }
+    }
+
+    /// Returns the block control enters the function at.
+    pub fn entry(&self) -> BlockId {
+        self.entry
+    }
+
+    /// Returns the blocks control can continue to after the block.
+    pub fn successors(&self, block: BlockId) -> &[BlockId] {
+        &self.successors[block.0]
+    }
+
+    /// Returns the blocks control can arrive at the block from.
+    pub fn predecessors(&self, block: BlockId) -> &[BlockId] {
+        &self.predecessors[block.0]
+    }
+
+    /// Returns the blocks reachable from the entry, each coming before its successors
+    /// unless the edge between them is a back-edge.
+    pub fn reverse_postorder(&self) -> &[BlockId] {
+        &self.reverse_postorder
+    }
+
+    /// Returns true if control can reach the block from the entry.
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.reverse_postorder.contains(&block)
+    }
+}
+
+/// The dominator tree of a function body. A block dominates another if every path
+/// from the entry to the other block goes through it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DominatorTree {
+    /// The immediate dominator of every reachable block, the entry being its own.
+    immediate: Vec<Option<BlockId>>,
+    /// The position of every reachable block in reverse postorder.
+    order: Vec<Option<usize>>,
+}
+
+impl DominatorTree {
+    /// Computes the dominator tree, using the algorithm of Cooper, Harvey and Kennedy.
+    pub fn new(cfg: &ControlFlowGraph) -> Self {
+        let count = cfg.successors.len();
+        let mut order = vec![None; count];
+        for (index, block) in cfg.reverse_postorder.iter().enumerate() {
+            order[block.0] = Some(index);
+        }
+        let mut immediate = vec![None; count];
+        immediate[cfg.entry.0] = Some(cfg.entry);
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in cfg.reverse_postorder.iter().skip(1) {
+                let mut processed = cfg
+                    .predecessors(*block)
+                    .iter()
+                    .copied()
+                    .filter(|x| immediate[x.0].is_some());
+                let Some(first) = processed.next() else {
+                    continue;
+                };
+                let dominator =
+                    processed.fold(first, |a, b| Self::intersect(&immediate, &order, a, b));
+                if immediate[block.0] != Some(dominator) {
+                    immediate[block.0] = Some(dominator);
+                    changed = true;
+                }
+            }
+        }
+        DominatorTree { immediate, order }
+    }
+
+    /// Walks up from both blocks until the closest block dominating both is found.
+    fn intersect(
+        immediate: &[Option<BlockId>],
+        order: &[Option<usize>],
+        mut a: BlockId,
+        mut b: BlockId,
+    ) -> BlockId {
+        while a != b {
+            while order[a.0] > order[b.0] {
+                a = immediate[a.0].unwrap();
+            }
+            while order[b.0] > order[a.0] {
+                b = immediate[b.0].unwrap();
+            }
+        }
+        a
+    }
+
+    /// Returns the closest block strictly dominating the block,
+    /// or nothing for the entry and unreachable blocks.
+    pub fn immediate_dominator(&self, block: BlockId) -> Option<BlockId> {
+        self.immediate[block.0].filter(|x| *x != block)
+    }
+
+    /// Returns the blocks immediately dominated by the block, its children in the tree.
+    pub fn children(&self, block: BlockId) -> Vec<BlockId> {
+        (0..self.immediate.len())
+            .map(BlockId)
+            .filter(|x| *x != block && self.immediate[x.0] == Some(block))
+            .collect()
+    }
+
+    /// Returns true if the block `a` dominates the block `b`. Every block dominates itself,
+    /// while blocks unreachable from the entry are not dominated by any other block.
+    pub fn dominates(&self, a: BlockId, mut b: BlockId) -> bool {
+        loop {
+            if a == b {
+                return true;
+            }
+            match self.immediate_dominator(b) {
+                Some(dominator) => b = dominator,
+                None => return false,
+            }
+        }
+    }
+
+    /// Returns true if control can reach the block from the entry.
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.order[block.0].is_some()
+    }
+
+    /// Returns the dominance frontier of the block: the blocks where its dominance ends,
+    /// which it does not strictly dominate, but dominates a predecessor of.
+    pub fn frontier(&self, cfg: &ControlFlowGraph, block: BlockId) -> Vec<BlockId> {
+        let mut frontier = Vec::new();
+        for join in cfg.reverse_postorder() {
+            let predecessors = cfg.predecessors(*join);
+            if predecessors.len() < 2 || frontier.contains(join) {
+                continue;
+            }
+            let strictly_dominated = *join != block && self.dominates(block, *join);
+            let dominates_predecessor = predecessors
+                .iter()
+                .any(|x| self.is_reachable(*x) && self.dominates(block, *x));
+            if dominates_predecessor && !strictly_dominated {
+                frontier.push(*join);
+            }
+        }
+        frontier
+    }
+}
+
+/// A natural loop, entered through its header and repeated through back-edges from its latches.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Loop {
+    header: BlockId,
+    latches: Vec<BlockId>,
+    blocks: Vec<BlockId>,
+}
+
+impl Loop {
+    /// Returns the block every iteration of the loop starts at.
+    pub fn header(&self) -> BlockId {
+        self.header
+    }
+
+    /// Returns the blocks branching back to the header.
+    pub fn latches(&self) -> &[BlockId] {
+        &self.latches
+    }
+
+    /// Returns every block of the loop, starting with the header.
+    pub fn blocks(&self) -> &[BlockId] {
+        &self.blocks
+    }
+
+    /// Returns true if the block is part of the loop.
+    pub fn contains(&self, block: BlockId) -> bool {
+        self.blocks.contains(&block)
+    }
+}
+
+/// The natural loops of a function body.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoopInfo {
+    loops: Vec<Loop>,
+}
+
+impl LoopInfo {
+    /// Finds every loop, from the back-edges of the control flow graph:
+    /// edges to a block dominating the block they come from.
+    pub fn new(cfg: &ControlFlowGraph, dominators: &DominatorTree) -> Self {
+        let mut loops: Vec<Loop> = Vec::new();
+        for block in cfg.reverse_postorder() {
+            let latches = cfg
+                .predecessors(*block)
+                .iter()
+                .copied()
+                .filter(|x| dominators.is_reachable(*x) && dominators.dominates(*block, *x))
+                .collect::<Vec<_>>();
+            if latches.is_empty() {
+                continue;
+            }
+            // the body is everything reaching a latch without passing through the header
+            let mut blocks = vec![*block];
+            let mut pending = latches.clone();
+            while let Some(next) = pending.pop() {
+                if blocks.contains(&next) {
+                    continue;
+                }
+                blocks.push(next);
+                pending.extend(
+                    cfg.predecessors(next)
+                        .iter()
+                        .filter(|x| dominators.is_reachable(**x)),
+                );
+            }
+            loops.push(Loop {
+                header: *block,
+                latches,
+                blocks,
+            });
+        }
+        LoopInfo { loops }
+    }
+
+    /// Returns every loop, outer loops coming before the loops nested in them.
+    pub fn loops(&self) -> &[Loop] {
+        &self.loops
+    }
+
+    /// Returns the innermost loop containing the block, if any.
+    pub fn innermost(&self, block: BlockId) -> Option<&Loop> {
+        self.loops
+            .iter()
+            .filter(|x| x.contains(block))
+            .min_by_key(|x| x.blocks.len())
+    }
+
+    /// Returns the number of loops the block is nested in.
+    pub fn loop_depth(&self, block: BlockId) -> usize {
+        self.loops.iter().filter(|x| x.contains(block)).count()
+    }
+}
+
+impl FunctionBody {
+    /// Returns the instruction defining the register, if it is defined in this body.
+    pub fn definition(&self, value: &Value) -> Option<InstId> {
+        let Value::LocalIdentifier(name, _) = value else {
+            return None;
+        };
+        self.definitions.get(name).copied()
+    }
+
+    /// Returns the instructions using the register defined by the instruction, in layout order.
+    pub fn users(&self, id: InstId) -> Vec<InstId> {
+        let Some(Value::LocalIdentifier(name, _)) = self.instruction(id).result() else {
+            return Vec::new();
+        };
+        let mut users = self.users.get(name).cloned().unwrap_or_default();
+        let positions = self.positions();
+        users.sort_by_cached_key(|x| {
+            let block = self.instruction(*x).block().unwrap();
+            let index = self.block(block).instructions().iter().position(|y| y == x);
+            (positions[block.0], index)
+        });
+        users
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{ControlFlowGraph, DominatorTree, LoopInfo};
+    use crate::instruction::{BlockId, InstructionKind};
+    use crate::module::FunctionBody;
+    use crate::types::Types;
+    use crate::value::Values;
+    use std::vec;
+
+    /// Builds a loop counting to ten, returning the identifiers of its blocks.
+    fn counting_loop() -> (FunctionBody, [BlockId; 4]) {
+        let mut blocks = [BlockId(0); 4];
+        let body = FunctionBody::new(|block| {
+            let header = block.br_returning();
+            let counter = header.phi(Types::integer(32), vec![(Values::integer("0", 32), &block)]);
+            let done = header.trunc(counter.value(), Types::integer(1));
+            let (repeat, exit) = header.br_if_returning(done);
+            let incremented = repeat.add(counter.value(), Values::integer("1", 32));
+            counter.add_incoming(incremented, &repeat);
+            repeat.goto(&header);
+            exit.ret(counter.value());
+            blocks = [block.id(), header.id(), repeat.id(), exit.id()];
+        });
+        (body, blocks)
+    }
+
+    #[test]
+    fn compute_control_flow_graph() {
+        let (body, [entry, header, repeat, exit]) = counting_loop();
+        let cfg = ControlFlowGraph::new(&body);
+        assert_eq!(cfg.successors(entry), [header]);
+        assert_eq!(cfg.successors(header), [repeat, exit]);
+        assert_eq!(cfg.predecessors(header), [entry, repeat]);
+        assert_eq!(cfg.predecessors(entry), []);
+        assert_eq!(cfg.reverse_postorder()[..2], [entry, header]);
+    }
+
+    #[test]
+    fn compute_dominators_and_loops() {
+        let (body, [entry, header, repeat, exit]) = counting_loop();
+        let cfg = ControlFlowGraph::new(&body);
+        let dominators = DominatorTree::new(&cfg);
+        assert_eq!(dominators.immediate_dominator(entry), None);
+        assert_eq!(dominators.immediate_dominator(repeat), Some(header));
+        assert_eq!(dominators.immediate_dominator(exit), Some(header));
+        assert!(dominators.dominates(entry, exit));
+        assert!(!dominators.dominates(repeat, exit));
+        assert_eq!(dominators.children(header), vec![repeat, exit]);
+
+        let loops = LoopInfo::new(&cfg, &dominators);
+        assert_eq!(loops.loops().len(), 1);
+        assert_eq!(loops.loops()[0].header(), header);
+        assert_eq!(loops.loops()[0].latches(), [repeat]);
+        assert_eq!(loops.loop_depth(repeat), 1);
+        assert_eq!(loops.loop_depth(exit), 0);
+    }
+
+    #[test]
+    fn query_uses_and_definitions() {
+        let (mut body, [_, header, repeat, exit]) = counting_loop();
+        let (phi, trunc) = (
+            body.block(header).instructions()[0],
+            body.block(header).instructions()[1],
+        );
+        let (add, ret) = (
+            body.block(repeat).instructions()[0],
+            body.block(exit).instructions()[0],
+        );
+        let counter = body.instruction(phi).result().unwrap().clone();
+        assert_eq!(body.definition(&counter), Some(phi));
+        assert_eq!(body.users(phi), vec![trunc, add, ret]);
+        // the incoming value of the phi is added after the phi is built
+        assert_eq!(body.users(add), vec![phi]);
+
+        body.replace_uses(&counter, &Values::integer("5", 32));
+        assert!(matches!(
+            body.instruction(add).kind(),
+            InstructionKind::Binary { lhs, .. } if *lhs == Values::integer("5", 32)
+        ));
+        assert_eq!(body.users(phi), vec![]);
+
+        body.remove_instruction(phi);
+        assert_eq!(body.definition(&counter), None);
+        assert_eq!(body.users(add), vec![]);
+    }
+}
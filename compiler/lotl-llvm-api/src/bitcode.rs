@@ -0,0 +1,299 @@
+//! Writes modules as LLVM bitcode, the binary form of LLVM IR.
+//!
+//! https://llvm.org/docs/BitCodeFormat.html
+
+mod stream;
+mod writer;
+
+#[cfg(test)]
+mod reader;
+
+use crate::module::Module;
+use std::io::{Result, Write};
+
+// The identifiers of the blocks, starting at the first identifier available to applications.
+const MODULE_BLOCK: u64 = 8;
+const PARAMATTR_BLOCK: u64 = 9;
+const PARAMATTR_GROUP_BLOCK: u64 = 10;
+const CONSTANTS_BLOCK: u64 = 11;
+const FUNCTION_BLOCK: u64 = 12;
+const IDENTIFICATION_BLOCK: u64 = 13;
+const VALUE_SYMTAB_BLOCK: u64 = 14;
+const TYPE_BLOCK: u64 = 17;
+const STRTAB_BLOCK: u64 = 23;
+const SYNC_SCOPE_NAMES_BLOCK: u64 = 26;
+
+// The records of the identification block.
+const IDENTIFICATION_STRING: u64 = 1;
+const IDENTIFICATION_EPOCH: u64 = 2;
+
+// The records of the module block.
+const MODULE_VERSION: u64 = 1;
+const MODULE_TRIPLE: u64 = 2;
+const MODULE_DATALAYOUT: u64 = 3;
+const MODULE_ASM: u64 = 4;
+const MODULE_SECTIONNAME: u64 = 5;
+const MODULE_GLOBALVAR: u64 = 7;
+const MODULE_FUNCTION: u64 = 8;
+const MODULE_SOURCE_FILENAME: u64 = 16;
+
+// The records of the attribute blocks.
+const PARAMATTR_ENTRY: u64 = 2;
+const PARAMATTR_GROUP_ENTRY: u64 = 3;
+
+// The records of the type block.
+const TYPE_NUMENTRY: u64 = 1;
+const TYPE_VOID: u64 = 2;
+const TYPE_FLOAT: u64 = 3;
+const TYPE_DOUBLE: u64 = 4;
+const TYPE_OPAQUE: u64 = 6;
+const TYPE_INTEGER: u64 = 7;
+const TYPE_HALF: u64 = 10;
+const TYPE_ARRAY: u64 = 11;
+const TYPE_VECTOR: u64 = 12;
+const TYPE_FP128: u64 = 14;
+const TYPE_STRUCT_ANON: u64 = 18;
+const TYPE_STRUCT_NAME: u64 = 19;
+const TYPE_STRUCT_NAMED: u64 = 20;
+const TYPE_FUNCTION: u64 = 21;
+const TYPE_OPAQUE_POINTER: u64 = 25;
+
+// The records of the constants block.
+const CONSTANT_SETTYPE: u64 = 1;
+const CONSTANT_NULL: u64 = 2;
+const CONSTANT_UNDEF: u64 = 3;
+const CONSTANT_INTEGER: u64 = 4;
+const CONSTANT_WIDE_INTEGER: u64 = 5;
+const CONSTANT_FLOAT: u64 = 6;
+const CONSTANT_AGGREGATE: u64 = 7;
+const CONSTANT_STRING: u64 = 8;
+const CONSTANT_CSTRING: u64 = 9;
+const CONSTANT_CE_CAST: u64 = 11;
+const CONSTANT_CE_GEP: u64 = 12;
+const CONSTANT_CE_INBOUNDS_GEP: u64 = 20;
+const CONSTANT_BLOCKADDRESS: u64 = 21;
+const CONSTANT_DATA: u64 = 22;
+const CONSTANT_POISON: u64 = 26;
+const CONSTANT_INLINEASM: u64 = 30;
+
+// The records of the function block.
+const FUNCTION_DECLAREBLOCKS: u64 = 1;
+const INST_BINOP: u64 = 2;
+const INST_CAST: u64 = 3;
+const INST_EXTRACTELT: u64 = 6;
+const INST_INSERTELT: u64 = 7;
+const INST_SHUFFLEVEC: u64 = 8;
+const INST_RET: u64 = 10;
+const INST_BR: u64 = 11;
+const INST_SWITCH: u64 = 12;
+const INST_INVOKE: u64 = 13;
+const INST_UNREACHABLE: u64 = 15;
+const INST_PHI: u64 = 16;
+const INST_ALLOCA: u64 = 19;
+const INST_LOAD: u64 = 20;
+const INST_EXTRACTVAL: u64 = 26;
+const INST_INSERTVAL: u64 = 27;
+const INST_VSELECT: u64 = 29;
+const INST_INDIRECTBR: u64 = 31;
+const INST_CALL: u64 = 34;
+const INST_RESUME: u64 = 39;
+const INST_FENCE: u64 = 36;
+const INST_LOADATOMIC: u64 = 41;
+const INST_GEP: u64 = 43;
+const INST_STORE: u64 = 44;
+const INST_STOREATOMIC: u64 = 45;
+const INST_CMPXCHG: u64 = 46;
+const INST_LANDINGPAD: u64 = 47;
+const INST_UNOP: u64 = 56;
+const INST_ATOMICRMW: u64 = 59;
+
+// The records of the value symbol table, string table and sync scope names blocks.
+const VST_ENTRY: u64 = 1;
+const VST_BBENTRY: u64 = 2;
+const STRTAB_BLOB: u64 = 1;
+const SYNC_SCOPE_NAME: u64 = 1;
+
+// The kinds of function and parameter attributes.
+const ATTRIBUTE_ALWAYS_INLINE: u64 = 2;
+const ATTRIBUTE_BY_VAL: u64 = 3;
+const ATTRIBUTE_NO_INLINE: u64 = 14;
+const ATTRIBUTE_NO_RETURN: u64 = 17;
+const ATTRIBUTE_NO_UNWIND: u64 = 18;
+const ATTRIBUTE_STRUCT_RET: u64 = 29;
+const ATTRIBUTE_COLD: u64 = 36;
+const ATTRIBUTE_NON_NULL: u64 = 39;
+const ATTRIBUTE_NO_UNDEF: u64 = 68;
+
+/// The index attribute groups of the function itself are written at, rather than a parameter.
+const ATTRIBUTE_FUNCTION_INDEX: u64 = u32::MAX as u64;
+
+// The bits of the calling convention operand of a call.
+const CALL_TAIL: u64 = 1;
+const CALL_CCONV: u32 = 1;
+const CALL_MUSTTAIL: u64 = 1 << 14;
+const CALL_EXPLICIT_TYPE: u64 = 1 << 15;
+const CALL_NOTAIL: u64 = 1 << 16;
+const INVOKE_EXPLICIT_TYPE: u64 = 1 << 13;
+const LANDINGPAD_CATCH: u64 = 0;
+const LANDINGPAD_FILTER: u64 = 1;
+
+/// The flag of an alloca record telling its allocated type is given explicitly.
+const ALLOCA_EXPLICIT_TYPE: u64 = 1 << 6;
+/// The bit the upper bits of the alignment of an alloca record start at,
+/// while the lower five bits start at zero.
+const ALLOCA_ALIGNMENT_UPPER: u32 = 8;
+
+/// The numbers of the sync scopes every module knows, where the names of other scopes
+/// are numbered after them in the order they are written.
+const SYNC_SCOPE_SINGLE_THREAD: u64 = 0;
+const SYNC_SCOPE_SYSTEM: u64 = 1;
+
+impl Module {
+    /// Writes the module as LLVM bitcode, which tools such as `llvm-link` and `lld`
+    /// read without parsing textual IR.
+    ///
+    /// Metadata is not part of the bitcode, so debug records and attached metadata are left out.
+    /// Constants of `fp128` type keep the precision of a `double`.
+    /// Returns an error if an instruction uses a value that is not defined,
+    /// or if writing fails.
+    pub fn write_bitcode(&self, writer: &mut impl Write) -> Result<()> {
+        writer.write_all(&writer::write_module(self)?)
+    }
+}
+
+/// Encodes a signed number so small magnitudes stay small, with the sign in the lowest bit.
+fn encode_signed(value: i64) -> u64 {
+    match value >= 0 {
+        true => (value as u64) << 1,
+        false => (value.unsigned_abs() << 1) | 1,
+    }
+}
+
+/// Decodes a number encoded by `encode_signed`.
+fn decode_signed(value: u64) -> i64 {
+    match (value & 1, value >> 1) {
+        (0, magnitude) => magnitude as i64,
+        // the magnitude of the smallest number does not fit, so it is written as negative zero
+        (_, 0) => i64::MIN,
+        (_, magnitude) => -(magnitude as i64),
+    }
+}
+
+/// Encodes an alignment in bytes as its logarithm plus one, or zero if there is none.
+fn encode_alignment(alignment: Option<u32>) -> u64 {
+    alignment.map_or(0, |x| x.trailing_zeros() as u64 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bitcode::reader::read_bitcode;
+    use crate::bitcode::stream::BitWriter;
+    use crate::bitcode::{decode_signed, encode_signed};
+    use crate::fixtures::{self, llvm_tool};
+    use crate::module::{GlobalFunction, GlobalVariable, Module};
+    use crate::types::Types;
+    use crate::IRComponent;
+    use std::io::ErrorKind;
+    use std::vec::Vec;
+
+    #[test]
+    fn write_bitstream() {
+        let mut stream = BitWriter::new();
+        stream.fixed(0b101, 3);
+        stream.vbr(100, 4);
+        stream.align();
+        stream.enter_block(8, 3);
+        stream.record(1, &[2]);
+        stream.end_block();
+        assert_eq!(
+            stream.finish(),
+            [
+                0x65, 0x0e, 0x00, 0x00, // 101, then 100 as the chunks 1100 1100 0001
+                0x21, 0x0c, 0x00, 0x00, // enter block 8 with 3 bit abbreviations
+                0x01, 0x00, 0x00, 0x00, // the block is 1 word long
+                0x0b, 0x02, 0x01, 0x00, // the record, ending the block at 000
+            ]
+        );
+        for value in [0, 5, -5, i64::MAX, i64::MIN] {
+            assert_eq!(decode_signed(encode_signed(value)), value);
+        }
+        assert_eq!(encode_signed(-1), 3);
+    }
+
+    /// Writes the module as bitcode, then checks that reading it back
+    /// and that LLVM reading it give the module again.
+    fn round_trip(module: Module) {
+        let mut bytes = Vec::new();
+        module.write_bitcode(&mut bytes).unwrap();
+        assert_eq!(&bytes[..4], b"BC\xc0\xde");
+        let read = read_bitcode(&bytes).unwrap();
+        assert_eq!(read.emit(), module.emit());
+        // LLVM must read the text and the bitcode as the same module
+        if let Some(assembled) = llvm_tool("llvm-as", module.emit().as_bytes()) {
+            let expected = llvm_tool("llvm-dis", &assembled).unwrap();
+            assert_eq!(llvm_tool("llvm-dis", &bytes).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn round_trip_globals() {
+        round_trip(fixtures::globals_module());
+    }
+
+    #[test]
+    fn round_trip_functions() {
+        round_trip(fixtures::functions_module());
+    }
+
+    #[test]
+    fn round_trip_atomics() {
+        round_trip(fixtures::atomics_module());
+    }
+
+    #[test]
+    fn round_trip_vectors() {
+        round_trip(fixtures::vectors_module());
+    }
+
+    #[test]
+    fn round_trip_function_attributes() {
+        round_trip(fixtures::function_attributes_module());
+    }
+
+    #[test]
+    fn round_trip_exceptions() {
+        round_trip(fixtures::exceptions_module());
+    }
+
+    #[test]
+    fn round_trip_jump_tables() {
+        round_trip(fixtures::jump_tables_module());
+    }
+
+    #[test]
+    fn round_trip_constant_expressions() {
+        round_trip(fixtures::constant_expressions_module());
+    }
+
+    #[test]
+    fn round_trip_inline_asm() {
+        round_trip(fixtures::inline_asm_module());
+    }
+
+    #[test]
+    fn reject_undefined_values() {
+        let mut module = Module::new();
+        module
+            .functions
+            .push(
+                GlobalFunction::new("lost", Types::integer(32)).build_body(|block, _| {
+                    let missing = GlobalVariable::new("missing", Types::integer(32)).reference();
+                    let loaded = block.load(Types::integer(32), missing);
+                    block.ret(loaded);
+                }),
+            );
+        let error = module.write_bitcode(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert_eq!(error.to_string(), "undefined value @missing");
+    }
+}
@@ -0,0 +1,1514 @@
+//! Reads back the bitcode written by this crate, so tests can compare it with the module written.
+//!
+//! Only the subset of bitcode the writer produces is understood.
+
+use crate::bitcode::*;
+use crate::instruction::{
+    check_shufflevector, cmpxchg_type, element_type, AtomicOrdering, AtomicRmwOperator,
+    BinaryFlags, BinaryOperator, BlockId, CallOptions, CastOperator, FastMathFlags,
+    InstructionKind, LandingPadClause, MemoryOptions, SyncScope, TailMarker,
+};
+use crate::module::{
+    CallingConvention, FunctionAttribute, FunctionBody, GlobalFunction, GlobalVariable,
+    LinkageType, Module, Parameter, ParameterAttribute, ThreadLocalMode, UnnamedAddr, Visibility,
+};
+use crate::types::{Type, Types};
+use crate::value::{half_value, quad_value, InlineAsm, Value};
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::format;
+use std::result::Result;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+const END_BLOCK: u64 = 0;
+const ENTER_SUBBLOCK: u64 = 1;
+const DEFINE_ABBREV: u64 = 2;
+const UNABBREV_RECORD: u64 = 3;
+
+/// An operand of an abbreviation.
+#[derive(Clone)]
+enum AbbreviationOperand {
+    Literal(u64),
+    Fixed(u32),
+    Vbr(u32),
+    Array(Box<AbbreviationOperand>),
+    Char6,
+    Blob,
+}
+
+struct Record {
+    code: u64,
+    operands: Vec<u64>,
+    blob: Vec<u8>,
+}
+
+struct Block {
+    id: u64,
+    records: Vec<Record>,
+    blocks: Vec<Block>,
+    /// The order records and blocks appear in, where true means the next block.
+    order: Vec<bool>,
+}
+
+struct BitReader<'b> {
+    bytes: &'b [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn fixed(&mut self, width: u32) -> Result<u64, String> {
+        let mut value = 0;
+        for bit in 0..width as usize {
+            let byte = self
+                .bytes
+                .get(self.position / 8)
+                .ok_or("unexpected end of bitcode")?;
+            value |= ((*byte as u64 >> (self.position % 8)) & 1) << bit;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn vbr(&mut self, width: u32) -> Result<u64, String> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let chunk = self.fixed(width)?;
+            value |= (chunk & ((1 << (width - 1)) - 1)) << shift;
+            if chunk & (1 << (width - 1)) == 0 {
+                return Ok(value);
+            }
+            shift += width - 1;
+        }
+    }
+
+    fn align(&mut self) {
+        self.position = self.position.next_multiple_of(32);
+    }
+
+    fn at_end(&self) -> bool {
+        self.position >= self.bytes.len() * 8
+    }
+
+    /// Reads the contents of a block, after its header, up to its end.
+    fn block(&mut self, id: u64, width: u32) -> Result<Block, String> {
+        let mut block = Block {
+            id,
+            records: Vec::new(),
+            blocks: Vec::new(),
+            order: Vec::new(),
+        };
+        let mut abbreviations = Vec::new();
+        loop {
+            match self.fixed(width)? {
+                END_BLOCK => {
+                    self.align();
+                    return Ok(block);
+                }
+                ENTER_SUBBLOCK => {
+                    let inner = self.enter()?;
+                    block.blocks.push(inner);
+                    block.order.push(true);
+                }
+                DEFINE_ABBREV => abbreviations.push(self.abbreviation()?),
+                UNABBREV_RECORD => {
+                    let code = self.vbr(6)?;
+                    let count = self.vbr(6)?;
+                    let operands = (0..count).map(|_| self.vbr(6)).collect::<Result<_, _>>()?;
+                    block.records.push(Record {
+                        code,
+                        operands,
+                        blob: Vec::new(),
+                    });
+                    block.order.push(false);
+                }
+                abbreviation => {
+                    let operands: &Vec<AbbreviationOperand> = abbreviations
+                        .get(abbreviation as usize - 4)
+                        .ok_or(format!("undefined abbreviation {}", abbreviation))?;
+                    let record = self.abbreviated(&operands.clone())?;
+                    block.records.push(record);
+                    block.order.push(false);
+                }
+            }
+        }
+    }
+
+    /// Reads the header of a block after its identifier, and then the block.
+    fn enter(&mut self) -> Result<Block, String> {
+        let id = self.vbr(8)?;
+        let width = self.vbr(4)? as u32;
+        self.align();
+        let _length = self.fixed(32)?;
+        self.block(id, width)
+    }
+
+    fn abbreviation(&mut self) -> Result<Vec<AbbreviationOperand>, String> {
+        let count = self.vbr(5)?;
+        let mut operands = Vec::new();
+        let mut index = 0;
+        while index < count {
+            operands.push(self.abbreviation_operand()?);
+            if let Some(AbbreviationOperand::Array(_)) = operands.last() {
+                // the element of an array is the next operand
+                index += 1;
+            }
+            index += 1;
+        }
+        Ok(operands)
+    }
+
+    fn abbreviation_operand(&mut self) -> Result<AbbreviationOperand, String> {
+        if self.fixed(1)? == 1 {
+            return Ok(AbbreviationOperand::Literal(self.vbr(8)?));
+        }
+        Ok(match self.fixed(3)? {
+            1 => AbbreviationOperand::Fixed(self.vbr(5)? as u32),
+            2 => AbbreviationOperand::Vbr(self.vbr(5)? as u32),
+            3 => AbbreviationOperand::Array(Box::new(self.abbreviation_operand()?)),
+            4 => AbbreviationOperand::Char6,
+            5 => AbbreviationOperand::Blob,
+            encoding => return Err(format!("unknown abbreviation encoding {}", encoding)),
+        })
+    }
+
+    fn scalar(&mut self, operand: &AbbreviationOperand) -> Result<u64, String> {
+        match operand {
+            AbbreviationOperand::Literal(value) => Ok(*value),
+            AbbreviationOperand::Fixed(width) => self.fixed(*width),
+            AbbreviationOperand::Vbr(width) => self.vbr(*width),
+            AbbreviationOperand::Char6 => {
+                let value = self.fixed(6)? as u8;
+                Ok(match value {
+                    0..=25 => b'a' + value,
+                    26..=51 => b'A' + value - 26,
+                    52..=61 => b'0' + value - 52,
+                    62 => b'.',
+                    _ => b'_',
+                } as u64)
+            }
+            _ => Err("arrays and blobs are not scalars".to_string()),
+        }
+    }
+
+    fn abbreviated(&mut self, operands: &[AbbreviationOperand]) -> Result<Record, String> {
+        let mut values = Vec::new();
+        let mut blob = Vec::new();
+        for operand in operands {
+            match operand {
+                AbbreviationOperand::Array(element) => {
+                    let count = self.vbr(6)?;
+                    for _ in 0..count {
+                        values.push(self.scalar(element)?);
+                    }
+                }
+                AbbreviationOperand::Blob => {
+                    let length = self.vbr(6)? as usize;
+                    self.align();
+                    let start = self.position / 8;
+                    blob = self
+                        .bytes
+                        .get(start..start + length)
+                        .ok_or("unexpected end of blob")?
+                        .to_vec();
+                    self.position = (start + length) * 8;
+                    self.align();
+                }
+                scalar => values.push(self.scalar(scalar)?),
+            }
+        }
+        Ok(Record {
+            code: values[0],
+            operands: values[1..].to_vec(),
+            blob,
+        })
+    }
+}
+
+/// Reads a module from bitcode written by `Module::write_bitcode`.
+pub(crate) fn read_bitcode(bytes: &[u8]) -> Result<Module, String> {
+    if bytes.get(..4) != Some(b"BC\xc0\xde") {
+        return Err("missing bitcode magic".to_string());
+    }
+    let mut reader = BitReader {
+        bytes,
+        position: 32,
+    };
+    let mut blocks = Vec::new();
+    while !reader.at_end() {
+        match reader.fixed(2)? {
+            ENTER_SUBBLOCK => blocks.push(reader.enter()?),
+            code => return Err(format!("unexpected top-level code {}", code)),
+        }
+    }
+    let strings = blocks
+        .iter()
+        .find(|x| x.id == STRTAB_BLOCK)
+        .and_then(|x| x.records.first())
+        .map(|x| x.blob.clone())
+        .ok_or("missing string table")?;
+    let module = blocks
+        .iter()
+        .find(|x| x.id == MODULE_BLOCK)
+        .ok_or("missing module block")?;
+    ModuleReader::default().read(module, &strings)
+}
+
+/// A function declared by the module block, built once its body has been read.
+struct PendingFunction {
+    name: String,
+    signature: Type,
+    prototype: bool,
+    linkage: Option<LinkageType>,
+    attributes: u64,
+    calling_convention: Option<CallingConvention>,
+    section: Option<String>,
+    visibility: Option<Visibility>,
+    /// The personality function, numbered plus one, where zero means there is none.
+    personality: u64,
+    dso_local: bool,
+    body: Option<FunctionBody>,
+    names: HashMap<u64, String>,
+}
+
+/// An attribute of a function, or of one of its parameters.
+enum Attribute {
+    Function(FunctionAttribute),
+    Parameter(ParameterAttribute),
+}
+
+#[derive(Default)]
+struct ModuleReader {
+    types: Vec<Type>,
+    /// The attributes of every group, along with the index of the parameter they belong to.
+    groups: HashMap<u64, (u64, Vec<Attribute>)>,
+    lists: Vec<Vec<u64>>,
+    values: Vec<Value>,
+    /// The names of the sync scopes, by number.
+    sync_scopes: Vec<String>,
+}
+
+impl ModuleReader {
+    fn read(mut self, block: &Block, strings: &[u8]) -> Result<Module, String> {
+        let mut module = Module::new();
+        let mut sections = Vec::new();
+        let mut vars = Vec::new();
+        let mut functions = Vec::new();
+        let (mut records, mut blocks) = (block.records.iter(), block.blocks.iter());
+        for is_block in &block.order {
+            if *is_block {
+                let inner = blocks.next().unwrap();
+                match inner.id {
+                    TYPE_BLOCK => self.read_types(inner, &mut module)?,
+                    PARAMATTR_GROUP_BLOCK => self.read_groups(inner)?,
+                    PARAMATTR_BLOCK => {
+                        self.lists = inner.records.iter().map(|x| x.operands.clone()).collect()
+                    }
+                    SYNC_SCOPE_NAMES_BLOCK => {
+                        self.sync_scopes =
+                            inner.records.iter().map(|x| string(&x.operands)).collect()
+                    }
+                    CONSTANTS_BLOCK => {
+                        let mut values = std::mem::take(&mut self.values);
+                        self.read_constants(inner, &mut values)?;
+                        self.values = values;
+                    }
+                    FUNCTION_BLOCK => {
+                        let function = functions
+                            .iter_mut()
+                            .find(|x: &&mut PendingFunction| !x.prototype && x.body.is_none())
+                            .ok_or("function block without a defined function")?;
+                        self.read_function(inner, function)?;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            let record = records.next().unwrap();
+            let operands = &record.operands;
+            match record.code {
+                MODULE_TRIPLE => module.target_triple = Some(string(operands)),
+                MODULE_DATALAYOUT => module.data_layout = Some(string(operands)),
+                MODULE_ASM => module.append_module_asm(&string(operands)),
+                MODULE_SOURCE_FILENAME => module.source_filename = Some(string(operands)),
+                MODULE_SECTIONNAME => sections.push(string(operands)),
+                MODULE_GLOBALVAR => {
+                    let name = strtab(strings, operands[0], operands[1])?;
+                    self.values
+                        .push(Value::GlobalIdentifier(name.clone(), Types::pointer(0)));
+                    vars.push((name, operands.clone()));
+                }
+                MODULE_FUNCTION => {
+                    let name = strtab(strings, operands[0], operands[1])?;
+                    let signature = self.ty(operands[2])?;
+                    self.values
+                        .push(Value::Function(name.clone(), signature.clone()));
+                    functions.push(PendingFunction {
+                        name,
+                        signature,
+                        prototype: operands[4] == 1,
+                        linkage: linkage(operands[5])?,
+                        attributes: operands[6],
+                        calling_convention: calling_convention(operands[3]),
+                        section: match operands[8] {
+                            0 => None,
+                            section => Some(sections[section as usize - 1].clone()),
+                        },
+                        visibility: match operands[9] {
+                            0 => None,
+                            1 => Some(Visibility::Hidden),
+                            _ => Some(Visibility::Protected),
+                        },
+                        personality: operands[16],
+                        dso_local: operands[17] == 1,
+                        body: None,
+                        names: HashMap::new(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        for (name, operands) in vars {
+            let mut var = GlobalVariable::new(&name, self.ty(operands[2])?);
+            if operands[3] & 1 == 1 {
+                var = var.constant();
+            }
+            if operands[4] != 0 {
+                var = var.with_value(self.value(operands[4] - 1)?);
+            }
+            if let Some(linkage) = linkage(operands[5])? {
+                var = var.with_linkage(linkage);
+            }
+            if operands[6] != 0 {
+                var = var.with_alignment(1 << (operands[6] - 1));
+            }
+            if operands[7] != 0 {
+                var = var.with_section(&sections[operands[7] as usize - 1]);
+            }
+            var.thread_local = match operands[9] {
+                0 => None,
+                1 => Some(ThreadLocalMode::GeneralDynamic),
+                2 => Some(ThreadLocalMode::LocalDynamic),
+                3 => Some(ThreadLocalMode::InitialExec),
+                _ => Some(ThreadLocalMode::LocalExec),
+            };
+            var.unnamed_addr = match operands[10] {
+                0 => None,
+                1 => Some(UnnamedAddr::Global),
+                _ => Some(UnnamedAddr::Local),
+            };
+            module.vars.push(var);
+        }
+        for pending in functions {
+            module.functions.push(self.build_function(pending)?);
+        }
+        resolve_block_addresses(&mut module)?;
+        Ok(module)
+    }
+
+    fn sync_scope(&self, id: u64) -> Result<SyncScope, String> {
+        if self.sync_scopes.is_empty() {
+            return match id {
+                SYNC_SCOPE_SINGLE_THREAD => Ok(SyncScope::SingleThread),
+                SYNC_SCOPE_SYSTEM => Ok(SyncScope::System),
+                _ => Err(format!("unknown sync scope {}", id)),
+            };
+        }
+        let name = self
+            .sync_scopes
+            .get(id as usize)
+            .ok_or_else(|| format!("unknown sync scope {}", id))?;
+        Ok(match name.as_str() {
+            "" => SyncScope::System,
+            "singlethread" => SyncScope::SingleThread,
+            _ => SyncScope::Target(name.clone()),
+        })
+    }
+
+    fn read_types(&mut self, block: &Block, module: &mut Module) -> Result<(), String> {
+        // named structures are created first, since any type may refer to them
+        let mut named = HashMap::new();
+        let mut name = None;
+        let mut index = 0;
+        for record in &block.records {
+            match record.code {
+                TYPE_NUMENTRY => continue,
+                TYPE_STRUCT_NAME => {
+                    name = Some(string(&record.operands));
+                    continue;
+                }
+                TYPE_STRUCT_NAMED | TYPE_OPAQUE => {
+                    let name = name.take().ok_or("named structure without a name")?;
+                    named.insert(index, module.declare_type(&name));
+                }
+                _ => {}
+            }
+            index += 1;
+        }
+        for record in &block.records {
+            let operands = &record.operands;
+            let ty = match record.code {
+                TYPE_NUMENTRY | TYPE_STRUCT_NAME => continue,
+                TYPE_INTEGER => Types::integer(operands[0] as u32),
+                TYPE_HALF => Type::Half,
+                TYPE_FLOAT => Type::Float,
+                TYPE_DOUBLE => Type::Double,
+                TYPE_FP128 => Type::FP128,
+                TYPE_VOID => Type::Void,
+                TYPE_OPAQUE_POINTER => Types::pointer(operands[0] as u32),
+                TYPE_ARRAY => Types::array(operands[0] as u32, self.ty(operands[1])?),
+                TYPE_VECTOR => match operands.get(2) {
+                    Some(1) => Types::scalable_vector(operands[0] as u32, self.ty(operands[1])?),
+                    _ => Types::vector(operands[0] as u32, self.ty(operands[1])?),
+                },
+                TYPE_STRUCT_ANON => match operands[0] {
+                    0 => Types::structure(self.types_of(&operands[1..], &named)?),
+                    _ => Types::packed_structure(self.types_of(&operands[1..], &named)?),
+                },
+                TYPE_FUNCTION => {
+                    let return_type = self.ty_or_named(operands[1], &named)?;
+                    let parameters = self.types_of(&operands[2..], &named)?;
+                    match operands[0] {
+                        0 => Types::function(return_type, parameters),
+                        _ => Types::variadic_function(return_type, parameters),
+                    }
+                }
+                TYPE_STRUCT_NAMED => {
+                    let ty = named[&self.types.len()].clone();
+                    let fields = self.types_of(&operands[1..], &named)?;
+                    let body = match operands[0] {
+                        0 => Types::structure(fields),
+                        _ => Types::packed_structure(fields),
+                    };
+                    if let Type::Named(named) = &ty {
+                        named.set_body(body);
+                    }
+                    ty
+                }
+                TYPE_OPAQUE => named[&self.types.len()].clone(),
+                code => return Err(format!("unknown type code {}", code)),
+            };
+            self.types.push(ty);
+        }
+        Ok(())
+    }
+
+    fn ty_or_named(&self, id: u64, named: &HashMap<usize, Type>) -> Result<Type, String> {
+        match named.get(&(id as usize)) {
+            Some(ty) => Ok(ty.clone()),
+            None => self.ty(id),
+        }
+    }
+
+    fn types_of(&self, ids: &[u64], named: &HashMap<usize, Type>) -> Result<Vec<Type>, String> {
+        ids.iter().map(|x| self.ty_or_named(*x, named)).collect()
+    }
+
+    fn ty(&self, id: u64) -> Result<Type, String> {
+        self.types
+            .get(id as usize)
+            .cloned()
+            .ok_or(format!("undefined type {}", id))
+    }
+
+    fn value(&self, id: u64) -> Result<Value, String> {
+        self.values
+            .get(id as usize)
+            .cloned()
+            .ok_or(format!("undefined value {}", id))
+    }
+
+    fn read_groups(&mut self, block: &Block) -> Result<(), String> {
+        for record in &block.records {
+            let operands = &record.operands;
+            let mut attributes = Vec::new();
+            let mut index = 2;
+            while index < operands.len() {
+                let attribute = match (operands[index], operands[index + 1]) {
+                    (0, ATTRIBUTE_NO_UNWIND) => Attribute::Function(FunctionAttribute::NoUnwind),
+                    (0, ATTRIBUTE_NO_INLINE) => Attribute::Function(FunctionAttribute::NoInline),
+                    (0, ATTRIBUTE_ALWAYS_INLINE) => {
+                        Attribute::Function(FunctionAttribute::AlwaysInline)
+                    }
+                    (0, ATTRIBUTE_COLD) => Attribute::Function(FunctionAttribute::Cold),
+                    (0, ATTRIBUTE_NO_RETURN) => Attribute::Function(FunctionAttribute::NoReturn),
+                    (0, ATTRIBUTE_NO_UNDEF) => Attribute::Parameter(ParameterAttribute::NoUndef),
+                    (0, ATTRIBUTE_NON_NULL) => Attribute::Parameter(ParameterAttribute::NonNull),
+                    (6, ATTRIBUTE_STRUCT_RET) => Attribute::Parameter(
+                        ParameterAttribute::StructRet(self.ty(operands[index + 2])?),
+                    ),
+                    (6, ATTRIBUTE_BY_VAL) => Attribute::Parameter(ParameterAttribute::ByVal(
+                        self.ty(operands[index + 2])?,
+                    )),
+                    (kind, attribute) => {
+                        return Err(format!("unknown attribute {} of kind {}", attribute, kind))
+                    }
+                };
+                // attributes with a type take an extra operand
+                index += match operands[index] {
+                    6 => 3,
+                    _ => 2,
+                };
+                attributes.push(attribute);
+            }
+            self.groups.insert(operands[0], (operands[1], attributes));
+        }
+        Ok(())
+    }
+
+    /// Reads a constants block, numbering every constant after the values given.
+    fn read_constants(&self, block: &Block, values: &mut Vec<Value>) -> Result<(), String> {
+        let mut ty = Types::integer(32);
+        for record in &block.records {
+            let operands = &record.operands;
+            let value = match record.code {
+                CONSTANT_SETTYPE => {
+                    ty = self.ty(operands[0])?;
+                    continue;
+                }
+                CONSTANT_NULL if ty.is_pointer() => Value::Null(ty.clone()),
+                CONSTANT_NULL => Value::ZeroInitializer(ty.clone()),
+                CONSTANT_UNDEF => Value::Undef(ty.clone()),
+                CONSTANT_POISON => Value::Poison(ty.clone()),
+                CONSTANT_CE_CAST => {
+                    let value = constant(values, operands[2])?;
+                    Value::Cast(cast_operator(operands[0])?, Box::new(value), ty.clone())
+                }
+                CONSTANT_INLINEASM => {
+                    // the template and the constraints are each preceded by their length
+                    let length = operands[2] as usize;
+                    Value::InlineAsm(InlineAsm {
+                        template: string(&operands[3..3 + length]),
+                        constraints: string(&operands[4 + length..]),
+                        signature: self.ty(operands[0])?,
+                        side_effects: operands[1] & 1 != 0,
+                        align_stack: operands[1] & 2 != 0,
+                        intel_dialect: operands[1] & 4 != 0,
+                        unwind: operands[1] & 8 != 0,
+                    })
+                }
+                // an odd number of operands starts with the type the indices step into
+                CONSTANT_CE_GEP | CONSTANT_CE_INBOUNDS_GEP if operands.len() % 2 == 1 => {
+                    let mut elements = operands[1..]
+                        .chunks(2)
+                        .map(|x| constant(values, x[1]))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if elements.is_empty() {
+                        return Err("constant getelementptr without a base".to_string());
+                    }
+                    let base = elements.remove(0);
+                    let inbounds = record.code == CONSTANT_CE_INBOUNDS_GEP;
+                    Value::GetElementPtr(self.ty(operands[0])?, Box::new(base), elements, inbounds)
+                }
+                CONSTANT_INTEGER => {
+                    let value = decode_signed(operands[0]);
+                    let contents = match ty {
+                        Type::Integer(1) => (value & 1).to_string(),
+                        _ => value.to_string(),
+                    };
+                    Value::Number(contents, ty.clone())
+                }
+                CONSTANT_WIDE_INTEGER => {
+                    let Type::Integer(width) = ty else {
+                        return Err("wide integer of a type that is not an integer".to_string());
+                    };
+                    let words = operands.iter().map(|x| decode_signed(*x) as u64).collect();
+                    Value::Number(wide_integer(words, width), ty.clone())
+                }
+                CONSTANT_FLOAT => {
+                    let contents = match ty {
+                        Type::Half => half_value(operands[0] as u16).to_string(),
+                        Type::Float => f32::from_bits(operands[0] as u32).to_string(),
+                        Type::FP128 => quad_value(operands[0], operands[1]).to_string(),
+                        _ => f64::from_bits(operands[0]).to_string(),
+                    };
+                    Value::Number(contents, ty.clone())
+                }
+                CONSTANT_AGGREGATE => {
+                    let elements = operands
+                        .iter()
+                        .map(|x| constant(values, *x))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    match ty {
+                        Type::Array(..) => Value::Array(elements, ty.clone()),
+                        Type::Vector(..) => Value::Vector(elements, ty.clone()),
+                        _ => Value::Structure(elements, ty.clone()),
+                    }
+                }
+                // arrays and vectors of numbers are written as the bits of their elements
+                CONSTANT_DATA => {
+                    let element = ty.scalar().clone();
+                    let element = match &ty {
+                        Type::Array(_, element) => *element.clone(),
+                        _ => element,
+                    };
+                    let elements = operands
+                        .iter()
+                        .map(|x| data_element(&element, *x))
+                        .collect::<Vec<_>>();
+                    match ty {
+                        Type::Array(..) => Value::Array(elements, ty.clone()),
+                        _ => Value::Vector(elements, ty.clone()),
+                    }
+                }
+                CONSTANT_STRING => {
+                    Value::CString(operands.iter().map(|x| *x as u8).collect(), ty.clone())
+                }
+                CONSTANT_CSTRING => {
+                    let mut bytes = operands.iter().map(|x| *x as u8).collect::<Vec<_>>();
+                    bytes.push(0);
+                    Value::CString(bytes, ty.clone())
+                }
+                CONSTANT_BLOCKADDRESS => {
+                    let Some(Value::Function(function, _)) = values.get(operands[1] as usize)
+                    else {
+                        return Err(format!(
+                            "blockaddress of {}, which is not a function",
+                            operands[1]
+                        ));
+                    };
+                    // the label is only known once the function body has been read
+                    Value::BlockAddress(function.clone(), operands[2].to_string())
+                }
+                code => return Err(format!("unknown constant code {}", code)),
+            };
+            values.push(value);
+        }
+        Ok(())
+    }
+
+    fn read_function(&self, block: &Block, function: &mut PendingFunction) -> Result<(), String> {
+        let Type::Function(_, parameters, _) = &function.signature else {
+            return Err("function of a type that is not a function".to_string());
+        };
+        // names come last in the block, but forward references need them earlier
+        let mut labels = HashMap::new();
+        for symbols in block.blocks.iter().filter(|x| x.id == VALUE_SYMTAB_BLOCK) {
+            for record in &symbols.records {
+                let name = string(&record.operands[1..]);
+                match record.code {
+                    VST_BBENTRY => labels.insert(record.operands[0], name),
+                    _ => function.names.insert(record.operands[0], name),
+                };
+            }
+        }
+        let mut reader = FunctionReader {
+            module: self,
+            values: self.values.clone(),
+            names: &function.names,
+            next: 0,
+        };
+        for (index, parameter) in parameters.iter().enumerate() {
+            let id = reader.values.len() as u64;
+            let name = reader.name(id, &format!("arg{}", index));
+            reader
+                .values
+                .push(Value::LocalIdentifier(name, (**parameter).clone()));
+        }
+
+        let mut body = FunctionBody::empty();
+        let mut current = 0;
+        let (mut records, mut blocks) = (block.records.iter(), block.blocks.iter());
+        for is_block in &block.order {
+            if *is_block {
+                let inner = blocks.next().unwrap();
+                if inner.id == CONSTANTS_BLOCK {
+                    self.read_constants(inner, &mut reader.values)?;
+                }
+                continue;
+            }
+            let record = records.next().unwrap();
+            if record.code == FUNCTION_DECLAREBLOCKS {
+                for index in 0..record.operands[0] {
+                    let label = labels
+                        .get(&index)
+                        .cloned()
+                        .unwrap_or(format!("bb{}", index));
+                    body.append_block(&label);
+                }
+                continue;
+            }
+            reader.next = reader.values.len() as u64;
+            let (kind, ty) = reader.instruction(record)?;
+            let result = match ty {
+                Some(Type::Void) | None => None,
+                Some(ty) => {
+                    let name = reader.name(reader.next, &format!("v{}", reader.next));
+                    let result = Value::LocalIdentifier(name, ty);
+                    reader.values.push(result.clone());
+                    Some(result)
+                }
+            };
+            let terminator = kind.is_terminator();
+            let block = BlockId(current);
+            let index = body.block(block).instructions().len();
+            body.insert_instruction(block, index, kind, result);
+            if terminator {
+                current += 1;
+            }
+        }
+        body.block_index = body.blocks.len();
+        function.body = Some(body);
+        Ok(())
+    }
+
+    fn build_function(&self, pending: PendingFunction) -> Result<GlobalFunction, String> {
+        let Type::Function(return_type, parameters, variadic) = &pending.signature else {
+            return Err("function of a type that is not a function".to_string());
+        };
+        let mut function = GlobalFunction::new(&pending.name, (**return_type).clone());
+        let groups = match pending.attributes {
+            0 => Vec::new(),
+            list => self.lists[list as usize - 1].clone(),
+        };
+        let first = self.values.len() as u64;
+        for (index, ty) in parameters.iter().enumerate() {
+            let mut parameter = Parameter::new((**ty).clone());
+            if let Some(name) = pending.names.get(&(first + index as u64)) {
+                parameter = parameter.with_name(name);
+            }
+            for group in &groups {
+                match self.groups.get(group) {
+                    Some((position, attributes)) if *position == index as u64 + 1 => {
+                        for attribute in attributes {
+                            if let Attribute::Parameter(attribute) = attribute {
+                                parameter = parameter.with_attribute(attribute.clone());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            function = function
+                .try_with_parameter(parameter)
+                .map_err(|e| e.to_string())?;
+        }
+        for group in &groups {
+            let Some((ATTRIBUTE_FUNCTION_INDEX, attributes)) = self.groups.get(group) else {
+                continue;
+            };
+            for attribute in attributes {
+                if let Attribute::Function(attribute) = attribute {
+                    function = function.with_attribute(*attribute);
+                }
+            }
+        }
+        if *variadic {
+            function = function.variadic();
+        }
+        if let Some(linkage) = pending.linkage {
+            function = function.linkage(linkage);
+        }
+        function.calling_convention = pending.calling_convention;
+        function.section = pending.section;
+        function.visibility = pending.visibility;
+        if pending.personality != 0 {
+            function = function.with_personality(self.value(pending.personality - 1)?);
+        }
+        function.dso_local = pending.dso_local;
+        if let Some(body) = pending.body {
+            function = function.body(body);
+        }
+        Ok(function)
+    }
+}
+
+/// Reads the instructions of a function body, whose operands are relative to the next value.
+struct FunctionReader<'r> {
+    module: &'r ModuleReader,
+    values: Vec<Value>,
+    names: &'r HashMap<u64, String>,
+    next: u64,
+}
+
+impl FunctionReader<'_> {
+    fn name(&self, id: u64, fallback: &str) -> String {
+        self.names.get(&id).cloned().unwrap_or(fallback.to_string())
+    }
+
+    /// Returns the value the distance points back to, which is a placeholder
+    /// of the given type if the value is defined later.
+    fn get(&self, id: u64, ty: Type) -> Value {
+        match self.values.get(id as usize) {
+            Some(value) => value.clone(),
+            None => Value::LocalIdentifier(self.name(id, &format!("v{}", id)), ty),
+        }
+    }
+
+    fn relative(&self, operand: u64) -> u64 {
+        (self.next as u32).wrapping_sub(operand as u32) as u64
+    }
+
+    fn value(&self, operands: &[u64], index: &mut usize, ty: &Type) -> Value {
+        let id = self.relative(operands[*index]);
+        *index += 1;
+        self.get(id, ty.clone())
+    }
+
+    fn value_and_type(&self, operands: &[u64], index: &mut usize) -> Result<Value, String> {
+        let id = self.relative(operands[*index]);
+        *index += 1;
+        if id < self.next {
+            return Ok(self.get(id, Type::Void));
+        }
+        let ty = self.module.ty(operands[*index])?;
+        *index += 1;
+        Ok(self.get(id, ty))
+    }
+
+    /// Reads the arguments of a call, where the extra arguments of variadic functions carry their type.
+    fn arguments(
+        &self,
+        operands: &[u64],
+        index: &mut usize,
+        parameters: &[Box<Type>],
+    ) -> Result<Vec<Value>, String> {
+        let mut arguments = Vec::new();
+        while *index < operands.len() {
+            match parameters.get(arguments.len()) {
+                Some(ty) => arguments.push(self.value(operands, index, ty)),
+                None => arguments.push(self.value_and_type(operands, index)?),
+            }
+        }
+        Ok(arguments)
+    }
+
+    /// Reads an instruction, and returns it along with the type of its result.
+    fn instruction(&self, record: &Record) -> Result<(InstructionKind, Option<Type>), String> {
+        let operands = &record.operands;
+        let mut index = 0;
+        // blocks are appended in the order they are laid out, so their number is their identifier
+        let block = |id: u64| BlockId(id as usize);
+        Ok(match record.code {
+            INST_BINOP => {
+                let lhs = self.value_and_type(operands, &mut index)?;
+                let rhs = self.value(operands, &mut index, lhs.ty());
+                let floating = lhs.ty().scalar().is_floating_point();
+                let operator = binary_operator(operands[index], floating)?;
+                let bits = operands.get(index + 1).copied().unwrap_or(0);
+                let flags = binary_flags(operator, bits);
+                let ty = lhs.ty().clone();
+                let kind = InstructionKind::Binary {
+                    operator,
+                    flags,
+                    lhs,
+                    rhs,
+                };
+                (kind, Some(ty))
+            }
+            INST_UNOP => {
+                let value = self.value_and_type(operands, &mut index)?;
+                let ty = value.ty().clone();
+                (InstructionKind::FloatNegate { value }, Some(ty))
+            }
+            INST_CAST => {
+                let value = self.value_and_type(operands, &mut index)?;
+                let target = self.module.ty(operands[index])?;
+                let operator = cast_operator(operands[index + 1])?;
+                let kind = InstructionKind::Cast {
+                    operator,
+                    value,
+                    target: target.clone(),
+                };
+                (kind, Some(target))
+            }
+            INST_ALLOCA => {
+                let ty = self.module.ty(operands[0])?;
+                let bits = operands[3];
+                let alignment =
+                    decode_alignment((bits & 31) | (bits >> ALLOCA_ALIGNMENT_UPPER) << 5);
+                let kind = InstructionKind::Alloca { ty, alignment };
+                (kind, Some(Types::pointer(0)))
+            }
+            INST_LOAD | INST_LOADATOMIC => {
+                let pointer = self.value_and_type(operands, &mut index)?;
+                let ty = self.module.ty(operands[index])?;
+                let mut options = MemoryOptions {
+                    volatile: operands[index + 2] == 1,
+                    alignment: decode_alignment(operands[index + 1]),
+                    ..MemoryOptions::default()
+                };
+                let ordering = match record.code {
+                    INST_LOADATOMIC => {
+                        options.sync_scope = self.module.sync_scope(operands[index + 4])?;
+                        Some(atomic_ordering(operands[index + 3])?)
+                    }
+                    _ => None,
+                };
+                let kind = InstructionKind::Load {
+                    ty: ty.clone(),
+                    pointer,
+                    ordering,
+                    options,
+                };
+                (kind, Some(ty))
+            }
+            INST_STORE | INST_STOREATOMIC => {
+                let pointer = self.value_and_type(operands, &mut index)?;
+                let value = self.value_and_type(operands, &mut index)?;
+                let mut options = MemoryOptions {
+                    volatile: operands[index + 1] == 1,
+                    alignment: decode_alignment(operands[index]),
+                    ..MemoryOptions::default()
+                };
+                let ordering = match record.code {
+                    INST_STOREATOMIC => {
+                        options.sync_scope = self.module.sync_scope(operands[index + 3])?;
+                        Some(atomic_ordering(operands[index + 2])?)
+                    }
+                    _ => None,
+                };
+                let kind = InstructionKind::Store {
+                    value,
+                    pointer,
+                    ordering,
+                    options,
+                };
+                (kind, None)
+            }
+            INST_ATOMICRMW => {
+                let pointer = self.value_and_type(operands, &mut index)?;
+                let value = self.value_and_type(operands, &mut index)?;
+                let options = MemoryOptions {
+                    volatile: operands[index + 1] == 1,
+                    alignment: decode_alignment(operands[index + 4]),
+                    sync_scope: self.module.sync_scope(operands[index + 3])?,
+                };
+                let ty = value.ty().clone();
+                let kind = InstructionKind::AtomicRmw {
+                    operator: atomicrmw_operator(operands[index])?,
+                    pointer,
+                    value,
+                    ordering: atomic_ordering(operands[index + 2])?,
+                    options,
+                };
+                (kind, Some(ty))
+            }
+            INST_CMPXCHG => {
+                let pointer = self.value_and_type(operands, &mut index)?;
+                let expected = self.value_and_type(operands, &mut index)?;
+                let replacement = self.value(operands, &mut index, expected.ty());
+                let options = MemoryOptions {
+                    volatile: operands[index] == 1,
+                    alignment: decode_alignment(operands[index + 5]),
+                    sync_scope: self.module.sync_scope(operands[index + 2])?,
+                };
+                let ty = cmpxchg_type(expected.ty());
+                let kind = InstructionKind::CompareExchange {
+                    pointer,
+                    expected,
+                    replacement,
+                    success: atomic_ordering(operands[index + 1])?,
+                    failure: atomic_ordering(operands[index + 3])?,
+                    weak: operands[index + 4] == 1,
+                    options,
+                };
+                (kind, Some(ty))
+            }
+            INST_FENCE => {
+                let kind = InstructionKind::Fence {
+                    ordering: atomic_ordering(operands[0])?,
+                    sync_scope: self.module.sync_scope(operands[1])?,
+                };
+                (kind, None)
+            }
+            INST_GEP => {
+                let ty = self.module.ty(operands[1])?;
+                index = 2;
+                let base = self.value_and_type(operands, &mut index)?;
+                let mut indices = Vec::new();
+                while index < operands.len() {
+                    indices.push(self.value_and_type(operands, &mut index)?);
+                }
+                let kind = InstructionKind::GetElementPtr { ty, base, indices };
+                (kind, Some(Types::pointer(0)))
+            }
+            INST_EXTRACTVAL => {
+                let aggregate = self.value_and_type(operands, &mut index)?;
+                let position = operands[index] as usize;
+                let ty = element_type(aggregate.ty(), position).map_err(|e| e.to_string())?;
+                let kind = InstructionKind::ExtractValue {
+                    aggregate,
+                    index: position,
+                };
+                (kind, Some(ty))
+            }
+            INST_INSERTVAL => {
+                let aggregate = self.value_and_type(operands, &mut index)?;
+                let value = self.value_and_type(operands, &mut index)?;
+                let ty = aggregate.ty().clone();
+                let kind = InstructionKind::InsertValue {
+                    aggregate,
+                    value,
+                    index: operands[index] as usize,
+                };
+                (kind, Some(ty))
+            }
+            INST_EXTRACTELT => {
+                let vector = self.value_and_type(operands, &mut index)?;
+                let position = self.value_and_type(operands, &mut index)?;
+                let ty = vector.ty().scalar().clone();
+                let kind = InstructionKind::ExtractElement {
+                    vector,
+                    index: position,
+                };
+                (kind, Some(ty))
+            }
+            INST_INSERTELT => {
+                let vector = self.value_and_type(operands, &mut index)?;
+                let value = self.value(operands, &mut index, vector.ty().scalar());
+                let position = self.value_and_type(operands, &mut index)?;
+                let ty = vector.ty().clone();
+                let kind = InstructionKind::InsertElement {
+                    vector,
+                    value,
+                    index: position,
+                };
+                (kind, Some(ty))
+            }
+            INST_SHUFFLEVEC => {
+                let lhs = self.value_and_type(operands, &mut index)?;
+                let rhs = self.value(operands, &mut index, lhs.ty());
+                // masks are always constants, so their type is known
+                let mask = self.value(operands, &mut index, &Type::Void);
+                let ty = check_shufflevector(&lhs, &rhs, &mask).map_err(|e| e.to_string())?;
+                (InstructionKind::ShuffleVector { lhs, rhs, mask }, Some(ty))
+            }
+            INST_PHI => {
+                let ty = self.module.ty(operands[0])?;
+                let mut incoming = Vec::new();
+                for pair in operands[1..].chunks(2) {
+                    let id = (self.next as i64 - decode_signed(pair[0])) as u64;
+                    incoming.push((self.get(id, ty.clone()), block(pair[1])));
+                }
+                (
+                    InstructionKind::Phi {
+                        ty: ty.clone(),
+                        incoming,
+                    },
+                    Some(ty),
+                )
+            }
+            INST_VSELECT => {
+                let if_true = self.value_and_type(operands, &mut index)?;
+                let if_false = self.value(operands, &mut index, if_true.ty());
+                let condition = self.value_and_type(operands, &mut index)?;
+                let ty = if_true.ty().clone();
+                let kind = InstructionKind::Select {
+                    condition,
+                    if_true,
+                    if_false,
+                };
+                (kind, Some(ty))
+            }
+            INST_CALL => {
+                let bits = operands[1];
+                let signature = self.module.ty(operands[2])?;
+                let Type::Function(return_type, parameters, _) = &signature else {
+                    return Err("call of a type that is not a function".to_string());
+                };
+                index = 3;
+                let callee = match self.value_and_type(operands, &mut index)? {
+                    // the callee is called with the signature of the call
+                    Value::Function(name, _) => Value::Function(name, signature.clone()),
+                    callee => callee,
+                };
+                let arguments = self.arguments(operands, &mut index, parameters)?;
+                let mut options = CallOptions::new();
+                if bits & CALL_MUSTTAIL != 0 {
+                    options = options.with_tail_marker(TailMarker::MustTail);
+                } else if bits & CALL_TAIL != 0 {
+                    options = options.with_tail_marker(TailMarker::Tail);
+                } else if bits & CALL_NOTAIL != 0 {
+                    options = options.with_tail_marker(TailMarker::NoTail);
+                }
+                if let Some(convention) = calling_convention((bits >> CALL_CCONV) & 0x3ff) {
+                    options = options.with_calling_convention(convention);
+                }
+                let ty = (**return_type).clone();
+                let kind = InstructionKind::Call {
+                    options,
+                    signature,
+                    callee,
+                    arguments,
+                };
+                (kind, Some(ty))
+            }
+            INST_INVOKE => {
+                let signature = self.module.ty(operands[4])?;
+                let Type::Function(return_type, parameters, _) = &signature else {
+                    return Err("invoke of a type that is not a function".to_string());
+                };
+                index = 5;
+                let callee = match self.value_and_type(operands, &mut index)? {
+                    Value::Function(name, _) => Value::Function(name, signature.clone()),
+                    callee => callee,
+                };
+                let arguments = self.arguments(operands, &mut index, parameters)?;
+                let mut options = CallOptions::new();
+                if let Some(convention) = calling_convention(operands[1] & 0x3ff) {
+                    options = options.with_calling_convention(convention);
+                }
+                let ty = (**return_type).clone();
+                let kind = InstructionKind::Invoke {
+                    options,
+                    signature,
+                    callee,
+                    arguments,
+                    normal: block(operands[2]),
+                    unwind: block(operands[3]),
+                };
+                (kind, Some(ty))
+            }
+            INST_LANDINGPAD => {
+                let ty = self.module.ty(operands[0])?;
+                index = 3;
+                let mut clauses = Vec::new();
+                for _ in 0..operands[2] {
+                    let kind = operands[index];
+                    index += 1;
+                    let value = self.value_and_type(operands, &mut index)?;
+                    clauses.push(match kind {
+                        LANDINGPAD_CATCH => LandingPadClause::Catch(value),
+                        _ => LandingPadClause::Filter(value),
+                    });
+                }
+                let kind = InstructionKind::LandingPad {
+                    ty: ty.clone(),
+                    cleanup: operands[1] != 0,
+                    clauses,
+                };
+                (kind, Some(ty))
+            }
+            INST_RESUME => {
+                let value = self.value_and_type(operands, &mut index)?;
+                (InstructionKind::Resume { value }, None)
+            }
+            INST_RET => match operands.is_empty() {
+                true => (InstructionKind::Return { value: None }, None),
+                false => {
+                    let value = Some(self.value_and_type(operands, &mut index)?);
+                    (InstructionKind::Return { value }, None)
+                }
+            },
+            INST_BR => match operands.len() {
+                1 => (
+                    InstructionKind::Branch {
+                        target: block(operands[0]),
+                    },
+                    None,
+                ),
+                _ => {
+                    index = 2;
+                    let condition = self.value(operands, &mut index, &Types::integer(1));
+                    let kind = InstructionKind::ConditionalBranch {
+                        condition,
+                        if_true: block(operands[0]),
+                        if_false: block(operands[1]),
+                    };
+                    (kind, None)
+                }
+            },
+            INST_SWITCH => {
+                let ty = self.module.ty(operands[0])?;
+                index = 1;
+                let value = self.value(operands, &mut index, &ty);
+                let cases = operands[3..]
+                    .chunks(2)
+                    .map(|x| (self.get(x[0], ty.clone()), block(x[1])))
+                    .collect();
+                let kind = InstructionKind::Switch {
+                    value,
+                    default: block(operands[2]),
+                    cases,
+                };
+                (kind, None)
+            }
+            INST_INDIRECTBR => {
+                let ty = self.module.ty(operands[0])?;
+                index = 1;
+                let address = self.value(operands, &mut index, &ty);
+                let kind = InstructionKind::IndirectBranch {
+                    address,
+                    destinations: operands[2..].iter().map(|x| block(*x)).collect(),
+                };
+                (kind, None)
+            }
+            INST_UNREACHABLE => (InstructionKind::Unreachable, None),
+            code => return Err(format!("unknown instruction code {}", code)),
+        })
+    }
+}
+
+/// Replaces the position of the block in every `blockaddress` by its label,
+/// now that every function body has been read.
+fn resolve_block_addresses(module: &mut Module) -> Result<(), String> {
+    let labels = module
+        .functions
+        .iter()
+        .filter_map(|x| {
+            let body = x.get_body()?;
+            let labels = body
+                .blocks()
+                .into_iter()
+                .map(|y| body.block(y).label().to_string());
+            Some((x.name().to_string(), labels.collect::<Vec<_>>()))
+        })
+        .collect::<HashMap<_, _>>();
+    fn resolve(value: &mut Value, labels: &HashMap<String, Vec<String>>) -> Result<(), String> {
+        match value {
+            Value::BlockAddress(function, label) => {
+                *label = label
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|x| labels.get(function)?.get(x))
+                    .cloned()
+                    .ok_or_else(|| {
+                        format!("blockaddress of undefined block {} in @{}", label, function)
+                    })?;
+            }
+            Value::Structure(elements, _)
+            | Value::Array(elements, _)
+            | Value::Vector(elements, _) => {
+                for element in elements {
+                    resolve(element, labels)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+    for var in &mut module.vars {
+        if let Some(value) = &mut var.value {
+            resolve(value, &labels)?;
+        }
+    }
+    for function in &mut module.functions {
+        let Some(body) = function.body_mut() else {
+            continue;
+        };
+        for id in body.instructions() {
+            body.modify_instruction(id, |kind| {
+                kind.operands_mut()
+                    .into_iter()
+                    .try_for_each(|operand| resolve(operand, &labels))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the constant or global value with the number, which must be read already.
+fn constant(values: &[Value], id: u64) -> Result<Value, String> {
+    values
+        .get(id as usize)
+        .cloned()
+        .ok_or(format!("undefined value {}", id))
+}
+
+/// Reads an element of a `CONSTANT_DATA` record, which holds the bits of an integer or floating point.
+fn data_element(ty: &Type, bits: u64) -> Value {
+    let contents = match ty {
+        Type::Integer(1) => (bits & 1).to_string(),
+        Type::Integer(width) if *width < 64 => {
+            let shift = 64 - width;
+            (((bits << shift) as i64) >> shift).to_string()
+        }
+        Type::Integer(_) => (bits as i64).to_string(),
+        Type::Half => half_value(bits as u16).to_string(),
+        Type::Float => f32::from_bits(bits as u32).to_string(),
+        _ => f64::from_bits(bits).to_string(),
+    };
+    Value::Number(contents, ty.clone())
+}
+
+fn string(operands: &[u64]) -> String {
+    operands.iter().map(|x| *x as u8 as char).collect()
+}
+
+fn strtab(strings: &[u8], offset: u64, size: u64) -> Result<String, String> {
+    strings
+        .get(offset as usize..(offset + size) as usize)
+        .map(|x| String::from_utf8_lossy(x).into_owned())
+        .ok_or("name outside of the string table".to_string())
+}
+
+/// Decodes a calling convention, where the C calling convention is written as zero.
+fn calling_convention(convention: u64) -> Option<CallingConvention> {
+    match convention {
+        0 => None,
+        8 => Some(CallingConvention::Fast),
+        9 => Some(CallingConvention::Cold),
+        18 => Some(CallingConvention::Tail),
+        number => Some(CallingConvention::Numbered(number as u32)),
+    }
+}
+
+fn linkage(linkage: u64) -> Result<Option<LinkageType>, String> {
+    Ok(match linkage {
+        0 => None,
+        3 => Some(LinkageType::Internal),
+        9 => Some(LinkageType::Private),
+        12 => Some(LinkageType::AvailableExternally),
+        linkage => return Err(format!("unknown linkage {}", linkage)),
+    })
+}
+
+/// Decodes an alignment written as its logarithm plus one, where zero means there is none.
+fn decode_alignment(encoded: u64) -> Option<u32> {
+    (encoded != 0).then(|| 1 << (encoded - 1))
+}
+
+fn atomic_ordering(ordering: u64) -> Result<AtomicOrdering, String> {
+    Ok(match ordering {
+        1 => AtomicOrdering::Unordered,
+        2 => AtomicOrdering::Monotonic,
+        3 => AtomicOrdering::Acquire,
+        4 => AtomicOrdering::Release,
+        5 => AtomicOrdering::AcquireRelease,
+        6 => AtomicOrdering::SequentiallyConsistent,
+        _ => return Err(format!("unknown atomic ordering {}", ordering)),
+    })
+}
+
+fn atomicrmw_operator(opcode: u64) -> Result<AtomicRmwOperator, String> {
+    Ok(match opcode {
+        0 => AtomicRmwOperator::Exchange,
+        1 => AtomicRmwOperator::Add,
+        2 => AtomicRmwOperator::Sub,
+        3 => AtomicRmwOperator::And,
+        4 => AtomicRmwOperator::Nand,
+        5 => AtomicRmwOperator::Or,
+        6 => AtomicRmwOperator::Xor,
+        7 => AtomicRmwOperator::Max,
+        8 => AtomicRmwOperator::Min,
+        9 => AtomicRmwOperator::UnsignedMax,
+        10 => AtomicRmwOperator::UnsignedMin,
+        11 => AtomicRmwOperator::FloatAdd,
+        12 => AtomicRmwOperator::FloatSub,
+        13 => AtomicRmwOperator::FloatMax,
+        14 => AtomicRmwOperator::FloatMin,
+        15 => AtomicRmwOperator::UnsignedIncrementWrap,
+        16 => AtomicRmwOperator::UnsignedDecrementWrap,
+        17 => AtomicRmwOperator::UnsignedSubtractConditional,
+        18 => AtomicRmwOperator::UnsignedSubtractSaturate,
+        _ => return Err(format!("unknown atomicrmw operation {}", opcode)),
+    })
+}
+
+fn binary_operator(opcode: u64, floating: bool) -> Result<BinaryOperator, String> {
+    Ok(match (opcode, floating) {
+        (0, false) => BinaryOperator::IntegerAdd,
+        (0, true) => BinaryOperator::FloatAdd,
+        (1, false) => BinaryOperator::IntegerSub,
+        (1, true) => BinaryOperator::FloatSub,
+        (2, false) => BinaryOperator::IntegerMul,
+        (2, true) => BinaryOperator::FloatMul,
+        (3, false) => BinaryOperator::IntegerUnsignedDiv,
+        (4, false) => BinaryOperator::IntegerSignedDiv,
+        (4, true) => BinaryOperator::FloatDiv,
+        (5, false) => BinaryOperator::IntegerUnsignedRem,
+        (6, false) => BinaryOperator::IntegerSignedRem,
+        (6, true) => BinaryOperator::FloatRem,
+        (7, false) => BinaryOperator::ShiftLeft,
+        (8, false) => BinaryOperator::LogicalShiftRight,
+        (9, false) => BinaryOperator::ArithmeticShiftRight,
+        (10, false) => BinaryOperator::And,
+        (11, false) => BinaryOperator::Or,
+        (12, false) => BinaryOperator::Xor,
+        (opcode, _) => return Err(format!("unknown binary opcode {}", opcode)),
+    })
+}
+
+fn binary_flags(operator: BinaryOperator, bits: u64) -> BinaryFlags {
+    let mut flags = BinaryFlags::new();
+    if operator.is_floating_point() {
+        // the lowest bit used to mean every flag at once
+        let mut fast_math = match bits & 1 {
+            1 => FastMathFlags::fast(),
+            _ => FastMathFlags::new(),
+        };
+        let setters: [fn(FastMathFlags) -> FastMathFlags; 7] = [
+            FastMathFlags::no_nans,
+            FastMathFlags::no_infs,
+            FastMathFlags::no_signed_zeros,
+            FastMathFlags::allow_reciprocal,
+            FastMathFlags::allow_contract,
+            FastMathFlags::approximate_functions,
+            FastMathFlags::allow_reassociation,
+        ];
+        for (index, setter) in setters.iter().enumerate() {
+            if bits & (1 << (index + 1)) != 0 {
+                fast_math = setter(fast_math);
+            }
+        }
+        return match bits {
+            0 => flags,
+            _ => flags.fast_math(fast_math),
+        };
+    }
+    if operator.supports_wrap_flags() {
+        if bits & 1 != 0 {
+            flags = flags.no_unsigned_wrap();
+        }
+        if bits & 2 != 0 {
+            flags = flags.no_signed_wrap();
+        }
+    } else if bits & 1 != 0 {
+        flags = flags.exact();
+    }
+    flags
+}
+
+fn cast_operator(opcode: u64) -> Result<CastOperator, String> {
+    Ok(match opcode {
+        0 => CastOperator::Trunc,
+        1 => CastOperator::ZeroExtend,
+        2 => CastOperator::SignExtend,
+        3 => CastOperator::FloatToUnsigned,
+        4 => CastOperator::FloatToSigned,
+        5 => CastOperator::UnsignedToFloat,
+        6 => CastOperator::SignedToFloat,
+        7 => CastOperator::FloatTrunc,
+        8 => CastOperator::FloatExtend,
+        9 => CastOperator::PtrToInt,
+        10 => CastOperator::IntToPtr,
+        11 => CastOperator::BitCast,
+        12 => CastOperator::AddrSpaceCast,
+        opcode => return Err(format!("unknown cast opcode {}", opcode)),
+    })
+}
+
+/// Prints the two's complement words of an integer of the width as a signed decimal.
+fn wide_integer(mut words: Vec<u64>, width: u32) -> String {
+    let top = (width - 1) as usize;
+    let negative = words[top / 64] >> (top % 64) & 1 == 1;
+    if negative {
+        let mut carry = true;
+        for word in &mut words {
+            (*word, carry) = (!*word).overflowing_add(carry as u64);
+        }
+        if !width.is_multiple_of(64) {
+            *words.last_mut().unwrap() &= (1 << (width % 64)) - 1;
+        }
+    }
+    let mut digits = Vec::new();
+    while words.iter().any(|x| *x != 0) {
+        let mut remainder = 0u128;
+        for word in words.iter_mut().rev() {
+            let current = (remainder << 64) | *word as u128;
+            *word = (current / 10) as u64;
+            remainder = current % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+    if digits.is_empty() {
+        digits.push(b'0');
+    }
+    if negative {
+        digits.push(b'-');
+    }
+    digits.iter().rev().map(|x| *x as char).collect()
+}
@@ -0,0 +1,145 @@
+use std::vec::Vec;
+
+const END_BLOCK: u64 = 0;
+const ENTER_SUBBLOCK: u64 = 1;
+const DEFINE_ABBREV: u64 = 2;
+const UNABBREV_RECORD: u64 = 3;
+
+/// The first abbreviation identifier available to the abbreviations defined in a block.
+const FIRST_ABBREVIATION: u64 = 4;
+
+/// The encoding of a blob operand in an abbreviation.
+const ENCODING_BLOB: u64 = 5;
+
+/// Writes the bits of an LLVM bitstream.
+///
+/// Bits are packed starting from the least significant bit of each byte.
+/// Every record is written unabbreviated, except for blobs, which need an abbreviation.
+pub(crate) struct BitWriter {
+    bytes: Vec<u8>,
+    /// The bits that do not fill a byte yet.
+    pending: u64,
+    pending_bits: u32,
+    abbreviation_width: u32,
+    /// The abbreviations defined in the current block.
+    abbreviations: u64,
+    /// The abbreviation width, abbreviation count and length position of every enclosing block.
+    blocks: Vec<(u32, u64, usize)>,
+}
+
+impl BitWriter {
+    /// Creates a new writer at the top level of the stream.
+    pub(crate) fn new() -> Self {
+        BitWriter {
+            bytes: Vec::new(),
+            pending: 0,
+            pending_bits: 0,
+            abbreviation_width: 2,
+            abbreviations: 0,
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Writes the lowest bits of the value.
+    pub(crate) fn fixed(&mut self, value: u64, width: u32) {
+        self.pending |= value << self.pending_bits;
+        self.pending_bits += width;
+        while self.pending_bits >= 8 {
+            self.bytes.push(self.pending as u8);
+            self.pending >>= 8;
+            self.pending_bits -= 8;
+        }
+    }
+
+    /// Writes the value in chunks of the width, where the highest bit of each chunk
+    /// tells whether another chunk follows.
+    pub(crate) fn vbr(&mut self, mut value: u64, width: u32) {
+        let continuation = 1 << (width - 1);
+        while value >= continuation {
+            self.fixed((value & (continuation - 1)) | continuation, width);
+            value >>= width - 1;
+        }
+        self.fixed(value, width);
+    }
+
+    /// Pads the stream with zeros up to the next 32-bit word.
+    pub(crate) fn align(&mut self) {
+        let position = self.bytes.len() * 8 + self.pending_bits as usize;
+        let padding = (32 - position % 32) % 32;
+        self.fixed(0, padding as u32 % 8);
+        self.bytes.extend(std::iter::repeat_n(0, padding / 8));
+    }
+
+    /// Enters a new block, whose records use abbreviation identifiers of the given width.
+    pub(crate) fn enter_block(&mut self, id: u64, abbreviation_width: u32) {
+        self.fixed(ENTER_SUBBLOCK, self.abbreviation_width);
+        self.vbr(id, 8);
+        self.vbr(abbreviation_width as u64, 4);
+        self.align();
+        // the length of the block is filled in once it ends
+        self.blocks.push((
+            self.abbreviation_width,
+            self.abbreviations,
+            self.bytes.len(),
+        ));
+        self.bytes.extend([0; 4]);
+        self.abbreviation_width = abbreviation_width;
+        self.abbreviations = 0;
+    }
+
+    /// Ends the innermost block.
+    pub(crate) fn end_block(&mut self) {
+        self.fixed(END_BLOCK, self.abbreviation_width);
+        self.align();
+        let (width, abbreviations, start) = self.blocks.pop().expect("no block to end");
+        let words = ((self.bytes.len() - start - 4) / 4) as u32;
+        self.bytes[start..start + 4].copy_from_slice(&words.to_le_bytes());
+        self.abbreviation_width = width;
+        self.abbreviations = abbreviations;
+    }
+
+    /// Writes an unabbreviated record.
+    pub(crate) fn record(&mut self, code: u64, operands: &[u64]) {
+        self.fixed(UNABBREV_RECORD, self.abbreviation_width);
+        self.vbr(code, 6);
+        self.vbr(operands.len() as u64, 6);
+        for operand in operands {
+            self.vbr(*operand, 6);
+        }
+    }
+
+    /// Writes an unabbreviated record, with a string as its operands.
+    pub(crate) fn string_record(&mut self, code: u64, string: &[u8]) {
+        let operands = string.iter().map(|x| *x as u64).collect::<Vec<_>>();
+        self.record(code, &operands);
+    }
+
+    /// Defines an abbreviation for records of the code holding a single blob,
+    /// and returns its identifier.
+    pub(crate) fn define_blob_abbreviation(&mut self, code: u64) -> u64 {
+        self.fixed(DEFINE_ABBREV, self.abbreviation_width);
+        self.vbr(2, 5);
+        // the code is a literal, followed by the encoding of the blob
+        self.fixed(1, 1);
+        self.vbr(code, 8);
+        self.fixed(0, 1);
+        self.fixed(ENCODING_BLOB, 3);
+        self.abbreviations += 1;
+        FIRST_ABBREVIATION + self.abbreviations - 1
+    }
+
+    /// Writes a record holding a single blob, using an abbreviation defined for it.
+    pub(crate) fn blob_record(&mut self, abbreviation: u64, blob: &[u8]) {
+        self.fixed(abbreviation, self.abbreviation_width);
+        self.vbr(blob.len() as u64, 6);
+        self.align();
+        self.bytes.extend_from_slice(blob);
+        self.align();
+    }
+
+    /// Returns the bytes of the stream, which ends padded to a 32-bit word.
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
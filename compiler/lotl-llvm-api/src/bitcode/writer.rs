@@ -0,0 +1,1418 @@
+use crate::bitcode::stream::BitWriter;
+use crate::bitcode::*;
+use crate::instruction::{
+    AtomicOrdering, AtomicRmwOperator, BinaryFlags, BinaryOperator, BlockId, CastOperator,
+    InstructionKind, LandingPadClause, SyncScope, TailMarker,
+};
+use crate::module::{
+    CallingConvention, FunctionAttribute, FunctionBody, GlobalFunction, LinkageType, Module,
+    ParameterAttribute, ThreadLocalMode, UnnamedAddr, Visibility,
+};
+use crate::types::{NamedType, Type, Types};
+use crate::value::{float_value, half_bits, quad_bits, Value};
+use crate::IRComponent;
+use std::collections::HashMap;
+use std::format;
+use std::io::{Error, ErrorKind, Result};
+use std::string::String;
+use std::vec::Vec;
+
+/// Writes the module as a complete bitcode file.
+pub(crate) fn write_module(module: &Module) -> Result<Vec<u8>> {
+    let declarations = module.missing_intrinsics();
+    if !declarations.is_empty() {
+        let mut module = module.clone();
+        module.functions.extend(declarations);
+        return write_module(&module);
+    }
+    let mut stream = BitWriter::new();
+    for byte in *b"BC\xc0\xde" {
+        stream.fixed(byte as u64, 8);
+    }
+    stream.enter_block(IDENTIFICATION_BLOCK, 3);
+    stream.string_record(IDENTIFICATION_STRING, b"lotl-llvm-api");
+    stream.record(IDENTIFICATION_EPOCH, &[0]);
+    stream.end_block();
+
+    let mut writer = ModuleWriter::new(module, stream);
+    writer.write()?;
+    let (mut stream, strings) = (writer.stream, writer.strings);
+    stream.enter_block(STRTAB_BLOCK, 3);
+    let abbreviation = stream.define_blob_abbreviation(STRTAB_BLOB);
+    stream.blob_record(abbreviation, &strings);
+    stream.end_block();
+    Ok(stream.finish())
+}
+
+fn undefined(value: &Value) -> Error {
+    let mut name = String::new();
+    value.append_to_string_untyped(&mut name);
+    Error::new(ErrorKind::InvalidInput, format!("undefined value {}", name))
+}
+
+/// Numbers every type the module uses, so records can refer to them.
+///
+/// Types are numbered after the types they contain, except for named structures,
+/// which bitcode allows to be referred to before they are defined.
+#[derive(Default)]
+struct TypeTable {
+    types: Vec<Type>,
+    ids: HashMap<String, u64>,
+    /// Named structures referred to before they are numbered.
+    deferred: Vec<NamedType>,
+}
+
+impl TypeTable {
+    /// Numbers every type used by the module.
+    fn new(module: &Module) -> Self {
+        let mut table = TypeTable::default();
+        // named types are numbered first, so they are read back in the same order
+        for named in &module.types {
+            table.define(named);
+        }
+        if !module.vars.is_empty() || !module.functions.is_empty() {
+            table.enumerate(&Types::pointer(0));
+        }
+        for var in &module.vars {
+            table.enumerate(var.ty());
+            var.value().iter().for_each(|x| table.enumerate_value(x));
+        }
+        for function in &module.functions {
+            table.enumerate(&function.signature());
+            for parameter in &function.parameters {
+                for attribute in &parameter.attributes {
+                    if let ParameterAttribute::StructRet(ty) | ParameterAttribute::ByVal(ty) =
+                        attribute
+                    {
+                        table.enumerate(ty);
+                    }
+                }
+            }
+            function
+                .get_body()
+                .iter()
+                .for_each(|x| table.enumerate_body(x));
+        }
+        while let Some(named) = table.deferred.pop() {
+            table.define(&named);
+        }
+        table
+    }
+
+    fn enumerate_body(&mut self, body: &FunctionBody) {
+        for id in body.instructions() {
+            let instruction = body.instruction(id);
+            for operand in instruction.kind().operands() {
+                self.enumerate_value(operand);
+            }
+            instruction
+                .result()
+                .iter()
+                .for_each(|x| self.enumerate(x.ty()));
+            match instruction.kind() {
+                InstructionKind::Alloca { ty, .. } => {
+                    self.enumerate(ty);
+                    self.enumerate(&Types::integer(32));
+                }
+                InstructionKind::Load { ty, .. }
+                | InstructionKind::GetElementPtr { ty, .. }
+                | InstructionKind::Cast { target: ty, .. }
+                | InstructionKind::Phi { ty, .. }
+                | InstructionKind::Call { signature: ty, .. }
+                | InstructionKind::Invoke { signature: ty, .. }
+                | InstructionKind::LandingPad { ty, .. } => self.enumerate(ty),
+                _ => {}
+            }
+        }
+    }
+
+    fn enumerate_value(&mut self, value: &Value) {
+        self.enumerate(value.ty());
+        match value {
+            Value::Structure(elements, _)
+            | Value::Array(elements, _)
+            | Value::Vector(elements, _) => elements.iter().for_each(|x| self.enumerate_value(x)),
+            Value::GetElementPtr(ty, base, indices, _) => {
+                self.enumerate(ty);
+                self.enumerate_value(base);
+                indices.iter().for_each(|x| self.enumerate_value(x));
+            }
+            Value::Cast(_, value, _) => self.enumerate_value(value),
+            Value::InlineAsm(asm) => self.enumerate(asm.signature()),
+            _ => {}
+        }
+    }
+
+    fn enumerate(&mut self, ty: &Type) {
+        let key = ty.emit();
+        if self.ids.contains_key(&key) {
+            return;
+        }
+        match ty {
+            Type::Named(named) => match named.body() {
+                Some(Type::Structure(_) | Type::PackedStructure(_)) | None => {
+                    self.deferred.push(named.clone())
+                }
+                // only structures can be named in bitcode, other named types are written as their body
+                Some(body) => {
+                    self.enumerate(&body);
+                    let id = self.ids[&body.emit()];
+                    self.ids.insert(key, id);
+                }
+            },
+            Type::Array(_, element)
+            | Type::Vector(_, element)
+            | Type::ScalableVector(_, element) => {
+                self.enumerate(element);
+                self.push(key, ty);
+            }
+            Type::Structure(fields) | Type::PackedStructure(fields) => {
+                fields.iter().for_each(|x| self.enumerate(x));
+                self.push(key, ty);
+            }
+            Type::Function(return_type, parameters, _) => {
+                self.enumerate(return_type);
+                parameters.iter().for_each(|x| self.enumerate(x));
+                self.push(key, ty);
+            }
+            _ => self.push(key, ty),
+        }
+    }
+
+    /// Numbers a named type after the types it contains.
+    fn define(&mut self, named: &NamedType) {
+        let ty = Type::Named(named.clone());
+        let key = ty.emit();
+        if self.ids.contains_key(&key) {
+            return;
+        }
+        match named.body() {
+            Some(Type::Structure(fields) | Type::PackedStructure(fields)) => {
+                fields.iter().for_each(|x| self.enumerate(x));
+                self.push(key, &ty);
+            }
+            Some(_) => self.enumerate(&ty),
+            None => self.push(key, &ty),
+        }
+    }
+
+    fn push(&mut self, key: String, ty: &Type) {
+        self.ids.insert(key, self.types.len() as u64);
+        self.types.push(ty.clone());
+    }
+
+    fn id(&self, ty: &Type) -> u64 {
+        self.ids[&ty.emit()]
+    }
+
+    fn write(&self, stream: &mut BitWriter) {
+        stream.enter_block(TYPE_BLOCK, 4);
+        stream.record(TYPE_NUMENTRY, &[self.types.len() as u64]);
+        for ty in &self.types {
+            match ty {
+                Type::Integer(width) => stream.record(TYPE_INTEGER, &[*width as u64]),
+                Type::Half => stream.record(TYPE_HALF, &[]),
+                Type::Float => stream.record(TYPE_FLOAT, &[]),
+                Type::Double => stream.record(TYPE_DOUBLE, &[]),
+                Type::FP128 => stream.record(TYPE_FP128, &[]),
+                Type::Void => stream.record(TYPE_VOID, &[]),
+                Type::Ptr => stream.record(TYPE_OPAQUE_POINTER, &[0]),
+                Type::AddressSpacePtr(address_space) => {
+                    stream.record(TYPE_OPAQUE_POINTER, &[*address_space as u64])
+                }
+                Type::Array(length, element) => {
+                    stream.record(TYPE_ARRAY, &[*length as u64, self.id(element)])
+                }
+                Type::Vector(length, element) => {
+                    stream.record(TYPE_VECTOR, &[*length as u64, self.id(element)])
+                }
+                Type::ScalableVector(length, element) => {
+                    stream.record(TYPE_VECTOR, &[*length as u64, self.id(element), 1])
+                }
+                Type::Structure(fields) => {
+                    stream.record(TYPE_STRUCT_ANON, &self.fields(false, fields))
+                }
+                Type::PackedStructure(fields) => {
+                    stream.record(TYPE_STRUCT_ANON, &self.fields(true, fields))
+                }
+                Type::Function(return_type, parameters, variadic) => {
+                    let mut operands = Vec::from([*variadic as u64, self.id(return_type)]);
+                    operands.extend(parameters.iter().map(|x| self.id(x)));
+                    stream.record(TYPE_FUNCTION, &operands);
+                }
+                Type::Named(named) => {
+                    stream.string_record(TYPE_STRUCT_NAME, named.name().as_bytes());
+                    match named.body() {
+                        Some(Type::Structure(fields)) => {
+                            stream.record(TYPE_STRUCT_NAMED, &self.fields(false, &fields))
+                        }
+                        Some(Type::PackedStructure(fields)) => {
+                            stream.record(TYPE_STRUCT_NAMED, &self.fields(true, &fields))
+                        }
+                        _ => stream.record(TYPE_OPAQUE, &[0]),
+                    }
+                }
+            }
+        }
+        stream.end_block();
+    }
+
+    fn fields(&self, packed: bool, fields: &[Type]) -> Vec<u64> {
+        let mut operands = Vec::from([packed as u64]);
+        operands.extend(fields.iter().map(|x| self.id(x)));
+        operands
+    }
+}
+
+/// The constants of a module or function, numbered after the elements of aggregates.
+struct Constants {
+    values: Vec<Value>,
+    ids: HashMap<String, u64>,
+    /// The number of the first constant.
+    first: u64,
+}
+
+impl Constants {
+    fn new(first: u64) -> Self {
+        Constants {
+            values: Vec::new(),
+            ids: HashMap::new(),
+            first,
+        }
+    }
+
+    /// Numbers the value if it is a constant that is not numbered yet, here or in the module.
+    fn add(&mut self, value: &Value, module: Option<&Constants>) {
+        if matches!(
+            value,
+            Value::GlobalIdentifier(..) | Value::Function(..) | Value::LocalIdentifier(..)
+        ) {
+            return;
+        }
+        let key = constant_key(value);
+        if self.ids.contains_key(&key) || module.is_some_and(|x| x.ids.contains_key(&key)) {
+            return;
+        }
+        // operands are numbered first, as constants are written in order
+        match value {
+            Value::Structure(elements, _)
+            | Value::Array(elements, _)
+            | Value::Vector(elements, _) => elements.iter().for_each(|x| self.add(x, module)),
+            Value::GetElementPtr(_, base, indices, _) => {
+                self.add(base, module);
+                indices.iter().for_each(|x| self.add(x, module));
+            }
+            Value::Cast(_, value, _) => self.add(value, module),
+            _ => {}
+        }
+        self.ids.insert(key, self.first + self.values.len() as u64);
+        self.values.push(value.clone());
+    }
+
+    fn id(&self, value: &Value) -> Option<u64> {
+        self.ids.get(&constant_key(value)).copied()
+    }
+}
+
+/// Returns the key a constant is numbered by, which is its text
+/// along with the signature of inline assembly, as its text does not hold it.
+fn constant_key(value: &Value) -> String {
+    match value {
+        Value::InlineAsm(asm) => format!("{} {}", asm.signature().emit(), value.emit()),
+        _ => value.emit(),
+    }
+}
+
+/// Writes the module block, collecting the names of global values into a string table.
+struct ModuleWriter<'m> {
+    module: &'m Module,
+    stream: BitWriter,
+    strings: Vec<u8>,
+    types: TypeTable,
+    /// The numbers of global variables and functions, by name.
+    globals: HashMap<String, u64>,
+    constants: Constants,
+    /// The names of the sync scopes defined by the target, in the order they are numbered.
+    sync_scopes: Vec<String>,
+}
+
+impl<'m> ModuleWriter<'m> {
+    fn new(module: &'m Module, stream: BitWriter) -> Self {
+        let globals = module
+            .vars
+            .iter()
+            .map(|x| x.name())
+            .chain(module.functions.iter().map(|x| x.name()))
+            .enumerate()
+            .map(|(index, name)| (String::from(name), index as u64))
+            .collect::<HashMap<_, _>>();
+        let mut constants = Constants::new(globals.len() as u64);
+        for value in module.vars.iter().filter_map(|x| x.value()) {
+            constants.add(value, None);
+        }
+        let mut sync_scopes = Vec::new();
+        for body in module.functions.iter().filter_map(|x| x.get_body()) {
+            for id in body.instructions() {
+                let scope = match body.instruction(id).kind() {
+                    InstructionKind::Load { options, .. }
+                    | InstructionKind::Store { options, .. }
+                    | InstructionKind::AtomicRmw { options, .. }
+                    | InstructionKind::CompareExchange { options, .. } => &options.sync_scope,
+                    InstructionKind::Fence { sync_scope, .. } => sync_scope,
+                    _ => continue,
+                };
+                match scope {
+                    SyncScope::Target(name) if !sync_scopes.contains(name) => {
+                        sync_scopes.push(name.clone())
+                    }
+                    _ => {}
+                }
+            }
+        }
+        ModuleWriter {
+            module,
+            stream,
+            strings: Vec::new(),
+            types: TypeTable::new(module),
+            globals,
+            constants,
+            sync_scopes,
+        }
+    }
+
+    fn write(&mut self) -> Result<()> {
+        let module = self.module;
+        self.stream.enter_block(MODULE_BLOCK, 3);
+        // version 2 uses relative operands, and takes the names of globals from the string table
+        self.stream.record(MODULE_VERSION, &[2]);
+        self.types.write(&mut self.stream);
+        let attributes = self.write_attributes();
+        if let Some(triple) = &module.target_triple {
+            self.stream.string_record(MODULE_TRIPLE, triple.as_bytes());
+        }
+        if let Some(layout) = &module.data_layout {
+            self.stream
+                .string_record(MODULE_DATALAYOUT, layout.as_bytes());
+        }
+        if !module.module_asm.is_empty() {
+            // every line of the assembly ends with a newline, as in LLVM
+            let asm = module
+                .module_asm
+                .iter()
+                .flat_map(|x| x.bytes().chain([b'\n']))
+                .collect::<Vec<_>>();
+            self.stream.string_record(MODULE_ASM, &asm);
+        }
+        if let Some(name) = &module.source_filename {
+            self.stream
+                .string_record(MODULE_SOURCE_FILENAME, name.as_bytes());
+        }
+
+        let mut sections = Vec::new();
+        let var_sections = module.vars.iter().filter_map(|x| x.section.as_ref());
+        let function_sections = module.functions.iter().filter_map(|x| x.section.as_ref());
+        for section in var_sections.chain(function_sections) {
+            if !sections.contains(section) {
+                self.stream
+                    .string_record(MODULE_SECTIONNAME, section.as_bytes());
+                sections.push(section.clone());
+            }
+        }
+        for var in &module.vars {
+            let (offset, size) = self.add_string(var.name());
+            let initializer = match var.value() {
+                Some(value) => self.value_id(value, None)? + 1,
+                None => 0,
+            };
+            let section = var.section.as_ref().map_or(0, |x| {
+                sections.iter().position(|y| y == x).unwrap() as u64 + 1
+            });
+            let linkage = linkage(&var.linkage);
+            self.stream.record(
+                MODULE_GLOBALVAR,
+                &[
+                    offset,
+                    size,
+                    self.types.id(var.ty()),
+                    // the type is the type of the value, not the pointer to it
+                    2 | var.is_constant() as u64,
+                    initializer,
+                    linkage,
+                    encode_alignment(var.alignment),
+                    section,
+                    0,
+                    thread_local(&var.thread_local),
+                    unnamed_addr(&var.unnamed_addr),
+                    0,
+                    0,
+                    0,
+                    0,
+                    is_local(linkage) as u64,
+                ],
+            );
+        }
+        for (function, attributes) in module.functions.iter().zip(attributes) {
+            let (offset, size) = self.add_string(function.name());
+            let section = function.section.as_ref().map_or(0, |x| {
+                sections.iter().position(|y| y == x).unwrap() as u64 + 1
+            });
+            let personality = match &function.personality {
+                Some(value) => self.value_id(value, None)? + 1,
+                None => 0,
+            };
+            self.stream.record(
+                MODULE_FUNCTION,
+                &[
+                    offset,
+                    size,
+                    self.types.id(&function.signature()),
+                    function
+                        .calling_convention
+                        .as_ref()
+                        .map_or(0, calling_convention),
+                    function.get_body().is_none() as u64,
+                    linkage(&function.linkage),
+                    attributes,
+                    0,
+                    section,
+                    visibility(&function.visibility),
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    personality,
+                    (function.dso_local || function.is_implicitly_dso_local()) as u64,
+                    0,
+                ],
+            );
+        }
+
+        if !self.constants.values.is_empty() {
+            // aggregates find their elements among the constants they are written with
+            let constants = std::mem::replace(&mut self.constants, Constants::new(0));
+            let written = self.write_constants(&constants);
+            self.constants = constants;
+            written?;
+        }
+        if !self.sync_scopes.is_empty() {
+            self.write_sync_scopes();
+        }
+        for function in &module.functions {
+            if let Some(body) = function.get_body() {
+                FunctionWriter::new(self, function, body).write()?;
+            }
+        }
+        self.stream.end_block();
+        Ok(())
+    }
+
+    /// Writes the names of every sync scope, including the ones every module knows,
+    /// so the scopes defined by the target are numbered after them.
+    fn write_sync_scopes(&mut self) {
+        self.stream.enter_block(SYNC_SCOPE_NAMES_BLOCK, 3);
+        let known = [String::from("singlethread"), String::new()];
+        for name in known.iter().chain(&self.sync_scopes) {
+            self.stream.string_record(SYNC_SCOPE_NAME, name.as_bytes());
+        }
+        self.stream.end_block();
+    }
+
+    fn sync_scope_id(&self, scope: &SyncScope) -> u64 {
+        match scope {
+            SyncScope::System => SYNC_SCOPE_SYSTEM,
+            SyncScope::SingleThread => SYNC_SCOPE_SINGLE_THREAD,
+            SyncScope::Target(name) => {
+                let position = self.sync_scopes.iter().position(|x| x == name).unwrap();
+                SYNC_SCOPE_SYSTEM + 1 + position as u64
+            }
+        }
+    }
+
+    /// Writes the attributes of every function and its parameters, and returns the number of the
+    /// attribute list of every function, where zero means it has no attributes.
+    fn write_attributes(&mut self) -> Vec<u64> {
+        // every group holds the attributes of one place, along with their encoded operands
+        let mut groups: Vec<(u64, Vec<u64>)> = Vec::new();
+        let mut lists: Vec<Vec<u64>> = Vec::new();
+        let mut functions = Vec::new();
+        for function in &self.module.functions {
+            let mut places = Vec::new();
+            if !function.attributes.is_empty() {
+                let operands = function.attributes.iter().flat_map(|x| {
+                    let kind = match x {
+                        FunctionAttribute::NoUnwind => ATTRIBUTE_NO_UNWIND,
+                        FunctionAttribute::NoInline => ATTRIBUTE_NO_INLINE,
+                        FunctionAttribute::AlwaysInline => ATTRIBUTE_ALWAYS_INLINE,
+                        FunctionAttribute::Cold => ATTRIBUTE_COLD,
+                        FunctionAttribute::NoReturn => ATTRIBUTE_NO_RETURN,
+                    };
+                    [0, kind]
+                });
+                places.push((ATTRIBUTE_FUNCTION_INDEX, operands.collect()));
+            }
+            for (index, parameter) in function.parameters.iter().enumerate() {
+                if parameter.attributes.is_empty() {
+                    continue;
+                }
+                let mut operands = Vec::new();
+                for attribute in &parameter.attributes {
+                    // kinds without a value are written as 0, kinds with a type as 6
+                    match attribute {
+                        ParameterAttribute::NoUndef => operands.extend([0, ATTRIBUTE_NO_UNDEF]),
+                        ParameterAttribute::NonNull => operands.extend([0, ATTRIBUTE_NON_NULL]),
+                        ParameterAttribute::StructRet(ty) => {
+                            operands.extend([6, ATTRIBUTE_STRUCT_RET, self.types.id(ty)])
+                        }
+                        ParameterAttribute::ByVal(ty) => {
+                            operands.extend([6, ATTRIBUTE_BY_VAL, self.types.id(ty)])
+                        }
+                    }
+                }
+                // attribute indices start at one for parameters, zero is the return value
+                places.push((index as u64 + 1, operands));
+            }
+            let mut list = Vec::new();
+            for group in places {
+                let id = match groups.iter().position(|x| *x == group) {
+                    Some(position) => position,
+                    None => {
+                        groups.push(group);
+                        groups.len() - 1
+                    }
+                };
+                list.push(id as u64 + 1);
+            }
+            functions.push(match list.is_empty() {
+                true => 0,
+                false => match lists.iter().position(|x| *x == list) {
+                    Some(position) => position as u64 + 1,
+                    None => {
+                        lists.push(list);
+                        lists.len() as u64
+                    }
+                },
+            });
+        }
+        if groups.is_empty() {
+            return functions;
+        }
+
+        self.stream.enter_block(PARAMATTR_GROUP_BLOCK, 3);
+        for (id, (index, attributes)) in groups.iter().enumerate() {
+            let mut operands = Vec::from([id as u64 + 1, *index]);
+            operands.extend(attributes);
+            self.stream.record(PARAMATTR_GROUP_ENTRY, &operands);
+        }
+        self.stream.end_block();
+        self.stream.enter_block(PARAMATTR_BLOCK, 3);
+        for list in &lists {
+            self.stream.record(PARAMATTR_ENTRY, list);
+        }
+        self.stream.end_block();
+        functions
+    }
+
+    /// Writes a constants block, where values are only numbered once they have been written.
+    fn write_constants(&mut self, constants: &Constants) -> Result<()> {
+        self.stream.enter_block(CONSTANTS_BLOCK, 4);
+        let mut current = None;
+        for value in &constants.values {
+            let ty = self.types.id(value.ty());
+            if current != Some(ty) {
+                self.stream.record(CONSTANT_SETTYPE, &[ty]);
+                current = Some(ty);
+            }
+            match value {
+                Value::Number(contents, Type::Integer(width)) => {
+                    let words = integer_words(contents, *width).ok_or_else(|| invalid(value))?;
+                    match *width <= 64 {
+                        true => {
+                            let shift = 64 - *width;
+                            let extended = ((words[0] << shift) as i64) >> shift;
+                            self.stream
+                                .record(CONSTANT_INTEGER, &[encode_signed(extended)]);
+                        }
+                        false => {
+                            let words = words
+                                .iter()
+                                .map(|x| encode_signed(*x as i64))
+                                .collect::<Vec<_>>();
+                            self.stream.record(CONSTANT_WIDE_INTEGER, &words);
+                        }
+                    }
+                }
+                Value::Number(contents, ty) => {
+                    let number = float_value(contents).ok_or_else(|| invalid(value))?;
+                    let operands = match ty {
+                        Type::Half => Vec::from([half_bits(number as f32)]),
+                        Type::Float => Vec::from([(number as f32).to_bits() as u64]),
+                        Type::FP128 => {
+                            let bits = quad_bits(number);
+                            Vec::from([bits as u64, (bits >> 64) as u64])
+                        }
+                        _ => Vec::from([number.to_bits()]),
+                    };
+                    self.stream.record(CONSTANT_FLOAT, &operands);
+                }
+                Value::ZeroInitializer(_) | Value::Null(_) => {
+                    self.stream.record(CONSTANT_NULL, &[])
+                }
+                Value::Undef(_) => self.stream.record(CONSTANT_UNDEF, &[]),
+                Value::Poison(_) => self.stream.record(CONSTANT_POISON, &[]),
+                Value::GetElementPtr(ty, base, indices, inbounds) => {
+                    // an odd number of operands starts with the type the indices step into
+                    let mut operands = Vec::from([self.types.id(ty)]);
+                    for operand in [&**base].into_iter().chain(indices) {
+                        operands.push(self.types.id(operand.ty()));
+                        operands.push(self.value_id(operand, Some(constants))?);
+                    }
+                    let code = match inbounds {
+                        true => CONSTANT_CE_INBOUNDS_GEP,
+                        false => CONSTANT_CE_GEP,
+                    };
+                    self.stream.record(code, &operands);
+                }
+                Value::Cast(operator, value, _) => {
+                    let operands = [
+                        cast_opcode(*operator),
+                        self.types.id(value.ty()),
+                        self.value_id(value, Some(constants))?,
+                    ];
+                    self.stream.record(CONSTANT_CE_CAST, &operands);
+                }
+                Value::InlineAsm(asm) => {
+                    let flags = asm.side_effects as u64
+                        | (asm.align_stack as u64) << 1
+                        | (asm.intel_dialect as u64) << 2
+                        | (asm.unwind as u64) << 3;
+                    let mut operands = Vec::from([self.types.id(asm.signature()), flags]);
+                    for text in [&asm.template, &asm.constraints] {
+                        operands.push(text.len() as u64);
+                        operands.extend(text.bytes().map(u64::from));
+                    }
+                    self.stream.record(CONSTANT_INLINEASM, &operands);
+                }
+                Value::Structure(elements, _)
+                | Value::Array(elements, _)
+                | Value::Vector(elements, _) => {
+                    let operands = elements
+                        .iter()
+                        .map(|x| self.value_id(x, Some(constants)))
+                        .collect::<Result<Vec<_>>>()?;
+                    self.stream.record(CONSTANT_AGGREGATE, &operands);
+                }
+                Value::CString(bytes, _) => match bytes.split_last() {
+                    // strings ending in their only null byte are written without it
+                    Some((0, contents)) if !contents.contains(&0) => {
+                        self.stream.string_record(CONSTANT_CSTRING, contents)
+                    }
+                    _ => self.stream.string_record(CONSTANT_STRING, bytes),
+                },
+                Value::BlockAddress(function, label) => {
+                    let block = self
+                        .module
+                        .functions
+                        .iter()
+                        .find(|x| x.name() == function)
+                        .and_then(|x| x.get_body())
+                        .and_then(|x| {
+                            x.blocks()
+                                .into_iter()
+                                .position(|y| x.block(y).label() == label)
+                        })
+                        .ok_or_else(|| undefined(value))?;
+                    let operands = [
+                        self.types.id(&Types::pointer(0)),
+                        self.globals
+                            .get(function)
+                            .copied()
+                            .ok_or_else(|| undefined(value))?,
+                        block as u64,
+                    ];
+                    self.stream.record(CONSTANT_BLOCKADDRESS, &operands);
+                }
+                _ => return Err(undefined(value)),
+            }
+        }
+        self.stream.end_block();
+        Ok(())
+    }
+
+    /// Returns the number of a global value or constant, looking at the constants
+    /// of a function before the constants of the module.
+    fn value_id(&self, value: &Value, locals: Option<&Constants>) -> Result<u64> {
+        let id = match value {
+            Value::GlobalIdentifier(name, _) | Value::Function(name, _) => {
+                self.globals.get(name).copied()
+            }
+            Value::LocalIdentifier(..) => None,
+            _ => locals
+                .and_then(|x| x.id(value))
+                .or_else(|| self.constants.id(value)),
+        };
+        id.ok_or_else(|| undefined(value))
+    }
+
+    /// Adds the name to the string table, and returns its offset and size.
+    fn add_string(&mut self, name: &str) -> (u64, u64) {
+        let offset = self.strings.len() as u64;
+        self.strings.extend_from_slice(name.as_bytes());
+        (offset, name.len() as u64)
+    }
+}
+
+/// Writes the block of a function body, numbering its values after the values of the module.
+struct FunctionWriter<'w, 'm> {
+    module: &'w mut ModuleWriter<'m>,
+    body: &'m FunctionBody,
+    constants: Constants,
+    /// The numbers of the parameters and instruction results, by name.
+    locals: HashMap<String, u64>,
+    /// The position of every block in the layout.
+    blocks: HashMap<BlockId, u64>,
+    /// The number the next instruction result gets, which operands are relative to.
+    next: u64,
+}
+
+impl<'w, 'm> FunctionWriter<'w, 'm> {
+    fn new(
+        module: &'w mut ModuleWriter<'m>,
+        function: &'m GlobalFunction,
+        body: &'m FunctionBody,
+    ) -> Self {
+        let first = module.globals.len() as u64 + module.constants.values.len() as u64;
+        let arguments = function.arguments();
+        let mut constants = Constants::new(first + arguments.len() as u64);
+        let one = Value::Number(String::from("1"), Types::integer(32));
+        for id in body.instructions() {
+            let kind = body.instruction(id).kind();
+            if let InstructionKind::Alloca { .. } = kind {
+                constants.add(&one, Some(&module.constants));
+            }
+            for operand in kind.operands() {
+                constants.add(operand, Some(&module.constants));
+            }
+        }
+        let mut locals = HashMap::new();
+        for (index, argument) in arguments.iter().enumerate() {
+            locals.insert(local_name(argument), first + index as u64);
+        }
+        let next = constants.first + constants.values.len() as u64;
+        let results = body
+            .instructions()
+            .into_iter()
+            .filter_map(|x| body.instruction(x).result());
+        for (index, result) in results.enumerate() {
+            locals.insert(local_name(result), next + index as u64);
+        }
+        let blocks = body
+            .blocks()
+            .into_iter()
+            .enumerate()
+            .map(|(index, id)| (id, index as u64))
+            .collect();
+        FunctionWriter {
+            module,
+            body,
+            constants,
+            locals,
+            blocks,
+            next,
+        }
+    }
+
+    fn write(mut self) -> Result<()> {
+        let stream = &mut self.module.stream;
+        stream.enter_block(FUNCTION_BLOCK, 4);
+        stream.record(FUNCTION_DECLAREBLOCKS, &[self.blocks.len() as u64]);
+        if !self.constants.values.is_empty() {
+            self.module.write_constants(&self.constants)?;
+        }
+        for block in self.body.blocks() {
+            for id in self.body.block(block).instructions() {
+                let instruction = self.body.instruction(*id);
+                if let Some((code, operands)) = self.instruction(instruction.kind())? {
+                    self.module.stream.record(code, &operands);
+                }
+                if instruction.result().is_some() {
+                    self.next += 1;
+                }
+            }
+        }
+        self.write_symbols();
+        self.module.stream.end_block();
+        Ok(())
+    }
+
+    /// Returns the code and operands of the record of an instruction,
+    /// or nothing if the instruction is left out of bitcode.
+    fn instruction(&self, kind: &InstructionKind) -> Result<Option<(u64, Vec<u64>)>> {
+        let mut operands = Vec::new();
+        let code = match kind {
+            InstructionKind::Binary {
+                operator,
+                flags,
+                lhs,
+                rhs,
+            } => {
+                self.push_value_and_type(&mut operands, lhs)?;
+                self.push_value(&mut operands, rhs)?;
+                operands.push(binary_opcode(*operator));
+                let flags = binary_flags(*operator, flags);
+                if flags != 0 {
+                    operands.push(flags);
+                }
+                INST_BINOP
+            }
+            InstructionKind::FloatNegate { value } => {
+                self.push_value_and_type(&mut operands, value)?;
+                operands.push(0);
+                INST_UNOP
+            }
+            InstructionKind::Cast {
+                operator,
+                value,
+                target,
+            } => {
+                self.push_value_and_type(&mut operands, value)?;
+                operands.push(self.module.types.id(target));
+                operands.push(cast_opcode(*operator));
+                INST_CAST
+            }
+            InstructionKind::Alloca { ty, alignment } => {
+                let size = Value::Number(String::from("1"), Types::integer(32));
+                let alignment = encode_alignment(*alignment);
+                operands.push(self.module.types.id(ty));
+                operands.push(self.module.types.id(size.ty()));
+                operands.push(self.value_id(&size)?);
+                operands.push(
+                    (alignment & 31)
+                        | ALLOCA_EXPLICIT_TYPE
+                        | (alignment >> 5) << ALLOCA_ALIGNMENT_UPPER,
+                );
+                INST_ALLOCA
+            }
+            InstructionKind::Load {
+                ty,
+                pointer,
+                ordering,
+                options,
+            } => {
+                self.push_value_and_type(&mut operands, pointer)?;
+                operands.push(self.module.types.id(ty));
+                operands.push(encode_alignment(options.alignment));
+                operands.push(options.volatile as u64);
+                match ordering {
+                    Some(ordering) => {
+                        operands.push(atomic_ordering(*ordering));
+                        operands.push(self.module.sync_scope_id(&options.sync_scope));
+                        INST_LOADATOMIC
+                    }
+                    None => INST_LOAD,
+                }
+            }
+            InstructionKind::Store {
+                value,
+                pointer,
+                ordering,
+                options,
+            } => {
+                self.push_value_and_type(&mut operands, pointer)?;
+                self.push_value_and_type(&mut operands, value)?;
+                operands.push(encode_alignment(options.alignment));
+                operands.push(options.volatile as u64);
+                match ordering {
+                    Some(ordering) => {
+                        operands.push(atomic_ordering(*ordering));
+                        operands.push(self.module.sync_scope_id(&options.sync_scope));
+                        INST_STOREATOMIC
+                    }
+                    None => INST_STORE,
+                }
+            }
+            InstructionKind::AtomicRmw {
+                operator,
+                pointer,
+                value,
+                ordering,
+                options,
+            } => {
+                self.push_value_and_type(&mut operands, pointer)?;
+                self.push_value_and_type(&mut operands, value)?;
+                operands.push(atomicrmw_opcode(*operator));
+                operands.push(options.volatile as u64);
+                operands.push(atomic_ordering(*ordering));
+                operands.push(self.module.sync_scope_id(&options.sync_scope));
+                operands.push(encode_alignment(options.alignment));
+                INST_ATOMICRMW
+            }
+            InstructionKind::CompareExchange {
+                pointer,
+                expected,
+                replacement,
+                success,
+                failure,
+                weak,
+                options,
+            } => {
+                self.push_value_and_type(&mut operands, pointer)?;
+                self.push_value_and_type(&mut operands, expected)?;
+                self.push_value(&mut operands, replacement)?;
+                operands.push(options.volatile as u64);
+                operands.push(atomic_ordering(*success));
+                operands.push(self.module.sync_scope_id(&options.sync_scope));
+                operands.push(atomic_ordering(*failure));
+                operands.push(*weak as u64);
+                operands.push(encode_alignment(options.alignment));
+                INST_CMPXCHG
+            }
+            InstructionKind::Fence {
+                ordering,
+                sync_scope,
+            } => {
+                operands.push(atomic_ordering(*ordering));
+                operands.push(self.module.sync_scope_id(sync_scope));
+                INST_FENCE
+            }
+            InstructionKind::GetElementPtr { ty, base, indices } => {
+                operands.push(0);
+                operands.push(self.module.types.id(ty));
+                self.push_value_and_type(&mut operands, base)?;
+                for index in indices {
+                    self.push_value_and_type(&mut operands, index)?;
+                }
+                INST_GEP
+            }
+            InstructionKind::ExtractValue { aggregate, index } => {
+                self.push_value_and_type(&mut operands, aggregate)?;
+                operands.push(*index as u64);
+                INST_EXTRACTVAL
+            }
+            InstructionKind::InsertValue {
+                aggregate,
+                value,
+                index,
+            } => {
+                self.push_value_and_type(&mut operands, aggregate)?;
+                self.push_value_and_type(&mut operands, value)?;
+                operands.push(*index as u64);
+                INST_INSERTVAL
+            }
+            InstructionKind::ExtractElement { vector, index } => {
+                self.push_value_and_type(&mut operands, vector)?;
+                self.push_value_and_type(&mut operands, index)?;
+                INST_EXTRACTELT
+            }
+            InstructionKind::InsertElement {
+                vector,
+                value,
+                index,
+            } => {
+                self.push_value_and_type(&mut operands, vector)?;
+                self.push_value(&mut operands, value)?;
+                self.push_value_and_type(&mut operands, index)?;
+                INST_INSERTELT
+            }
+            InstructionKind::ShuffleVector { lhs, rhs, mask } => {
+                self.push_value_and_type(&mut operands, lhs)?;
+                self.push_value(&mut operands, rhs)?;
+                self.push_value(&mut operands, mask)?;
+                INST_SHUFFLEVEC
+            }
+            InstructionKind::Phi { ty, incoming } => {
+                operands.push(self.module.types.id(ty));
+                for (value, block) in incoming {
+                    // incoming values may be defined later, so their distance is signed
+                    let distance = self.next as i64 - self.value_id(value)? as i64;
+                    operands.push(encode_signed(distance));
+                    operands.push(self.blocks[block]);
+                }
+                INST_PHI
+            }
+            InstructionKind::Select {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                self.push_value_and_type(&mut operands, if_true)?;
+                self.push_value(&mut operands, if_false)?;
+                self.push_value_and_type(&mut operands, condition)?;
+                INST_VSELECT
+            }
+            InstructionKind::Call {
+                options,
+                signature,
+                callee,
+                arguments,
+            } => {
+                let tail = match options.tail {
+                    Some(TailMarker::Tail) => CALL_TAIL,
+                    Some(TailMarker::MustTail) => CALL_TAIL | CALL_MUSTTAIL,
+                    Some(TailMarker::NoTail) => CALL_NOTAIL,
+                    None => 0,
+                };
+                let convention = options.convention.as_ref().map_or(0, calling_convention);
+                operands.push(0);
+                operands.push(convention << CALL_CCONV | tail | CALL_EXPLICIT_TYPE);
+                operands.push(self.module.types.id(signature));
+                self.push_value_and_type(&mut operands, callee)?;
+                self.push_arguments(&mut operands, signature, arguments)?;
+                INST_CALL
+            }
+            InstructionKind::Invoke {
+                options,
+                signature,
+                callee,
+                arguments,
+                normal,
+                unwind,
+            } => {
+                let convention = options.convention.as_ref().map_or(0, calling_convention);
+                operands.push(0);
+                operands.push(convention | INVOKE_EXPLICIT_TYPE);
+                operands.push(self.blocks[normal]);
+                operands.push(self.blocks[unwind]);
+                operands.push(self.module.types.id(signature));
+                self.push_value_and_type(&mut operands, callee)?;
+                self.push_arguments(&mut operands, signature, arguments)?;
+                INST_INVOKE
+            }
+            InstructionKind::LandingPad {
+                ty,
+                cleanup,
+                clauses,
+            } => {
+                operands.push(self.module.types.id(ty));
+                operands.push(*cleanup as u64);
+                operands.push(clauses.len() as u64);
+                for clause in clauses {
+                    let value = match clause {
+                        LandingPadClause::Catch(value) => {
+                            operands.push(LANDINGPAD_CATCH);
+                            value
+                        }
+                        LandingPadClause::Filter(value) => {
+                            operands.push(LANDINGPAD_FILTER);
+                            value
+                        }
+                    };
+                    self.push_value_and_type(&mut operands, value)?;
+                }
+                INST_LANDINGPAD
+            }
+            InstructionKind::Resume { value } => {
+                self.push_value_and_type(&mut operands, value)?;
+                INST_RESUME
+            }
+            InstructionKind::Return { value } => {
+                if let Some(value) = value {
+                    self.push_value_and_type(&mut operands, value)?;
+                }
+                INST_RET
+            }
+            InstructionKind::Branch { target } => {
+                operands.push(self.blocks[target]);
+                INST_BR
+            }
+            InstructionKind::ConditionalBranch {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                operands.push(self.blocks[if_true]);
+                operands.push(self.blocks[if_false]);
+                self.push_value(&mut operands, condition)?;
+                INST_BR
+            }
+            InstructionKind::Switch {
+                value,
+                default,
+                cases,
+            } => {
+                operands.push(self.module.types.id(value.ty()));
+                self.push_value(&mut operands, value)?;
+                operands.push(self.blocks[default]);
+                // case values are numbered absolutely, unlike every other operand
+                for (case, block) in cases {
+                    operands.push(self.value_id(case)?);
+                    operands.push(self.blocks[block]);
+                }
+                INST_SWITCH
+            }
+            InstructionKind::IndirectBranch {
+                address,
+                destinations,
+            } => {
+                operands.push(self.module.types.id(address.ty()));
+                self.push_value(&mut operands, address)?;
+                operands.extend(destinations.iter().map(|x| self.blocks[x]));
+                INST_INDIRECTBR
+            }
+            InstructionKind::Unreachable => INST_UNREACHABLE,
+            InstructionKind::DeclareVariable { .. } => return Ok(None),
+        };
+        Ok(Some((code, operands)))
+    }
+
+    /// Pushes the arguments of a call, where the extra arguments of variadic functions carry their type.
+    fn push_arguments(
+        &self,
+        operands: &mut Vec<u64>,
+        signature: &Type,
+        arguments: &[Value],
+    ) -> Result<()> {
+        let Type::Function(_, parameters, _) = signature else {
+            unreachable!("call signatures are always function types");
+        };
+        for (index, argument) in arguments.iter().enumerate() {
+            match index < parameters.len() {
+                true => self.push_value(operands, argument)?,
+                false => self.push_value_and_type(operands, argument)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Pushes the distance back to the value, which has the type of the operand.
+    fn push_value(&self, operands: &mut Vec<u64>, value: &Value) -> Result<()> {
+        let id = self.value_id(value)?;
+        operands.push((self.next as u32).wrapping_sub(id as u32) as u64);
+        Ok(())
+    }
+
+    /// Pushes the distance back to the value, followed by its type if it is defined later.
+    fn push_value_and_type(&self, operands: &mut Vec<u64>, value: &Value) -> Result<()> {
+        self.push_value(operands, value)?;
+        if self.value_id(value)? >= self.next {
+            operands.push(self.module.types.id(value.ty()));
+        }
+        Ok(())
+    }
+
+    fn value_id(&self, value: &Value) -> Result<u64> {
+        match value {
+            Value::LocalIdentifier(name, _) => self
+                .locals
+                .get(name)
+                .copied()
+                .ok_or_else(|| undefined(value)),
+            _ => self.module.value_id(value, Some(&self.constants)),
+        }
+    }
+
+    /// Writes the names of the parameters, instruction results and blocks.
+    fn write_symbols(&mut self) {
+        let mut names = self.locals.iter().collect::<Vec<_>>();
+        names.sort_by_key(|(_, id)| **id);
+        let stream = &mut self.module.stream;
+        stream.enter_block(VALUE_SYMTAB_BLOCK, 4);
+        for (name, id) in names {
+            let mut operands = Vec::from([*id]);
+            operands.extend(name.bytes().map(|x| x as u64));
+            stream.record(VST_ENTRY, &operands);
+        }
+        for (index, block) in self.body.blocks().into_iter().enumerate() {
+            let mut operands = Vec::from([index as u64]);
+            operands.extend(self.body.block(block).label().bytes().map(|x| x as u64));
+            stream.record(VST_BBENTRY, &operands);
+        }
+        stream.end_block();
+    }
+}
+
+fn local_name(value: &Value) -> String {
+    match value {
+        Value::LocalIdentifier(name, _) => name.clone(),
+        _ => unreachable!("registers and parameters are always local identifiers"),
+    }
+}
+
+fn invalid(value: &Value) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("invalid constant {}", value.emit()),
+    )
+}
+
+fn linkage(linkage: &Option<LinkageType>) -> u64 {
+    match linkage {
+        None | Some(LinkageType::External) => 0,
+        Some(LinkageType::Internal) => 3,
+        Some(LinkageType::Private) => 9,
+        Some(LinkageType::AvailableExternally) => 12,
+    }
+}
+
+/// Returns true if the encoded linkage keeps the value inside of the module.
+fn is_local(linkage: u64) -> bool {
+    linkage == 3 || linkage == 9
+}
+
+fn visibility(visibility: &Option<Visibility>) -> u64 {
+    match visibility {
+        None | Some(Visibility::Default) => 0,
+        Some(Visibility::Hidden) => 1,
+        Some(Visibility::Protected) => 2,
+    }
+}
+
+fn thread_local(mode: &Option<ThreadLocalMode>) -> u64 {
+    match mode {
+        None => 0,
+        Some(ThreadLocalMode::GeneralDynamic) => 1,
+        Some(ThreadLocalMode::LocalDynamic) => 2,
+        Some(ThreadLocalMode::InitialExec) => 3,
+        Some(ThreadLocalMode::LocalExec) => 4,
+    }
+}
+
+fn unnamed_addr(unnamed_addr: &Option<UnnamedAddr>) -> u64 {
+    match unnamed_addr {
+        None => 0,
+        Some(UnnamedAddr::Global) => 1,
+        Some(UnnamedAddr::Local) => 2,
+    }
+}
+
+fn atomic_ordering(ordering: AtomicOrdering) -> u64 {
+    match ordering {
+        AtomicOrdering::Unordered => 1,
+        AtomicOrdering::Monotonic => 2,
+        AtomicOrdering::Acquire => 3,
+        AtomicOrdering::Release => 4,
+        AtomicOrdering::AcquireRelease => 5,
+        AtomicOrdering::SequentiallyConsistent => 6,
+    }
+}
+
+fn atomicrmw_opcode(operator: AtomicRmwOperator) -> u64 {
+    match operator {
+        AtomicRmwOperator::Exchange => 0,
+        AtomicRmwOperator::Add => 1,
+        AtomicRmwOperator::Sub => 2,
+        AtomicRmwOperator::And => 3,
+        AtomicRmwOperator::Nand => 4,
+        AtomicRmwOperator::Or => 5,
+        AtomicRmwOperator::Xor => 6,
+        AtomicRmwOperator::Max => 7,
+        AtomicRmwOperator::Min => 8,
+        AtomicRmwOperator::UnsignedMax => 9,
+        AtomicRmwOperator::UnsignedMin => 10,
+        AtomicRmwOperator::FloatAdd => 11,
+        AtomicRmwOperator::FloatSub => 12,
+        AtomicRmwOperator::FloatMax => 13,
+        AtomicRmwOperator::FloatMin => 14,
+        AtomicRmwOperator::UnsignedIncrementWrap => 15,
+        AtomicRmwOperator::UnsignedDecrementWrap => 16,
+        AtomicRmwOperator::UnsignedSubtractConditional => 17,
+        AtomicRmwOperator::UnsignedSubtractSaturate => 18,
+    }
+}
+
+fn calling_convention(convention: &CallingConvention) -> u64 {
+    match convention {
+        CallingConvention::C => 0,
+        CallingConvention::Fast => 8,
+        CallingConvention::Cold => 9,
+        CallingConvention::Tail => 18,
+        CallingConvention::Numbered(number) => *number as u64,
+    }
+}
+
+/// Returns the opcode of a binary operator, which floating-point operators share
+/// with their integer counterparts.
+fn binary_opcode(operator: BinaryOperator) -> u64 {
+    match operator {
+        BinaryOperator::IntegerAdd | BinaryOperator::FloatAdd => 0,
+        BinaryOperator::IntegerSub | BinaryOperator::FloatSub => 1,
+        BinaryOperator::IntegerMul | BinaryOperator::FloatMul => 2,
+        BinaryOperator::IntegerUnsignedDiv => 3,
+        BinaryOperator::IntegerSignedDiv | BinaryOperator::FloatDiv => 4,
+        BinaryOperator::IntegerUnsignedRem => 5,
+        BinaryOperator::IntegerSignedRem | BinaryOperator::FloatRem => 6,
+        BinaryOperator::ShiftLeft => 7,
+        BinaryOperator::LogicalShiftRight => 8,
+        BinaryOperator::ArithmeticShiftRight => 9,
+        BinaryOperator::And => 10,
+        BinaryOperator::Or => 11,
+        BinaryOperator::Xor => 12,
+    }
+}
+
+fn binary_flags(operator: BinaryOperator, flags: &BinaryFlags) -> u64 {
+    if operator.is_floating_point() {
+        let fast_math = flags.fast_math;
+        return [
+            fast_math.no_nans,
+            fast_math.no_infs,
+            fast_math.no_signed_zeros,
+            fast_math.allow_reciprocal,
+            fast_math.allow_contract,
+            fast_math.approximate_functions,
+            fast_math.allow_reassociation,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (index, set)| {
+            bits | ((*set as u64) << (index + 1))
+        });
+    }
+    match operator.supports_wrap_flags() {
+        true => flags.no_unsigned_wrap as u64 | (flags.no_signed_wrap as u64) << 1,
+        false => flags.exact as u64,
+    }
+}
+
+fn cast_opcode(operator: CastOperator) -> u64 {
+    match operator {
+        CastOperator::Trunc => 0,
+        CastOperator::ZeroExtend => 1,
+        CastOperator::SignExtend => 2,
+        CastOperator::FloatToUnsigned => 3,
+        CastOperator::FloatToSigned => 4,
+        CastOperator::UnsignedToFloat => 5,
+        CastOperator::SignedToFloat => 6,
+        CastOperator::FloatTrunc => 7,
+        CastOperator::FloatExtend => 8,
+        CastOperator::PtrToInt => 9,
+        CastOperator::IntToPtr => 10,
+        CastOperator::BitCast => 11,
+        CastOperator::AddrSpaceCast => 12,
+    }
+}
+
+/// Parses a decimal integer into the 64-bit words of its two's complement at the width,
+/// least significant first. Bits past the width are cleared.
+fn integer_words(contents: &str, width: u32) -> Option<Vec<u64>> {
+    let (negative, digits) = match contents.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, contents),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    let mut words = Vec::from_iter(std::iter::repeat_n(0u64, width.div_ceil(64) as usize));
+    for digit in digits.bytes() {
+        let mut carry = match digit {
+            b'0'..=b'9' => (digit - b'0') as u128,
+            _ => return None,
+        };
+        for word in &mut words {
+            let product = *word as u128 * 10 + carry;
+            *word = product as u64;
+            carry = product >> 64;
+        }
+    }
+    if negative {
+        let mut carry = true;
+        for word in &mut words {
+            (*word, carry) = (!*word).overflowing_add(carry as u64);
+        }
+    }
+    if !width.is_multiple_of(64) {
+        *words.last_mut().unwrap() &= (1 << (width % 64)) - 1;
+    }
+    Some(words)
+}
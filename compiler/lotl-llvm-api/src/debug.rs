@@ -0,0 +1,312 @@
+use crate::metadata::{Metadata, MetadataNode, MetadataTable, ModuleFlagBehavior};
+use crate::module::Module;
+use crate::value::Values;
+use lotl_error::span::Span;
+use std::vec;
+use std::vec::Vec;
+
+/// The version of the debug info metadata format emitted by the builder.
+const DEBUG_INFO_VERSION: &str = "3";
+/// The version of DWARF the builder asks the backend for.
+const DWARF_VERSION: &str = "5";
+
+/// Builds the DWARF debug information of a module, so it can be stepped through in a debugger.
+///
+/// Every method adds a node to the metadata of the module, and returns a reference to it.
+/// Locations can be attached to instructions through `SharedBasicBlock::set_debug_location`,
+/// and subprograms to functions through `GlobalFunction::with_metadata("dbg", ..)`.
+pub struct DIBuilder {
+    metadata: MetadataTable,
+    file: Metadata,
+    compile_unit: Metadata,
+}
+
+impl DIBuilder {
+    /// Creates the compile unit of the module, describing the given source file.
+    pub fn new(module: &mut Module, filename: &str, directory: &str, producer: &str) -> Self {
+        let file = module.add_metadata(MetadataNode::specialized(
+            "DIFile",
+            vec![
+                ("filename", Metadata::string(filename)),
+                ("directory", Metadata::string(directory)),
+            ],
+        ));
+        let compile_unit = module.add_metadata(
+            MetadataNode::specialized(
+                "DICompileUnit",
+                vec![
+                    // there is no language code for Lotl, C has the closest semantics
+                    ("language", Metadata::literal("DW_LANG_C")),
+                    ("file", file.clone()),
+                    ("producer", Metadata::string(producer)),
+                    ("isOptimized", Metadata::literal(false)),
+                    ("runtimeVersion", Metadata::literal(0)),
+                    ("emissionKind", Metadata::literal("FullDebug")),
+                ],
+            )
+            .distinct(),
+        );
+        module.add_named_metadata("llvm.dbg.cu", compile_unit.clone());
+        module.add_flag(
+            ModuleFlagBehavior::Max,
+            "Dwarf Version",
+            Metadata::Value(Values::integer(DWARF_VERSION, 32)),
+        );
+        module.add_flag(
+            ModuleFlagBehavior::Warning,
+            "Debug Info Version",
+            Metadata::Value(Values::integer(DEBUG_INFO_VERSION, 32)),
+        );
+        DIBuilder {
+            metadata: module.metadata.clone(),
+            file,
+            compile_unit,
+        }
+    }
+
+    /// Returns the file of the compile unit.
+    pub fn file(&self) -> Metadata {
+        self.file.clone()
+    }
+
+    /// Returns the compile unit of the module.
+    pub fn compile_unit(&self) -> Metadata {
+        self.compile_unit.clone()
+    }
+
+    /// Describes a basic type, such as an integer or a floating-point number.
+    pub fn basic_type(&self, name: &str, size_in_bits: u32, encoding: DwarfEncoding) -> Metadata {
+        self.metadata.add(MetadataNode::specialized(
+            "DIBasicType",
+            vec![
+                ("name", Metadata::string(name)),
+                ("size", Metadata::literal(size_in_bits)),
+                ("encoding", Metadata::literal(encoding.name())),
+            ],
+        ))
+    }
+
+    /// Describes the type of a function.
+    /// The return type comes first, and is `None` for functions returning nothing.
+    pub fn subroutine_type(
+        &self,
+        return_type: Option<Metadata>,
+        parameters: Vec<Metadata>,
+    ) -> Metadata {
+        let mut types = vec![return_type.unwrap_or(Metadata::Null)];
+        types.extend(parameters);
+        self.metadata.add(MetadataNode::specialized(
+            "DISubroutineType",
+            vec![("types", Metadata::Tuple(types))],
+        ))
+    }
+
+    /// Describes a function defined in the source file, starting at the given line.
+    pub fn subprogram(&self, name: &str, line: usize, ty: Metadata) -> Metadata {
+        self.metadata.add(
+            MetadataNode::specialized(
+                "DISubprogram",
+                vec![
+                    ("name", Metadata::string(name)),
+                    ("scope", self.file.clone()),
+                    ("file", self.file.clone()),
+                    ("line", Metadata::literal(line)),
+                    ("type", ty),
+                    ("scopeLine", Metadata::literal(line)),
+                    ("spFlags", Metadata::literal("DISPFlagDefinition")),
+                    ("unit", self.compile_unit.clone()),
+                ],
+            )
+            .distinct(),
+        )
+    }
+
+    /// Describes a local variable declared at the given line of a subprogram.
+    pub fn local_variable(
+        &self,
+        name: &str,
+        scope: Metadata,
+        line: usize,
+        ty: Metadata,
+    ) -> Metadata {
+        self.variable(name, None, scope, line, ty)
+    }
+
+    /// Describes a parameter of a subprogram. Parameters are numbered starting from 1.
+    pub fn parameter_variable(
+        &self,
+        name: &str,
+        argument: u32,
+        scope: Metadata,
+        line: usize,
+        ty: Metadata,
+    ) -> Metadata {
+        self.variable(name, Some(argument), scope, line, ty)
+    }
+
+    fn variable(
+        &self,
+        name: &str,
+        argument: Option<u32>,
+        scope: Metadata,
+        line: usize,
+        ty: Metadata,
+    ) -> Metadata {
+        let mut fields = vec![("name", Metadata::string(name))];
+        if let Some(argument) = argument {
+            fields.push(("arg", Metadata::literal(argument)));
+        }
+        fields.extend([
+            ("scope", scope),
+            ("file", self.file.clone()),
+            ("line", Metadata::literal(line)),
+            ("type", ty),
+        ]);
+        self.metadata
+            .add(MetadataNode::specialized("DILocalVariable", fields))
+    }
+
+    /// Describes a line and column inside a scope, such as a subprogram.
+    /// Both the line and the column start from 1.
+    pub fn location(&self, line: usize, column: usize, scope: Metadata) -> Metadata {
+        self.metadata.add(MetadataNode::specialized(
+            "DILocation",
+            vec![
+                ("line", Metadata::literal(line)),
+                ("column", Metadata::literal(column)),
+                ("scope", scope),
+            ],
+        ))
+    }
+
+    /// Describes where a span of Lotl source starts, inside a scope such as a subprogram.
+    pub fn span_location(&self, span: &Span, scope: Metadata) -> Metadata {
+        let (line, column) = span.line_column();
+        self.location(line, column, scope)
+    }
+}
+
+/// Represents how the bits of a basic type are interpreted by a debugger.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DwarfEncoding {
+    /// A true or false value.
+    Boolean,
+    /// A floating-point number.
+    Float,
+    /// A signed integer.
+    Signed,
+    /// An unsigned integer.
+    Unsigned,
+    /// A signed character.
+    SignedChar,
+    /// An unsigned character.
+    UnsignedChar,
+    /// A machine address.
+    Address,
+}
+
+impl DwarfEncoding {
+    fn name(&self) -> &'static str {
+        match self {
+            DwarfEncoding::Boolean => "DW_ATE_boolean",
+            DwarfEncoding::Float => "DW_ATE_float",
+            DwarfEncoding::Signed => "DW_ATE_signed",
+            DwarfEncoding::Unsigned => "DW_ATE_unsigned",
+            DwarfEncoding::SignedChar => "DW_ATE_signed_char",
+            DwarfEncoding::UnsignedChar => "DW_ATE_unsigned_char",
+            DwarfEncoding::Address => "DW_ATE_address",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debug::{DIBuilder, DwarfEncoding};
+    use crate::fixtures::llvm_tool;
+    use crate::module::{FunctionBody, GlobalFunction, Module};
+    use crate::types::Types;
+    use crate::value::Values;
+    use crate::IRComponent;
+    use lotl_error::file::SourceFile;
+    use lotl_error::span::Span;
+    use std::vec;
+
+    #[test]
+    fn generate_debug_info() {
+        let mut module = Module::new();
+        let builder = DIBuilder::new(&mut module, "main.lotl", "/src", "lotl");
+        let int = builder.basic_type("i32", 32, DwarfEncoding::Signed);
+        let ty = builder.subroutine_type(Some(int.clone()), vec![]);
+        let subprogram = builder.subprogram("main", 1, ty);
+        let variable = builder.local_variable("x", subprogram.clone(), 2, int);
+        let declared = builder.location(2, 5, subprogram.clone());
+        let returned = builder.location(3, 5, subprogram.clone());
+
+        let body = FunctionBody::new(|block| {
+            block.set_debug_location(Some(declared.clone()));
+            let x = block.alloca(Types::integer(32));
+            block.declare_variable(x.clone(), variable, declared);
+            block.store(Values::integer("10", 32), x.clone());
+            block.set_debug_location(Some(returned));
+            let loaded = block.load(Types::integer(32), x);
+            block.ret(loaded);
+        });
+        module.functions.push(
+            GlobalFunction::new("main", Types::integer(32))
+                .with_metadata("dbg", subprogram)
+                .body(body),
+        );
+        assert_eq!(
+            module.emit(),
+            "define i32 @main() !dbg !6 { \
+                entry: \
+                    %r0 = alloca i32, !dbg !8 \
+                    #dbg_declare(ptr %r0, !7, !DIExpression(), !8) \
+                    store i32 10, ptr %r0, !dbg !8 \
+                    %r1 = load i32, ptr %r0, !dbg !9 \
+                    ret i32 %r1, !dbg !9 \
+            }\n\n\
+            !llvm.dbg.cu = !{!1}\n\
+            !llvm.module.flags = !{!2, !3}\n\
+            !0 = !DIFile(filename: \"main.lotl\", directory: \"/src\")\n\
+            !1 = distinct !DICompileUnit(language: DW_LANG_C, file: !0, producer: \"lotl\", \
+                isOptimized: false, runtimeVersion: 0, emissionKind: FullDebug)\n\
+            !2 = !{i32 7, !\"Dwarf Version\", i32 5}\n\
+            !3 = !{i32 2, !\"Debug Info Version\", i32 3}\n\
+            !4 = !DIBasicType(name: \"i32\", size: 32, encoding: DW_ATE_signed)\n\
+            !5 = !DISubroutineType(types: !{!4})\n\
+            !6 = distinct !DISubprogram(name: \"main\", scope: !0, file: !0, line: 1, type: !5, \
+                scopeLine: 1, spFlags: DISPFlagDefinition, unit: !1)\n\
+            !7 = !DILocalVariable(name: \"x\", scope: !6, file: !0, line: 2, type: !4)\n\
+            !8 = !DILocation(line: 2, column: 5, scope: !6)\n\
+            !9 = !DILocation(line: 3, column: 5, scope: !6)\n"
+        );
+    }
+
+    #[test]
+    fn locate_spans() {
+        let source = SourceFile::new("main.lotl", "func main() -> i32 {\n    return 7;\n}\n");
+        let mut module = Module::new();
+        let builder = DIBuilder::new(&mut module, &source.name, "/src", "lotl");
+        let int = builder.basic_type("i32", 32, DwarfEncoding::Signed);
+        let ty = builder.subroutine_type(Some(int), vec![]);
+        let subprogram = builder.subprogram("main", 1, ty);
+        // the span of `return 7;`
+        let returned = builder.span_location(&Span::new(source, 25, 34), subprogram.clone());
+
+        let body = FunctionBody::new(|block| {
+            block.set_debug_location(Some(returned));
+            block.ret(Values::integer("7", 32));
+        });
+        module.functions.push(
+            GlobalFunction::new("main", Types::integer(32))
+                .with_metadata("dbg", subprogram)
+                .body(body),
+        );
+        let text = module.emit();
+        assert!(text.contains("ret i32 7, !dbg !7"));
+        assert!(text.contains("!7 = !DILocation(line: 2, column: 5, scope: !6)\n"));
+        // LLVM must accept the locations
+        llvm_tool("llvm-as", text.as_bytes());
+    }
+}
@@ -0,0 +1,273 @@
+use crate::instruction::{
+    AtomicOrdering, BinaryFlags, BinaryOperator, CastOperator, LandingPadClause, VectorReduction,
+};
+use crate::intrinsic::Intrinsic;
+use crate::types::Type;
+use crate::value::{InlineAsm, Value};
+use crate::IRComponent;
+use std::fmt::{Display, Formatter};
+use std::string::String;
+
+/// Represents invalid input given to a builder method.
+///
+/// Every builder method that can fail has a `try_` variant returning this error,
+/// while the plain variant panics with its message.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum BuildError {
+    /// An integer type is wider than LLVM allows.
+    IntegerTooWide(u32),
+    /// An integer type has a width of zero bits.
+    ZeroWidthInteger,
+    /// The contents of a numeric constant are not a valid number.
+    InvalidNumber(String),
+    /// A value is of another type than expected.
+    TypeMismatch {
+        /// The type that was expected.
+        expected: Type,
+        /// The type of the value.
+        found: Type,
+    },
+    /// A structure or array type was expected.
+    NotAggregate(Type),
+    /// A pointer type was expected.
+    NotPointer(Type),
+    /// An index is past the end of an aggregate.
+    IndexOutOfBounds {
+        /// The index that was given.
+        index: usize,
+        /// The type of the aggregate.
+        ty: Type,
+    },
+    /// Indices into structures must be integer constants.
+    NonConstantIndex(Value),
+    /// The operands of a binary operation are not integers or floating points, as required.
+    InvalidOperands {
+        /// The binary operation.
+        operator: BinaryOperator,
+        /// The type of the operands.
+        ty: Type,
+    },
+    /// The binary operation does not accept the flags.
+    InvalidFlags {
+        /// The binary operation.
+        operator: BinaryOperator,
+        /// The flags given to the operation.
+        flags: BinaryFlags,
+    },
+    /// The conversion cannot be made between the two types.
+    InvalidCast {
+        /// The conversion.
+        operator: CastOperator,
+        /// The type of the value being converted.
+        from: Type,
+        /// The type the value is converted into.
+        to: Type,
+    },
+    /// A function reference was expected.
+    NotFunction(Value),
+    /// A function was called with the wrong number of arguments.
+    ArgumentCount {
+        /// The number of parameters of the function.
+        expected: usize,
+        /// The number of arguments given.
+        found: usize,
+    },
+    /// The atomic ordering is not allowed on the instruction, such as a `release` load.
+    InvalidOrdering {
+        /// The instruction, such as `load atomic`.
+        instruction: String,
+        /// The ordering given to the instruction.
+        ordering: AtomicOrdering,
+    },
+    /// The atomic instruction does not accept operands of the type.
+    InvalidAtomicType {
+        /// The instruction, such as `atomicrmw fadd`.
+        instruction: String,
+        /// The type of the operands.
+        ty: Type,
+    },
+    /// Atomic loads and stores must be given an alignment.
+    AlignmentRequired(String),
+    /// An integer type was expected, such as for the value a `switch` compares.
+    NotInteger(Type),
+    /// The cases of a `switch` must be integer constants.
+    NonConstantCase(Value),
+    /// Two cases of a `switch` have the same value.
+    DuplicateCase(Value),
+    /// A vector type was expected.
+    NotVector(Type),
+    /// A vector type has no elements.
+    EmptyVector(Type),
+    /// Vectors can only hold integers, floating points and pointers.
+    InvalidVectorElement(Type),
+    /// An element of a `shufflevector` mask is not a constant index into either operand.
+    InvalidShuffleMask(Value),
+    /// An `invoke` cannot be marked as a tail call.
+    TailInvoke,
+    /// A `landingpad` must be a cleanup or have at least one clause.
+    EmptyLandingPad,
+    /// A `catch` clause must take a pointer, and a `filter` clause an array.
+    InvalidClause(LandingPadClause),
+    /// The vector reduction does not accept vectors of the type,
+    /// such as an integer reduction of floating points.
+    InvalidReduction {
+        /// The reduction.
+        reduction: VectorReduction,
+        /// The type of the vector.
+        ty: Type,
+    },
+    /// The intrinsic is not overloaded for the types it is used with,
+    /// such as `llvm.sqrt` of an integer.
+    InvalidIntrinsic(Intrinsic),
+    /// The operands of a constant expression must be constants,
+    /// such as globals or numbers, and not local values of a function.
+    NonConstantOperand(Value),
+    /// The constraints of inline assembly do not match its signature, which must return
+    /// every output and take an argument for every input.
+    InvalidConstraints(InlineAsm),
+    /// A parameter is named like another parameter, or like a name the builder generates
+    /// for registers, blocks and unnamed parameters, such as `r0`, `bb1` or `arg2`.
+    NameCollision(String),
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::IntegerTooWide(width) => {
+                write!(f, "too big integer i{}, must be i8388607 or smaller", width)
+            }
+            BuildError::ZeroWidthInteger => write!(f, "integers must be at least i1"),
+            BuildError::InvalidNumber(contents) => write!(f, "invalid number: '{}'", contents),
+            BuildError::TypeMismatch { expected, found } => {
+                write!(f, "expected type {:?}, found {:?}", expected, found)
+            }
+            BuildError::NotAggregate(ty) => {
+                write!(f, "expected a structure or array type, found {:?}", ty)
+            }
+            BuildError::NotPointer(ty) => write!(f, "expected a pointer type, found {:?}", ty),
+            BuildError::IndexOutOfBounds { index, ty } => {
+                write!(f, "index {} is out of bounds of {:?}", index, ty)
+            }
+            BuildError::NonConstantIndex(value) => write!(
+                f,
+                "index into a structure must be an integer constant, found {}",
+                value.emit()
+            ),
+            BuildError::InvalidOperands { operator, ty } => write!(
+                f,
+                "{} does not accept operands of type {:?}",
+                operator.emit(),
+                ty
+            ),
+            BuildError::InvalidFlags { operator, flags } => write!(
+                f,
+                "{} does not accept the flags {}",
+                operator.emit(),
+                flags.emit()
+            ),
+            BuildError::InvalidCast { operator, from, to } => {
+                write!(f, "cannot {} from {:?} to {:?}", operator.emit(), from, to)
+            }
+            BuildError::NotFunction(value) => {
+                write!(f, "expected a function reference, found {}", value.emit())
+            }
+            BuildError::ArgumentCount { expected, found } => {
+                write!(f, "expected {} arguments, found {}", expected, found)
+            }
+            BuildError::InvalidOrdering {
+                instruction,
+                ordering,
+            } => write!(
+                f,
+                "{} does not accept the ordering {}",
+                instruction,
+                ordering.emit()
+            ),
+            BuildError::InvalidAtomicType { instruction, ty } => {
+                write!(
+                    f,
+                    "{} does not accept operands of type {:?}",
+                    instruction, ty
+                )
+            }
+            BuildError::AlignmentRequired(instruction) => {
+                write!(f, "{} requires an alignment", instruction)
+            }
+            BuildError::NotInteger(ty) => write!(f, "expected an integer type, found {:?}", ty),
+            BuildError::NonConstantCase(value) => write!(
+                f,
+                "switch cases must be integer constants, found {}",
+                value.emit()
+            ),
+            BuildError::DuplicateCase(value) => {
+                write!(f, "switch has more than one case for {}", value.emit())
+            }
+            BuildError::NotVector(ty) => write!(f, "expected a vector type, found {:?}", ty),
+            BuildError::EmptyVector(ty) => write!(f, "vector type {:?} has no elements", ty),
+            BuildError::InvalidVectorElement(ty) => write!(
+                f,
+                "vectors can only hold integers, floating points and pointers, found {:?}",
+                ty
+            ),
+            BuildError::InvalidShuffleMask(mask) => write!(
+                f,
+                "shufflevector mask must hold constant indices into its operands, found {}",
+                mask.emit()
+            ),
+            BuildError::TailInvoke => write!(f, "invoke cannot be a tail call"),
+            BuildError::EmptyLandingPad => write!(
+                f,
+                "landingpad must be a cleanup or have at least one clause"
+            ),
+            BuildError::InvalidClause(clause) => write!(
+                f,
+                "catch clauses take a pointer and filter clauses an array, found {}",
+                clause.emit()
+            ),
+            BuildError::InvalidReduction { reduction, ty } => write!(
+                f,
+                "{} reduction does not accept vectors of type {:?}",
+                reduction.emit(),
+                ty
+            ),
+            BuildError::InvalidIntrinsic(intrinsic) => {
+                write!(f, "no intrinsic {} for its types", intrinsic.name())
+            }
+            BuildError::NonConstantOperand(value) => write!(
+                f,
+                "constant expressions take constant operands, found {}",
+                value.emit()
+            ),
+            BuildError::InvalidConstraints(asm) => write!(
+                f,
+                "constraints \"{}\" do not match the signature {:?}",
+                asm.constraints, asm.signature
+            ),
+            BuildError::NameCollision(name) => {
+                write!(f, "parameter name %{} is already taken", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// Represents invalid LLVM IR text given to `Module::parse`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// The line the problem was found on, starting at 1.
+    pub line: usize,
+    /// The column the problem was found at, starting at 1.
+    pub column: usize,
+    /// The description of the problem.
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,578 @@
+use crate::instruction::{
+    AtomicOrdering, AtomicRmwOperator, BinaryFlags, BinaryOperator, CallOptions, FastMathFlags,
+    LandingPadClause, MemoryOptions, SyncScope, TailMarker, VectorReduction,
+};
+use crate::module::{
+    CallingConvention, FunctionAttribute, GlobalFunction, GlobalVariable, LinkageType, Module,
+    Parameter, ParameterAttribute, ThreadLocalMode, UnnamedAddr, Visibility,
+};
+use crate::types::Types;
+use crate::value::{InlineAsm, Values};
+use std::format;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::string::String;
+use std::vec;
+use std::vec::Vec;
+
+/// Runs an LLVM tool, such as `llvm-as`, on the input and returns its output,
+/// or nothing if the tool is not installed. Tools reporting an error fail the test.
+pub(crate) fn llvm_tool(tool: &str, input: &[u8]) -> Option<Vec<u8>> {
+    // distributions install the tools with their version as a suffix
+    let (name, major) = [String::from(tool)]
+        .into_iter()
+        .chain((14..=20).rev().map(|x| format!("{}-{}", tool, x)))
+        .find_map(|name| {
+            let output = Command::new(&name).arg("--version").output().ok()?;
+            let version = String::from_utf8_lossy(&output.stdout).into_owned();
+            let major = version
+                .split("version ")
+                .nth(1)?
+                .split('.')
+                .next()?
+                .parse::<u32>()
+                .ok()?;
+            Some((name, major))
+        })?;
+    let mut command = Command::new(name);
+    // pointers are only opaque by default from LLVM 15 on
+    if major < 15 {
+        command.arg("-opaque-pointers");
+    }
+    let mut child = command
+        .args(["-", "-o", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    if !output.status.success() {
+        panic!(
+            "{} failed: {}",
+            tool,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Some(output.stdout)
+}
+
+/// Types, strings, constants of every kind and the global variable options.
+pub(crate) fn globals_module() -> Module {
+    let mut module = Module::new()
+        .with_source_filename("globals \"1\".lotl")
+        .with_target_triple("x86_64-unknown-linux-gnu");
+    let node = module.define_type(
+        "Node",
+        Types::structure(vec![
+            Types::integer(32),
+            Types::pointer(0),
+            Types::array(2, Types::integer(16)),
+        ]),
+    );
+    module.declare_type("Handle");
+    let packed = Types::packed_structure(vec![Types::integer(8), Types::integer(64)]);
+    let greeting = module.c_string("Hello\n");
+    module.vars.push(
+        GlobalVariable::new("wide", Types::integer(128))
+            .with_value(Values::integer("-18446744073709551621", 128)),
+    );
+    module.vars.push(
+        GlobalVariable::new(
+            "floats",
+            Types::structure(vec![
+                Types::fp16(),
+                Types::fp32(),
+                Types::fp64(),
+                Types::fp128(),
+            ]),
+        )
+        .with_value(Values::structure(vec![
+            Values::float("1.5", Types::fp16()),
+            Values::float("0.1", Types::fp32()),
+            Values::float("-2.25", Types::fp64()),
+            Values::float("1024.5", Types::fp128()),
+        ]))
+        .with_linkage(LinkageType::Internal)
+        .with_alignment(16),
+    );
+    module.vars.push(
+        GlobalVariable::new("pointers", Types::array(2, Types::pointer(0)))
+            .with_value(Values::array(
+                Types::pointer(0),
+                vec![greeting.clone(), greeting],
+            ))
+            .constant()
+            .with_unnamed_addr(UnnamedAddr::Local)
+            .with_section(".rodata"),
+    );
+    module.vars.push(
+        GlobalVariable::new("head", node.clone())
+            .with_value(Values::zeroinitializer(node))
+            .with_thread_local(ThreadLocalMode::LocalExec),
+    );
+    module.vars.push(
+        GlobalVariable::new("packed", packed).with_value(Values::packed_structure(vec![
+            Values::integer("-1", 8),
+            Values::integer("4", 64),
+        ])),
+    );
+    module.vars.push(
+        GlobalVariable::new("bytes", Types::array(3, Types::integer(8)))
+            .with_value(Values::bytes(vec![0, 1, 2])),
+    );
+    module.vars.push(
+        GlobalVariable::new("device", Types::integer(1))
+            .with_value(Values::integer("1", 1))
+            .with_linkage(LinkageType::AvailableExternally)
+            .with_thread_local(ThreadLocalMode::GeneralDynamic),
+    );
+    module
+        .vars
+        .push(GlobalVariable::new("external", Types::integer(1)));
+    let main = GlobalFunction::new("main", Types::integer(32))
+        .build_body(|block, _| block.ret(Values::integer("0", 32)));
+    module
+        .vars
+        .push(GlobalVariable::new("entry", Types::pointer(0)).with_value(main.reference()));
+    module.functions.push(main);
+    module
+}
+
+/// Loops, calls, casts and the parameter and instruction options.
+pub(crate) fn functions_module() -> Module {
+    let mut module = Module::new();
+    let printf = GlobalFunction::new("printf", Types::integer(32))
+        .with_parameter(
+            Parameter::new(Types::pointer(0)).with_attribute(ParameterAttribute::NoUndef),
+        )
+        .variadic();
+    let pair = Types::structure(vec![Types::integer(32), Types::fp64()]);
+    let swap = GlobalFunction::new("swap", Types::void())
+        .with_parameter(
+            Parameter::new(Types::pointer(0))
+                .with_name("result")
+                .with_attribute(ParameterAttribute::StructRet(pair.clone())),
+        )
+        .with_parameter(
+            Parameter::new(Types::pointer(0))
+                .with_name("input")
+                .with_attribute(ParameterAttribute::NonNull)
+                .with_attribute(ParameterAttribute::ByVal(pair.clone())),
+        )
+        .linkage(LinkageType::Internal)
+        .build_body(|block, arguments| {
+            let loaded = block.load(pair.clone(), arguments[1].clone());
+            let first = block.extractvalue(loaded.clone(), 0);
+            let second = block.extractvalue(loaded.clone(), 1);
+            let negated = block.fneg(second);
+            let converted = block.fptosi(negated, Types::integer(32));
+            let swapped = block.insertvalue(loaded, converted, 0);
+            let widened = block.sitofp(first, Types::fp64());
+            let swapped = block.insertvalue(swapped, widened, 1);
+            block.store(swapped, arguments[0].clone());
+            block.ret_void();
+        });
+    // calls leave out the parameter attributes, which LLVM 14 fails to read for sret and byval
+    let reset = GlobalFunction::new("reset", Types::void())
+        .with_parameter(Types::pointer(0))
+        .with_calling_convention(CallingConvention::Fast);
+    let format = module.c_string("%d\n");
+    let (printf_reference, reset_reference) = (printf.reference(), reset.reference());
+    let main = GlobalFunction::new("main", Types::integer(32))
+        .with_parameter(Parameter::new(Types::integer(32)).with_name("count"))
+        .build_body(|block, arguments| {
+            let header = block.br_returning();
+            let counter = header.phi(Types::integer(32), vec![(Values::integer("0", 32), &block)]);
+            let remaining = header.sub(arguments[0].clone(), counter.value());
+            let sign = header.lshr(remaining, Values::integer("31", 32));
+            let done = header.trunc(sign, Types::integer(1));
+            let (body, exit) = header.br_if_returning(done);
+            let incremented = body.binop_with_flags(
+                BinaryOperator::IntegerAdd,
+                counter.value(),
+                Values::integer("1", 32),
+                BinaryFlags::new().no_signed_wrap().no_unsigned_wrap(),
+            );
+            let halved = body.binop_with_flags(
+                BinaryOperator::IntegerSignedDiv,
+                incremented.clone(),
+                Values::integer("2", 32),
+                BinaryFlags::new().exact(),
+            );
+            let scaled = body.binop_with_flags(
+                BinaryOperator::FloatMul,
+                Values::float("0.5", Types::fp64()),
+                Values::float("3", Types::fp64()),
+                BinaryFlags::new().fast_math(FastMathFlags::new().no_nans().allow_contract()),
+            );
+            let odd = body.and(incremented.clone(), Values::integer("1", 32));
+            let odd = body.trunc(odd, Types::integer(1));
+            let chosen = body.select(odd, halved, incremented.clone());
+            body.call_with(
+                printf_reference,
+                vec![format, chosen, scaled],
+                CallOptions::new().with_tail_marker(TailMarker::Tail),
+            );
+            counter.add_incoming(incremented, &body);
+            body.goto(&header);
+            let slot = exit.alloca(pair.clone());
+            let element = exit.getelementptr(
+                pair.clone(),
+                slot.clone(),
+                vec![Values::integer("0", 32), Values::integer("1", 32)],
+            );
+            let address = exit.ptrtoint(element, Types::integer(64));
+            let element = exit.inttoptr(address, Types::pointer(1));
+            let element = exit.addrspacecast(element, Types::pointer(0));
+            exit.store(Values::float("2", Types::fp64()), element);
+            exit.call_with(
+                reset_reference.clone(),
+                vec![slot.clone()],
+                CallOptions::new().with_calling_convention(CallingConvention::Fast),
+            );
+            exit.call_with(
+                reset_reference,
+                vec![slot],
+                CallOptions::new()
+                    .with_tail_marker(TailMarker::NoTail)
+                    .with_calling_convention(CallingConvention::Numbered(10)),
+            );
+            exit.ret(counter.value());
+        });
+    module.functions.push(printf);
+    module.functions.push(swap);
+    module.functions.push(reset);
+    module.functions.push(main);
+    module.functions.push(
+        GlobalFunction::new("never", Types::void())
+            .linkage(LinkageType::Private)
+            .build_body(|block, _| block.unreachable()),
+    );
+    module
+}
+
+/// Atomic and volatile memory accesses with orderings and sync scopes.
+pub(crate) fn atomics_module() -> Module {
+    let mut module = Module::new();
+    module
+        .functions
+        .push(
+            GlobalFunction::new("main", Types::integer(32)).build_body(|block, _| {
+                let counter = block.alloca_with_alignment(Types::integer(32), 64);
+                let aligned = MemoryOptions::new().with_alignment(4);
+                block.store_with(
+                    Values::integer("1", 32),
+                    counter.clone(),
+                    aligned.clone().volatile(),
+                );
+                block.store_atomic(
+                    Values::integer("2", 32),
+                    counter.clone(),
+                    AtomicOrdering::Unordered,
+                    aligned.clone(),
+                );
+                let previous = block.atomicrmw(
+                    AtomicRmwOperator::UnsignedMax,
+                    counter.clone(),
+                    Values::integer("3", 32),
+                    AtomicOrdering::AcquireRelease,
+                    aligned.clone().with_sync_scope(SyncScope::SingleThread),
+                );
+                let exchanged = block.cmpxchg_weak(
+                    counter.clone(),
+                    previous,
+                    Values::integer("4", 32),
+                    AtomicOrdering::SequentiallyConsistent,
+                    AtomicOrdering::Acquire,
+                    aligned.clone().volatile(),
+                );
+                let old = block.extractvalue(exchanged, 0);
+                block.fence(
+                    AtomicOrdering::Release,
+                    SyncScope::Target(String::from("agent")),
+                );
+                block.fence(AtomicOrdering::Acquire, SyncScope::System);
+                let loaded = block.load_atomic(
+                    Types::integer(32),
+                    counter.clone(),
+                    AtomicOrdering::Monotonic,
+                    aligned.with_sync_scope(SyncScope::Target(String::from("wavefront"))),
+                );
+                let plain =
+                    block.load_with(Types::integer(32), counter, MemoryOptions::new().volatile());
+                let sum = block.add(old, loaded);
+                block.ret(block.add(sum, plain));
+            }),
+        );
+    module
+}
+
+/// Fixed and scalable vectors, their instructions and reductions.
+pub(crate) fn vectors_module() -> Module {
+    let mut module = Module::new();
+    let floats = Types::vector(4, Types::fp32());
+    let weights = GlobalVariable::new("weights", floats.clone()).with_value(Values::vector(
+        Types::fp32(),
+        vec![
+            Values::float("0.5", Types::fp32()),
+            Values::float("1.5", Types::fp32()),
+            Values::float("-2.25", Types::fp32()),
+            Values::float("3.5", Types::fp32()),
+        ],
+    ));
+    let reference = weights.reference();
+    module.vars.push(weights);
+    let maximum = VectorReduction::FloatMax.declaration(&floats);
+    let scalable = Types::scalable_vector(4, Types::integer(8));
+    module.functions.push(maximum);
+    module.functions.push(
+        GlobalFunction::new("main", Types::fp32())
+            .with_parameter(Types::vector(2, Types::integer(32)))
+            .with_parameter(scalable.clone())
+            .build_body(|block, arguments| {
+                let loaded = block.load(floats.clone(), reference);
+                let negated = block.fneg(loaded.clone());
+                let shifted = block.shl(
+                    arguments[0].clone(),
+                    Values::vector(
+                        Types::integer(32),
+                        vec![Values::integer("1", 32), Values::integer("2", 32)],
+                    ),
+                );
+                let lane = block.extractelement(shifted, Values::integer("1", 64));
+                let converted = block.sitofp(lane, Types::fp32());
+                let inserted = block.insertelement(negated, converted, Values::integer("3", 32));
+                let reversed = block.shufflevector(loaded, inserted, vec![7, 2, 1, 4]);
+                block.splat(scalable.clone(), Values::integer("3", 8));
+                block.insertelement(
+                    arguments[1].clone(),
+                    Values::integer("1", 8),
+                    Values::integer("0", 32),
+                );
+                block.ret(block.vector_reduce(VectorReduction::FloatMax, reversed));
+            }),
+    );
+    module
+}
+
+/// Function attributes, calling conventions, visibility and personalities.
+pub(crate) fn function_attributes_module() -> Module {
+    let mut module = Module::new();
+    let personality = GlobalFunction::new("__gxx_personality_v0", Types::integer(32))
+        .variadic()
+        .dso_local();
+    let abort = GlobalFunction::new("abort", Types::void())
+        .with_attribute(FunctionAttribute::NoReturn)
+        .with_attribute(FunctionAttribute::NoUnwind)
+        .with_attribute(FunctionAttribute::Cold);
+    let helper = GlobalFunction::new("helper", Types::integer(32))
+        .linkage(LinkageType::Internal)
+        .with_calling_convention(CallingConvention::Fast)
+        .with_parameter(Types::integer(32))
+        .with_attribute(FunctionAttribute::AlwaysInline)
+        .with_attribute(FunctionAttribute::NoUnwind)
+        .with_section(".text.hot")
+        .build_body(|block, arguments| block.ret(arguments[0].clone()));
+    let helper_reference = helper.reference();
+    let main = GlobalFunction::new("main", Types::integer(32))
+        .dso_local()
+        .with_visibility(Visibility::Protected)
+        .with_attribute(FunctionAttribute::NoInline)
+        .with_personality(personality.reference())
+        .build_body(|block, _| {
+            let result = block
+                .call_with(
+                    helper_reference,
+                    vec![Values::integer("7", 32)],
+                    CallOptions::new().with_calling_convention(CallingConvention::Fast),
+                )
+                .unwrap();
+            block.ret(result);
+        });
+    module.functions.push(personality);
+    module.functions.push(abort);
+    module.functions.push(helper);
+    module.functions.push(main);
+    module.functions.push(
+        GlobalFunction::new("fail", Types::void())
+            .with_calling_convention(CallingConvention::Cold)
+            .with_attribute(FunctionAttribute::NoReturn)
+            .with_attribute(FunctionAttribute::NoUnwind)
+            .with_attribute(FunctionAttribute::Cold)
+            .build_body(|block, _| block.unreachable()),
+    );
+    module
+}
+
+/// Invokes, landing pads and resumes.
+pub(crate) fn exceptions_module() -> Module {
+    let mut module = Module::new();
+    let personality = GlobalFunction::new("__gxx_personality_v0", Types::integer(32)).variadic();
+    let typeinfo = GlobalVariable::new("typeinfo", Types::integer(8))
+        .constant()
+        .with_value(Values::integer("0", 8));
+    let may_throw =
+        GlobalFunction::new("may_throw", Types::integer(32)).with_parameter(Types::integer(32));
+    let cleanup = GlobalFunction::new("cleanup", Types::void())
+        .with_calling_convention(CallingConvention::Fast);
+    let (typeinfo_reference, may_throw_reference) = (typeinfo.reference(), may_throw.reference());
+    let cleanup_reference = cleanup.reference();
+    let main = GlobalFunction::new("main", Types::integer(32))
+        .with_parameter(Types::integer(32))
+        .with_personality(personality.reference())
+        .build_body(|block, arguments| {
+            let (result, normal, unwind) =
+                block.invoke_returning(may_throw_reference, vec![arguments[0].clone()]);
+            normal.ret(result.unwrap());
+            let ty = Types::structure(vec![Types::pointer(0), Types::integer(32)]);
+            let filter = Values::array(Types::pointer(0), vec![typeinfo_reference.clone()]);
+            let exception = unwind.landingpad(
+                ty.clone(),
+                true,
+                vec![
+                    LandingPadClause::Catch(typeinfo_reference.clone()),
+                    LandingPadClause::Filter(filter),
+                ],
+            );
+            let (resume, terminate) = (unwind.child(), unwind.child());
+            unwind.invoke_to_with(
+                cleanup_reference,
+                vec![],
+                &resume,
+                &terminate,
+                CallOptions::new().with_calling_convention(CallingConvention::Fast),
+            );
+            resume.resume(exception);
+            let clause = LandingPadClause::Catch(typeinfo_reference);
+            terminate.landingpad(ty, false, vec![clause]);
+            terminate.unreachable();
+        });
+    module.vars.push(typeinfo);
+    module.functions.push(personality);
+    module.functions.push(may_throw);
+    module.functions.push(cleanup);
+    module.functions.push(main);
+    module
+}
+
+/// Switches, indirect branches and block addresses.
+pub(crate) fn jump_tables_module() -> Module {
+    let mut module = Module::new();
+    let table_type = Types::array(2, Types::pointer(0));
+    let table = GlobalVariable::new("table", table_type.clone()).constant();
+    let reference = table.reference();
+    let mut addresses = Vec::new();
+    let dispatch = GlobalFunction::new("dispatch", Types::integer(32))
+        .with_parameter(Types::integer(32))
+        .build_body(|block, arguments| {
+            let (default, cases) = block.switch_returning(
+                arguments[0].clone(),
+                vec![Values::integer("0", 32), Values::integer("-1", 32)],
+            );
+            let slot = default.getelementptr(
+                table_type,
+                reference,
+                vec![Values::integer("0", 32), Values::integer("1", 32)],
+            );
+            let address = default.load(Types::pointer(0), slot);
+            default.indirectbr(address, vec![&cases[0], &cases[1]]);
+            cases[0].ret(Values::integer("10", 32));
+            let exit = cases[1].child();
+            cases[1].switch_to(
+                Values::integer("3", 32),
+                &exit,
+                vec![(Values::integer("3", 32), &exit)],
+            );
+            exit.ret(Values::integer("20", 32));
+            addresses.push(Values::block_address("dispatch", &cases[0]));
+            addresses.push(Values::block_address("dispatch", &cases[1]));
+        });
+    module
+        .vars
+        .push(table.with_value(Values::array(Types::pointer(0), addresses)));
+    module.functions.push(dispatch);
+    module
+}
+
+/// Constant expressions, null, undef and poison values.
+pub(crate) fn constant_expressions_module() -> Module {
+    let mut module = Module::new();
+    let table_type = Types::array(4, Types::integer(32));
+    let table = GlobalVariable::new("table", table_type.clone())
+        .with_value(Values::zeroinitializer(table_type.clone()));
+    let second = Values::getelementptr(
+        table_type.clone(),
+        table.reference(),
+        vec![Values::integer("0", 64), Values::integer("1", 64)],
+    );
+    let third = Values::inbounds_getelementptr(
+        table_type,
+        table.reference(),
+        vec![Values::integer("0", 64), Values::integer("2", 64)],
+    );
+    let address = Values::ptrtoint(table.reference(), Types::integer(64));
+    module.vars.push(table);
+    module
+        .vars
+        .push(GlobalVariable::new("second", Types::pointer(0)).with_value(second));
+    module
+        .vars
+        .push(GlobalVariable::new("third", Types::pointer(0)).with_value(third));
+    module
+        .vars
+        .push(GlobalVariable::new("address", Types::integer(64)).with_value(address.clone()));
+    module.vars.push(
+        GlobalVariable::new("nothing", Types::pointer(0))
+            .with_value(Values::null(Types::pointer(0))),
+    );
+    module.vars.push(
+        GlobalVariable::new(
+            "unknown",
+            Types::structure(vec![Types::integer(32), Types::fp32(), Types::fp16()]),
+        )
+        .with_value(Values::structure(vec![
+            Values::undef(Types::integer(32)),
+            Values::float("0.1", Types::fp32()),
+            Values::float("0.1", Types::fp16()),
+        ])),
+    );
+    module.functions.push(
+        GlobalFunction::new("offset", Types::integer(64)).build_body(|block, _| {
+            let sum = block.add(address, Values::poison(Types::integer(64)));
+            block.ret(sum);
+        }),
+    );
+    module
+}
+
+/// Module level and inline assembly.
+pub(crate) fn inline_asm_module() -> Module {
+    let mut module = Module::new().with_target_triple("x86_64-unknown-linux-gnu");
+    module.append_module_asm(".globl lotl_marker\nlotl_marker: ret");
+    let syscall = InlineAsm::new(
+        "syscall",
+        "={rax},{rax},{rdi},~{rcx},~{r11},~{memory}",
+        Types::function(
+            Types::integer(64),
+            vec![Types::integer(64), Types::integer(64)],
+        ),
+    )
+    .with_side_effects();
+    let pause = InlineAsm::new("pause", "", Types::function(Types::void(), vec![]))
+        .with_side_effects()
+        .with_aligned_stack()
+        .with_intel_dialect();
+    module.functions.push(
+        GlobalFunction::new("exit", Types::void())
+            .with_parameter(Types::integer(64))
+            .build_body(|block, arguments| {
+                block.call(pause.reference(), vec![]);
+                let arguments = vec![Values::integer("60", 64), arguments[0].clone()];
+                block.call(syscall.reference(), arguments);
+                block.unreachable();
+            }),
+    );
+    module
+}
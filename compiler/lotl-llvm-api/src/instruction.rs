@@ -1,79 +1,845 @@
 mod aggregate;
+mod atomic;
 mod binop;
 mod block;
+mod call;
+mod cast;
+mod debug;
+mod exception;
 mod flow;
 mod memory;
-mod trunc;
+mod phi;
+mod print;
 mod unop;
+mod vector;
 
+pub use atomic::{AtomicOrdering, AtomicRmwOperator, SyncScope};
+pub use binop::{BinaryFlags, BinaryOperator, FastMathFlags};
+pub use call::{CallOptions, TailMarker};
+pub use cast::CastOperator;
+pub use exception::LandingPadClause;
+pub use flow::CaseLabel;
+pub use memory::MemoryOptions;
+pub use phi::PhiNode;
+pub use vector::VectorReduction;
+
+pub(crate) use aggregate::{check_getelementptr, element_type};
+pub(crate) use atomic::cmpxchg_type;
+pub(crate) use cast::check_cast;
+pub(crate) use vector::check_shufflevector;
+
+use crate::metadata::Metadata;
+use crate::module::FunctionBody;
 use crate::types::Type;
 use crate::value::Value;
-use crate::IRComponent;
-use std::boxed::Box;
-use std::string::String;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::format;
+use std::string::{String, ToString};
+use std::sync::{Arc, Mutex};
+use std::vec;
 use std::vec::Vec;
 
-/// Represents an instruction in LLVM IR.
-pub trait Instruction: IRComponent {}
+/// Identifies an instruction inside of the arena of a function body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InstId(pub(crate) usize);
+
+/// Identifies a basic block inside of the arena of a function body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockId(pub(crate) usize);
+
+/// Represents the operation of an instruction in LLVM IR, along with its operands.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum InstructionKind {
+    /// A binary operation on two operands of the same type, such as `add`.
+    Binary {
+        /// The operation performed.
+        operator: BinaryOperator,
+        /// The flags of the operation, such as `nsw`.
+        flags: BinaryFlags,
+        /// The left-hand operand.
+        lhs: Value,
+        /// The right-hand operand.
+        rhs: Value,
+    },
+    /// Negates a floating point, `fneg`.
+    FloatNegate {
+        /// The value negated.
+        value: Value,
+    },
+    /// Converts a value into another type, such as `zext`.
+    Cast {
+        /// The conversion performed.
+        operator: CastOperator,
+        /// The value converted.
+        value: Value,
+        /// The type the value is converted into.
+        target: Type,
+    },
+    /// Allocates memory on the stack, `alloca`.
+    Alloca {
+        /// The type of the value the memory is allocated for.
+        ty: Type,
+        /// The alignment of the memory in bytes, or the alignment of the type if there is none.
+        alignment: Option<u32>,
+    },
+    /// Reads a value from memory, `load`.
+    Load {
+        /// The type of the value read.
+        ty: Type,
+        /// The pointer read from.
+        pointer: Value,
+        /// The ordering of an atomic load, or nothing if the load is not atomic.
+        ordering: Option<AtomicOrdering>,
+        /// The options of the access, such as its alignment.
+        options: MemoryOptions,
+    },
+    /// Writes a value into memory, `store`.
+    Store {
+        /// The value written.
+        value: Value,
+        /// The pointer written to.
+        pointer: Value,
+        /// The ordering of an atomic store, or nothing if the store is not atomic.
+        ordering: Option<AtomicOrdering>,
+        /// The options of the access, such as its alignment.
+        options: MemoryOptions,
+    },
+    /// Atomically combines a value in memory with an operand, `atomicrmw`.
+    /// The result is the value memory held before.
+    AtomicRmw {
+        /// The operation combining the values.
+        operator: AtomicRmwOperator,
+        /// The pointer to the memory modified.
+        pointer: Value,
+        /// The operand combined with the value in memory.
+        value: Value,
+        /// The ordering of the operation.
+        ordering: AtomicOrdering,
+        /// The options of the access, such as its alignment.
+        options: MemoryOptions,
+    },
+    /// Atomically replaces a value in memory if it equals the expected value, `cmpxchg`.
+    /// The result is a pair of the value memory held before, and whether it was replaced.
+    CompareExchange {
+        /// The pointer to the memory modified.
+        pointer: Value,
+        /// The value memory is compared against.
+        expected: Value,
+        /// The value written if memory held the expected value.
+        replacement: Value,
+        /// The ordering if the value is replaced.
+        success: AtomicOrdering,
+        /// The ordering if the value is not replaced.
+        failure: AtomicOrdering,
+        /// Whether the value may fail to be replaced even if memory held the expected value.
+        weak: bool,
+        /// The options of the access, such as its alignment.
+        options: MemoryOptions,
+    },
+    /// Orders memory operations without accessing memory, `fence`.
+    Fence {
+        /// The ordering of the fence.
+        ordering: AtomicOrdering,
+        /// The threads the fence synchronizes with.
+        sync_scope: SyncScope,
+    },
+    /// Computes the address of an element inside of an aggregate, `getelementptr`.
+    GetElementPtr {
+        /// The type the first index steps over.
+        ty: Type,
+        /// The pointer the address is computed from.
+        base: Value,
+        /// The indices stepping into the aggregate.
+        indices: Vec<Value>,
+    },
+    /// Reads an element out of an aggregate value, `extractvalue`.
+    ExtractValue {
+        /// The aggregate read from.
+        aggregate: Value,
+        /// The index of the element.
+        index: usize,
+    },
+    /// Replaces an element of an aggregate value, `insertvalue`.
+    InsertValue {
+        /// The aggregate written into.
+        aggregate: Value,
+        /// The new value of the element.
+        value: Value,
+        /// The index of the element.
+        index: usize,
+    },
+    /// Reads an element out of a vector, `extractelement`.
+    ExtractElement {
+        /// The vector read from.
+        vector: Value,
+        /// The integer index of the element.
+        index: Value,
+    },
+    /// Replaces an element of a vector, `insertelement`.
+    InsertElement {
+        /// The vector written into.
+        vector: Value,
+        /// The new value of the element.
+        value: Value,
+        /// The integer index of the element.
+        index: Value,
+    },
+    /// Builds a vector out of the elements of two vectors, `shufflevector`.
+    ShuffleVector {
+        /// The vector whose elements are numbered first.
+        lhs: Value,
+        /// The vector whose elements are numbered after those of `lhs`.
+        rhs: Value,
+        /// The constant `i32` vector of the indices of the elements picked.
+        mask: Value,
+    },
+    /// Selects a value depending on which block control arrived from, `phi`.
+    Phi {
+        /// The type of the value selected.
+        ty: Type,
+        /// The values selected, along with the block control arrives from.
+        incoming: Vec<(Value, BlockId)>,
+    },
+    /// Chooses one of two values based on an `i1` condition, `select`.
+    Select {
+        /// The condition.
+        condition: Value,
+        /// The value chosen if the condition is true.
+        if_true: Value,
+        /// The value chosen if the condition is false.
+        if_false: Value,
+    },
+    /// Calls a function, `call`.
+    Call {
+        /// The options of the call, such as its tail marker.
+        options: CallOptions,
+        /// The function type of the callee.
+        signature: Type,
+        /// The function called.
+        callee: Value,
+        /// The arguments given to the function.
+        arguments: Vec<Value>,
+    },
+    /// Calls a function, continuing in the normal block once it returns,
+    /// or in the unwind block if it raises an exception, `invoke`.
+    Invoke {
+        /// The options of the call, such as its calling convention.
+        options: CallOptions,
+        /// The function type of the callee.
+        signature: Type,
+        /// The function called.
+        callee: Value,
+        /// The arguments given to the function.
+        arguments: Vec<Value>,
+        /// The block continued in once the function returns.
+        normal: BlockId,
+        /// The block continued in if the function raises an exception.
+        unwind: BlockId,
+    },
+    /// Receives an exception at the start of the unwind block of an `invoke`, `landingpad`.
+    LandingPad {
+        /// The type of the value describing the exception.
+        ty: Type,
+        /// Whether the block runs cleanup code, so it receives every exception.
+        cleanup: bool,
+        /// The clauses naming the exceptions received.
+        clauses: Vec<LandingPadClause>,
+    },
+    /// Describes a source variable living in memory, `#dbg_declare`.
+    DeclareVariable {
+        /// The pointer to the memory of the variable.
+        address: Value,
+        /// The debug info describing the variable.
+        variable: Metadata,
+        /// The location of the declaration.
+        location: Metadata,
+    },
+    /// Returns control to the caller, `ret`.
+    Return {
+        /// The value returned, or nothing for void functions.
+        value: Option<Value>,
+    },
+    /// Continues in another block, `br`.
+    Branch {
+        /// The block continued in.
+        target: BlockId,
+    },
+    /// Continues in one of two blocks based on an `i1` condition, `br`.
+    ConditionalBranch {
+        /// The condition.
+        condition: Value,
+        /// The block continued in if the condition is true.
+        if_true: BlockId,
+        /// The block continued in if the condition is false.
+        if_false: BlockId,
+    },
+    /// Continues in the block of the case equal to an integer, `switch`.
+    Switch {
+        /// The integer compared against the cases.
+        value: Value,
+        /// The block continued in if no case is equal to the value.
+        default: BlockId,
+        /// The integer constants of the cases, along with the block continued in.
+        cases: Vec<(Value, BlockId)>,
+    },
+    /// Continues in the block at an address taken with `blockaddress`, `indirectbr`.
+    IndirectBranch {
+        /// The address of the block continued in.
+        address: Value,
+        /// Every block the address can point to.
+        destinations: Vec<BlockId>,
+    },
+    /// Continues raising an exception received by a `landingpad` in the caller, `resume`.
+    Resume {
+        /// The value describing the exception.
+        value: Value,
+    },
+    /// Marks a place control never reaches, `unreachable`.
+    Unreachable,
+}
+
+impl InstructionKind {
+    /// Returns the values used by this instruction, including the incoming values of a `phi`.
+    pub fn operands(&self) -> Vec<&Value> {
+        match self {
+            InstructionKind::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            InstructionKind::FloatNegate { value } | InstructionKind::Cast { value, .. } => {
+                vec![value]
+            }
+            InstructionKind::Alloca { .. } => vec![],
+            InstructionKind::Load { pointer, .. } => vec![pointer],
+            InstructionKind::Store { value, pointer, .. }
+            | InstructionKind::AtomicRmw { pointer, value, .. } => vec![value, pointer],
+            InstructionKind::CompareExchange {
+                pointer,
+                expected,
+                replacement,
+                ..
+            } => vec![pointer, expected, replacement],
+            InstructionKind::Fence { .. } => vec![],
+            InstructionKind::GetElementPtr { base, indices, .. } => {
+                let mut operands = vec![base];
+                operands.extend(indices);
+                operands
+            }
+            InstructionKind::ExtractValue { aggregate, .. } => vec![aggregate],
+            InstructionKind::InsertValue {
+                aggregate, value, ..
+            } => vec![aggregate, value],
+            InstructionKind::ExtractElement { vector, index } => vec![vector, index],
+            InstructionKind::InsertElement {
+                vector,
+                value,
+                index,
+            } => vec![vector, value, index],
+            InstructionKind::ShuffleVector { lhs, rhs, mask } => vec![lhs, rhs, mask],
+            InstructionKind::Phi { incoming, .. } => incoming.iter().map(|(x, _)| x).collect(),
+            InstructionKind::Select {
+                condition,
+                if_true,
+                if_false,
+            } => vec![condition, if_true, if_false],
+            InstructionKind::Call {
+                callee, arguments, ..
+            }
+            | InstructionKind::Invoke {
+                callee, arguments, ..
+            } => {
+                let mut operands = vec![callee];
+                operands.extend(arguments);
+                operands
+            }
+            InstructionKind::LandingPad { clauses, .. } => clauses
+                .iter()
+                .map(|x| match x {
+                    LandingPadClause::Catch(value) | LandingPadClause::Filter(value) => value,
+                })
+                .collect(),
+            InstructionKind::DeclareVariable { address, .. } => vec![address],
+            InstructionKind::Return { value } => value.iter().collect(),
+            InstructionKind::Branch { .. } => vec![],
+            InstructionKind::ConditionalBranch { condition, .. } => vec![condition],
+            InstructionKind::Switch { value, cases, .. } => {
+                let mut operands = vec![value];
+                operands.extend(cases.iter().map(|(x, _)| x));
+                operands
+            }
+            InstructionKind::IndirectBranch { address, .. } => vec![address],
+            InstructionKind::Resume { value } => vec![value],
+            InstructionKind::Unreachable => vec![],
+        }
+    }
+
+    /// Returns the values used by this instruction, in the same order as `operands`,
+    /// so they can be replaced.
+    pub fn operands_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            InstructionKind::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            InstructionKind::FloatNegate { value } | InstructionKind::Cast { value, .. } => {
+                vec![value]
+            }
+            InstructionKind::Alloca { .. } => vec![],
+            InstructionKind::Load { pointer, .. } => vec![pointer],
+            InstructionKind::Store { value, pointer, .. }
+            | InstructionKind::AtomicRmw { pointer, value, .. } => vec![value, pointer],
+            InstructionKind::CompareExchange {
+                pointer,
+                expected,
+                replacement,
+                ..
+            } => vec![pointer, expected, replacement],
+            InstructionKind::Fence { .. } => vec![],
+            InstructionKind::GetElementPtr { base, indices, .. } => {
+                let mut operands = vec![base];
+                operands.extend(indices);
+                operands
+            }
+            InstructionKind::ExtractValue { aggregate, .. } => vec![aggregate],
+            InstructionKind::InsertValue {
+                aggregate, value, ..
+            } => vec![aggregate, value],
+            InstructionKind::ExtractElement { vector, index } => vec![vector, index],
+            InstructionKind::InsertElement {
+                vector,
+                value,
+                index,
+            } => vec![vector, value, index],
+            InstructionKind::ShuffleVector { lhs, rhs, mask } => vec![lhs, rhs, mask],
+            InstructionKind::Phi { incoming, .. } => incoming.iter_mut().map(|(x, _)| x).collect(),
+            InstructionKind::Select {
+                condition,
+                if_true,
+                if_false,
+            } => vec![condition, if_true, if_false],
+            InstructionKind::Call {
+                callee, arguments, ..
+            }
+            | InstructionKind::Invoke {
+                callee, arguments, ..
+            } => {
+                let mut operands = vec![callee];
+                operands.extend(arguments);
+                operands
+            }
+            InstructionKind::LandingPad { clauses, .. } => clauses
+                .iter_mut()
+                .map(|x| match x {
+                    LandingPadClause::Catch(value) | LandingPadClause::Filter(value) => value,
+                })
+                .collect(),
+            InstructionKind::DeclareVariable { address, .. } => vec![address],
+            InstructionKind::Return { value } => value.iter_mut().collect(),
+            InstructionKind::Branch { .. } => vec![],
+            InstructionKind::ConditionalBranch { condition, .. } => vec![condition],
+            InstructionKind::Switch { value, cases, .. } => {
+                let mut operands = vec![value];
+                operands.extend(cases.iter_mut().map(|(x, _)| x));
+                operands
+            }
+            InstructionKind::IndirectBranch { address, .. } => vec![address],
+            InstructionKind::Resume { value } => vec![value],
+            InstructionKind::Unreachable => vec![],
+        }
+    }
+
+    /// Returns true if this instruction ends a basic block.
+    pub fn is_terminator(&self) -> bool {
+        matches!(
+            self,
+            InstructionKind::Return { .. }
+                | InstructionKind::Branch { .. }
+                | InstructionKind::ConditionalBranch { .. }
+                | InstructionKind::Switch { .. }
+                | InstructionKind::IndirectBranch { .. }
+                | InstructionKind::Invoke { .. }
+                | InstructionKind::Resume { .. }
+                | InstructionKind::Unreachable
+        )
+    }
+
+    /// Returns true if this instruction does nothing besides computing its result,
+    /// so it can be removed once the result is unused.
+    pub fn is_pure(&self) -> bool {
+        match self {
+            InstructionKind::Load {
+                ordering, options, ..
+            } => ordering.is_none() && !options.volatile,
+            InstructionKind::Store { .. }
+            | InstructionKind::AtomicRmw { .. }
+            | InstructionKind::CompareExchange { .. }
+            | InstructionKind::Fence { .. }
+            | InstructionKind::Call { .. }
+            | InstructionKind::LandingPad { .. }
+            | InstructionKind::DeclareVariable { .. } => false,
+            _ => !self.is_terminator(),
+        }
+    }
+
+    /// Returns the blocks control can continue to after this instruction.
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            InstructionKind::Branch { target } => vec![*target],
+            InstructionKind::ConditionalBranch {
+                if_true, if_false, ..
+            } => vec![*if_true, *if_false],
+            InstructionKind::Switch { default, cases, .. } => {
+                let mut successors = vec![*default];
+                successors.extend(cases.iter().map(|(_, x)| *x));
+                successors
+            }
+            InstructionKind::IndirectBranch { destinations, .. } => destinations.clone(),
+            InstructionKind::Invoke { normal, unwind, .. } => vec![*normal, *unwind],
+            _ => vec![],
+        }
+    }
+
+    /// Returns the blocks this instruction refers to, its successors followed by
+    /// the blocks `phi` values arrive from, so they can be replaced.
+    pub(crate) fn blocks_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            InstructionKind::Phi { incoming, .. } => incoming.iter_mut().map(|(_, x)| x).collect(),
+            InstructionKind::Branch { target } => vec![target],
+            InstructionKind::ConditionalBranch {
+                if_true, if_false, ..
+            } => vec![if_true, if_false],
+            InstructionKind::Switch { default, cases, .. } => {
+                let mut blocks = vec![default];
+                blocks.extend(cases.iter_mut().map(|(_, x)| x));
+                blocks
+            }
+            InstructionKind::IndirectBranch { destinations, .. } => {
+                destinations.iter_mut().collect()
+            }
+            InstructionKind::Invoke { normal, unwind, .. } => vec![normal, unwind],
+            _ => vec![],
+        }
+    }
+
+    /// Checks that the operands of this instruction have types it accepts.
+    pub fn check_types(&self) -> Result<(), String> {
+        match self {
+            InstructionKind::Binary {
+                operator,
+                flags,
+                lhs,
+                rhs,
+            } => binop::check_binop(*operator, lhs, rhs, *flags).map_err(|e| e.to_string()),
+            InstructionKind::FloatNegate { value } => match value.ty().scalar().is_floating_point()
+            {
+                true => Ok(()),
+                false => Err(format!(
+                    "fneg requires a floating point, found {:?}",
+                    value.ty()
+                )),
+            },
+            InstructionKind::Cast {
+                operator,
+                value,
+                target,
+            } => cast::check_cast(*operator, value, target).map_err(|e| e.to_string()),
+            InstructionKind::Load {
+                ty,
+                pointer,
+                ordering: Some(ordering),
+                options,
+            } => atomic::check_atomic_load(ty, pointer, *ordering, options)
+                .map_err(|e| e.to_string()),
+            InstructionKind::Store {
+                value,
+                pointer,
+                ordering: Some(ordering),
+                options,
+            } => atomic::check_atomic_store(value, pointer, *ordering, options)
+                .map_err(|e| e.to_string()),
+            InstructionKind::Load { pointer, .. } => memory::check_pointer("load", pointer),
+            InstructionKind::Store { pointer, .. } => memory::check_pointer("store", pointer),
+            InstructionKind::AtomicRmw {
+                operator,
+                pointer,
+                value,
+                ordering,
+                ..
+            } => atomic::check_atomicrmw(*operator, pointer, value, *ordering)
+                .map_err(|e| e.to_string()),
+            InstructionKind::CompareExchange {
+                pointer,
+                expected,
+                replacement,
+                success,
+                failure,
+                ..
+            } => atomic::check_cmpxchg(pointer, expected, replacement, *success, *failure)
+                .map_err(|e| e.to_string()),
+            InstructionKind::Fence { ordering, .. } => {
+                atomic::check_fence(*ordering).map_err(|e| e.to_string())
+            }
+            InstructionKind::GetElementPtr { base, .. } => {
+                memory::check_pointer("getelementptr", base)
+            }
+            InstructionKind::DeclareVariable { address, .. } => {
+                memory::check_pointer("#dbg_declare", address)
+            }
+            InstructionKind::ExtractValue { aggregate, index }
+            | InstructionKind::InsertValue {
+                aggregate, index, ..
+            } => element_type(aggregate.ty(), *index)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            InstructionKind::ExtractElement { vector, index } => {
+                vector::check_element(vector, index)
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            }
+            InstructionKind::InsertElement {
+                vector,
+                value,
+                index,
+            } => vector::check_insertelement(vector, value, index).map_err(|e| e.to_string()),
+            InstructionKind::ShuffleVector { lhs, rhs, mask } => {
+                vector::check_shufflevector(lhs, rhs, mask)
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            }
+            InstructionKind::Phi { ty, incoming } => {
+                match incoming.iter().find(|(x, _)| x.ty() != ty) {
+                    Some((value, _)) => Err(format!(
+                        "expected phi incoming type {:?}, found {:?}",
+                        ty,
+                        value.ty()
+                    )),
+                    None => Ok(()),
+                }
+            }
+            InstructionKind::Select {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                if *condition.ty() != Type::Integer(1) {
+                    return Err(format!(
+                        "select condition must be of type i1, found {:?}",
+                        condition.ty()
+                    ));
+                }
+                if if_true.ty() != if_false.ty() {
+                    return Err(format!(
+                        "expected select operand type {:?}, found {:?}",
+                        if_true.ty(),
+                        if_false.ty()
+                    ));
+                }
+                Ok(())
+            }
+            InstructionKind::Call {
+                signature,
+                arguments,
+                ..
+            } => call::check_call(signature, arguments).map_err(|e| e.to_string()),
+            InstructionKind::Invoke {
+                options,
+                signature,
+                arguments,
+                ..
+            } => exception::check_invoke(signature, arguments, options).map_err(|e| e.to_string()),
+            InstructionKind::LandingPad {
+                cleanup, clauses, ..
+            } => exception::check_landingpad(*cleanup, clauses).map_err(|e| e.to_string()),
+            InstructionKind::ConditionalBranch { condition, .. }
+                if *condition.ty() != Type::Integer(1) =>
+            {
+                Err(format!(
+                    "branch condition must be of type i1, found {:?}",
+                    condition.ty()
+                ))
+            }
+            InstructionKind::Switch { value, cases, .. } => {
+                let cases = cases.iter().map(|(x, _)| x).collect::<Vec<_>>();
+                flow::check_switch(value, &cases).map_err(|e| e.to_string())
+            }
+            InstructionKind::IndirectBranch { address, .. } => {
+                memory::check_pointer("indirectbr", address)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Represents an instruction in LLVM IR, living in the arena of a function body.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instruction {
+    kind: InstructionKind,
+    result: Option<Value>,
+    block: Option<BlockId>,
+    metadata: Vec<(String, Metadata)>,
+}
+
+impl Instruction {
+    /// Returns the operation of this instruction.
+    pub fn kind(&self) -> &InstructionKind {
+        &self.kind
+    }
 
-/// Represents a basic block in LLVM IR.
+    /// Returns the register defined by this instruction, if any.
+    pub fn result(&self) -> Option<&Value> {
+        self.result.as_ref()
+    }
+
+    /// Returns the block this instruction is in, or nothing if it has been removed.
+    pub fn block(&self) -> Option<BlockId> {
+        self.block
+    }
+
+    /// Returns the metadata attached to this instruction, such as its `!dbg` location.
+    pub fn metadata(&self) -> &[(String, Metadata)] {
+        &self.metadata
+    }
+}
+
+/// Represents a basic block in LLVM IR, living in the arena of a function body.
+#[derive(Clone, Debug, PartialEq)]
 pub struct BasicBlock {
-    basic_block_index: Arc<AtomicUsize>,
-    ssa_register_index: Arc<AtomicUsize>,
     label: String,
-    instructions: Vec<Box<dyn Instruction + Send>>,
-    pub(crate) children: Vec<SharedBasicBlock>,
+    instructions: Vec<InstId>,
+    /// The blocks created from this block, which are laid out right after it.
+    children: Vec<BlockId>,
+    debug_location: Option<Metadata>,
+}
+
+impl BasicBlock {
+    /// Returns the label of this block.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the instructions of this block, in order.
+    pub fn instructions(&self) -> &[InstId] {
+        &self.instructions
+    }
+
+    /// Returns the blocks created from this block.
+    pub fn children(&self) -> &[BlockId] {
+        &self.children
+    }
+
+    /// Returns the last instruction of this block, if it is a terminator.
+    pub fn terminator(&self, body: &FunctionBody) -> Option<InstId> {
+        self.instructions
+            .last()
+            .copied()
+            .filter(|x| body.instruction(*x).kind.is_terminator())
+    }
 }
 
-/// A basic block with multiple owners.
+/// A handle to a basic block of a function body that is being built.
+///
+/// Every block of a body shares its arena, so handles can be cloned and kept around freely.
 #[derive(Clone)]
 pub struct SharedBasicBlock {
-    inner: Arc<Mutex<BasicBlock>>,
+    body: Arc<Mutex<FunctionBody>>,
+    id: BlockId,
 }
 
 impl SharedBasicBlock {
-    /// Creates a new shared basic block.
-    pub fn new(bb: BasicBlock) -> Self {
-        SharedBasicBlock {
-            inner: Arc::new(Mutex::new(bb)),
+    /// Creates a handle to the entry block of the function body.
+    pub(crate) fn entry(body: Arc<Mutex<FunctionBody>>) -> Self {
+        let id = body.lock().unwrap().entry();
+        SharedBasicBlock { body, id }
+    }
+
+    /// Returns the identifier of this block.
+    pub fn id(&self) -> BlockId {
+        self.id
+    }
+
+    /// Returns the label of this block.
+    pub fn label(&self) -> String {
+        self.unlock(|body| body.block(self.id).label.clone())
+    }
+
+    /// Gain temporary access to the function body this block is in, and get a value out.
+    pub fn unlock<T, F: FnOnce(&mut FunctionBody) -> T>(&self, f: F) -> T {
+        f(&mut self.body.lock().unwrap())
+    }
+
+    /// Returns the identifier of another block of the same function body.
+    pub(crate) fn target(&self, block: &SharedBasicBlock) -> BlockId {
+        if !Arc::ptr_eq(&self.body, &block.body) {
+            panic!("cannot refer to a block of another function body");
         }
+        block.id
     }
 
-    /// Gain temporary access to the data inside.
-    pub fn unlock<F: FnOnce(MutexGuard<BasicBlock>)>(&self, f: F) {
-        let inner = self.inner.lock().unwrap();
-        f(inner);
+    /// Pushes a new instruction into this block, defining the given register.
+    /// If a debug location is set, it is attached to the instruction as `!dbg`.
+    pub fn push_instruction(&self, kind: InstructionKind, result: Option<Value>) -> InstId {
+        self.unlock(|body| {
+            // debug records carry their own location, and never have a `!dbg` attachment
+            let location = match kind {
+                InstructionKind::DeclareVariable { .. } => None,
+                _ => body.block(self.id).debug_location.clone(),
+            };
+            let id = body.add_instruction(Instruction {
+                kind,
+                result,
+                block: Some(self.id),
+                metadata: location
+                    .map(|x| vec![("dbg".to_string(), x)])
+                    .unwrap_or_default(),
+            });
+            body.block_mut(self.id).instructions.push(id);
+            body.index_uses(id);
+            id
+        })
     }
 
-    /// Gain temporary access to the data inside, and get a value out.
-    pub fn unlock_out<T, F: FnOnce(MutexGuard<BasicBlock>) -> T>(&self, f: F) -> T {
-        let inner = self.inner.lock().unwrap();
-        f(inner)
+    /// Sets the debug location attached to every instruction pushed into this block afterwards.
+    /// Blocks created from this block start out with the same location.
+    pub fn set_debug_location(&self, location: Option<Metadata>) {
+        self.unlock(|body| body.block_mut(self.id).debug_location = location);
     }
 
-    /// Pushes a new instruction into this block.
-    pub fn push_instruction(&self, instruction: Box<dyn Instruction + Send>) {
-        let mut inner = self.inner.lock().unwrap();
-        inner.instructions.push(instruction);
+    /// Attaches metadata of the given kind to the last instruction pushed into this block,
+    /// such as `!range` or `!nonnull`.
+    pub fn attach_metadata(&self, kind: &str, metadata: Metadata) {
+        self.unlock(|body| {
+            let Some(last) = body.block(self.id).instructions.last().copied() else {
+                panic!("cannot attach !{} metadata to an empty block", kind);
+            };
+            body.instructions[last.0]
+                .metadata
+                .push((kind.to_string(), metadata));
+        });
     }
 
     /// Creates a local register
     pub fn create_local_register(&self, ty: Type) -> (String, Value) {
-        let idx = self
-            .unlock_out(|x| x.basic_block_index.clone())
-            .fetch_add(1, Ordering::AcqRel);
-        (
-            format!("r{idx}"),
-            Value::LocalIdentifier(format!("r{idx}"), ty),
-        )
+        let value = self.unlock(|body| body.create_register(ty));
+        let Value::LocalIdentifier(name, _) = &value else {
+            unreachable!("registers are always local identifiers");
+        };
+        (name.clone(), value)
+    }
+
+    /// Creates a child of the block, which is laid out after it.
+    pub fn child(&self) -> SharedBasicBlock {
+        let id = self.unlock(|body| {
+            let label = format!("bb{}", body.next_block());
+            let id = body.add_block(&label);
+            body.block_mut(id).debug_location = body.block(self.id).debug_location.clone();
+            body.block_mut(self.id).children.push(id);
+            body.update_layout();
+            id
+        });
+        SharedBasicBlock {
+            body: self.body.clone(),
+            id,
+        }
     }
 
-    /// Creates a child of the block.
-    pub fn create_child<F: FnOnce(SharedBasicBlock)>(&self, f: F) -> String {
-        let bb = BasicBlock::child(self);
+    /// Creates a child of the block, and builds it with the handler.
+    pub fn create_child<F: FnOnce(SharedBasicBlock)>(&self, f: F) -> BlockId {
+        let bb = self.child();
         f(bb.clone());
-        bb.unlock_out(|x| x.label.clone()).clone()
+        bb.id
     }
 }
@@ -1,184 +1,144 @@
-use crate::instruction::{Instruction, SharedBasicBlock};
+use crate::error::BuildError;
+use crate::instruction::{InstructionKind, SharedBasicBlock};
 use crate::types::Type;
 use crate::value::Value;
-use crate::IRComponent;
-use std::boxed::Box;
-use std::string::{String, ToString};
 use std::vec::Vec;
 
-pub struct ExtractValue {
-    returns_in: String,
-    structure: Value,
-    index: usize,
-}
-
-impl IRComponent for ExtractValue {
-    fn append_to_string(&self, string: &mut String) {
-        string.push('%');
-        string.push_str(&self.returns_in);
-        string.push_str(" = extractvalue ");
-
-        self.structure.append_to_string(string);
-        string.push_str(", ");
-        string.push_str(&self.index.to_string());
+impl SharedBasicBlock {
+    /// Extracts a value out of the aggregate at the index.
+    pub fn extractvalue(&self, structure: Value, index: usize) -> Value {
+        self.try_extractvalue(structure, index)
+            .unwrap_or_else(|e| panic!("{}", e))
     }
-}
-impl Instruction for ExtractValue {}
 
-pub struct InsertValue {
-    returns_in: String,
-    structure: Value,
-    insertion: Value,
-    index: usize,
-}
+    /// Extracts a value out of the aggregate at the index,
+    /// or returns an error if the index is not inside of the aggregate.
+    pub fn try_extractvalue(&self, structure: Value, index: usize) -> Result<Value, BuildError> {
+        let element = element_type(structure.ty(), index)?;
+        let (_, value) = self.create_local_register(element);
+        self.push_instruction(
+            InstructionKind::ExtractValue {
+                aggregate: structure,
+                index,
+            },
+            Some(value.clone()),
+        );
+        Ok(value)
+    }
 
-impl IRComponent for InsertValue {
-    fn append_to_string(&self, string: &mut String) {
-        string.push('%');
-        string.push_str(&self.returns_in);
-        string.push_str(" = insertvalue ");
+    /// Inserts a value into the aggregate at the index.
+    pub fn insertvalue(&self, structure: Value, insertion: Value, index: usize) -> Value {
+        self.try_insertvalue(structure, insertion, index)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
 
-        self.structure.append_to_string(string);
-        string.push_str(", ");
-        self.insertion.append_to_string(string);
-        string.push_str(", ");
-        string.push_str(&self.index.to_string());
+    /// Inserts a value into the aggregate at the index, or returns an error if the index
+    /// is not inside of the aggregate, or the value is not of the element type.
+    pub fn try_insertvalue(
+        &self,
+        structure: Value,
+        insertion: Value,
+        index: usize,
+    ) -> Result<Value, BuildError> {
+        let element = element_type(structure.ty(), index)?;
+        if element != *insertion.ty() {
+            return Err(BuildError::TypeMismatch {
+                expected: element,
+                found: insertion.ty().clone(),
+            });
+        }
+        let (_, value) = self.create_local_register(structure.ty().clone());
+        self.push_instruction(
+            InstructionKind::InsertValue {
+                aggregate: structure,
+                value: insertion,
+                index,
+            },
+            Some(value.clone()),
+        );
+        Ok(value)
     }
-}
-impl Instruction for InsertValue {}
 
-pub struct GetElementPtr {
-    returns_in: String,
-    ty: Type,
-    base: Value,
-    indices: Vec<Value>,
-}
+    /// Gets a pointer to the element at the aggregate at the index
+    ///
+    /// The first index steps over whole values of type `ty` behind the base pointer,
+    /// every following index steps into the aggregate.
+    pub fn getelementptr(&self, ty: Type, base: Value, indices: Vec<Value>) -> Value {
+        self.try_getelementptr(ty, base, indices)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
 
-impl IRComponent for GetElementPtr {
-    fn append_to_string(&self, string: &mut String) {
-        string.push('%');
-        string.push_str(&self.returns_in);
-        string.push_str(" = getelementptr ");
-        self.ty.append_to_string(string);
-        string.push_str(", ");
-        self.base.append_to_string(string);
-        for value in &self.indices {
-            string.push_str(", ");
-            value.append_to_string(string);
-        }
+    /// Gets a pointer to the element at the aggregate at the index, or returns an error
+    /// if the indices do not lead into the aggregate.
+    pub fn try_getelementptr(
+        &self,
+        ty: Type,
+        base: Value,
+        indices: Vec<Value>,
+    ) -> Result<Value, BuildError> {
+        check_getelementptr(&ty, &base, &indices)?;
+        let (_, value) = self.create_local_register(Type::Ptr);
+        self.push_instruction(
+            InstructionKind::GetElementPtr { ty, base, indices },
+            Some(value.clone()),
+        );
+        Ok(value)
     }
 }
-impl Instruction for GetElementPtr {}
 
-impl SharedBasicBlock {
-    /// Extracts a value out of the aggregate at the index.
-    pub fn extractvalue(&self, structure: Value, index: usize) -> Value {
-        match structure.ty() {
-            Type::Structure(parameters) => {
-                let (name, value) =
-                    self.create_local_register(parameters.get(index).unwrap().clone());
-                self.push_instruction(Box::new(ExtractValue {
-                    returns_in: name,
-                    structure,
-                    index,
-                }));
-                value
-            }
-            Type::Array(length, element) => {
-                if index > (*length as usize) {
-                    panic!("extractvalue index out of bounds");
-                }
-                let (name, value) = self.create_local_register(*element.clone());
-                self.push_instruction(Box::new(ExtractValue {
-                    returns_in: name,
-                    structure,
-                    index,
-                }));
-                value
-            }
-            _ => panic!("extractvalue requires a structure or array type"),
-        }
+/// Checks that the base of a `getelementptr` is a pointer,
+/// and that every index after the first leads into an aggregate.
+pub(crate) fn check_getelementptr(
+    ty: &Type,
+    base: &Value,
+    indices: &[Value],
+) -> Result<(), BuildError> {
+    if !base.ty().is_pointer() {
+        return Err(BuildError::NotPointer(base.ty().clone()));
     }
-
-    /// Inserts a value into the aggregate at the index.
-    pub fn insertvalue(&self, structure: Value, insertion: Value, index: usize) -> Value {
-        match structure.ty() {
-            Type::Structure(parameters) => {
-                let (name, value) = self.create_local_register(structure.ty().clone());
-                if parameters.get(index).unwrap().clone() != *insertion.ty() {
-                    panic!(
-                        "expected parameter type {:?}, found {:?}",
-                        value.ty(),
-                        insertion.ty()
-                    );
-                }
-                self.push_instruction(Box::new(InsertValue {
-                    returns_in: name,
-                    structure,
-                    insertion,
-                    index,
-                }));
-                value
+    let mut param_ty: Type = ty.clone();
+    for index in indices.iter().skip(1) {
+        param_ty = match param_ty.resolved() {
+            Type::Array(_, param) => *param.clone(),
+            Type::Structure(_) | Type::PackedStructure(_) => {
+                let constant = match index {
+                    Value::Number(number, _) => number.parse::<usize>().ok(),
+                    _ => None,
+                };
+                let Some(constant) = constant else {
+                    return Err(BuildError::NonConstantIndex(index.clone()));
+                };
+                element_type(&param_ty, constant)?
             }
-            Type::Array(length, element) => {
-                if index > (*length as usize) {
-                    panic!("insertvalue index out of bounds");
-                }
-                let (name, value) = self.create_local_register(structure.ty().clone());
-                if **element != *insertion.ty() {
-                    panic!(
-                        "expected parameter type {:?}, found {:?}",
-                        value.ty(),
-                        insertion.ty()
-                    );
-                }
-                self.push_instruction(Box::new(InsertValue {
-                    returns_in: name,
-                    structure,
-                    insertion,
-                    index,
-                }));
-                value
-            }
-            _ => panic!("insertvalue requires a structure or array type"),
-        }
+            _ => return Err(BuildError::NotAggregate(param_ty)),
+        };
     }
+    Ok(())
+}
 
-    /// Gets a pointer to the element at the aggregate at the index
-    pub fn getelementptr(&self, ty: Type, base: Value, indices: Vec<Value>) -> Value {
-        let mut param_ty: Type = ty.clone();
-        for index in &indices {
-            match param_ty {
-                Type::Array(_, param) => {
-                    param_ty = *param.clone();
-                }
-                Type::Structure(params) => {
-                    let Value::Number(length, _) = index.clone() else {
-                        panic!("getelementptr index for a structure must be an integer");
-                    };
-                    param_ty = params
-                        .get(length.parse::<usize>().unwrap())
-                        .unwrap()
-                        .clone();
-                }
-                _ => panic!("getelementptr requires a structure or array type"),
-            }
+/// Returns the type of the element at the index of the aggregate type.
+pub(crate) fn element_type(ty: &Type, index: usize) -> Result<Type, BuildError> {
+    let out_of_bounds = || BuildError::IndexOutOfBounds {
+        index,
+        ty: ty.clone(),
+    };
+    match ty.resolved() {
+        Type::Structure(parameters) | Type::PackedStructure(parameters) => {
+            parameters.get(index).cloned().ok_or_else(out_of_bounds)
         }
-        let (name, value) = self.create_local_register(Type::Ptr);
-        self.push_instruction(Box::new(GetElementPtr {
-            returns_in: name,
-            ty,
-            base,
-            indices,
-        }));
-        value
+        Type::Array(length, element) => match index < length as usize {
+            true => Ok(*element),
+            false => Err(out_of_bounds()),
+        },
+        _ => Err(BuildError::NotAggregate(ty.clone())),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::module::{FunctionBody, GlobalFunction};
-    use crate::types::Types;
+    use crate::error::BuildError;
+    use crate::module::{FunctionBody, GlobalFunction, Module};
+    use crate::types::{Type, Types};
     use crate::value::Values;
     use crate::IRComponent;
     use std::vec;
@@ -225,4 +185,66 @@ mod tests {
             }"
         );
     }
+
+    #[test]
+    fn build_named_type_function() {
+        let mut module = Module::new();
+        let node = module.define_type(
+            "Node",
+            Types::structure(vec![Types::integer(32), Type::Ptr]),
+        );
+        let body = FunctionBody::new(|block| {
+            let stack_ptr = block.alloca(node.clone());
+            let next_ptr = block.getelementptr(
+                node.clone(),
+                stack_ptr.clone(),
+                vec![Values::integer("0", 32), Values::integer("1", 32)],
+            );
+            block.store(stack_ptr.clone(), next_ptr);
+            let loaded = block.load(node.clone(), stack_ptr);
+            let value = block.extractvalue(loaded, 0);
+            block.ret(value);
+        });
+        let f = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(
+            f.emit(),
+            "define i32 @main() { \
+                entry: \
+                    %r0 = alloca %Node \
+                    %r1 = getelementptr %Node, ptr %r0, i32 0, i32 1 \
+                    store ptr %r0, ptr %r1 \
+                    %r2 = load %Node, ptr %r0 \
+                    %r3 = extractvalue %Node %r2, 0 \
+                    ret i32 %r3 \
+            }"
+        );
+    }
+
+    #[test]
+    fn reject_out_of_bounds_indices() {
+        FunctionBody::new(|block| {
+            let array = Values::zeroinitializer(Types::array(2, Types::integer(32)));
+            assert_eq!(
+                block.try_extractvalue(array.clone(), 2),
+                Err(BuildError::IndexOutOfBounds {
+                    index: 2,
+                    ty: Types::array(2, Types::integer(32)),
+                })
+            );
+            assert!(block
+                .try_insertvalue(array, Values::integer("1", 64), 1)
+                .is_err());
+            let pair = Types::structure(vec![Types::integer(32), Types::integer(32)]);
+            let pointer = block.alloca(pair.clone());
+            let index = block.add(Values::integer("0", 32), Values::integer("1", 32));
+            assert_eq!(
+                block.try_getelementptr(
+                    pair,
+                    pointer,
+                    vec![Values::integer("0", 32), index.clone()]
+                ),
+                Err(BuildError::NonConstantIndex(index))
+            );
+        });
+    }
 }
@@ -0,0 +1,677 @@
+use crate::error::BuildError;
+use crate::instruction::{InstructionKind, MemoryOptions, SharedBasicBlock};
+use crate::types::{Type, Types};
+use crate::value::{append_escaped, Value};
+use crate::IRComponent;
+use std::format;
+use std::string::{String, ToString};
+use std::vec;
+
+/// Represents how an atomic operation synchronizes with other threads.
+///
+/// Every ordering gives the guarantees of the orderings before it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AtomicOrdering {
+    /// The operation is atomic, but gives no guarantees about other memory, `unordered`.
+    /// Only allowed on loads and stores.
+    Unordered,
+    /// Every operation on the same address happens in a single total order, `monotonic`.
+    Monotonic,
+    /// Memory operations after this operation are not moved before it, `acquire`.
+    Acquire,
+    /// Memory operations before this operation are not moved after it, `release`.
+    Release,
+    /// Both `acquire` and `release`, `acq_rel`.
+    AcquireRelease,
+    /// Both `acquire` and `release`, and every sequentially consistent operation happens
+    /// in a single total order, `seq_cst`.
+    SequentiallyConsistent,
+}
+
+impl AtomicOrdering {
+    fn acquires(self) -> bool {
+        matches!(
+            self,
+            AtomicOrdering::Acquire
+                | AtomicOrdering::AcquireRelease
+                | AtomicOrdering::SequentiallyConsistent
+        )
+    }
+
+    fn releases(self) -> bool {
+        matches!(
+            self,
+            AtomicOrdering::Release
+                | AtomicOrdering::AcquireRelease
+                | AtomicOrdering::SequentiallyConsistent
+        )
+    }
+}
+
+impl IRComponent for AtomicOrdering {
+    fn append_to_string(&self, string: &mut String) {
+        match self {
+            AtomicOrdering::Unordered => string.push_str("unordered"),
+            AtomicOrdering::Monotonic => string.push_str("monotonic"),
+            AtomicOrdering::Acquire => string.push_str("acquire"),
+            AtomicOrdering::Release => string.push_str("release"),
+            AtomicOrdering::AcquireRelease => string.push_str("acq_rel"),
+            AtomicOrdering::SequentiallyConsistent => string.push_str("seq_cst"),
+        }
+    }
+}
+
+/// Represents the threads an atomic operation synchronizes with.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum SyncScope {
+    /// Every thread of the system, which is written as no scope at all.
+    #[default]
+    System,
+    /// Only the current thread, such as a signal handler running on it, `syncscope("singlethread")`.
+    SingleThread,
+    /// A scope defined by the target, such as `syncscope("agent")` on AMDGPU.
+    Target(String),
+}
+
+impl IRComponent for SyncScope {
+    fn append_to_string(&self, string: &mut String) {
+        let name = match self {
+            SyncScope::System => return,
+            SyncScope::SingleThread => "singlethread",
+            SyncScope::Target(name) => name,
+        };
+        string.push_str("syncscope(\"");
+        append_escaped(string, name.as_bytes());
+        string.push_str("\")");
+    }
+}
+
+/// Represents the operations of `atomicrmw`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AtomicRmwOperator {
+    /// Replaces the value in memory, `xchg`.
+    Exchange,
+    /// Integer addition, `add`.
+    Add,
+    /// Integer subtraction, `sub`.
+    Sub,
+    /// Bitwise and, `and`.
+    And,
+    /// Bitwise not-and, `nand`.
+    Nand,
+    /// Bitwise or, `or`.
+    Or,
+    /// Bitwise exclusive or, `xor`.
+    Xor,
+    /// Signed integer maximum, `max`.
+    Max,
+    /// Signed integer minimum, `min`.
+    Min,
+    /// Unsigned integer maximum, `umax`.
+    UnsignedMax,
+    /// Unsigned integer minimum, `umin`.
+    UnsignedMin,
+    /// Floating-point addition, `fadd`.
+    FloatAdd,
+    /// Floating-point subtraction, `fsub`.
+    FloatSub,
+    /// Floating-point maximum, ignoring NaN, `fmax`.
+    FloatMax,
+    /// Floating-point minimum, ignoring NaN, `fmin`.
+    FloatMin,
+    /// Increments, wrapping to zero once the value is reached, `uinc_wrap`.
+    UnsignedIncrementWrap,
+    /// Decrements, wrapping to the value once below zero or above it, `udec_wrap`.
+    UnsignedDecrementWrap,
+    /// Subtracts the value unless it would wrap around, `usub_cond`.
+    UnsignedSubtractConditional,
+    /// Subtracts the value, clamping at zero, `usub_sat`.
+    UnsignedSubtractSaturate,
+}
+
+impl AtomicRmwOperator {
+    /// Returns true if the operation works on floating points instead of integers.
+    pub fn is_floating_point(self) -> bool {
+        matches!(
+            self,
+            AtomicRmwOperator::FloatAdd
+                | AtomicRmwOperator::FloatSub
+                | AtomicRmwOperator::FloatMax
+                | AtomicRmwOperator::FloatMin
+        )
+    }
+}
+
+impl IRComponent for AtomicRmwOperator {
+    fn append_to_string(&self, string: &mut String) {
+        match self {
+            AtomicRmwOperator::Exchange => string.push_str("xchg"),
+            AtomicRmwOperator::Add => string.push_str("add"),
+            AtomicRmwOperator::Sub => string.push_str("sub"),
+            AtomicRmwOperator::And => string.push_str("and"),
+            AtomicRmwOperator::Nand => string.push_str("nand"),
+            AtomicRmwOperator::Or => string.push_str("or"),
+            AtomicRmwOperator::Xor => string.push_str("xor"),
+            AtomicRmwOperator::Max => string.push_str("max"),
+            AtomicRmwOperator::Min => string.push_str("min"),
+            AtomicRmwOperator::UnsignedMax => string.push_str("umax"),
+            AtomicRmwOperator::UnsignedMin => string.push_str("umin"),
+            AtomicRmwOperator::FloatAdd => string.push_str("fadd"),
+            AtomicRmwOperator::FloatSub => string.push_str("fsub"),
+            AtomicRmwOperator::FloatMax => string.push_str("fmax"),
+            AtomicRmwOperator::FloatMin => string.push_str("fmin"),
+            AtomicRmwOperator::UnsignedIncrementWrap => string.push_str("uinc_wrap"),
+            AtomicRmwOperator::UnsignedDecrementWrap => string.push_str("udec_wrap"),
+            AtomicRmwOperator::UnsignedSubtractConditional => string.push_str("usub_cond"),
+            AtomicRmwOperator::UnsignedSubtractSaturate => string.push_str("usub_sat"),
+        }
+    }
+}
+
+fn check_pointer(pointer: &Value) -> Result<(), BuildError> {
+    match pointer.ty().is_pointer() {
+        true => Ok(()),
+        false => Err(BuildError::NotPointer(pointer.ty().clone())),
+    }
+}
+
+fn invalid_ordering(instruction: &str, ordering: AtomicOrdering) -> BuildError {
+    BuildError::InvalidOrdering {
+        instruction: instruction.to_string(),
+        ordering,
+    }
+}
+
+fn invalid_type(instruction: &str, ty: &Type) -> BuildError {
+    BuildError::InvalidAtomicType {
+        instruction: instruction.to_string(),
+        ty: ty.clone(),
+    }
+}
+
+/// Checks an atomic load, which must not release.
+pub(crate) fn check_atomic_load(
+    ty: &Type,
+    pointer: &Value,
+    ordering: AtomicOrdering,
+    options: &MemoryOptions,
+) -> Result<(), BuildError> {
+    if ordering.releases() && ordering != AtomicOrdering::SequentiallyConsistent {
+        return Err(invalid_ordering("load atomic", ordering));
+    }
+    check_atomic_access("load atomic", ty, pointer, options)
+}
+
+/// Checks an atomic store, which must not acquire.
+pub(crate) fn check_atomic_store(
+    value: &Value,
+    pointer: &Value,
+    ordering: AtomicOrdering,
+    options: &MemoryOptions,
+) -> Result<(), BuildError> {
+    if ordering.acquires() && ordering != AtomicOrdering::SequentiallyConsistent {
+        return Err(invalid_ordering("store atomic", ordering));
+    }
+    check_atomic_access("store atomic", value.ty(), pointer, options)
+}
+
+/// Checks the type and alignment of an atomic load or store.
+fn check_atomic_access(
+    instruction: &str,
+    ty: &Type,
+    pointer: &Value,
+    options: &MemoryOptions,
+) -> Result<(), BuildError> {
+    check_pointer(pointer)?;
+    if !matches!(ty, Type::Integer(_)) && !ty.is_floating_point() && !ty.is_pointer() {
+        return Err(invalid_type(instruction, ty));
+    }
+    if options.alignment.is_none() {
+        return Err(BuildError::AlignmentRequired(instruction.to_string()));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_atomicrmw(
+    operator: AtomicRmwOperator,
+    pointer: &Value,
+    value: &Value,
+    ordering: AtomicOrdering,
+) -> Result<(), BuildError> {
+    check_pointer(pointer)?;
+    let instruction = format!("atomicrmw {}", operator.emit());
+    if ordering == AtomicOrdering::Unordered {
+        return Err(invalid_ordering(&instruction, ordering));
+    }
+    let ty = value.ty();
+    let valid = match operator {
+        AtomicRmwOperator::Exchange => {
+            matches!(ty, Type::Integer(_)) || ty.is_floating_point() || ty.is_pointer()
+        }
+        operator if operator.is_floating_point() => ty.is_floating_point(),
+        _ => matches!(ty, Type::Integer(_)),
+    };
+    match valid {
+        true => Ok(()),
+        false => Err(invalid_type(&instruction, ty)),
+    }
+}
+
+pub(crate) fn check_cmpxchg(
+    pointer: &Value,
+    expected: &Value,
+    replacement: &Value,
+    success: AtomicOrdering,
+    failure: AtomicOrdering,
+) -> Result<(), BuildError> {
+    check_pointer(pointer)?;
+    for ordering in [success, failure] {
+        if ordering == AtomicOrdering::Unordered {
+            return Err(invalid_ordering("cmpxchg", ordering));
+        }
+    }
+    if failure.releases() && failure != AtomicOrdering::SequentiallyConsistent {
+        return Err(invalid_ordering("cmpxchg", failure));
+    }
+    let ty = expected.ty();
+    if !matches!(ty, Type::Integer(_)) && !ty.is_pointer() {
+        return Err(invalid_type("cmpxchg", ty));
+    }
+    if replacement.ty() != ty {
+        return Err(BuildError::TypeMismatch {
+            expected: ty.clone(),
+            found: replacement.ty().clone(),
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn check_fence(ordering: AtomicOrdering) -> Result<(), BuildError> {
+    match ordering.acquires() || ordering.releases() {
+        true => Ok(()),
+        false => Err(invalid_ordering("fence", ordering)),
+    }
+}
+
+/// Returns the type of the result of `cmpxchg`, the loaded value and whether it was replaced.
+pub(crate) fn cmpxchg_type(ty: &Type) -> Type {
+    Types::structure(vec![ty.clone(), Types::integer(1)])
+}
+
+impl SharedBasicBlock {
+    /// Atomically loads a value from the pointer. The options must provide an alignment.
+    pub fn load_atomic(
+        &self,
+        ty: Type,
+        pointer: Value,
+        ordering: AtomicOrdering,
+        options: MemoryOptions,
+    ) -> Value {
+        self.try_load_atomic(ty, pointer, ordering, options)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Atomically loads a value from the pointer, or returns an error if the ordering releases,
+    /// the type is not an integer, floating point or pointer, or the options have no alignment.
+    pub fn try_load_atomic(
+        &self,
+        ty: Type,
+        pointer: Value,
+        ordering: AtomicOrdering,
+        options: MemoryOptions,
+    ) -> Result<Value, BuildError> {
+        check_atomic_load(&ty, &pointer, ordering, &options)?;
+        let (_, value) = self.create_local_register(ty.clone());
+        let kind = InstructionKind::Load {
+            ty,
+            pointer,
+            ordering: Some(ordering),
+            options,
+        };
+        self.push_instruction(kind, Some(value.clone()));
+        Ok(value)
+    }
+
+    /// Atomically stores a value into the pointer. The options must provide an alignment.
+    pub fn store_atomic(
+        &self,
+        value: Value,
+        pointer: Value,
+        ordering: AtomicOrdering,
+        options: MemoryOptions,
+    ) {
+        self.try_store_atomic(value, pointer, ordering, options)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Atomically stores a value into the pointer, or returns an error if the ordering acquires,
+    /// the value is not an integer, floating point or pointer, or the options have no alignment.
+    pub fn try_store_atomic(
+        &self,
+        value: Value,
+        pointer: Value,
+        ordering: AtomicOrdering,
+        options: MemoryOptions,
+    ) -> Result<(), BuildError> {
+        check_atomic_store(&value, &pointer, ordering, &options)?;
+        let kind = InstructionKind::Store {
+            value,
+            pointer,
+            ordering: Some(ordering),
+            options,
+        };
+        self.push_instruction(kind, None);
+        Ok(())
+    }
+
+    /// Atomically combines the value in memory with the given value,
+    /// and returns the value memory held before.
+    pub fn atomicrmw(
+        &self,
+        operator: AtomicRmwOperator,
+        pointer: Value,
+        value: Value,
+        ordering: AtomicOrdering,
+        options: MemoryOptions,
+    ) -> Value {
+        self.try_atomicrmw(operator, pointer, value, ordering, options)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Atomically combines the value in memory with the given value, and returns the value
+    /// memory held before, or returns an error if the operation does not accept the type
+    /// of the value, or the ordering is `unordered`.
+    pub fn try_atomicrmw(
+        &self,
+        operator: AtomicRmwOperator,
+        pointer: Value,
+        value: Value,
+        ordering: AtomicOrdering,
+        options: MemoryOptions,
+    ) -> Result<Value, BuildError> {
+        check_atomicrmw(operator, &pointer, &value, ordering)?;
+        let (_, result) = self.create_local_register(value.ty().clone());
+        let kind = InstructionKind::AtomicRmw {
+            operator,
+            pointer,
+            value,
+            ordering,
+            options,
+        };
+        self.push_instruction(kind, Some(result.clone()));
+        Ok(result)
+    }
+
+    /// Atomically replaces the value in memory if it equals the expected value.
+    /// Returns a `{T, i1}` pair of the value memory held before, and whether it was replaced.
+    ///
+    /// The failure ordering applies when the value is not replaced, and must not release.
+    pub fn cmpxchg(
+        &self,
+        pointer: Value,
+        expected: Value,
+        replacement: Value,
+        success: AtomicOrdering,
+        failure: AtomicOrdering,
+        options: MemoryOptions,
+    ) -> Value {
+        self.try_cmpxchg(pointer, expected, replacement, success, failure, options)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Atomically replaces the value in memory if it equals the expected value,
+    /// or returns an error if the values are not integers or pointers of the same type,
+    /// or the orderings are invalid.
+    pub fn try_cmpxchg(
+        &self,
+        pointer: Value,
+        expected: Value,
+        replacement: Value,
+        success: AtomicOrdering,
+        failure: AtomicOrdering,
+        options: MemoryOptions,
+    ) -> Result<Value, BuildError> {
+        self.push_cmpxchg(
+            pointer,
+            expected,
+            replacement,
+            (success, failure),
+            false,
+            options,
+        )
+    }
+
+    /// Like `cmpxchg`, but may fail to replace the value even if it equals the expected value,
+    /// which is cheaper on some targets when retried in a loop.
+    pub fn cmpxchg_weak(
+        &self,
+        pointer: Value,
+        expected: Value,
+        replacement: Value,
+        success: AtomicOrdering,
+        failure: AtomicOrdering,
+        options: MemoryOptions,
+    ) -> Value {
+        self.try_cmpxchg_weak(pointer, expected, replacement, success, failure, options)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like `try_cmpxchg`, but may fail to replace the value even if it equals the expected value.
+    pub fn try_cmpxchg_weak(
+        &self,
+        pointer: Value,
+        expected: Value,
+        replacement: Value,
+        success: AtomicOrdering,
+        failure: AtomicOrdering,
+        options: MemoryOptions,
+    ) -> Result<Value, BuildError> {
+        self.push_cmpxchg(
+            pointer,
+            expected,
+            replacement,
+            (success, failure),
+            true,
+            options,
+        )
+    }
+
+    fn push_cmpxchg(
+        &self,
+        pointer: Value,
+        expected: Value,
+        replacement: Value,
+        (success, failure): (AtomicOrdering, AtomicOrdering),
+        weak: bool,
+        options: MemoryOptions,
+    ) -> Result<Value, BuildError> {
+        check_cmpxchg(&pointer, &expected, &replacement, success, failure)?;
+        let (_, result) = self.create_local_register(cmpxchg_type(expected.ty()));
+        let kind = InstructionKind::CompareExchange {
+            pointer,
+            expected,
+            replacement,
+            success,
+            failure,
+            weak,
+            options,
+        };
+        self.push_instruction(kind, Some(result.clone()));
+        Ok(result)
+    }
+
+    /// Orders the memory operations before and after the fence, without accessing memory.
+    pub fn fence(&self, ordering: AtomicOrdering, sync_scope: SyncScope) {
+        self.try_fence(ordering, sync_scope)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Orders the memory operations before and after the fence, or returns an error
+    /// if the ordering neither acquires nor releases.
+    pub fn try_fence(
+        &self,
+        ordering: AtomicOrdering,
+        sync_scope: SyncScope,
+    ) -> Result<(), BuildError> {
+        check_fence(ordering)?;
+        self.push_instruction(
+            InstructionKind::Fence {
+                ordering,
+                sync_scope,
+            },
+            None,
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::BuildError;
+    use crate::instruction::{AtomicOrdering, AtomicRmwOperator, MemoryOptions, SyncScope};
+    use crate::module::{FunctionBody, GlobalFunction};
+    use crate::types::Types;
+    use crate::value::Values;
+    use crate::IRComponent;
+    use std::string::String;
+
+    #[test]
+    fn build_atomic_function() {
+        let body = FunctionBody::new(|block| {
+            let counter = block.alloca_with_alignment(Types::integer(64), 8);
+            let aligned = MemoryOptions::new().with_alignment(8);
+            block.store_atomic(
+                Values::integer("0", 64),
+                counter.clone(),
+                AtomicOrdering::Release,
+                aligned.clone(),
+            );
+            let previous = block.atomicrmw(
+                AtomicRmwOperator::Add,
+                counter.clone(),
+                Values::integer("1", 64),
+                AtomicOrdering::SequentiallyConsistent,
+                MemoryOptions::new(),
+            );
+            let exchanged = block.cmpxchg_weak(
+                counter.clone(),
+                previous,
+                Values::integer("5", 64),
+                AtomicOrdering::AcquireRelease,
+                AtomicOrdering::Monotonic,
+                MemoryOptions::new()
+                    .volatile()
+                    .with_sync_scope(SyncScope::SingleThread),
+            );
+            let replaced = block.extractvalue(exchanged, 1);
+            block.fence(
+                AtomicOrdering::Acquire,
+                SyncScope::Target(String::from("agent")),
+            );
+            block.atomicrmw(
+                AtomicRmwOperator::UnsignedSubtractSaturate,
+                counter.clone(),
+                Values::integer("2", 64),
+                AtomicOrdering::Monotonic,
+                aligned.clone().volatile(),
+            );
+            let loaded = block.load_atomic(
+                Types::integer(64),
+                counter,
+                AtomicOrdering::Acquire,
+                aligned.with_sync_scope(SyncScope::SingleThread),
+            );
+            let truncated = block.trunc(loaded, Types::integer(1));
+            let result = block.and(truncated, replaced);
+            block.ret(result);
+        });
+        let f = GlobalFunction::new("main", Types::integer(1)).body(body);
+        assert_eq!(
+            f.emit(),
+            "define i1 @main() { \
+                entry: \
+                    %r0 = alloca i64, align 8 \
+                    store atomic i64 0, ptr %r0 release, align 8 \
+                    %r1 = atomicrmw add ptr %r0, i64 1 seq_cst \
+                    %r2 = cmpxchg weak volatile ptr %r0, i64 %r1, i64 5 \
+                        syncscope(\"singlethread\") acq_rel monotonic \
+                    %r3 = extractvalue {i64, i1} %r2, 1 \
+                    fence syncscope(\"agent\") acquire \
+                    %r4 = atomicrmw volatile usub_sat ptr %r0, i64 2 monotonic, align 8 \
+                    %r5 = load atomic i64, ptr %r0 syncscope(\"singlethread\") acquire, align 8 \
+                    %r6 = trunc i64 %r5 to i1 \
+                    %r7 = and i1 %r6, %r3 \
+                    ret i1 %r7 \
+            }"
+        );
+    }
+
+    #[test]
+    fn reject_invalid_atomics() {
+        let body = FunctionBody::new(|block| {
+            let pointer = block.alloca(Types::fp64());
+            assert_eq!(
+                block.try_load_atomic(
+                    Types::fp64(),
+                    pointer.clone(),
+                    AtomicOrdering::Release,
+                    MemoryOptions::new().with_alignment(8),
+                ),
+                Err(BuildError::InvalidOrdering {
+                    instruction: String::from("load atomic"),
+                    ordering: AtomicOrdering::Release,
+                })
+            );
+            assert_eq!(
+                block.try_store_atomic(
+                    Values::float("1", Types::fp64()),
+                    pointer.clone(),
+                    AtomicOrdering::Monotonic,
+                    MemoryOptions::new(),
+                ),
+                Err(BuildError::AlignmentRequired(String::from("store atomic")))
+            );
+            let error = block
+                .try_atomicrmw(
+                    AtomicRmwOperator::Add,
+                    pointer.clone(),
+                    Values::float("1", Types::fp64()),
+                    AtomicOrdering::Monotonic,
+                    MemoryOptions::new(),
+                )
+                .unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "atomicrmw add does not accept operands of type Double"
+            );
+            assert!(block
+                .try_atomicrmw(
+                    AtomicRmwOperator::FloatMax,
+                    pointer.clone(),
+                    Values::float("1", Types::fp64()),
+                    AtomicOrdering::Monotonic,
+                    MemoryOptions::new(),
+                )
+                .is_ok());
+            assert_eq!(
+                block.try_cmpxchg(
+                    pointer,
+                    Values::integer("1", 32),
+                    Values::integer("2", 32),
+                    AtomicOrdering::SequentiallyConsistent,
+                    AtomicOrdering::Release,
+                    MemoryOptions::new(),
+                ),
+                Err(BuildError::InvalidOrdering {
+                    instruction: String::from("cmpxchg"),
+                    ordering: AtomicOrdering::Release,
+                })
+            );
+            assert!(block
+                .try_fence(AtomicOrdering::Monotonic, SyncScope::System)
+                .is_err());
+            block.ret_void();
+        });
+        assert_eq!(body.instructions().len(), 3);
+    }
+}
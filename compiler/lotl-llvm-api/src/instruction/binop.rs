@@ -1,26 +1,49 @@
-use crate::instruction::{Instruction, SharedBasicBlock};
+use crate::error::BuildError;
+use crate::instruction::{InstructionKind, SharedBasicBlock};
 use crate::value::Value;
 use crate::IRComponent;
-use std::boxed::Box;
 use std::string::String;
+use std::vec::Vec;
 
-pub struct BinOp {
-    returns_in: String,
-    operator: BinaryOperator,
-    lhs: Value,
-    rhs: Value,
-}
-
+/// Represents the binary operations of LLVM.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BinaryOperator {
+    /// Integer addition, `add`.
     IntegerAdd,
+    /// Integer subtraction, `sub`.
     IntegerSub,
+    /// Integer multiplication, `mul`.
     IntegerMul,
+    /// Signed integer division, `sdiv`.
     IntegerSignedDiv,
+    /// Unsigned integer division, `udiv`.
     IntegerUnsignedDiv,
+    /// Signed integer remainder, `srem`.
+    IntegerSignedRem,
+    /// Unsigned integer remainder, `urem`.
+    IntegerUnsignedRem,
+    /// Shift left, `shl`.
+    ShiftLeft,
+    /// Logical shift right, filling the upper bits with zero, `lshr`.
+    LogicalShiftRight,
+    /// Arithmetic shift right, filling the upper bits with the sign bit, `ashr`.
+    ArithmeticShiftRight,
+    /// Bitwise and, `and`.
+    And,
+    /// Bitwise or, `or`.
+    Or,
+    /// Bitwise exclusive or, `xor`.
+    Xor,
+    /// Floating-point addition, `fadd`.
     FloatAdd,
+    /// Floating-point subtraction, `fsub`.
     FloatSub,
+    /// Floating-point multiplication, `fmul`.
     FloatMul,
+    /// Floating-point division, `fdiv`.
     FloatDiv,
+    /// Floating-point remainder, `frem`.
+    FloatRem,
 }
 
 impl IRComponent for BinaryOperator {
@@ -31,140 +54,423 @@ impl IRComponent for BinaryOperator {
             BinaryOperator::IntegerMul => string.push_str("mul"),
             BinaryOperator::IntegerSignedDiv => string.push_str("sdiv"),
             BinaryOperator::IntegerUnsignedDiv => string.push_str("udiv"),
+            BinaryOperator::IntegerSignedRem => string.push_str("srem"),
+            BinaryOperator::IntegerUnsignedRem => string.push_str("urem"),
+            BinaryOperator::ShiftLeft => string.push_str("shl"),
+            BinaryOperator::LogicalShiftRight => string.push_str("lshr"),
+            BinaryOperator::ArithmeticShiftRight => string.push_str("ashr"),
+            BinaryOperator::And => string.push_str("and"),
+            BinaryOperator::Or => string.push_str("or"),
+            BinaryOperator::Xor => string.push_str("xor"),
             BinaryOperator::FloatAdd => string.push_str("fadd"),
             BinaryOperator::FloatSub => string.push_str("fsub"),
             BinaryOperator::FloatMul => string.push_str("fmul"),
             BinaryOperator::FloatDiv => string.push_str("fdiv"),
+            BinaryOperator::FloatRem => string.push_str("frem"),
+        }
+    }
+}
+
+impl BinaryOperator {
+    /// Returns true if this operator works on floating-point operands, false for integers.
+    pub fn is_floating_point(&self) -> bool {
+        matches!(
+            self,
+            BinaryOperator::FloatAdd
+                | BinaryOperator::FloatSub
+                | BinaryOperator::FloatMul
+                | BinaryOperator::FloatDiv
+                | BinaryOperator::FloatRem
+        )
+    }
+
+    /// Returns true if this operator accepts the `nuw` and `nsw` flags.
+    pub fn supports_wrap_flags(&self) -> bool {
+        matches!(
+            self,
+            BinaryOperator::IntegerAdd
+                | BinaryOperator::IntegerSub
+                | BinaryOperator::IntegerMul
+                | BinaryOperator::ShiftLeft
+        )
+    }
+
+    /// Returns true if this operator accepts the `exact` flag.
+    pub fn supports_exact_flag(&self) -> bool {
+        matches!(
+            self,
+            BinaryOperator::IntegerSignedDiv
+                | BinaryOperator::IntegerUnsignedDiv
+                | BinaryOperator::LogicalShiftRight
+                | BinaryOperator::ArithmeticShiftRight
+        )
+    }
+}
+
+/// Flags on a binary operation, which produce poison when the assumptions they state are broken.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BinaryFlags {
+    pub(crate) no_unsigned_wrap: bool,
+    pub(crate) no_signed_wrap: bool,
+    pub(crate) exact: bool,
+    pub(crate) fast_math: FastMathFlags,
+}
+
+impl BinaryFlags {
+    /// Creates a new empty set of flags.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The result is poison if unsigned overflow occurs, `nuw`.
+    pub fn no_unsigned_wrap(mut self) -> Self {
+        self.no_unsigned_wrap = true;
+        self
+    }
+
+    /// The result is poison if signed overflow occurs, `nsw`.
+    pub fn no_signed_wrap(mut self) -> Self {
+        self.no_signed_wrap = true;
+        self
+    }
+
+    /// The result is poison if the division has a remainder,
+    /// or any non-zero bits are shifted out, `exact`.
+    pub fn exact(mut self) -> Self {
+        self.exact = true;
+        self
+    }
+
+    /// Provides fast-math flags to a floating-point operation.
+    pub fn fast_math(mut self, flags: FastMathFlags) -> Self {
+        self.fast_math = flags;
+        self
+    }
+
+    /// Returns true if the `nuw` flag is set.
+    pub fn has_no_unsigned_wrap(&self) -> bool {
+        self.no_unsigned_wrap
+    }
+
+    /// Returns true if the `nsw` flag is set.
+    pub fn has_no_signed_wrap(&self) -> bool {
+        self.no_signed_wrap
+    }
+
+    /// Returns true if the `exact` flag is set.
+    pub fn is_exact(&self) -> bool {
+        self.exact
+    }
+
+    fn is_valid_for(&self, operator: BinaryOperator) -> bool {
+        ((!self.no_unsigned_wrap && !self.no_signed_wrap) || operator.supports_wrap_flags())
+            && (!self.exact || operator.supports_exact_flag())
+            && (self.fast_math == FastMathFlags::default() || operator.is_floating_point())
+    }
+}
+
+impl IRComponent for BinaryFlags {
+    fn append_to_string(&self, string: &mut String) {
+        let mut flags = Vec::new();
+        if self.no_unsigned_wrap {
+            flags.push("nuw");
+        }
+        if self.no_signed_wrap {
+            flags.push("nsw");
+        }
+        if self.exact {
+            flags.push("exact");
         }
+        string.push_str(&flags.join(" "));
+        if !flags.is_empty() && self.fast_math != FastMathFlags::default() {
+            string.push(' ');
+        }
+        self.fast_math.append_to_string(string);
+    }
+}
+
+/// Fast-math flags, allowing otherwise unsafe floating-point transformations.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FastMathFlags {
+    pub(crate) no_nans: bool,
+    pub(crate) no_infs: bool,
+    pub(crate) no_signed_zeros: bool,
+    pub(crate) allow_reciprocal: bool,
+    pub(crate) allow_contract: bool,
+    pub(crate) approximate_functions: bool,
+    pub(crate) allow_reassociation: bool,
+}
+
+impl FastMathFlags {
+    /// Creates a new empty set of fast-math flags.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables every fast-math flag, `fast`.
+    pub fn fast() -> Self {
+        FastMathFlags {
+            no_nans: true,
+            no_infs: true,
+            no_signed_zeros: true,
+            allow_reciprocal: true,
+            allow_contract: true,
+            approximate_functions: true,
+            allow_reassociation: true,
+        }
+    }
+
+    /// Assumes the arguments and result are not NaN, `nnan`.
+    pub fn no_nans(mut self) -> Self {
+        self.no_nans = true;
+        self
+    }
+
+    /// Assumes the arguments and result are not infinite, `ninf`.
+    pub fn no_infs(mut self) -> Self {
+        self.no_infs = true;
+        self
+    }
+
+    /// Allows the sign of a zero argument or result to be ignored, `nsz`.
+    pub fn no_signed_zeros(mut self) -> Self {
+        self.no_signed_zeros = true;
+        self
+    }
+
+    /// Allows using the reciprocal of an argument instead of dividing, `arcp`.
+    pub fn allow_reciprocal(mut self) -> Self {
+        self.allow_reciprocal = true;
+        self
+    }
+
+    /// Allows floating-point contraction, such as fusing a multiply and add, `contract`.
+    pub fn allow_contract(mut self) -> Self {
+        self.allow_contract = true;
+        self
+    }
+
+    /// Allows substituting approximations of functions, `afn`.
+    pub fn approximate_functions(mut self) -> Self {
+        self.approximate_functions = true;
+        self
+    }
+
+    /// Allows reassociation of floating-point instructions, `reassoc`.
+    pub fn allow_reassociation(mut self) -> Self {
+        self.allow_reassociation = true;
+        self
     }
 }
 
-impl IRComponent for BinOp {
+impl IRComponent for FastMathFlags {
     fn append_to_string(&self, string: &mut String) {
-        string.push('%');
-        string.push_str(&self.returns_in);
-        string.push_str(" = ");
-        self.operator.append_to_string(string);
-        string.push(' ');
-        self.lhs.append_to_string(string);
-        string.push_str(", ");
-        self.rhs.append_to_string_untyped(string);
+        if *self == FastMathFlags::fast() {
+            string.push_str("fast");
+            return;
+        }
+        let mut flags = Vec::new();
+        if self.no_nans {
+            flags.push("nnan");
+        }
+        if self.no_infs {
+            flags.push("ninf");
+        }
+        if self.no_signed_zeros {
+            flags.push("nsz");
+        }
+        if self.allow_reciprocal {
+            flags.push("arcp");
+        }
+        if self.allow_contract {
+            flags.push("contract");
+        }
+        if self.approximate_functions {
+            flags.push("afn");
+        }
+        if self.allow_reassociation {
+            flags.push("reassoc");
+        }
+        string.push_str(&flags.join(" "));
+    }
+}
+
+pub(crate) fn check_binop(
+    operator: BinaryOperator,
+    lhs: &Value,
+    rhs: &Value,
+    flags: BinaryFlags,
+) -> Result<(), BuildError> {
+    if lhs.ty() != rhs.ty() {
+        return Err(BuildError::TypeMismatch {
+            expected: lhs.ty().clone(),
+            found: rhs.ty().clone(),
+        });
+    }
+    // vectors are operated on element-wise
+    let operands_valid = match operator.is_floating_point() {
+        true => lhs.ty().scalar().is_floating_point(),
+        false => lhs.ty().scalar().is_integer(),
+    };
+    if !operands_valid {
+        return Err(BuildError::InvalidOperands {
+            operator,
+            ty: lhs.ty().clone(),
+        });
     }
+    if !flags.is_valid_for(operator) {
+        return Err(BuildError::InvalidFlags { operator, flags });
+    }
+    Ok(())
 }
-impl Instruction for BinOp {}
 
 impl SharedBasicBlock {
+    /// Performs the binary operation on two operands of the same type.
+    /// Vectors are operated on element-wise.
+    pub fn binop(&self, operator: BinaryOperator, lhs: Value, rhs: Value) -> Value {
+        self.binop_with_flags(operator, lhs, rhs, BinaryFlags::new())
+    }
+
+    /// Performs the binary operation on two operands of the same type, with the provided flags.
+    pub fn binop_with_flags(
+        &self,
+        operator: BinaryOperator,
+        lhs: Value,
+        rhs: Value,
+        flags: BinaryFlags,
+    ) -> Value {
+        self.try_binop_with_flags(operator, lhs, rhs, flags)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Performs the binary operation, or returns an error if the operands are of different types,
+    /// or of a type the operation does not accept.
+    pub fn try_binop(
+        &self,
+        operator: BinaryOperator,
+        lhs: Value,
+        rhs: Value,
+    ) -> Result<Value, BuildError> {
+        self.try_binop_with_flags(operator, lhs, rhs, BinaryFlags::new())
+    }
+
+    /// Performs the binary operation with the provided flags, or returns an error if the operands
+    /// are of different types, or the operation does not accept the operands or the flags.
+    pub fn try_binop_with_flags(
+        &self,
+        operator: BinaryOperator,
+        lhs: Value,
+        rhs: Value,
+        flags: BinaryFlags,
+    ) -> Result<Value, BuildError> {
+        check_binop(operator, &lhs, &rhs, flags)?;
+        let (_, value) = self.create_local_register(lhs.ty().clone());
+        self.push_instruction(
+            InstructionKind::Binary {
+                operator,
+                flags,
+                lhs,
+                rhs,
+            },
+            Some(value.clone()),
+        );
+        Ok(value)
+    }
+
     /// Add two integers together.
     pub fn add(&self, lhs: Value, rhs: Value) -> Value {
-        let (name, value) = self.create_local_register(lhs.ty().clone());
-        self.push_instruction(Box::new(BinOp {
-            returns_in: name,
-            operator: BinaryOperator::IntegerAdd,
-            lhs,
-            rhs,
-        }));
-        value
+        self.binop(BinaryOperator::IntegerAdd, lhs, rhs)
     }
 
     /// Subtract two integers.
     pub fn sub(&self, lhs: Value, rhs: Value) -> Value {
-        let (name, value) = self.create_local_register(lhs.ty().clone());
-        self.push_instruction(Box::new(BinOp {
-            returns_in: name,
-            operator: BinaryOperator::IntegerSub,
-            lhs,
-            rhs,
-        }));
-        value
+        self.binop(BinaryOperator::IntegerSub, lhs, rhs)
     }
 
     /// Multiply two integers.
     pub fn mul(&self, lhs: Value, rhs: Value) -> Value {
-        let (name, value) = self.create_local_register(lhs.ty().clone());
-        self.push_instruction(Box::new(BinOp {
-            returns_in: name,
-            operator: BinaryOperator::IntegerMul,
-            lhs,
-            rhs,
-        }));
-        value
+        self.binop(BinaryOperator::IntegerMul, lhs, rhs)
     }
 
     /// Perform signed division.
     pub fn sdiv(&self, lhs: Value, rhs: Value) -> Value {
-        let (name, value) = self.create_local_register(lhs.ty().clone());
-        self.push_instruction(Box::new(BinOp {
-            returns_in: name,
-            operator: BinaryOperator::IntegerSignedDiv,
-            lhs,
-            rhs,
-        }));
-        value
+        self.binop(BinaryOperator::IntegerSignedDiv, lhs, rhs)
     }
 
     /// Perform unsigned division.
     pub fn udiv(&self, lhs: Value, rhs: Value) -> Value {
-        let (name, value) = self.create_local_register(lhs.ty().clone());
-        self.push_instruction(Box::new(BinOp {
-            returns_in: name,
-            operator: BinaryOperator::IntegerUnsignedDiv,
-            lhs,
-            rhs,
-        }));
-        value
+        self.binop(BinaryOperator::IntegerUnsignedDiv, lhs, rhs)
+    }
+
+    /// Get the remainder of signed division.
+    pub fn srem(&self, lhs: Value, rhs: Value) -> Value {
+        self.binop(BinaryOperator::IntegerSignedRem, lhs, rhs)
+    }
+
+    /// Get the remainder of unsigned division.
+    pub fn urem(&self, lhs: Value, rhs: Value) -> Value {
+        self.binop(BinaryOperator::IntegerUnsignedRem, lhs, rhs)
+    }
+
+    /// Shift an integer left.
+    pub fn shl(&self, lhs: Value, rhs: Value) -> Value {
+        self.binop(BinaryOperator::ShiftLeft, lhs, rhs)
+    }
+
+    /// Shift an integer right, filling in zeroes.
+    pub fn lshr(&self, lhs: Value, rhs: Value) -> Value {
+        self.binop(BinaryOperator::LogicalShiftRight, lhs, rhs)
+    }
+
+    /// Shift an integer right, filling in the sign bit.
+    pub fn ashr(&self, lhs: Value, rhs: Value) -> Value {
+        self.binop(BinaryOperator::ArithmeticShiftRight, lhs, rhs)
+    }
+
+    /// Bitwise and two integers.
+    pub fn and(&self, lhs: Value, rhs: Value) -> Value {
+        self.binop(BinaryOperator::And, lhs, rhs)
+    }
+
+    /// Bitwise or two integers.
+    pub fn or(&self, lhs: Value, rhs: Value) -> Value {
+        self.binop(BinaryOperator::Or, lhs, rhs)
+    }
+
+    /// Bitwise exclusive or two integers.
+    pub fn xor(&self, lhs: Value, rhs: Value) -> Value {
+        self.binop(BinaryOperator::Xor, lhs, rhs)
     }
 
     /// Adds two floats together.
     pub fn fadd(&self, lhs: Value, rhs: Value) -> Value {
-        let (name, value) = self.create_local_register(lhs.ty().clone());
-        self.push_instruction(Box::new(BinOp {
-            returns_in: name,
-            operator: BinaryOperator::FloatAdd,
-            lhs,
-            rhs,
-        }));
-        value
+        self.binop(BinaryOperator::FloatAdd, lhs, rhs)
     }
 
     /// Subtracts two floats.
     pub fn fsub(&self, lhs: Value, rhs: Value) -> Value {
-        let (name, value) = self.create_local_register(lhs.ty().clone());
-        self.push_instruction(Box::new(BinOp {
-            returns_in: name,
-            operator: BinaryOperator::FloatSub,
-            lhs,
-            rhs,
-        }));
-        value
+        self.binop(BinaryOperator::FloatSub, lhs, rhs)
     }
 
     /// Multiplies two floats together.
     pub fn fmul(&self, lhs: Value, rhs: Value) -> Value {
-        let (name, value) = self.create_local_register(lhs.ty().clone());
-        self.push_instruction(Box::new(BinOp {
-            returns_in: name,
-            operator: BinaryOperator::FloatMul,
-            lhs,
-            rhs,
-        }));
-        value
+        self.binop(BinaryOperator::FloatMul, lhs, rhs)
     }
 
     /// Divides two floats.
     pub fn fdiv(&self, lhs: Value, rhs: Value) -> Value {
-        let (name, value) = self.create_local_register(lhs.ty().clone());
-        self.push_instruction(Box::new(BinOp {
-            returns_in: name,
-            operator: BinaryOperator::FloatDiv,
-            lhs,
-            rhs,
-        }));
-        value
+        self.binop(BinaryOperator::FloatDiv, lhs, rhs)
+    }
+
+    /// Gets the remainder of dividing two floats.
+    pub fn frem(&self, lhs: Value, rhs: Value) -> Value {
+        self.binop(BinaryOperator::FloatRem, lhs, rhs)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::instruction::{BinaryFlags, BinaryOperator, FastMathFlags};
     use crate::module::{FunctionBody, GlobalFunction};
     use crate::types::Types;
     use crate::value::Values;
@@ -222,4 +528,131 @@ mod tests {
             }"
         );
     }
+
+    #[test]
+    fn build_bitwise_function() {
+        let body = FunctionBody::new(|block| {
+            let lhs = Values::integer("12", 32);
+            let rhs = Values::integer("10", 32);
+            let and = block.and(lhs.clone(), rhs.clone());
+            let or = block.or(and, rhs.clone());
+            let xor = block.xor(or, lhs.clone());
+            let shl = block.shl(xor, Values::integer("2", 32));
+            let lshr = block.lshr(shl, Values::integer("1", 32));
+            let ashr = block.ashr(lshr, Values::integer("1", 32));
+            let urem = block.urem(ashr, rhs.clone());
+            let srem = block.srem(urem, rhs);
+            block.ret(srem);
+        });
+        let f = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(
+            f.emit(),
+            "define i32 @main() { \
+                entry: \
+                    %r0 = and i32 12, 10 \
+                    %r1 = or i32 %r0, 10 \
+                    %r2 = xor i32 %r1, 12 \
+                    %r3 = shl i32 %r2, 2 \
+                    %r4 = lshr i32 %r3, 1 \
+                    %r5 = ashr i32 %r4, 1 \
+                    %r6 = urem i32 %r5, 10 \
+                    %r7 = srem i32 %r6, 10 \
+                    ret i32 %r7 \
+            }"
+        );
+    }
+
+    #[test]
+    fn build_flagged_function() {
+        let body = FunctionBody::new(|block| {
+            let summed = block.binop_with_flags(
+                BinaryOperator::IntegerAdd,
+                Values::integer("10", 32),
+                Values::integer("20", 32),
+                BinaryFlags::new().no_unsigned_wrap().no_signed_wrap(),
+            );
+            let _shifted = block.binop_with_flags(
+                BinaryOperator::ArithmeticShiftRight,
+                summed.clone(),
+                Values::integer("1", 32),
+                BinaryFlags::new().exact(),
+            );
+            block.ret(summed);
+        });
+        let f = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(
+            f.emit(),
+            "define i32 @main() { \
+                entry: \
+                    %r0 = add nuw nsw i32 10, 20 \
+                    %r1 = ashr exact i32 %r0, 1 \
+                    ret i32 %r0 \
+            }"
+        );
+    }
+
+    #[test]
+    fn build_fast_math_function() {
+        let body = FunctionBody::new(|block| {
+            let lhs = Values::float("1.5", Types::fp64());
+            let rhs = Values::float("0.5", Types::fp64());
+            let summed = block.binop_with_flags(
+                BinaryOperator::FloatAdd,
+                lhs.clone(),
+                rhs.clone(),
+                BinaryFlags::new().fast_math(FastMathFlags::new().no_nans().no_infs()),
+            );
+            let remainder = block.binop_with_flags(
+                BinaryOperator::FloatRem,
+                summed,
+                rhs,
+                BinaryFlags::new().fast_math(FastMathFlags::fast()),
+            );
+            block.ret(remainder);
+        });
+        let f = GlobalFunction::new("main", Types::fp64()).body(body);
+        assert_eq!(
+            f.emit(),
+            "define double @main() { \
+                entry: \
+                    %r0 = fadd nnan ninf double 1.5, 0.5 \
+                    %r1 = frem fast double %r0, 0.5 \
+                    ret double %r1 \
+            }"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn reject_mixed_operands() {
+        FunctionBody::new(|block| {
+            block.add(
+                Values::integer("1", 32),
+                Values::float("1.0", Types::fp32()),
+            );
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn reject_float_integer_operator() {
+        FunctionBody::new(|block| {
+            let value = Values::float("1.0", Types::fp32());
+            block.add(value.clone(), value);
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn reject_misplaced_flags() {
+        FunctionBody::new(|block| {
+            let value = Values::integer("1", 32);
+            block.binop_with_flags(
+                BinaryOperator::IntegerAdd,
+                value.clone(),
+                value,
+                BinaryFlags::new().exact(),
+            );
+        });
+    }
 }
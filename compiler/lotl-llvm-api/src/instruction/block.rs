@@ -1,69 +1,256 @@
-use crate::instruction::{BasicBlock, SharedBasicBlock};
+use crate::instruction::{BasicBlock, BlockId, InstId, Instruction, InstructionKind};
+use crate::metadata::Metadata;
+use crate::module::FunctionBody;
 use crate::types::Type;
 use crate::value::Value;
-use crate::IRComponent;
 use std::format;
-use std::string::String;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::string::{String, ToString};
 use std::vec::Vec;
 
 impl BasicBlock {
-    /// Creates a new basic block with the given label, that is the entry to a function.
-    pub fn entry(label: &str) -> BasicBlock {
+    /// Creates a new empty basic block with the given label.
+    pub(crate) fn new(label: &str) -> BasicBlock {
         BasicBlock {
-            basic_block_index: Arc::new(AtomicUsize::new(0)),
-            ssa_register_index: Arc::new(AtomicUsize::new(0)),
             label: String::from(label),
             instructions: Vec::new(),
             children: Vec::new(),
+            debug_location: None,
         }
     }
+}
 
-    /// Creates a child of the associated block, and returns it.
-    pub fn child(parent: &SharedBasicBlock) -> SharedBasicBlock {
-        let out = SharedBasicBlock::new(BasicBlock {
-            label: format!(
-                "bb{}",
-                parent
-                    .unlock_out(|x| x.basic_block_index.clone())
-                    .fetch_add(1, Ordering::AcqRel)
-            ),
-            basic_block_index: parent.unlock_out(|x| x.basic_block_index.clone()).clone(),
-            ssa_register_index: parent.unlock_out(|x| x.ssa_register_index.clone()).clone(),
-            instructions: Vec::new(),
-            children: Vec::new(),
+impl FunctionBody {
+    /// Returns the block control enters the function at.
+    pub fn entry(&self) -> BlockId {
+        BlockId(0)
+    }
+
+    /// Returns the block with the given identifier.
+    pub fn block(&self, id: BlockId) -> &BasicBlock {
+        &self.blocks[id.0]
+    }
+
+    pub(crate) fn block_mut(&mut self, id: BlockId) -> &mut BasicBlock {
+        &mut self.blocks[id.0]
+    }
+
+    /// Returns the instruction with the given identifier.
+    pub fn instruction(&self, id: InstId) -> &Instruction {
+        &self.instructions[id.0]
+    }
+
+    /// Changes the operation of the instruction, keeping track of the registers it uses.
+    pub fn modify_instruction<T, F: FnOnce(&mut InstructionKind) -> T>(
+        &mut self,
+        id: InstId,
+        f: F,
+    ) -> T {
+        self.unindex_uses(id);
+        let result = f(&mut self.instructions[id.0].kind);
+        self.index_uses(id);
+        result
+    }
+
+    /// Returns every block of the function, in the order they are laid out.
+    /// Blocks come right before the blocks created from them, starting with the entry.
+    pub fn blocks(&self) -> Vec<BlockId> {
+        self.layout.clone()
+    }
+
+    /// Returns every instruction still in a block, in the order they are laid out.
+    pub fn instructions(&self) -> Vec<InstId> {
+        self.layout
+            .iter()
+            .flat_map(|x| self.block(*x).instructions.iter().copied())
+            .collect()
+    }
+
+    /// Removes the instruction from its block. The instruction stays in the arena,
+    /// so its identifier remains valid, but it is no longer part of the function.
+    pub fn remove_instruction(&mut self, id: InstId) {
+        if self.instructions[id.0].block.is_none() {
+            return;
+        }
+        self.unindex_uses(id);
+        let block = self.instructions[id.0].block.take().unwrap();
+        self.block_mut(block).instructions.retain(|x| *x != id);
+    }
+
+    /// Inserts a new instruction into the block at the index, defining the given register.
+    pub fn insert_instruction(
+        &mut self,
+        block: BlockId,
+        index: usize,
+        kind: InstructionKind,
+        result: Option<Value>,
+    ) -> InstId {
+        let id = self.add_instruction(Instruction {
+            kind,
+            result,
+            block: Some(block),
+            metadata: Vec::new(),
         });
-        parent.unlock(|mut x| x.children.push(out.clone()));
-        out
+        self.block_mut(block).instructions.insert(index, id);
+        self.index_uses(id);
+        id
     }
 
-    pub(crate) fn create_local_register(&self, ty: Type) -> (String, Value) {
-        let idx = self.basic_block_index.fetch_add(1, Ordering::AcqRel);
-        (
-            format!("r{idx}"),
-            Value::LocalIdentifier(format!("r{idx}"), ty),
-        )
+    /// Moves the instruction to the end of another block.
+    pub fn move_instruction(&mut self, id: InstId, block: BlockId) {
+        self.remove_instruction(id);
+        self.instructions[id.0].block = Some(block);
+        self.block_mut(block).instructions.push(id);
+        self.index_uses(id);
+    }
+
+    /// Removes the block and its instructions from the function. The blocks created
+    /// from it take its place in the layout. The entry block cannot be removed.
+    pub fn remove_block(&mut self, id: BlockId) {
+        if id == self.entry() {
+            panic!("cannot remove the entry block of a function");
+        }
+        for instruction in self.block(id).instructions.clone() {
+            self.remove_instruction(instruction);
+        }
+        let children = std::mem::take(&mut self.block_mut(id).children);
+        for block in &mut self.blocks {
+            if let Some(index) = block.children.iter().position(|x| *x == id) {
+                block.children.splice(index..=index, children);
+                break;
+            }
+        }
+        self.update_layout();
+    }
+
+    /// Removes the incoming values of the `phi` instructions in the block,
+    /// that arrive from the predecessor.
+    pub fn remove_incoming(&mut self, block: BlockId, predecessor: BlockId) {
+        for id in self.block(block).instructions.clone() {
+            self.modify_instruction(id, |kind| {
+                if let InstructionKind::Phi { incoming, .. } = kind {
+                    incoming.retain(|(_, from)| *from != predecessor);
+                }
+            });
+        }
     }
-}
 
-impl IRComponent for BasicBlock {
-    fn append_to_string(&self, string: &mut String) {
-        string.push_str(self.label.as_str());
-        string.push(':');
-        string.push(' ');
-        string.push_str(
-            &self
-                .instructions
-                .iter()
-                .map(|x| x.emit())
-                .collect::<Vec<_>>()
-                .join(" "),
-        );
-        string.push(' ');
-
-        for child in &self.children {
-            child.unlock_out(|child| child.append_to_string(string));
+    /// Makes the incoming values of the `phi` instructions in the block,
+    /// that arrive from the predecessor, arrive from another block instead.
+    pub fn replace_incoming(&mut self, block: BlockId, predecessor: BlockId, new: BlockId) {
+        for id in self.block(block).instructions.clone() {
+            if let InstructionKind::Phi { incoming, .. } = &mut self.instructions[id.0].kind {
+                for (_, from) in incoming.iter_mut().filter(|(_, x)| *x == predecessor) {
+                    *from = new;
+                }
+            }
         }
     }
+
+    /// Creates a new register of the given type, named after the next free index.
+    pub fn create_register(&mut self, ty: Type) -> Value {
+        Value::LocalIdentifier(format!("r{}", self.next_register()), ty)
+    }
+
+    /// Replaces every use of the value by another value, in every instruction of the function.
+    pub fn replace_uses(&mut self, old: &Value, new: &Value) {
+        // only registers are tracked, other values are looked for in every instruction
+        let users = match old {
+            Value::LocalIdentifier(name, _) => self.users.get(name).cloned().unwrap_or_default(),
+            _ => self.instructions(),
+        };
+        for id in users {
+            self.modify_instruction(id, |kind| {
+                for operand in kind.operands_mut() {
+                    if operand == old {
+                        *operand = new.clone();
+                    }
+                }
+            });
+        }
+    }
+
+    pub(crate) fn add_block(&mut self, label: &str) -> BlockId {
+        self.blocks.push(BasicBlock::new(label));
+        BlockId(self.blocks.len() - 1)
+    }
+
+    /// Adds a new empty block, laid out after every other block of the function.
+    pub(crate) fn append_block(&mut self, label: &str) -> BlockId {
+        let id = self.add_block(label);
+        if id != self.entry() {
+            let entry = self.entry();
+            self.block_mut(entry).children.push(id);
+        }
+        self.update_layout();
+        id
+    }
+
+    /// Lays out the blocks again, after one of them is added or removed.
+    pub(crate) fn update_layout(&mut self) {
+        self.layout.clear();
+        let mut pending = Vec::from([self.entry()]);
+        while let Some(block) = pending.pop() {
+            self.layout.push(block);
+            // children are pushed in reverse, so they are visited in creation order
+            pending.extend(self.block(block).children.iter().rev());
+        }
+    }
+
+    /// Records the register defined by the instruction, and the registers it uses.
+    pub(crate) fn index_uses(&mut self, id: InstId) {
+        let instruction = &self.instructions[id.0];
+        if let Some(Value::LocalIdentifier(name, _)) = &instruction.result {
+            self.definitions.insert(name.clone(), id);
+        }
+        for operand in instruction.kind.operands() {
+            if let Value::LocalIdentifier(name, _) = operand {
+                let users = self.users.entry(name.clone()).or_default();
+                if !users.contains(&id) {
+                    users.push(id);
+                }
+            }
+        }
+    }
+
+    /// Forgets the register defined by the instruction, and the registers it uses.
+    fn unindex_uses(&mut self, id: InstId) {
+        let instruction = &self.instructions[id.0];
+        match &instruction.result {
+            // the register may have been defined again by another instruction
+            Some(Value::LocalIdentifier(name, _)) if self.definitions.get(name) == Some(&id) => {
+                self.definitions.remove(name);
+            }
+            _ => {}
+        }
+        for operand in instruction.kind.operands() {
+            let Value::LocalIdentifier(name, _) = operand else {
+                continue;
+            };
+            if let Some(users) = self.users.get_mut(name) {
+                users.retain(|x| *x != id);
+            }
+        }
+    }
+
+    /// Attaches metadata of the given kind to the instruction, such as `!dbg`.
+    pub(crate) fn attach_metadata(&mut self, id: InstId, kind: &str, metadata: Metadata) {
+        self.instructions[id.0]
+            .metadata
+            .push((kind.to_string(), metadata));
+    }
+
+    pub(crate) fn add_instruction(&mut self, instruction: Instruction) -> InstId {
+        self.instructions.push(instruction);
+        InstId(self.instructions.len() - 1)
+    }
+
+    pub(crate) fn next_block(&mut self) -> usize {
+        self.block_index += 1;
+        self.block_index - 1
+    }
+
+    pub(crate) fn next_register(&mut self) -> usize {
+        self.register_index += 1;
+        self.register_index - 1
+    }
 }
@@ -0,0 +1,435 @@
+use crate::error::BuildError;
+use crate::instruction::{InstructionKind, SharedBasicBlock};
+use crate::module::CallingConvention;
+use crate::types::Type;
+use crate::value::{check_inline_asm, Value};
+use crate::IRComponent;
+use std::boxed::Box;
+use std::string::String;
+use std::vec::Vec;
+
+pub(crate) fn check_call(signature: &Type, arguments: &[Value]) -> Result<(), BuildError> {
+    let Type::Function(_, parameters, variadic) = signature else {
+        return Err(BuildError::TypeMismatch {
+            expected: Type::Function(Box::new(Type::Void), Vec::new(), false),
+            found: signature.clone(),
+        });
+    };
+    let count = arguments.len();
+    if count < parameters.len() || (!variadic && count > parameters.len()) {
+        return Err(BuildError::ArgumentCount {
+            expected: parameters.len(),
+            found: count,
+        });
+    }
+    for (parameter, argument) in parameters.iter().zip(arguments) {
+        if **parameter != *argument.ty() {
+            return Err(BuildError::TypeMismatch {
+                expected: *parameter.clone(),
+                found: argument.ty().clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Marks whether a call may, must or must not be tail-call optimized.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TailMarker {
+    /// The callee does not access allocas from the caller, and the call may be tail-call optimized.
+    Tail,
+    /// The call must be tail-call optimized.
+    MustTail,
+    /// The call must never be tail-call optimized.
+    NoTail,
+}
+
+impl IRComponent for TailMarker {
+    fn append_to_string(&self, string: &mut String) {
+        match self {
+            TailMarker::Tail => string.push_str("tail"),
+            TailMarker::MustTail => string.push_str("musttail"),
+            TailMarker::NoTail => string.push_str("notail"),
+        }
+    }
+}
+
+/// Additional options for a `call` instruction.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CallOptions {
+    pub(crate) tail: Option<TailMarker>,
+    pub(crate) convention: Option<CallingConvention>,
+}
+
+impl CallOptions {
+    /// Creates a new set of call options, using the defaults of LLVM.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Provides a tail call marker to the call.
+    pub fn with_tail_marker(mut self, marker: TailMarker) -> Self {
+        self.tail = Some(marker);
+        self
+    }
+
+    /// Provides a calling convention to the call. This must match the convention of the callee.
+    pub fn with_calling_convention(mut self, convention: CallingConvention) -> Self {
+        self.convention = Some(convention);
+        self
+    }
+}
+
+impl SharedBasicBlock {
+    /// Calls the function with the provided arguments.
+    /// Returns the result of the call, or nothing if the function returns void.
+    pub fn call(&self, callee: Value, arguments: Vec<Value>) -> Option<Value> {
+        self.call_with(callee, arguments, CallOptions::new())
+    }
+
+    /// Calls the function with the provided arguments and call options.
+    /// Returns the result of the call, or nothing if the function returns void.
+    pub fn call_with(
+        &self,
+        callee: Value,
+        arguments: Vec<Value>,
+        options: CallOptions,
+    ) -> Option<Value> {
+        self.try_call_with(callee, arguments, options)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Calls the function with the provided arguments, or returns an error
+    /// if the callee is not a function, or the arguments do not match its parameters.
+    pub fn try_call(
+        &self,
+        callee: Value,
+        arguments: Vec<Value>,
+    ) -> Result<Option<Value>, BuildError> {
+        self.try_call_with(callee, arguments, CallOptions::new())
+    }
+
+    /// Calls the function with the provided arguments and call options, or returns an error
+    /// if the callee is not a function, the arguments do not match its parameters,
+    /// or the callee is inline assembly whose constraints do not match its signature.
+    pub fn try_call_with(
+        &self,
+        callee: Value,
+        arguments: Vec<Value>,
+        options: CallOptions,
+    ) -> Result<Option<Value>, BuildError> {
+        let Some(signature) = callee.signature().cloned() else {
+            return Err(BuildError::NotFunction(callee));
+        };
+        if let Value::InlineAsm(asm) = &callee {
+            check_inline_asm(asm)?;
+        }
+        self.build_call(signature, callee, arguments, options)
+    }
+
+    /// Calls the function pointer with the provided arguments, as a function of the signature.
+    /// Returns the result of the call, or nothing if the signature returns void.
+    pub fn call_indirect(
+        &self,
+        signature: Type,
+        callee: Value,
+        arguments: Vec<Value>,
+    ) -> Option<Value> {
+        self.call_indirect_with(signature, callee, arguments, CallOptions::new())
+    }
+
+    /// Calls the function pointer with the provided arguments and call options,
+    /// as a function of the signature.
+    /// Returns the result of the call, or nothing if the signature returns void.
+    pub fn call_indirect_with(
+        &self,
+        signature: Type,
+        callee: Value,
+        arguments: Vec<Value>,
+        options: CallOptions,
+    ) -> Option<Value> {
+        self.try_call_indirect_with(signature, callee, arguments, options)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Calls the function pointer with the provided arguments, or returns an error
+    /// if the callee is not a pointer, or the arguments do not match the signature.
+    pub fn try_call_indirect(
+        &self,
+        signature: Type,
+        callee: Value,
+        arguments: Vec<Value>,
+    ) -> Result<Option<Value>, BuildError> {
+        self.try_call_indirect_with(signature, callee, arguments, CallOptions::new())
+    }
+
+    /// Calls the function pointer with the provided arguments and call options, or returns
+    /// an error if the callee is not a pointer, the signature is not a function type,
+    /// or the arguments do not match the signature.
+    pub fn try_call_indirect_with(
+        &self,
+        signature: Type,
+        callee: Value,
+        arguments: Vec<Value>,
+        options: CallOptions,
+    ) -> Result<Option<Value>, BuildError> {
+        if !callee.ty().is_pointer() {
+            return Err(BuildError::TypeMismatch {
+                expected: Type::Ptr,
+                found: callee.ty().clone(),
+            });
+        }
+        self.build_call(signature, callee, arguments, options)
+    }
+
+    fn build_call(
+        &self,
+        signature: Type,
+        callee: Value,
+        arguments: Vec<Value>,
+        options: CallOptions,
+    ) -> Result<Option<Value>, BuildError> {
+        check_call(&signature, &arguments)?;
+        let Type::Function(return_type, _, _) = &signature else {
+            unreachable!("call signatures are always function types");
+        };
+
+        let value = match **return_type {
+            Type::Void => None,
+            ref ty => Some(self.create_local_register(ty.clone()).1),
+        };
+        self.push_instruction(
+            InstructionKind::Call {
+                options,
+                signature,
+                callee,
+                arguments,
+            },
+            value.clone(),
+        );
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::BuildError;
+    use crate::instruction::{CallOptions, TailMarker};
+    use crate::module::{CallingConvention, FunctionBody, GlobalFunction};
+    use crate::types::{Type, Types};
+    use crate::value::{InlineAsm, Value, Values};
+    use crate::IRComponent;
+    use std::string::ToString;
+    use std::vec;
+
+    #[test]
+    fn build_calling_function() {
+        let callee = GlobalFunction::new("answer", Types::integer(32));
+        let body = FunctionBody::new(|block| {
+            let answer = block.call(callee.reference(), vec![]).unwrap();
+            block.ret(answer);
+        });
+        let f = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(
+            f.emit(),
+            "define i32 @main() { \
+                entry: \
+                    %r0 = call i32 @answer() \
+                    ret i32 %r0 \
+            }"
+        );
+    }
+
+    #[test]
+    fn build_void_calling_function() {
+        let callee = GlobalFunction::new("exit", Types::void()).with_parameter(Types::integer(32));
+        let body = FunctionBody::new(|block| {
+            let result = block.call_with(
+                callee.reference(),
+                vec![Values::integer("0", 32)],
+                CallOptions::new()
+                    .with_tail_marker(TailMarker::Tail)
+                    .with_calling_convention(CallingConvention::Fast),
+            );
+            assert!(result.is_none());
+            block.unreachable();
+        });
+        let f = GlobalFunction::new("main", Types::void()).body(body);
+        assert_eq!(
+            f.emit(),
+            "define void @main() { \
+                entry: \
+                    tail call fastcc void @exit(i32 0) \
+                    unreachable \
+            }"
+        );
+    }
+
+    #[test]
+    fn build_variadic_calling_function() {
+        let printf = GlobalFunction::new("printf", Types::integer(32))
+            .with_parameter(Type::Ptr)
+            .variadic();
+        assert_eq!(printf.emit(), "declare i32 @printf(ptr, ...)");
+
+        let body = FunctionBody::new(|block| {
+            let format = Value::GlobalIdentifier("format".to_string(), Type::Ptr);
+            block.call(printf.reference(), vec![format, Values::integer("10", 32)]);
+            block.ret_void();
+        });
+        let f = GlobalFunction::new("main", Types::void()).body(body);
+        assert_eq!(
+            f.emit(),
+            "define void @main() { \
+                entry: \
+                    %r0 = call i32 (ptr, ...) @printf(ptr @format, i32 10) \
+                    ret void \
+            }"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn reject_missing_arguments() {
+        let callee = GlobalFunction::new("exit", Types::void()).with_parameter(Types::integer(32));
+        FunctionBody::new(|block| {
+            block.call(callee.reference(), vec![]);
+        });
+    }
+
+    #[test]
+    fn reject_invalid_calls() {
+        let callee = GlobalFunction::new("exit", Types::void()).with_parameter(Types::integer(32));
+        FunctionBody::new(|block| {
+            assert_eq!(
+                block.try_call(callee.reference(), vec![Values::integer("0", 64)]),
+                Err(BuildError::TypeMismatch {
+                    expected: Types::integer(32),
+                    found: Types::integer(64),
+                })
+            );
+            let pointer = Value::GlobalIdentifier("exit".to_string(), Type::Ptr);
+            assert_eq!(
+                block.try_call(pointer.clone(), vec![]),
+                Err(BuildError::NotFunction(pointer))
+            );
+        });
+    }
+
+    #[test]
+    fn build_indirect_calling_function() {
+        let signature = Types::function(Types::integer(32), vec![Types::integer(32)]);
+        let body = FunctionBody::new(|block| {
+            let slot = block.alloca(Types::pointer(0));
+            let callee = block.load(Types::pointer(0), slot);
+            let result = block
+                .call_indirect(signature.clone(), callee, vec![Values::integer("1", 32)])
+                .unwrap();
+            block.ret(result);
+        });
+        let f = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(
+            f.emit(),
+            "define i32 @main() { \
+                entry: \
+                    %r0 = alloca ptr \
+                    %r1 = load ptr, ptr %r0 \
+                    %r2 = call i32 %r1(i32 1) \
+                    ret i32 %r2 \
+            }"
+        );
+    }
+
+    #[test]
+    fn reject_invalid_indirect_calls() {
+        let signature = Types::function(Types::void(), vec![Types::integer(32)]);
+        let pointer = Value::GlobalIdentifier("exit".to_string(), Type::Ptr);
+        FunctionBody::new(|block| {
+            assert_eq!(
+                block.try_call_indirect(
+                    signature.clone(),
+                    Values::integer("0", 32),
+                    vec![Values::integer("0", 32)]
+                ),
+                Err(BuildError::TypeMismatch {
+                    expected: Type::Ptr,
+                    found: Types::integer(32),
+                })
+            );
+            assert_eq!(
+                block.try_call_indirect(signature.clone(), pointer.clone(), vec![]),
+                Err(BuildError::ArgumentCount {
+                    expected: 1,
+                    found: 0,
+                })
+            );
+            assert!(block
+                .try_call_indirect(Types::integer(32), pointer.clone(), vec![])
+                .is_err());
+            block.unreachable();
+        });
+    }
+
+    #[test]
+    fn build_inline_asm_calls() {
+        let syscall = InlineAsm::new(
+            "syscall",
+            "={rax},{rax},{rdi},~{rcx},~{r11},~{memory}",
+            Types::function(
+                Types::integer(64),
+                vec![Types::integer(64), Types::integer(64)],
+            ),
+        )
+        .with_side_effects();
+        let registers = Types::structure(vec![Types::integer(32); 4]);
+        let cpuid = InlineAsm::new(
+            "cpuid",
+            "={ax},={bx},={cx},={dx},{ax},{cx}",
+            Types::function(registers, vec![Types::integer(32), Types::integer(32)]),
+        )
+        .with_aligned_stack()
+        .with_intel_dialect();
+        let body = FunctionBody::new(|block| {
+            let zero = Values::integer("0", 32);
+            let leaves = block.call(cpuid.reference(), vec![zero.clone(), zero]);
+            let maximum = block.extractvalue(leaves.unwrap(), 0);
+            let code = block.zext(maximum, Types::integer(64));
+            let arguments = vec![Values::integer("60", 64), code];
+            block.call(syscall.reference(), arguments);
+            block.unreachable();
+        });
+        let f = GlobalFunction::new("main", Types::void()).body(body);
+        assert_eq!(
+            f.emit(),
+            "define void @main() { \
+                entry: \
+                    %r0 = call {i32, i32, i32, i32} asm alignstack inteldialect \"cpuid\", \
+                        \"={ax},={bx},={cx},={dx},{ax},{cx}\"(i32 0, i32 0) \
+                    %r1 = extractvalue {i32, i32, i32, i32} %r0, 0 \
+                    %r2 = zext i32 %r1 to i64 \
+                    %r3 = call i64 asm sideeffect \"syscall\", \
+                        \"={rax},{rax},{rdi},~{rcx},~{r11},~{memory}\"(i64 60, i64 %r2) \
+                    unreachable \
+            }"
+        );
+    }
+
+    #[test]
+    fn reject_invalid_inline_asm() {
+        let signature = Types::function(Types::integer(32), vec![Types::integer(32)]);
+        FunctionBody::new(|block| {
+            for constraints in ["=r", "=r,=r,r", "r,r"] {
+                let asm = InlineAsm::new("mov $1, $0", constraints, signature.clone());
+                assert_eq!(
+                    block.try_call(asm.reference(), vec![Values::integer("1", 32)]),
+                    Err(BuildError::InvalidConstraints(asm))
+                );
+            }
+            let asm = InlineAsm::new("mov $1, $0", "=r,r", signature.clone());
+            assert!(block
+                .try_call(asm.reference(), vec![Values::integer("1", 32)])
+                .is_ok());
+            block.unreachable();
+        });
+    }
+}
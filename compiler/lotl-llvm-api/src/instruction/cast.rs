@@ -0,0 +1,305 @@
+use crate::error::BuildError;
+use crate::instruction::{InstructionKind, SharedBasicBlock};
+use crate::types::Type;
+use crate::value::Value;
+use crate::IRComponent;
+use std::string::String;
+
+/// Represents the conversion operations of LLVM.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CastOperator {
+    /// Truncates an integer to a smaller integer type.
+    Trunc,
+    /// Zero-extends an integer to a larger integer type.
+    ZeroExtend,
+    /// Sign-extends an integer to a larger integer type.
+    SignExtend,
+    /// Truncates a floating-point value to a smaller floating-point type.
+    FloatTrunc,
+    /// Extends a floating-point value to a larger floating-point type.
+    FloatExtend,
+    /// Converts a floating-point value to the nearest unsigned integer, rounding towards zero.
+    FloatToUnsigned,
+    /// Converts a floating-point value to the nearest signed integer, rounding towards zero.
+    FloatToSigned,
+    /// Converts an unsigned integer to a floating-point value.
+    UnsignedToFloat,
+    /// Converts a signed integer to a floating-point value.
+    SignedToFloat,
+    /// Converts a pointer to an integer, truncating or zero-extending its address.
+    PtrToInt,
+    /// Converts an integer to a pointer, truncating or zero-extending the integer.
+    IntToPtr,
+    /// Reinterprets the bits of a value as another type of the same size.
+    BitCast,
+    /// Converts a pointer into a pointer of another address space.
+    AddrSpaceCast,
+}
+
+impl IRComponent for CastOperator {
+    fn append_to_string(&self, string: &mut String) {
+        match self {
+            CastOperator::Trunc => string.push_str("trunc"),
+            CastOperator::ZeroExtend => string.push_str("zext"),
+            CastOperator::SignExtend => string.push_str("sext"),
+            CastOperator::FloatTrunc => string.push_str("fptrunc"),
+            CastOperator::FloatExtend => string.push_str("fpext"),
+            CastOperator::FloatToUnsigned => string.push_str("fptoui"),
+            CastOperator::FloatToSigned => string.push_str("fptosi"),
+            CastOperator::UnsignedToFloat => string.push_str("uitofp"),
+            CastOperator::SignedToFloat => string.push_str("sitofp"),
+            CastOperator::PtrToInt => string.push_str("ptrtoint"),
+            CastOperator::IntToPtr => string.push_str("inttoptr"),
+            CastOperator::BitCast => string.push_str("bitcast"),
+            CastOperator::AddrSpaceCast => string.push_str("addrspacecast"),
+        }
+    }
+}
+
+impl CastOperator {
+    /// Returns true if this operator can convert a value of the source type into the target type.
+    pub fn is_valid(&self, source: &Type, target: &Type) -> bool {
+        let widths = (source.bit_width(), target.bit_width());
+        match self {
+            CastOperator::Trunc => {
+                source.is_integer() && target.is_integer() && widths.0 > widths.1
+            }
+            CastOperator::ZeroExtend | CastOperator::SignExtend => {
+                source.is_integer() && target.is_integer() && widths.0 < widths.1
+            }
+            CastOperator::FloatTrunc => {
+                source.is_floating_point() && target.is_floating_point() && widths.0 > widths.1
+            }
+            CastOperator::FloatExtend => {
+                source.is_floating_point() && target.is_floating_point() && widths.0 < widths.1
+            }
+            CastOperator::FloatToUnsigned | CastOperator::FloatToSigned => {
+                source.is_floating_point() && target.is_integer()
+            }
+            CastOperator::UnsignedToFloat | CastOperator::SignedToFloat => {
+                source.is_integer() && target.is_floating_point()
+            }
+            CastOperator::PtrToInt => source.is_pointer() && target.is_integer(),
+            CastOperator::IntToPtr => source.is_integer() && target.is_pointer(),
+            CastOperator::BitCast => {
+                if source.is_pointer() || target.is_pointer() {
+                    source.address_space().is_some()
+                        && source.address_space() == target.address_space()
+                } else {
+                    widths.0.is_some() && widths.0 == widths.1
+                }
+            }
+            CastOperator::AddrSpaceCast => {
+                source.is_pointer()
+                    && target.is_pointer()
+                    && source.address_space() != target.address_space()
+            }
+        }
+    }
+}
+
+pub(crate) fn check_cast(
+    operator: CastOperator,
+    value: &Value,
+    target: &Type,
+) -> Result<(), BuildError> {
+    if !operator.is_valid(value.ty(), target) {
+        return Err(BuildError::InvalidCast {
+            operator,
+            from: value.ty().clone(),
+            to: target.clone(),
+        });
+    }
+    Ok(())
+}
+
+impl SharedBasicBlock {
+    /// Converts the provided value into the target type, using the given conversion.
+    pub fn cast(&self, operator: CastOperator, value: Value, target: Type) -> Value {
+        self.try_cast(operator, value, target)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Converts the provided value into the target type, or returns an error
+    /// if the conversion cannot be made between the two types.
+    pub fn try_cast(
+        &self,
+        operator: CastOperator,
+        value: Value,
+        target: Type,
+    ) -> Result<Value, BuildError> {
+        check_cast(operator, &value, &target)?;
+        let (_, out) = self.create_local_register(target.clone());
+        self.push_instruction(
+            InstructionKind::Cast {
+                operator,
+                value,
+                target,
+            },
+            Some(out.clone()),
+        );
+        Ok(out)
+    }
+
+    /// Truncates the provided integer.
+    pub fn trunc(&self, value: Value, target: Type) -> Value {
+        self.cast(CastOperator::Trunc, value, target)
+    }
+
+    /// Zero-extends the provided integer.
+    pub fn zext(&self, value: Value, target: Type) -> Value {
+        self.cast(CastOperator::ZeroExtend, value, target)
+    }
+
+    /// Sign-extends the provided integer.
+    pub fn sext(&self, value: Value, target: Type) -> Value {
+        self.cast(CastOperator::SignExtend, value, target)
+    }
+
+    /// Truncates the provided floating point.
+    pub fn fptrunc(&self, value: Value, target: Type) -> Value {
+        self.cast(CastOperator::FloatTrunc, value, target)
+    }
+
+    /// Extends the provided floating point.
+    pub fn fpext(&self, value: Value, target: Type) -> Value {
+        self.cast(CastOperator::FloatExtend, value, target)
+    }
+
+    /// Converts the provided floating point to an unsigned integer.
+    pub fn fptoui(&self, value: Value, target: Type) -> Value {
+        self.cast(CastOperator::FloatToUnsigned, value, target)
+    }
+
+    /// Converts the provided floating point to a signed integer.
+    pub fn fptosi(&self, value: Value, target: Type) -> Value {
+        self.cast(CastOperator::FloatToSigned, value, target)
+    }
+
+    /// Converts the provided unsigned integer to a floating point.
+    pub fn uitofp(&self, value: Value, target: Type) -> Value {
+        self.cast(CastOperator::UnsignedToFloat, value, target)
+    }
+
+    /// Converts the provided signed integer to a floating point.
+    pub fn sitofp(&self, value: Value, target: Type) -> Value {
+        self.cast(CastOperator::SignedToFloat, value, target)
+    }
+
+    /// Converts the provided pointer to an integer.
+    pub fn ptrtoint(&self, value: Value, target: Type) -> Value {
+        self.cast(CastOperator::PtrToInt, value, target)
+    }
+
+    /// Converts the provided integer to a pointer.
+    pub fn inttoptr(&self, value: Value, target: Type) -> Value {
+        self.cast(CastOperator::IntToPtr, value, target)
+    }
+
+    /// Reinterprets the bits of the provided value as another type.
+    pub fn bitcast(&self, value: Value, target: Type) -> Value {
+        self.cast(CastOperator::BitCast, value, target)
+    }
+
+    /// Converts the provided pointer into another address space.
+    pub fn addrspacecast(&self, value: Value, target: Type) -> Value {
+        self.cast(CastOperator::AddrSpaceCast, value, target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::module::{FunctionBody, GlobalFunction};
+    use crate::types::{Type, Types};
+    use crate::value::Values;
+    use crate::IRComponent;
+
+    #[test]
+    fn build_integer_casting_function() {
+        let body = FunctionBody::new(|block| {
+            let truncated = block.trunc(Values::integer("300", 32), Types::integer(8));
+            let zero_extended = block.zext(truncated.clone(), Types::integer(64));
+            let _sign_extended = block.sext(truncated, Types::integer(16));
+            block.ret(zero_extended);
+        });
+        let f = GlobalFunction::new("main", Types::integer(64)).body(body);
+        assert_eq!(
+            f.emit(),
+            "define i64 @main() { \
+                entry: \
+                    %r0 = trunc i32 300 to i8 \
+                    %r1 = zext i8 %r0 to i64 \
+                    %r2 = sext i8 %r0 to i16 \
+                    ret i64 %r1 \
+            }"
+        );
+    }
+
+    #[test]
+    fn build_float_casting_function() {
+        let body = FunctionBody::new(|block| {
+            let truncated = block.fptrunc(Values::float("1.5", Types::fp64()), Types::fp32());
+            let extended = block.fpext(truncated, Types::fp128());
+            let unsigned = block.fptoui(extended.clone(), Types::integer(32));
+            let signed = block.fptosi(extended, Types::integer(32));
+            let _from_unsigned = block.uitofp(unsigned, Types::fp16());
+            let from_signed = block.sitofp(signed, Types::fp64());
+            block.ret(from_signed);
+        });
+        let f = GlobalFunction::new("main", Types::fp64()).body(body);
+        assert_eq!(
+            f.emit(),
+            "define double @main() { \
+                entry: \
+                    %r0 = fptrunc double 1.5 to float \
+                    %r1 = fpext float %r0 to fp128 \
+                    %r2 = fptoui fp128 %r1 to i32 \
+                    %r3 = fptosi fp128 %r1 to i32 \
+                    %r4 = uitofp i32 %r2 to half \
+                    %r5 = sitofp i32 %r3 to double \
+                    ret double %r5 \
+            }"
+        );
+    }
+
+    #[test]
+    fn build_pointer_casting_function() {
+        let body = FunctionBody::new(|block| {
+            let pointer = block.inttoptr(Values::integer("4096", 64), Type::Ptr);
+            let global = block.addrspacecast(pointer.clone(), Types::pointer(1));
+            let _same = block.bitcast(global, Types::pointer(1));
+            let address = block.ptrtoint(pointer, Types::integer(64));
+            let bits = block.bitcast(address, Types::fp64());
+            block.ret(bits);
+        });
+        let f = GlobalFunction::new("main", Types::fp64()).body(body);
+        assert_eq!(
+            f.emit(),
+            "define double @main() { \
+                entry: \
+                    %r0 = inttoptr i64 4096 to ptr \
+                    %r1 = addrspacecast ptr %r0 to ptr addrspace(1) \
+                    %r2 = bitcast ptr addrspace(1) %r1 to ptr addrspace(1) \
+                    %r3 = ptrtoint ptr %r0 to i64 \
+                    %r4 = bitcast i64 %r3 to double \
+                    ret double %r4 \
+            }"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn reject_widening_trunc() {
+        FunctionBody::new(|block| {
+            block.trunc(Values::integer("1", 8), Types::integer(32));
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn reject_mismatched_bitcast() {
+        FunctionBody::new(|block| {
+            block.bitcast(Values::integer("1", 32), Types::fp64());
+        });
+    }
+}
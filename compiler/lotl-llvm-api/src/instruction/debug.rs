@@ -0,0 +1,53 @@
+use crate::instruction::{InstructionKind, SharedBasicBlock};
+use crate::metadata::Metadata;
+use crate::value::Value;
+
+impl SharedBasicBlock {
+    /// Describes a source variable living in the memory the pointer points to,
+    /// such as the result of an `alloca`.
+    pub fn declare_variable(&self, address: Value, variable: Metadata, location: Metadata) {
+        if !address.ty().is_pointer() {
+            panic!(
+                "variable declarations require a pointer, found {:?}",
+                address.ty()
+            );
+        }
+        self.push_instruction(
+            InstructionKind::DeclareVariable {
+                address,
+                variable,
+                location,
+            },
+            None,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metadata::Metadata;
+    use crate::module::{FunctionBody, GlobalFunction};
+    use crate::types::Types;
+    use crate::IRComponent;
+
+    #[test]
+    fn build_declaring_function() {
+        let body = FunctionBody::new(|block| {
+            block.set_debug_location(Some(Metadata::Reference(3)));
+            let pointer = block.alloca(Types::integer(32));
+            block.declare_variable(pointer, Metadata::Reference(2), Metadata::Reference(3));
+            block.set_debug_location(None);
+            block.ret_void();
+        });
+        let f = GlobalFunction::new("main", Types::void()).body(body);
+        assert_eq!(
+            f.emit(),
+            "define void @main() { \
+                entry: \
+                    %r0 = alloca i32, !dbg !3 \
+                    #dbg_declare(ptr %r0, !2, !DIExpression(), !3) \
+                    ret void \
+            }"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use crate::error::BuildError;
+use crate::instruction::call::check_call;
+use crate::instruction::{CallOptions, InstructionKind, SharedBasicBlock};
+use crate::types::Type;
+use crate::value::{check_inline_asm, Value};
+use crate::IRComponent;
+use std::string::String;
+use std::vec::Vec;
+
+/// Represents a clause of a `landingpad`, naming the exceptions it receives.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LandingPadClause {
+    /// Receives exceptions of the type described by the pointer, such as a C++ typeinfo.
+    Catch(Value),
+    /// Receives every exception whose type is not in the array of pointers.
+    Filter(Value),
+}
+
+impl IRComponent for LandingPadClause {
+    fn append_to_string(&self, string: &mut String) {
+        let value = match self {
+            LandingPadClause::Catch(value) => {
+                string.push_str("catch ");
+                value
+            }
+            LandingPadClause::Filter(value) => {
+                string.push_str("filter ");
+                value
+            }
+        };
+        value.append_to_string(string);
+    }
+}
+
+/// Checks that an `invoke` calls a function with arguments matching its parameters,
+/// and is not marked as a tail call.
+pub(crate) fn check_invoke(
+    signature: &Type,
+    arguments: &[Value],
+    options: &CallOptions,
+) -> Result<(), BuildError> {
+    if options.tail.is_some() {
+        return Err(BuildError::TailInvoke);
+    }
+    check_call(signature, arguments)
+}
+
+/// Checks that a `landingpad` receives some exception,
+/// with `catch` clauses taking a pointer and `filter` clauses an array.
+pub(crate) fn check_landingpad(
+    cleanup: bool,
+    clauses: &[LandingPadClause],
+) -> Result<(), BuildError> {
+    if !cleanup && clauses.is_empty() {
+        return Err(BuildError::EmptyLandingPad);
+    }
+    for clause in clauses {
+        let valid = match clause {
+            LandingPadClause::Catch(value) => value.ty().is_pointer(),
+            LandingPadClause::Filter(value) => matches!(value.ty(), Type::Array(..)),
+        };
+        if !valid {
+            return Err(BuildError::InvalidClause(clause.clone()));
+        }
+    }
+    Ok(())
+}
+
+impl SharedBasicBlock {
+    /// Calls the function with the provided arguments, continuing in the returned normal block
+    /// once it returns, or in the returned unwind block if it raises an exception.
+    /// Returns the result of the call, which is only available in the normal block,
+    /// along with the normal and unwind blocks.
+    pub fn invoke_returning(
+        &self,
+        callee: Value,
+        arguments: Vec<Value>,
+    ) -> (Option<Value>, SharedBasicBlock, SharedBasicBlock) {
+        self.try_invoke_returning(callee, arguments)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Calls the function with the provided arguments, continuing in the returned normal block
+    /// once it returns, or in the returned unwind block if it raises an exception.
+    /// Returns an error if the callee is not a function, or the arguments do not match its parameters.
+    pub fn try_invoke_returning(
+        &self,
+        callee: Value,
+        arguments: Vec<Value>,
+    ) -> Result<(Option<Value>, SharedBasicBlock, SharedBasicBlock), BuildError> {
+        let normal = self.child();
+        let unwind = self.child();
+        let result =
+            self.try_invoke_to_with(callee, arguments, &normal, &unwind, CallOptions::new())?;
+        Ok((result, normal, unwind))
+    }
+
+    /// Calls the function with the provided arguments, continuing in the normal block
+    /// once it returns, or in the unwind block if it raises an exception.
+    /// Both blocks must be of the same function body, and the unwind block must start
+    /// with a `landingpad`. Returns the result of the call, which is only available in the normal block.
+    pub fn invoke_to(
+        &self,
+        callee: Value,
+        arguments: Vec<Value>,
+        normal: &SharedBasicBlock,
+        unwind: &SharedBasicBlock,
+    ) -> Option<Value> {
+        self.invoke_to_with(callee, arguments, normal, unwind, CallOptions::new())
+    }
+
+    /// Calls the function with the provided arguments and call options, continuing in the normal
+    /// block once it returns, or in the unwind block if it raises an exception.
+    /// Invokes cannot be tail calls, so the options must not have a tail marker.
+    pub fn invoke_to_with(
+        &self,
+        callee: Value,
+        arguments: Vec<Value>,
+        normal: &SharedBasicBlock,
+        unwind: &SharedBasicBlock,
+        options: CallOptions,
+    ) -> Option<Value> {
+        self.try_invoke_to_with(callee, arguments, normal, unwind, options)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Calls the function with the provided arguments, continuing in the normal block
+    /// once it returns, or in the unwind block if it raises an exception. Returns an error
+    /// if the callee is not a function, or the arguments do not match its parameters.
+    pub fn try_invoke_to(
+        &self,
+        callee: Value,
+        arguments: Vec<Value>,
+        normal: &SharedBasicBlock,
+        unwind: &SharedBasicBlock,
+    ) -> Result<Option<Value>, BuildError> {
+        self.try_invoke_to_with(callee, arguments, normal, unwind, CallOptions::new())
+    }
+
+    /// Calls the function with the provided arguments and call options, continuing in the normal
+    /// block once it returns, or in the unwind block if it raises an exception. Returns an error
+    /// if the callee is not a function, the arguments do not match its parameters,
+    /// or the options have a tail marker.
+    pub fn try_invoke_to_with(
+        &self,
+        callee: Value,
+        arguments: Vec<Value>,
+        normal: &SharedBasicBlock,
+        unwind: &SharedBasicBlock,
+        options: CallOptions,
+    ) -> Result<Option<Value>, BuildError> {
+        let Some(signature) = callee.signature() else {
+            return Err(BuildError::NotFunction(callee));
+        };
+        if let Value::InlineAsm(asm) = &callee {
+            check_inline_asm(asm)?;
+        }
+        check_invoke(signature, &arguments, &options)?;
+        let Type::Function(return_type, _, _) = signature else {
+            unreachable!("call signatures are always function types");
+        };
+
+        let signature = signature.clone();
+        let value = match **return_type {
+            Type::Void => None,
+            ref ty => Some(self.create_local_register(ty.clone()).1),
+        };
+        let kind = InstructionKind::Invoke {
+            options,
+            signature,
+            callee,
+            arguments,
+            normal: self.target(normal),
+            unwind: self.target(unwind),
+        };
+        self.push_instruction(kind, value.clone());
+        Ok(value)
+    }
+
+    /// Receives an exception raised in an `invoke`, which must be the first instruction
+    /// of its unwind block after any `phi`. Returns the value describing the exception,
+    /// which is usually of the type `{ptr, i32}`.
+    pub fn landingpad(&self, ty: Type, cleanup: bool, clauses: Vec<LandingPadClause>) -> Value {
+        self.try_landingpad(ty, cleanup, clauses)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Receives an exception raised in an `invoke`, or returns an error if the landing pad
+    /// receives no exception, or a clause does not describe the types of exceptions.
+    pub fn try_landingpad(
+        &self,
+        ty: Type,
+        cleanup: bool,
+        clauses: Vec<LandingPadClause>,
+    ) -> Result<Value, BuildError> {
+        check_landingpad(cleanup, &clauses)?;
+        let (_, value) = self.create_local_register(ty.clone());
+        let kind = InstructionKind::LandingPad {
+            ty,
+            cleanup,
+            clauses,
+        };
+        self.push_instruction(kind, Some(value.clone()));
+        Ok(value)
+    }
+
+    /// Continues raising the exception received by a `landingpad`, unwinding into the caller.
+    pub fn resume(&self, value: Value) {
+        self.push_instruction(InstructionKind::Resume { value }, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::BuildError;
+    use crate::instruction::{CallOptions, LandingPadClause, TailMarker};
+    use crate::module::{FunctionBody, GlobalFunction};
+    use crate::types::{Type, Types};
+    use crate::value::{Value, Values};
+    use crate::IRComponent;
+    use std::string::ToString;
+    use std::vec;
+
+    #[test]
+    fn build_invoking_function() {
+        let personality =
+            GlobalFunction::new("__gxx_personality_v0", Types::integer(32)).variadic();
+        let callee = GlobalFunction::new("may_throw", Types::integer(32));
+        let typeinfo = Value::GlobalIdentifier("typeinfo".to_string(), Type::Ptr);
+        let body = FunctionBody::new(|block| {
+            let (result, normal, unwind) = block.invoke_returning(callee.reference(), vec![]);
+            normal.ret(result.unwrap());
+            let ty = Types::structure(vec![Type::Ptr, Types::integer(32)]);
+            let exception = unwind.landingpad(ty, true, vec![LandingPadClause::Catch(typeinfo)]);
+            unwind.resume(exception);
+        });
+        let f = GlobalFunction::new("main", Types::integer(32))
+            .with_personality(personality.reference())
+            .body(body);
+        assert!(f.verify().is_empty());
+        assert_eq!(
+            f.emit(),
+            "define i32 @main() personality ptr @__gxx_personality_v0 { \
+                entry: \
+                    %r0 = invoke i32 @may_throw() to label %bb0 unwind label %bb1 \
+                bb0: \
+                    ret i32 %r0 \
+                bb1: \
+                    %r1 = landingpad {ptr, i32} cleanup catch ptr @typeinfo \
+                    resume {ptr, i32} %r1 \
+            }"
+        );
+    }
+
+    #[test]
+    fn reject_invalid_exception_handling() {
+        let callee = GlobalFunction::new("may_throw", Types::void());
+        let body = FunctionBody::new(|block| {
+            let (normal, unwind) = (block.child(), block.child());
+            let options = CallOptions::new().with_tail_marker(TailMarker::Tail);
+            assert_eq!(
+                block.try_invoke_to_with(callee.reference(), vec![], &normal, &unwind, options),
+                Err(BuildError::TailInvoke)
+            );
+            assert_eq!(
+                unwind.try_landingpad(Type::Ptr, false, vec![]),
+                Err(BuildError::EmptyLandingPad)
+            );
+            let clause = LandingPadClause::Filter(Values::integer("0", 32));
+            assert_eq!(
+                unwind.try_landingpad(Type::Ptr, false, vec![clause.clone()]),
+                Err(BuildError::InvalidClause(clause))
+            );
+            block.unreachable();
+        });
+        GlobalFunction::new("main", Types::void()).body(body);
+    }
+}
@@ -1,104 +1,72 @@
-use crate::instruction::{BasicBlock, Instruction, SharedBasicBlock};
+use crate::error::BuildError;
+use crate::instruction::{InstructionKind, SharedBasicBlock};
+use crate::pass::Integer;
 use crate::types::Type;
 use crate::value::Value;
-use crate::IRComponent;
 use std::boxed::Box;
-use std::format;
-use std::string::String;
+use std::vec::Vec;
 
-struct Return {
-    value: Option<Value>,
-}
-
-impl IRComponent for Return {
-    fn append_to_string(&self, string: &mut String) {
-        string.push_str("ret ");
-        let v = Type::Void;
-        self.value
-            .as_ref()
-            .map(|x| x.ty())
-            .unwrap_or_else(|| &v)
-            .append_to_string(string);
-        self.value.iter().for_each(|value| {
-            string.push(' ');
-            value.append_to_string_untyped(string)
-        });
-    }
-}
-impl Instruction for Return {}
-
-struct BranchCond {
-    cond: Value,
-    true_label: String,
-    false_label: String,
-}
-
-impl IRComponent for BranchCond {
-    fn append_to_string(&self, string: &mut String) {
-        string.push_str(
-            format!(
-                "br {}, label %{}, label %{}",
-                self.cond.emit(),
-                self.true_label,
-                self.false_label
-            )
-            .as_str(),
-        );
-    }
-}
-impl Instruction for BranchCond {}
-
-struct BranchConst {
-    true_label: String,
-}
+/// Builds the block of a `switch` case, which is created when the `switch` is pushed.
+pub type CaseLabel = Box<dyn FnOnce(SharedBasicBlock)>;
 
-impl IRComponent for BranchConst {
-    fn append_to_string(&self, string: &mut String) {
-        string.push_str("br label %");
-        string.push_str(&self.true_label);
+/// Checks that a `switch` compares an integer against distinct integer constants of its type.
+pub(crate) fn check_switch(value: &Value, cases: &[&Value]) -> Result<(), BuildError> {
+    if !matches!(value.ty(), Type::Integer(_)) {
+        return Err(BuildError::NotInteger(value.ty().clone()));
     }
-}
-impl Instruction for BranchConst {}
-
-struct Unreachable;
-impl IRComponent for Unreachable {
-    fn append_to_string(&self, string: &mut String) {
-        string.push_str("unreachable");
+    for (index, case) in cases.iter().enumerate() {
+        if !matches!(case, Value::Number(..)) {
+            return Err(BuildError::NonConstantCase((*case).clone()));
+        }
+        if case.ty() != value.ty() {
+            return Err(BuildError::TypeMismatch {
+                expected: value.ty().clone(),
+                found: case.ty().clone(),
+            });
+        }
+        // constants can be written in more than one way, such as -1 and 255 for an i8
+        let same = |other: &&Value| match (Integer::new(other), Integer::new(case)) {
+            (Some(a), Some(b)) => a.bits == b.bits,
+            _ => other == case,
+        };
+        if cases[..index].iter().any(same) {
+            return Err(BuildError::DuplicateCase((*case).clone()));
+        }
     }
+    Ok(())
 }
-impl Instruction for Unreachable {}
 
 impl SharedBasicBlock {
     /// Returns void.
     pub fn ret_void(&self) {
-        self.push_instruction(Box::new(Return { value: None }));
+        self.push_instruction(InstructionKind::Return { value: None }, None);
     }
 
     /// Returns the given value.
     pub fn ret(&self, value: Value) {
-        self.push_instruction(Box::new(Return { value: Some(value) }));
+        self.push_instruction(InstructionKind::Return { value: Some(value) }, None);
     }
 
     /// Marks the end of this block unreachable.
     pub fn unreachable(&self) {
-        self.push_instruction(Box::new(Unreachable));
+        self.push_instruction(InstructionKind::Unreachable, None);
     }
 
     /// Branches to the label unconditionally.
     pub fn br<F: FnOnce(SharedBasicBlock)>(&self, label: F) {
-        let br = Box::new(BranchConst {
-            true_label: self.create_child(label),
-        });
-        self.push_instruction(br);
+        let target = self.create_child(label);
+        self.push_instruction(InstructionKind::Branch { target }, None);
     }
 
     /// Branches to the label unconditionally.
     pub fn br_returning(&self) -> SharedBasicBlock {
-        let if_true = BasicBlock::child(self);
-        let br = Box::new(BranchConst {
-            true_label: if_true.unlock_out(|x| x.label.clone()),
-        });
-        self.push_instruction(br);
+        let if_true = self.child();
+        self.push_instruction(
+            InstructionKind::Branch {
+                target: if_true.id(),
+            },
+            None,
+        );
         if_true
     }
 
@@ -109,42 +77,176 @@ impl SharedBasicBlock {
         true_label: F1,
         false_label: F2,
     ) {
-        let br = Box::new(BranchCond {
-            cond: value,
-            true_label: self.create_child(true_label),
-            false_label: self.create_child(false_label),
-        });
-        self.push_instruction(br);
+        let if_true = self.create_child(true_label);
+        let if_false = self.create_child(false_label);
+        self.push_instruction(
+            InstructionKind::ConditionalBranch {
+                condition: value,
+                if_true,
+                if_false,
+            },
+            None,
+        );
     }
 
     /// Branches to the basic block if true, otherwise goes to the false label.
     pub fn br_if_returning(&self, value: Value) -> (SharedBasicBlock, SharedBasicBlock) {
-        let if_true = BasicBlock::child(self);
-        let if_false = BasicBlock::child(self);
-        let br = Box::new(BranchCond {
-            cond: value,
-            true_label: if_true.unlock_out(|x| x.label.clone()),
-            false_label: if_false.unlock_out(|x| x.label.clone()),
-        });
-        self.push_instruction(br);
+        let if_true = self.child();
+        let if_false = self.child();
+        self.push_instruction(
+            InstructionKind::ConditionalBranch {
+                condition: value,
+                if_true: if_true.id(),
+                if_false: if_false.id(),
+            },
+            None,
+        );
         (if_true, if_false)
     }
 
-    /// Branches to the specified basic block.
+    /// Branches to the specified basic block, which must be of the same function body.
     pub fn goto(&self, block: &SharedBasicBlock) {
-        let br = Box::new(BranchConst {
-            true_label: block.unlock_out(|x| x.label.clone()),
-        });
-        self.push_instruction(br);
+        let target = self.target(block);
+        self.push_instruction(InstructionKind::Branch { target }, None);
+    }
+
+    /// Branches to the label of the case equal to the integer, otherwise goes to the default label.
+    /// The cases must be distinct integer constants of the type of the value.
+    pub fn switch<F: FnOnce(SharedBasicBlock)>(
+        &self,
+        value: Value,
+        default_label: F,
+        cases: Vec<(Value, CaseLabel)>,
+    ) {
+        self.try_switch(value, default_label, cases)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Branches to the label of the case equal to the integer, otherwise goes to the default label,
+    /// or returns an error if the cases are not distinct integer constants of the type of the value.
+    pub fn try_switch<F: FnOnce(SharedBasicBlock)>(
+        &self,
+        value: Value,
+        default_label: F,
+        cases: Vec<(Value, CaseLabel)>,
+    ) -> Result<(), BuildError> {
+        check_switch(&value, &cases.iter().map(|(x, _)| x).collect::<Vec<_>>())?;
+        let default = self.create_child(default_label);
+        let cases = cases
+            .into_iter()
+            .map(|(case, label)| (case, self.create_child(label)))
+            .collect();
+        let kind = InstructionKind::Switch {
+            value,
+            default,
+            cases,
+        };
+        self.push_instruction(kind, None);
+        Ok(())
+    }
+
+    /// Branches to the basic block of the case equal to the integer, otherwise goes to the default.
+    /// Returns the default block, along with the block of every case in order.
+    pub fn switch_returning(
+        &self,
+        value: Value,
+        cases: Vec<Value>,
+    ) -> (SharedBasicBlock, Vec<SharedBasicBlock>) {
+        self.try_switch_returning(value, cases)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Branches to the basic block of the case equal to the integer, otherwise goes to the default,
+    /// or returns an error if the cases are not distinct integer constants of the type of the value.
+    pub fn try_switch_returning(
+        &self,
+        value: Value,
+        cases: Vec<Value>,
+    ) -> Result<(SharedBasicBlock, Vec<SharedBasicBlock>), BuildError> {
+        check_switch(&value, &cases.iter().collect::<Vec<_>>())?;
+        let default = self.child();
+        let blocks = cases.iter().map(|_| self.child()).collect::<Vec<_>>();
+        let kind = InstructionKind::Switch {
+            value,
+            default: default.id(),
+            cases: cases
+                .into_iter()
+                .zip(blocks.iter().map(|x| x.id()))
+                .collect(),
+        };
+        self.push_instruction(kind, None);
+        Ok((default, blocks))
+    }
+
+    /// Branches to the specified basic block of the case equal to the integer,
+    /// otherwise goes to the default. Cases may share a block, which must be of the same function body.
+    pub fn switch_to(
+        &self,
+        value: Value,
+        default: &SharedBasicBlock,
+        cases: Vec<(Value, &SharedBasicBlock)>,
+    ) {
+        self.try_switch_to(value, default, cases)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Branches to the specified basic block of the case equal to the integer,
+    /// otherwise goes to the default, or returns an error if the cases are not
+    /// distinct integer constants of the type of the value.
+    pub fn try_switch_to(
+        &self,
+        value: Value,
+        default: &SharedBasicBlock,
+        cases: Vec<(Value, &SharedBasicBlock)>,
+    ) -> Result<(), BuildError> {
+        check_switch(&value, &cases.iter().map(|(x, _)| x).collect::<Vec<_>>())?;
+        let kind = InstructionKind::Switch {
+            value,
+            default: self.target(default),
+            cases: cases
+                .into_iter()
+                .map(|(case, block)| (case, self.target(block)))
+                .collect(),
+        };
+        self.push_instruction(kind, None);
+        Ok(())
+    }
+
+    /// Branches to the block at the address, which is taken with `Values::block_address`.
+    /// Every block the address can point to must be listed, and be of the same function body.
+    pub fn indirectbr(&self, address: Value, destinations: Vec<&SharedBasicBlock>) {
+        self.try_indirectbr(address, destinations)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Branches to the block at the address, or returns an error if the address is not a pointer.
+    pub fn try_indirectbr(
+        &self,
+        address: Value,
+        destinations: Vec<&SharedBasicBlock>,
+    ) -> Result<(), BuildError> {
+        if !address.ty().is_pointer() {
+            return Err(BuildError::NotPointer(address.ty().clone()));
+        }
+        let kind = InstructionKind::IndirectBranch {
+            address,
+            destinations: destinations.into_iter().map(|x| self.target(x)).collect(),
+        };
+        self.push_instruction(kind, None);
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::error::BuildError;
+    use crate::instruction::SharedBasicBlock;
     use crate::module::{FunctionBody, GlobalFunction};
     use crate::types::Types;
     use crate::value::Values;
     use crate::IRComponent;
+    use std::boxed::Box;
+    use std::vec;
 
     #[test]
     fn build_returning_function() {
@@ -220,4 +322,118 @@ mod tests {
             }"
         );
     }
+
+    #[test]
+    fn build_switch_function() {
+        let body = FunctionBody::new(|block| {
+            block.switch(
+                Values::integer("7", 32),
+                |default| default.ret(Values::integer("0", 32)),
+                vec![
+                    (
+                        Values::integer("1", 32),
+                        Box::new(|one: SharedBasicBlock| one.ret(Values::integer("10", 32))),
+                    ),
+                    (
+                        Values::integer("-2", 32),
+                        Box::new(|two: SharedBasicBlock| two.ret(Values::integer("20", 32))),
+                    ),
+                ],
+            );
+        });
+        let f = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(
+            f.emit(),
+            "define i32 @main() { \
+                entry: \
+                    switch i32 7, label %bb0 [ i32 1, label %bb1 i32 -2, label %bb2 ] \
+                bb0: \
+                    ret i32 0 \
+                bb1: \
+                    ret i32 10 \
+                bb2: \
+                    ret i32 20 \
+            }"
+        );
+    }
+
+    #[test]
+    fn build_jump_table_function() {
+        let body = FunctionBody::new(|block| {
+            let (default, cases) = block.switch_returning(
+                Values::integer("2", 8),
+                vec![Values::integer("0", 8), Values::integer("1", 8)],
+            );
+            let exit = default.br_returning();
+            let address = Values::block_address("main", &cases[1]);
+            cases[0].indirectbr(address, vec![&cases[1], &exit]);
+            cases[1].switch_to(
+                Values::integer("3", 8),
+                &exit,
+                vec![
+                    (Values::integer("3", 8), &exit),
+                    (Values::integer("4", 8), &exit),
+                ],
+            );
+            exit.ret_void();
+        });
+        let f = GlobalFunction::new("main", Types::void()).body(body);
+        assert_eq!(
+            f.emit(),
+            "define void @main() { \
+                entry: \
+                    switch i8 2, label %bb0 [ i8 0, label %bb1 i8 1, label %bb2 ] \
+                bb0: \
+                    br label %bb3 \
+                bb3: \
+                    ret void \
+                bb1: \
+                    indirectbr ptr blockaddress(@main, %bb2), [label %bb2, label %bb3] \
+                bb2: \
+                    switch i8 3, label %bb3 [ i8 3, label %bb3 i8 4, label %bb3 ] \
+            }"
+        );
+    }
+
+    #[test]
+    fn reject_invalid_switches() {
+        FunctionBody::new(|block| {
+            assert_eq!(
+                block
+                    .try_switch_returning(Values::float("1", Types::fp64()), vec![])
+                    .err(),
+                Some(BuildError::NotInteger(Types::fp64()))
+            );
+            let register = block.add(Values::integer("1", 32), Values::integer("2", 32));
+            assert_eq!(
+                block
+                    .try_switch_returning(register.clone(), vec![register.clone()])
+                    .err(),
+                Some(BuildError::NonConstantCase(register.clone()))
+            );
+            assert_eq!(
+                block
+                    .try_switch_returning(register.clone(), vec![Values::integer("1", 8)])
+                    .err(),
+                Some(BuildError::TypeMismatch {
+                    expected: Types::integer(32),
+                    found: Types::integer(8),
+                })
+            );
+            assert_eq!(
+                block
+                    .try_switch_returning(
+                        Values::integer("0", 8),
+                        vec![Values::integer("-1", 8), Values::integer("255", 8)],
+                    )
+                    .err(),
+                Some(BuildError::DuplicateCase(Values::integer("255", 8)))
+            );
+            assert_eq!(
+                block.try_indirectbr(register, vec![]).err(),
+                Some(BuildError::NotPointer(Types::integer(32)))
+            );
+            block.unreachable();
+        });
+    }
 }
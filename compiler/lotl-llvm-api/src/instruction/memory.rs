@@ -1,88 +1,121 @@
-use crate::instruction::{Instruction, SharedBasicBlock};
+use crate::instruction::{InstructionKind, SharedBasicBlock, SyncScope};
 use crate::types::Type;
 use crate::value::Value;
-use crate::IRComponent;
-use std::boxed::Box;
+use std::format;
 use std::string::String;
 
-pub struct StoreValue {
-    value: Value,
-    pointer: Value,
-}
-
-impl IRComponent for StoreValue {
-    fn append_to_string(&self, string: &mut String) {
-        string.push_str("store ");
-        self.value.append_to_string(string);
-        string.push_str(", ");
-        self.pointer.append_to_string(string);
+pub(crate) fn check_pointer(instruction: &str, pointer: &Value) -> Result<(), String> {
+    if !pointer.ty().is_pointer() {
+        return Err(format!(
+            "{} requires a pointer, found {:?}",
+            instruction,
+            pointer.ty()
+        ));
     }
+    Ok(())
 }
-impl Instruction for StoreValue {}
 
-pub struct LoadValue {
-    returns_in: String,
-    ty: Type,
-    pointer: Value,
+/// Additional options for instructions accessing memory, such as `load` and `store`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MemoryOptions {
+    pub(crate) volatile: bool,
+    pub(crate) alignment: Option<u32>,
+    pub(crate) sync_scope: SyncScope,
 }
 
-impl IRComponent for LoadValue {
-    fn append_to_string(&self, string: &mut String) {
-        string.push('%');
-        string.push_str(&self.returns_in);
-        string.push_str(" = load ");
-        self.ty.append_to_string(string);
-        string.push_str(", ");
-        self.pointer.append_to_string(string);
+impl MemoryOptions {
+    /// Creates a new set of memory options, using the defaults of LLVM.
+    pub fn new() -> Self {
+        Self::default()
     }
-}
-impl Instruction for LoadValue {}
 
-pub struct Alloca {
-    returns_in: String,
-    ty: Type,
-}
+    /// Marks the access as volatile, so it is never removed, merged or reordered
+    /// with other volatile accesses.
+    pub fn volatile(mut self) -> Self {
+        self.volatile = true;
+        self
+    }
 
-impl IRComponent for Alloca {
-    fn append_to_string(&self, string: &mut String) {
-        string.push('%');
-        string.push_str(&self.returns_in);
-        string.push_str(" = alloca ");
-        self.ty.append_to_string(string);
+    /// Provides the alignment of the accessed memory in bytes, which must be a power of two.
+    /// Without one, the memory is assumed to be aligned to its type.
+    pub fn with_alignment(mut self, alignment: u32) -> Self {
+        if !alignment.is_power_of_two() {
+            panic!("alignment must be a power of two, found {}", alignment);
+        }
+        self.alignment = Some(alignment);
+        self
+    }
+
+    /// Provides the threads an atomic access synchronizes with, which defaults to the whole system.
+    /// Accesses that are not atomic ignore it.
+    pub fn with_sync_scope(mut self, sync_scope: SyncScope) -> Self {
+        self.sync_scope = sync_scope;
+        self
     }
 }
-impl Instruction for Alloca {}
 
 impl SharedBasicBlock {
     /// Stores a value into the pointer.
     pub fn store(&self, value: Value, pointer: Value) {
-        self.push_instruction(Box::new(StoreValue { value, pointer }));
+        self.store_with(value, pointer, MemoryOptions::new());
+    }
+
+    /// Stores a value into the pointer with the memory options.
+    pub fn store_with(&self, value: Value, pointer: Value, options: MemoryOptions) {
+        let kind = InstructionKind::Store {
+            value,
+            pointer,
+            ordering: None,
+            options,
+        };
+        self.push_instruction(kind, None);
     }
 
     /// Loads a value from the pointer.
     pub fn load(&self, ty: Type, pointer: Value) -> Value {
-        let (name, value) = self.create_local_register(ty.clone());
-        self.push_instruction(Box::new(LoadValue {
-            returns_in: name,
+        self.load_with(ty, pointer, MemoryOptions::new())
+    }
+
+    /// Loads a value from the pointer with the memory options.
+    pub fn load_with(&self, ty: Type, pointer: Value, options: MemoryOptions) -> Value {
+        let (_, value) = self.create_local_register(ty.clone());
+        let kind = InstructionKind::Load {
             ty,
             pointer,
-        }));
+            ordering: None,
+            options,
+        };
+        self.push_instruction(kind, Some(value.clone()));
         value
     }
 
     /// Allocates memory on the stack of the given type.
     pub fn alloca(&self, ty: Type) -> Value {
-        let (name, value) = self.create_local_register(Type::Ptr);
-        self.push_instruction(Box::new(Alloca {
-            returns_in: name,
-            ty,
-        }));
+        self.push_alloca(ty, None)
+    }
+
+    /// Allocates memory on the stack of the given type, aligned to the given number of bytes,
+    /// which must be a power of two.
+    pub fn alloca_with_alignment(&self, ty: Type, alignment: u32) -> Value {
+        if !alignment.is_power_of_two() {
+            panic!("alignment must be a power of two, found {}", alignment);
+        }
+        self.push_alloca(ty, Some(alignment))
+    }
+
+    fn push_alloca(&self, ty: Type, alignment: Option<u32>) -> Value {
+        let (_, value) = self.create_local_register(Type::Ptr);
+        self.push_instruction(
+            InstructionKind::Alloca { ty, alignment },
+            Some(value.clone()),
+        );
         value
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::instruction::MemoryOptions;
     use crate::module::{FunctionBody, GlobalFunction};
     use crate::types::Types;
     use crate::value::Values;
@@ -144,4 +177,30 @@ mod tests {
             }"
         );
     }
+
+    #[test]
+    fn build_volatile_function() {
+        let body = FunctionBody::new(|block| {
+            let stack_ptr = block.alloca_with_alignment(Types::integer(32), 16);
+            let options = MemoryOptions::new().volatile().with_alignment(4);
+            block.store_with(
+                Values::integer("10", 32),
+                stack_ptr.clone(),
+                options.clone(),
+            );
+            let loaded = block.load_with(Types::integer(32), stack_ptr, options);
+            block.ret(loaded);
+        });
+        let f = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(
+            f.emit(),
+            "define i32 @main() { \
+                entry: \
+                    %r0 = alloca i32, align 16 \
+                    store volatile i32 10, ptr %r0, align 4 \
+                    %r1 = load volatile i32, ptr %r0, align 4 \
+                    ret i32 %r1 \
+            }"
+        );
+    }
 }
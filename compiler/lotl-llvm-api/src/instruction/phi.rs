@@ -0,0 +1,227 @@
+use crate::error::BuildError;
+use crate::instruction::{InstId, InstructionKind, SharedBasicBlock};
+use crate::types::Type;
+use crate::value::Value;
+use std::vec::Vec;
+
+/// A handle to a `phi` instruction, which can be given more incoming edges after creation.
+///
+/// This is mostly useful for loops, where the value flowing in from the back-edge is only known
+/// once the body of the loop has been built.
+#[derive(Clone)]
+pub struct PhiNode {
+    value: Value,
+    /// The block the `phi` instruction is in.
+    block: SharedBasicBlock,
+    instruction: InstId,
+}
+
+impl PhiNode {
+    /// Returns the value produced by this `phi` instruction.
+    pub fn value(&self) -> Value {
+        self.value.clone()
+    }
+
+    /// Returns the `phi` instruction in the arena of the function body.
+    pub fn instruction(&self) -> InstId {
+        self.instruction
+    }
+
+    /// Adds an incoming edge, selecting `value` when control arrives from `block`.
+    pub fn add_incoming(&self, value: Value, block: &SharedBasicBlock) {
+        self.try_add_incoming(value, block)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Adds an incoming edge, or returns an error if the value is not of the type of the `phi`.
+    pub fn try_add_incoming(
+        &self,
+        value: Value,
+        block: &SharedBasicBlock,
+    ) -> Result<(), BuildError> {
+        if value.ty() != self.value.ty() {
+            return Err(BuildError::TypeMismatch {
+                expected: self.value.ty().clone(),
+                found: value.ty().clone(),
+            });
+        }
+        let from = self.block.target(block);
+        self.block.unlock(|body| {
+            body.modify_instruction(self.instruction, |kind| {
+                if let InstructionKind::Phi { incoming, .. } = kind {
+                    incoming.push((value, from));
+                }
+            })
+        });
+        Ok(())
+    }
+}
+
+impl SharedBasicBlock {
+    /// Selects a value depending on which block control arrived from.
+    ///
+    /// The `phi` instruction must be placed before any other instruction in the block.
+    pub fn phi(&self, ty: Type, incoming: Vec<(Value, &SharedBasicBlock)>) -> PhiNode {
+        self.try_phi(ty, incoming)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Selects a value depending on which block control arrived from,
+    /// or returns an error if an incoming value is not of the type.
+    pub fn try_phi(
+        &self,
+        ty: Type,
+        incoming: Vec<(Value, &SharedBasicBlock)>,
+    ) -> Result<PhiNode, BuildError> {
+        if let Some((value, _)) = incoming.iter().find(|(x, _)| *x.ty() != ty) {
+            return Err(BuildError::TypeMismatch {
+                expected: ty,
+                found: value.ty().clone(),
+            });
+        }
+        let incoming = incoming
+            .into_iter()
+            .map(|(value, block)| (value, self.target(block)))
+            .collect::<Vec<_>>();
+        let (_, value) = self.create_local_register(ty.clone());
+        let instruction =
+            self.push_instruction(InstructionKind::Phi { ty, incoming }, Some(value.clone()));
+        Ok(PhiNode {
+            value,
+            block: self.clone(),
+            instruction,
+        })
+    }
+
+    /// Chooses one of two values based on an `i1` condition, without branching.
+    pub fn select(&self, cond: Value, if_true: Value, if_false: Value) -> Value {
+        self.try_select(cond, if_true, if_false)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Chooses one of two values based on an `i1` condition, or returns an error
+    /// if the condition is not an `i1` or the values are of different types.
+    pub fn try_select(
+        &self,
+        cond: Value,
+        if_true: Value,
+        if_false: Value,
+    ) -> Result<Value, BuildError> {
+        if *cond.ty() != Type::Integer(1) {
+            return Err(BuildError::TypeMismatch {
+                expected: Type::Integer(1),
+                found: cond.ty().clone(),
+            });
+        }
+        if if_true.ty() != if_false.ty() {
+            return Err(BuildError::TypeMismatch {
+                expected: if_true.ty().clone(),
+                found: if_false.ty().clone(),
+            });
+        }
+        let (_, value) = self.create_local_register(if_true.ty().clone());
+        self.push_instruction(
+            InstructionKind::Select {
+                condition: cond,
+                if_true,
+                if_false,
+            },
+            Some(value.clone()),
+        );
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::module::{FunctionBody, GlobalFunction};
+    use crate::types::Types;
+    use crate::value::Values;
+    use crate::IRComponent;
+    use std::vec;
+
+    #[test]
+    fn build_merging_function() {
+        let body = FunctionBody::new(|block| {
+            let (if_true, if_false) = block.br_if_returning(Values::integer("1", 1));
+            let merge = if_true.br_returning();
+            if_false.goto(&merge);
+            let merged = merge.phi(
+                Types::integer(32),
+                vec![
+                    (Values::integer("10", 32), &if_true),
+                    (Values::integer("20", 32), &if_false),
+                ],
+            );
+            merge.ret(merged.value());
+        });
+        let f = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(
+            f.emit(),
+            "define i32 @main() { \
+                entry: \
+                    br i1 1, label %bb0, label %bb1 \
+                bb0: \
+                    br label %bb2 \
+                bb2: \
+                    %r0 = phi i32 [ 10, %bb0 ], [ 20, %bb1 ] \
+                    ret i32 %r0 \
+                bb1: \
+                    br label %bb2 \
+            }"
+        );
+    }
+
+    #[test]
+    fn build_looping_function() {
+        let body = FunctionBody::new(|block| {
+            let header = block.br_returning();
+            let counter = header.phi(Types::integer(32), vec![(Values::integer("0", 32), &block)]);
+            let incremented = header.add(counter.value(), Values::integer("1", 32));
+            counter.add_incoming(incremented, &header);
+            header.goto(&header);
+        });
+        let f = GlobalFunction::new("main", Types::void()).body(body);
+        assert_eq!(
+            f.emit(),
+            "define void @main() { \
+                entry: \
+                    br label %bb0 \
+                bb0: \
+                    %r0 = phi i32 [ 0, %entry ], [ %r1, %bb0 ] \
+                    %r1 = add i32 %r0, 1 \
+                    br label %bb0 \
+            }"
+        );
+    }
+
+    #[test]
+    fn build_selecting_function() {
+        let body = FunctionBody::new(|block| {
+            let selected = block.select(
+                Values::integer("0", 1),
+                Values::integer("10", 32),
+                Values::integer("20", 32),
+            );
+            block.ret(selected);
+        });
+        let f = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(
+            f.emit(),
+            "define i32 @main() { \
+                entry: \
+                    %r0 = select i1 0, i32 10, i32 20 \
+                    ret i32 %r0 \
+            }"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn reject_mismatched_phi() {
+        FunctionBody::new(|block| {
+            let next = block.br_returning();
+            next.phi(Types::integer(32), vec![(Values::integer("1", 64), &block)]);
+        });
+    }
+}
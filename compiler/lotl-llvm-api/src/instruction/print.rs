@@ -0,0 +1,451 @@
+use crate::instruction::{
+    AtomicOrdering, BlockId, CallOptions, Instruction, InstructionKind, MemoryOptions, SyncScope,
+};
+use crate::module::FunctionBody;
+use crate::types::Type;
+use crate::value::Value;
+use crate::IRComponent;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+impl FunctionBody {
+    /// Appends every block of the function, in the order they are laid out.
+    pub(crate) fn append_blocks(&self, string: &mut String) {
+        for id in self.blocks() {
+            let block = self.block(id);
+            string.push_str(block.label());
+            string.push(':');
+            string.push(' ');
+            string.push_str(
+                &block
+                    .instructions()
+                    .iter()
+                    .map(|x| {
+                        let mut instruction = String::new();
+                        self.append_instruction(self.instruction(*x), &mut instruction);
+                        instruction
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+            string.push(' ');
+        }
+    }
+
+    /// Appends the instruction, along with its attached metadata.
+    fn append_instruction(&self, instruction: &Instruction, string: &mut String) {
+        if let Some(result) = &instruction.result {
+            result.append_to_string_untyped(string);
+            string.push_str(" = ");
+        }
+        match &instruction.kind {
+            InstructionKind::Binary {
+                operator,
+                flags,
+                lhs,
+                rhs,
+            } => {
+                operator.append_to_string(string);
+                string.push(' ');
+                if *flags != Default::default() {
+                    flags.append_to_string(string);
+                    string.push(' ');
+                }
+                lhs.append_to_string(string);
+                string.push_str(", ");
+                rhs.append_to_string_untyped(string);
+            }
+            InstructionKind::FloatNegate { value } => {
+                string.push_str("fneg ");
+                value.append_to_string(string);
+            }
+            InstructionKind::Cast {
+                operator,
+                value,
+                target,
+            } => {
+                operator.append_to_string(string);
+                string.push(' ');
+                value.append_to_string(string);
+                string.push_str(" to ");
+                target.append_to_string(string);
+            }
+            InstructionKind::Alloca { ty, alignment } => {
+                string.push_str("alloca ");
+                ty.append_to_string(string);
+                append_alignment(*alignment, string);
+            }
+            InstructionKind::Load {
+                ty,
+                pointer,
+                ordering,
+                options,
+            } => {
+                string.push_str("load ");
+                append_access(ordering.is_some(), options, string);
+                ty.append_to_string(string);
+                string.push_str(", ");
+                pointer.append_to_string(string);
+                append_ordering(*ordering, options, string);
+                append_alignment(options.alignment, string);
+            }
+            InstructionKind::Store {
+                value,
+                pointer,
+                ordering,
+                options,
+            } => {
+                string.push_str("store ");
+                append_access(ordering.is_some(), options, string);
+                value.append_to_string(string);
+                string.push_str(", ");
+                pointer.append_to_string(string);
+                append_ordering(*ordering, options, string);
+                append_alignment(options.alignment, string);
+            }
+            InstructionKind::AtomicRmw {
+                operator,
+                pointer,
+                value,
+                ordering,
+                options,
+            } => {
+                string.push_str("atomicrmw ");
+                append_access(false, options, string);
+                operator.append_to_string(string);
+                string.push(' ');
+                pointer.append_to_string(string);
+                string.push_str(", ");
+                value.append_to_string(string);
+                append_ordering(Some(*ordering), options, string);
+                append_alignment(options.alignment, string);
+            }
+            InstructionKind::CompareExchange {
+                pointer,
+                expected,
+                replacement,
+                success,
+                failure,
+                weak,
+                options,
+            } => {
+                string.push_str("cmpxchg ");
+                if *weak {
+                    string.push_str("weak ");
+                }
+                append_access(false, options, string);
+                pointer.append_to_string(string);
+                string.push_str(", ");
+                expected.append_to_string(string);
+                string.push_str(", ");
+                replacement.append_to_string(string);
+                append_ordering(Some(*success), options, string);
+                string.push(' ');
+                failure.append_to_string(string);
+                append_alignment(options.alignment, string);
+            }
+            InstructionKind::Fence {
+                ordering,
+                sync_scope,
+            } => {
+                string.push_str("fence ");
+                if *sync_scope != SyncScope::System {
+                    sync_scope.append_to_string(string);
+                    string.push(' ');
+                }
+                ordering.append_to_string(string);
+            }
+            InstructionKind::GetElementPtr { ty, base, indices } => {
+                string.push_str("getelementptr ");
+                ty.append_to_string(string);
+                string.push_str(", ");
+                base.append_to_string(string);
+                for index in indices {
+                    string.push_str(", ");
+                    index.append_to_string(string);
+                }
+            }
+            InstructionKind::ExtractValue { aggregate, index } => {
+                string.push_str("extractvalue ");
+                aggregate.append_to_string(string);
+                string.push_str(", ");
+                string.push_str(&index.to_string());
+            }
+            InstructionKind::InsertValue {
+                aggregate,
+                value,
+                index,
+            } => {
+                string.push_str("insertvalue ");
+                aggregate.append_to_string(string);
+                string.push_str(", ");
+                value.append_to_string(string);
+                string.push_str(", ");
+                string.push_str(&index.to_string());
+            }
+            InstructionKind::ExtractElement { vector, index } => {
+                string.push_str("extractelement ");
+                vector.append_to_string(string);
+                string.push_str(", ");
+                index.append_to_string(string);
+            }
+            InstructionKind::InsertElement {
+                vector,
+                value,
+                index,
+            } => {
+                string.push_str("insertelement ");
+                vector.append_to_string(string);
+                string.push_str(", ");
+                value.append_to_string(string);
+                string.push_str(", ");
+                index.append_to_string(string);
+            }
+            InstructionKind::ShuffleVector { lhs, rhs, mask } => {
+                string.push_str("shufflevector ");
+                lhs.append_to_string(string);
+                string.push_str(", ");
+                rhs.append_to_string(string);
+                string.push_str(", ");
+                mask.append_to_string(string);
+            }
+            InstructionKind::Phi { ty, incoming } => {
+                string.push_str("phi ");
+                ty.append_to_string(string);
+                string.push(' ');
+                string.push_str(
+                    &incoming
+                        .iter()
+                        .map(|(value, block)| {
+                            let mut edge = String::from("[ ");
+                            value.append_to_string_untyped(&mut edge);
+                            edge.push_str(", ");
+                            self.append_label(*block, &mut edge);
+                            edge.push_str(" ]");
+                            edge
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                );
+            }
+            InstructionKind::Select {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                string.push_str("select ");
+                condition.append_to_string(string);
+                string.push_str(", ");
+                if_true.append_to_string(string);
+                string.push_str(", ");
+                if_false.append_to_string(string);
+            }
+            InstructionKind::Call {
+                options,
+                signature,
+                callee,
+                arguments,
+            } => {
+                if let Some(marker) = &options.tail {
+                    marker.append_to_string(string);
+                    string.push(' ');
+                }
+                string.push_str("call ");
+                append_call(options, signature, callee, arguments, string);
+            }
+            InstructionKind::Invoke {
+                options,
+                signature,
+                callee,
+                arguments,
+                normal,
+                unwind,
+            } => {
+                string.push_str("invoke ");
+                append_call(options, signature, callee, arguments, string);
+                string.push_str(" to label ");
+                self.append_label(*normal, string);
+                string.push_str(" unwind label ");
+                self.append_label(*unwind, string);
+            }
+            InstructionKind::LandingPad {
+                ty,
+                cleanup,
+                clauses,
+            } => {
+                string.push_str("landingpad ");
+                ty.append_to_string(string);
+                if *cleanup {
+                    string.push_str(" cleanup");
+                }
+                for clause in clauses {
+                    string.push(' ');
+                    clause.append_to_string(string);
+                }
+            }
+            InstructionKind::DeclareVariable {
+                address,
+                variable,
+                location,
+            } => {
+                string.push_str("#dbg_declare(");
+                address.append_to_string(string);
+                string.push_str(", ");
+                variable.append_to_string(string);
+                string.push_str(", !DIExpression(), ");
+                location.append_to_string(string);
+                string.push(')');
+            }
+            InstructionKind::Return { value } => {
+                string.push_str("ret ");
+                match value {
+                    Some(value) => value.append_to_string(string),
+                    None => Type::Void.append_to_string(string),
+                }
+            }
+            InstructionKind::Branch { target } => {
+                string.push_str("br label ");
+                self.append_label(*target, string);
+            }
+            InstructionKind::ConditionalBranch {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                string.push_str("br ");
+                condition.append_to_string(string);
+                string.push_str(", label ");
+                self.append_label(*if_true, string);
+                string.push_str(", label ");
+                self.append_label(*if_false, string);
+            }
+            InstructionKind::Switch {
+                value,
+                default,
+                cases,
+            } => {
+                // cases are separated by whitespace rather than commas, which LLVM writes as newlines
+                string.push_str("switch ");
+                value.append_to_string(string);
+                string.push_str(", label ");
+                self.append_label(*default, string);
+                string.push_str(" [ ");
+                for (case, block) in cases {
+                    case.append_to_string(string);
+                    string.push_str(", label ");
+                    self.append_label(*block, string);
+                    string.push(' ');
+                }
+                string.push(']');
+            }
+            InstructionKind::IndirectBranch {
+                address,
+                destinations,
+            } => {
+                string.push_str("indirectbr ");
+                address.append_to_string(string);
+                string.push_str(", [");
+                string.push_str(
+                    &destinations
+                        .iter()
+                        .map(|x| {
+                            let mut label = String::from("label ");
+                            self.append_label(*x, &mut label);
+                            label
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                );
+                string.push(']');
+            }
+            InstructionKind::Resume { value } => {
+                string.push_str("resume ");
+                value.append_to_string(string);
+            }
+            InstructionKind::Unreachable => string.push_str("unreachable"),
+        }
+        for (kind, metadata) in &instruction.metadata {
+            string.push_str(", !");
+            string.push_str(kind);
+            string.push(' ');
+            metadata.append_to_string(string);
+        }
+    }
+
+    fn append_label(&self, block: BlockId, string: &mut String) {
+        string.push('%');
+        string.push_str(self.block(block).label());
+    }
+}
+
+/// Appends the calling convention, callee and arguments shared by `call` and `invoke`.
+fn append_call(
+    options: &CallOptions,
+    signature: &Type,
+    callee: &Value,
+    arguments: &[Value],
+    string: &mut String,
+) {
+    let Type::Function(return_type, _, variadic) = signature else {
+        unreachable!("call signatures are always function types");
+    };
+    if let Some(convention) = &options.convention {
+        convention.append_to_string(string);
+        string.push(' ');
+    }
+    // variadic callees need the full function type, everything else only the return type
+    if *variadic {
+        signature.append_to_string(string);
+    } else {
+        return_type.append_to_string(string);
+    }
+    string.push(' ');
+    callee.append_to_string_untyped(string);
+    string.push('(');
+    string.push_str(
+        &arguments
+            .iter()
+            .map(Value::emit)
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+    string.push(')');
+}
+
+/// Appends the `atomic` and `volatile` markers of a memory access, which come before its operands.
+fn append_access(atomic: bool, options: &MemoryOptions, string: &mut String) {
+    if atomic {
+        string.push_str("atomic ");
+    }
+    if options.volatile {
+        string.push_str("volatile ");
+    }
+}
+
+/// Appends the sync scope and ordering of an atomic memory access, which come after its operands.
+fn append_ordering(ordering: Option<AtomicOrdering>, options: &MemoryOptions, string: &mut String) {
+    let Some(ordering) = ordering else {
+        return;
+    };
+    if options.sync_scope != SyncScope::System {
+        string.push(' ');
+        options.sync_scope.append_to_string(string);
+    }
+    string.push(' ');
+    ordering.append_to_string(string);
+}
+
+fn append_alignment(alignment: Option<u32>, string: &mut String) {
+    if let Some(alignment) = alignment {
+        string.push_str(", align ");
+        string.push_str(&alignment.to_string());
+    }
+}
+
+impl IRComponent for FunctionBody {
+    fn append_to_string(&self, string: &mut String) {
+        string.push('{');
+        self.append_blocks(string);
+        string.push('}');
+    }
+}
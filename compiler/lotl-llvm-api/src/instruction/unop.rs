@@ -1,39 +1,36 @@
-use crate::instruction::{Instruction, SharedBasicBlock};
+use crate::instruction::{InstructionKind, SharedBasicBlock};
 use crate::value::Value;
-use crate::IRComponent;
-use std::boxed::Box;
-use std::string::String;
-
-struct UnaryOp {
-    returns_in: String,
-    operator: &'static str,
-    value: Value,
-}
-
-impl IRComponent for UnaryOp {
-    fn append_to_string(&self, string: &mut String) {
-        string.push('%');
-        string.push_str(&self.returns_in);
-        string.push_str(" = ");
-        string.push_str(self.operator);
-        string.push(' ');
-        self.value.append_to_string(string);
-    }
-}
-impl Instruction for UnaryOp {}
 
 impl SharedBasicBlock {
     /// Negates the provided floating point.
-    pub fn fneg(&mut self, value: Value) -> Value {
-        let (name, out) = self.create_local_register(value.ty().clone());
-        self.push_instruction(Box::new(UnaryOp {
-            returns_in: name,
-            operator: "fneg",
-            value,
-        }));
+    pub fn fneg(&self, value: Value) -> Value {
+        let (_, out) = self.create_local_register(value.ty().clone());
+        self.push_instruction(InstructionKind::FloatNegate { value }, Some(out.clone()));
         out
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use crate::module::{FunctionBody, GlobalFunction};
+    use crate::types::Types;
+    use crate::value::Values;
+    use crate::IRComponent;
+
+    #[test]
+    fn build_negating_function() {
+        let body = FunctionBody::new(|block| {
+            let negated = block.fneg(Values::float("2.5", Types::fp32()));
+            block.ret(negated);
+        });
+        let f = GlobalFunction::new("main", Types::fp32()).body(body);
+        assert_eq!(
+            f.emit(),
+            "define float @main() { \
+                entry: \
+                    %r0 = fneg float 2.5 \
+                    ret float %r0 \
+            }"
+        );
+    }
+}
@@ -0,0 +1,438 @@
+use crate::error::BuildError;
+use crate::instruction::{InstructionKind, SharedBasicBlock};
+use crate::module::GlobalFunction;
+use crate::pass::Integer;
+use crate::types::{Type, Types};
+use crate::value::{check_vector_type, Value, Values};
+use crate::IRComponent;
+use std::format;
+use std::string::{String, ToString};
+use std::vec;
+use std::vec::Vec;
+
+/// Represents the `llvm.vector.reduce.*` intrinsics, which combine every element of a vector.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VectorReduction {
+    /// Integer addition, `add`.
+    Add,
+    /// Integer multiplication, `mul`.
+    Mul,
+    /// Bitwise and, `and`.
+    And,
+    /// Bitwise or, `or`.
+    Or,
+    /// Bitwise exclusive or, `xor`.
+    Xor,
+    /// The greatest signed integer, `smax`.
+    SignedMax,
+    /// The least signed integer, `smin`.
+    SignedMin,
+    /// The greatest unsigned integer, `umax`.
+    UnsignedMax,
+    /// The least unsigned integer, `umin`.
+    UnsignedMin,
+    /// Floating-point addition in order of the elements, `fadd`.
+    FloatAdd,
+    /// Floating-point multiplication in order of the elements, `fmul`.
+    FloatMul,
+    /// The greatest floating point, ignoring NaN, `fmax`.
+    FloatMax,
+    /// The least floating point, ignoring NaN, `fmin`.
+    FloatMin,
+}
+
+impl IRComponent for VectorReduction {
+    fn append_to_string(&self, string: &mut String) {
+        match self {
+            VectorReduction::Add => string.push_str("add"),
+            VectorReduction::Mul => string.push_str("mul"),
+            VectorReduction::And => string.push_str("and"),
+            VectorReduction::Or => string.push_str("or"),
+            VectorReduction::Xor => string.push_str("xor"),
+            VectorReduction::SignedMax => string.push_str("smax"),
+            VectorReduction::SignedMin => string.push_str("smin"),
+            VectorReduction::UnsignedMax => string.push_str("umax"),
+            VectorReduction::UnsignedMin => string.push_str("umin"),
+            VectorReduction::FloatAdd => string.push_str("fadd"),
+            VectorReduction::FloatMul => string.push_str("fmul"),
+            VectorReduction::FloatMax => string.push_str("fmax"),
+            VectorReduction::FloatMin => string.push_str("fmin"),
+        }
+    }
+}
+
+impl VectorReduction {
+    /// Returns true if this reduction combines floating points, false for integers.
+    pub fn is_floating_point(&self) -> bool {
+        matches!(
+            self,
+            VectorReduction::FloatAdd
+                | VectorReduction::FloatMul
+                | VectorReduction::FloatMax
+                | VectorReduction::FloatMin
+        )
+    }
+
+    /// Returns true if the intrinsic takes the value the reduction starts from
+    /// before the vector, which is the case for `fadd` and `fmul`.
+    pub fn has_start_value(&self) -> bool {
+        matches!(self, VectorReduction::FloatAdd | VectorReduction::FloatMul)
+    }
+
+    /// Returns the name of the intrinsic reducing vectors of the type,
+    /// such as `llvm.vector.reduce.add.v4i32`.
+    pub fn name(&self, vector: &Type) -> String {
+        format!("llvm.vector.reduce.{}.{}", self.emit(), vector.mangled())
+    }
+
+    /// Returns the declaration of the intrinsic reducing vectors of the type,
+    /// which the module calling it must contain.
+    pub fn declaration(&self, vector: &Type) -> GlobalFunction {
+        let element = vector.scalar().clone();
+        let function = GlobalFunction::new(&self.name(vector), element.clone());
+        match self.has_start_value() {
+            true => function.with_parameter(element),
+            false => function,
+        }
+        .with_parameter(vector.clone())
+    }
+}
+
+/// Checks that an element of a vector is accessed at an integer index,
+/// and returns the type of the element.
+pub(crate) fn check_element(vector: &Value, index: &Value) -> Result<Type, BuildError> {
+    if !vector.ty().is_vector() {
+        return Err(BuildError::NotVector(vector.ty().clone()));
+    }
+    if !index.ty().is_integer() {
+        return Err(BuildError::NotInteger(index.ty().clone()));
+    }
+    Ok(vector.ty().scalar().clone())
+}
+
+/// Checks that the value inserted into a vector is of its element type.
+pub(crate) fn check_insertelement(
+    vector: &Value,
+    value: &Value,
+    index: &Value,
+) -> Result<(), BuildError> {
+    let element = check_element(vector, index)?;
+    if element != *value.ty() {
+        return Err(BuildError::TypeMismatch {
+            expected: element,
+            found: value.ty().clone(),
+        });
+    }
+    Ok(())
+}
+
+/// Checks that both operands of a `shufflevector` are vectors of the same type,
+/// and that the mask picks elements out of them. Returns the type of the result,
+/// which has as many elements as the mask.
+///
+/// Scalable vectors can only be shuffled by a `zeroinitializer` mask, which splats their first element.
+pub(crate) fn check_shufflevector(
+    lhs: &Value,
+    rhs: &Value,
+    mask: &Value,
+) -> Result<Type, BuildError> {
+    if !lhs.ty().is_vector() {
+        return Err(BuildError::NotVector(lhs.ty().clone()));
+    }
+    if lhs.ty() != rhs.ty() {
+        return Err(BuildError::TypeMismatch {
+            expected: lhs.ty().clone(),
+            found: rhs.ty().clone(),
+        });
+    }
+    let invalid = || BuildError::InvalidShuffleMask(mask.clone());
+    if *mask.ty().scalar() != Types::integer(32) {
+        return Err(invalid());
+    }
+    let element = lhs.ty().scalar().clone();
+    match (lhs.ty(), mask) {
+        (Type::Vector(length, _), Value::Vector(indices, Type::Vector(picked, _))) => {
+            let valid = indices
+                .iter()
+                .all(|x| Integer::new(x).is_some_and(|x| x.bits < 2 * *length as u128));
+            match valid {
+                true => Ok(Types::vector(*picked, element)),
+                false => Err(invalid()),
+            }
+        }
+        (Type::Vector(..), Value::ZeroInitializer(Type::Vector(picked, _))) => {
+            Ok(Types::vector(*picked, element))
+        }
+        (Type::ScalableVector(..), Value::ZeroInitializer(Type::ScalableVector(picked, _))) => {
+            Ok(Types::scalable_vector(*picked, element))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+impl SharedBasicBlock {
+    /// Extracts the element of the vector at the index.
+    pub fn extractelement(&self, vector: Value, index: Value) -> Value {
+        self.try_extractelement(vector, index)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Extracts the element of the vector at the index,
+    /// or returns an error if the value is not a vector or the index is not an integer.
+    /// Indices past the end of the vector produce poison.
+    pub fn try_extractelement(&self, vector: Value, index: Value) -> Result<Value, BuildError> {
+        let element = check_element(&vector, &index)?;
+        let (_, value) = self.create_local_register(element);
+        self.push_instruction(
+            InstructionKind::ExtractElement { vector, index },
+            Some(value.clone()),
+        );
+        Ok(value)
+    }
+
+    /// Inserts a value into the vector at the index.
+    pub fn insertelement(&self, vector: Value, insertion: Value, index: Value) -> Value {
+        self.try_insertelement(vector, insertion, index)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Inserts a value into the vector at the index, or returns an error if the value
+    /// is not of the element type of the vector, or the index is not an integer.
+    pub fn try_insertelement(
+        &self,
+        vector: Value,
+        insertion: Value,
+        index: Value,
+    ) -> Result<Value, BuildError> {
+        check_insertelement(&vector, &insertion, &index)?;
+        let (_, value) = self.create_local_register(vector.ty().clone());
+        self.push_instruction(
+            InstructionKind::InsertElement {
+                vector,
+                value: insertion,
+                index,
+            },
+            Some(value.clone()),
+        );
+        Ok(value)
+    }
+
+    /// Builds a vector of the elements of the two vectors at the indices of the mask.
+    /// The elements of `lhs` are numbered first, followed by those of `rhs`.
+    pub fn shufflevector(&self, lhs: Value, rhs: Value, mask: Vec<u32>) -> Value {
+        self.try_shufflevector(lhs, rhs, mask)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Builds a vector of the elements of the two vectors at the indices of the mask,
+    /// or returns an error if the operands are not vectors of the same type,
+    /// or an index is past the end of both.
+    pub fn try_shufflevector(
+        &self,
+        lhs: Value,
+        rhs: Value,
+        mask: Vec<u32>,
+    ) -> Result<Value, BuildError> {
+        let indices = mask
+            .iter()
+            .map(|x| Values::integer(&x.to_string(), 32))
+            .collect();
+        let mask = Values::try_vector(Types::integer(32), indices)?;
+        let ty = check_shufflevector(&lhs, &rhs, &mask)?;
+        let (_, value) = self.create_local_register(ty);
+        self.push_instruction(
+            InstructionKind::ShuffleVector { lhs, rhs, mask },
+            Some(value.clone()),
+        );
+        Ok(value)
+    }
+
+    /// Builds a vector of the type with every element set to the value,
+    /// by inserting it into the first element and shuffling it into the others.
+    pub fn splat(&self, ty: Type, value: Value) -> Value {
+        self.try_splat(ty, value)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Builds a vector of the type with every element set to the value, or returns an error
+    /// if the type is not a vector, or the value is not of its element type.
+    pub fn try_splat(&self, ty: Type, value: Value) -> Result<Value, BuildError> {
+        check_vector_type(&ty)?;
+        let mask = match &ty {
+            Type::ScalableVector(length, _) => Types::scalable_vector(*length, Types::integer(32)),
+            Type::Vector(length, _) => Types::vector(*length, Types::integer(32)),
+            _ => unreachable!("the type was checked to be a vector"),
+        };
+        let inserted =
+            self.try_insertelement(Values::zeroinitializer(ty), value, Values::integer("0", 32))?;
+        let mask = Values::zeroinitializer(mask);
+        let ty = check_shufflevector(&inserted, &inserted, &mask)?;
+        let (_, result) = self.create_local_register(ty);
+        self.push_instruction(
+            InstructionKind::ShuffleVector {
+                lhs: inserted.clone(),
+                rhs: inserted,
+                mask,
+            },
+            Some(result.clone()),
+        );
+        Ok(result)
+    }
+
+    /// Combines every element of the vector into a single value,
+    /// by calling the `llvm.vector.reduce.*` intrinsic.
+    ///
+    /// The intrinsic is declared when the module is emitted or written as bitcode,
+    /// or can be declared by hand, see `VectorReduction::declaration`.
+    /// Floating-point additions and multiplications start from their identity, `-0.0` and `1.0`.
+    pub fn vector_reduce(&self, reduction: VectorReduction, vector: Value) -> Value {
+        self.try_vector_reduce(reduction, vector)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Combines every element of the vector into a single value, or returns an error
+    /// if the value is not a vector of the integers or floating points the reduction accepts.
+    pub fn try_vector_reduce(
+        &self,
+        reduction: VectorReduction,
+        vector: Value,
+    ) -> Result<Value, BuildError> {
+        check_vector_type(vector.ty())?;
+        let element = vector.ty().scalar().clone();
+        let valid = match reduction.is_floating_point() {
+            true => element.is_floating_point(),
+            false => element.is_integer(),
+        };
+        if !valid {
+            return Err(BuildError::InvalidReduction {
+                reduction,
+                ty: vector.ty().clone(),
+            });
+        }
+        let callee = reduction.declaration(vector.ty()).reference();
+        let arguments = match reduction {
+            VectorReduction::FloatAdd => vec![Values::float("-0.0", element), vector],
+            VectorReduction::FloatMul => vec![Values::float("1.0", element), vector],
+            _ => vec![vector],
+        };
+        let result = self.try_call(callee, arguments)?;
+        Ok(result.expect("reductions always return their element type"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::BuildError;
+    use crate::instruction::{BinaryOperator, VectorReduction};
+    use crate::module::{FunctionBody, GlobalFunction, Module};
+    use crate::types::Types;
+    use crate::value::Values;
+    use crate::IRComponent;
+    use std::vec;
+
+    #[test]
+    fn build_vector_function() {
+        let vector = Types::vector(4, Types::integer(32));
+        let body = FunctionBody::new(|block| {
+            let constant = Values::vector(
+                Types::integer(32),
+                vec![
+                    Values::integer("1", 32),
+                    Values::integer("2", 32),
+                    Values::integer("3", 32),
+                    Values::integer("4", 32),
+                ],
+            );
+            let doubled = block.add(constant.clone(), constant.clone());
+            let inserted =
+                block.insertelement(doubled, Values::integer("10", 32), Values::integer("0", 64));
+            let shuffled = block.shufflevector(inserted, constant, vec![3, 2, 5, 4]);
+            let first = block.extractelement(shuffled.clone(), Values::integer("0", 32));
+            let summed = block.vector_reduce(VectorReduction::Add, shuffled);
+            block.ret(block.mul(first, summed));
+        });
+        let mut module = Module::new();
+        module
+            .functions
+            .push(VectorReduction::Add.declaration(&vector));
+        module
+            .functions
+            .push(GlobalFunction::new("main", Types::integer(32)).body(body));
+        assert_eq!(
+            module.emit(),
+            "declare i32 @llvm.vector.reduce.add.v4i32(<4 x i32>)\n\n\
+            define i32 @main() { \
+                entry: \
+                    %r0 = add <4 x i32> <i32 1, i32 2, i32 3, i32 4>, <i32 1, i32 2, i32 3, i32 4> \
+                    %r1 = insertelement <4 x i32> %r0, i32 10, i64 0 \
+                    %r2 = shufflevector <4 x i32> %r1, <4 x i32> <i32 1, i32 2, i32 3, i32 4>, \
+                        <4 x i32> <i32 3, i32 2, i32 5, i32 4> \
+                    %r3 = extractelement <4 x i32> %r2, i32 0 \
+                    %r4 = call i32 @llvm.vector.reduce.add.v4i32(<4 x i32> %r2) \
+                    %r5 = mul i32 %r3, %r4 \
+                    ret i32 %r5 \
+            }\n\n"
+        );
+    }
+
+    #[test]
+    fn build_float_reductions() {
+        let vector = Types::scalable_vector(2, Types::fp64());
+        let f = GlobalFunction::new("total", Types::fp64())
+            .with_parameter(vector.clone())
+            .build_body(|block, arguments| {
+                let squared = block.fmul(arguments[0].clone(), arguments[0].clone());
+                let total = block.vector_reduce(VectorReduction::FloatAdd, squared);
+                block.ret(total);
+            });
+        assert_eq!(
+            f.emit(),
+            "define double @total(<vscale x 2 x double> %arg0) { \
+                entry: \
+                    %r0 = fmul <vscale x 2 x double> %arg0, %arg0 \
+                    %r1 = call double @llvm.vector.reduce.fadd.nxv2f64(double -0.0, \
+                        <vscale x 2 x double> %r0) \
+                    ret double %r1 \
+            }"
+        );
+        assert_eq!(
+            VectorReduction::FloatAdd.declaration(&vector).emit(),
+            "declare double @llvm.vector.reduce.fadd.nxv2f64(double, <vscale x 2 x double>)"
+        );
+    }
+
+    #[test]
+    fn reject_invalid_vector_operations() {
+        FunctionBody::new(|block| {
+            let vector = Values::zeroinitializer(Types::vector(2, Types::fp32()));
+            assert_eq!(
+                block.try_extractelement(Values::integer("1", 32), Values::integer("0", 32)),
+                Err(BuildError::NotVector(Types::integer(32)))
+            );
+            assert_eq!(
+                block.try_insertelement(
+                    vector.clone(),
+                    Values::integer("1", 32),
+                    Values::integer("0", 32)
+                ),
+                Err(BuildError::TypeMismatch {
+                    expected: Types::fp32(),
+                    found: Types::integer(32),
+                })
+            );
+            assert!(block
+                .try_shufflevector(vector.clone(), vector.clone(), vec![0, 4])
+                .is_err());
+            assert_eq!(
+                block.try_vector_reduce(VectorReduction::SignedMax, vector.clone()),
+                Err(BuildError::InvalidReduction {
+                    reduction: VectorReduction::SignedMax,
+                    ty: Types::vector(2, Types::fp32()),
+                })
+            );
+            assert!(block
+                .try_binop(BinaryOperator::IntegerAdd, vector.clone(), vector)
+                .is_err());
+        });
+    }
+}
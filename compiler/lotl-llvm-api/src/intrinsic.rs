@@ -0,0 +1,529 @@
+use crate::error::BuildError;
+use crate::instruction::SharedBasicBlock;
+use crate::module::GlobalFunction;
+use crate::types::{Type, Types};
+use crate::value::{Value, Values};
+use crate::IRComponent;
+use std::format;
+use std::string::{String, ToString};
+use std::vec;
+use std::vec::Vec;
+
+/// Represents an overload of an LLVM intrinsic function, described by the types it is used with.
+///
+/// Calls to intrinsics are built through the methods of `SharedBasicBlock`,
+/// and their declarations are added when the module is emitted or written as bitcode.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Intrinsic {
+    /// `llvm.memcpy`, which copies bytes between memory that does not overlap.
+    MemCpy {
+        /// The type of the pointer copied to.
+        destination: Type,
+        /// The type of the pointer copied from.
+        source: Type,
+        /// The integer type of the number of bytes.
+        length: Type,
+    },
+    /// `llvm.memset`, which fills memory with a byte.
+    MemSet {
+        /// The type of the pointer filled.
+        destination: Type,
+        /// The integer type of the number of bytes.
+        length: Type,
+    },
+    /// `llvm.*.with.overflow`, which computes an arithmetic operation on integers
+    /// along with whether it overflowed.
+    WithOverflow(OverflowOperator, Type),
+    /// `llvm.trap`, which stops the program abnormally.
+    Trap,
+    /// `llvm.lifetime.start`, which marks the memory of an `alloca` as starting to be used.
+    LifetimeStart(Type),
+    /// `llvm.lifetime.end`, which marks the memory of an `alloca` as no longer used.
+    LifetimeEnd(Type),
+    /// `llvm.ctpop`, which counts the bits set in an integer.
+    CountPopulation(Type),
+    /// `llvm.sqrt`, which computes the square root of a floating point.
+    Sqrt(Type),
+    /// `llvm.fma`, which multiplies two floating points and adds a third, rounding only once.
+    Fma(Type),
+}
+
+impl Intrinsic {
+    /// Returns the name of the overload, mangled after the types it is used with,
+    /// such as `llvm.memcpy.p0.p0.i64`.
+    pub fn name(&self) -> String {
+        match self {
+            Intrinsic::MemCpy {
+                destination,
+                source,
+                length,
+            } => format!(
+                "llvm.memcpy.{}.{}.{}",
+                destination.mangled(),
+                source.mangled(),
+                length.mangled()
+            ),
+            Intrinsic::MemSet {
+                destination,
+                length,
+            } => format!("llvm.memset.{}.{}", destination.mangled(), length.mangled()),
+            Intrinsic::WithOverflow(operator, ty) => {
+                format!("llvm.{}.with.overflow.{}", operator.emit(), ty.mangled())
+            }
+            Intrinsic::Trap => "llvm.trap".to_string(),
+            Intrinsic::LifetimeStart(pointer) => {
+                format!("llvm.lifetime.start.{}", pointer.mangled())
+            }
+            Intrinsic::LifetimeEnd(pointer) => format!("llvm.lifetime.end.{}", pointer.mangled()),
+            Intrinsic::CountPopulation(ty) => format!("llvm.ctpop.{}", ty.mangled()),
+            Intrinsic::Sqrt(ty) => format!("llvm.sqrt.{}", ty.mangled()),
+            Intrinsic::Fma(ty) => format!("llvm.fma.{}", ty.mangled()),
+        }
+    }
+
+    /// Returns the function type of the overload.
+    pub fn signature(&self) -> Type {
+        let (return_type, parameters) = match self {
+            Intrinsic::MemCpy {
+                destination,
+                source,
+                length,
+            } => (
+                Types::void(),
+                vec![
+                    destination.clone(),
+                    source.clone(),
+                    length.clone(),
+                    Types::integer(1),
+                ],
+            ),
+            Intrinsic::MemSet {
+                destination,
+                length,
+            } => (
+                Types::void(),
+                vec![
+                    destination.clone(),
+                    Types::integer(8),
+                    length.clone(),
+                    Types::integer(1),
+                ],
+            ),
+            Intrinsic::WithOverflow(_, ty) => {
+                // vectors report whether each of their elements overflowed
+                let overflowed = match ty {
+                    Type::Vector(length, _) => Types::vector(*length, Types::integer(1)),
+                    _ => Types::integer(1),
+                };
+                let result = Types::structure(vec![ty.clone(), overflowed]);
+                (result, vec![ty.clone(), ty.clone()])
+            }
+            Intrinsic::Trap => (Types::void(), Vec::new()),
+            Intrinsic::LifetimeStart(pointer) | Intrinsic::LifetimeEnd(pointer) => {
+                (Types::void(), vec![Types::integer(64), pointer.clone()])
+            }
+            Intrinsic::CountPopulation(ty) | Intrinsic::Sqrt(ty) => (ty.clone(), vec![ty.clone()]),
+            Intrinsic::Fma(ty) => (ty.clone(), vec![ty.clone(), ty.clone(), ty.clone()]),
+        };
+        Types::function(return_type, parameters)
+    }
+
+    /// Returns a value referring to the overload, which can be called.
+    pub fn reference(&self) -> Value {
+        Value::Function(self.name(), self.signature())
+    }
+
+    /// Returns the declaration of the overload.
+    pub fn declaration(&self) -> GlobalFunction {
+        declaration(&self.name(), &self.signature())
+    }
+
+    /// Checks that the intrinsic is overloaded for the types it is used with.
+    fn check(&self) -> Result<(), BuildError> {
+        let valid = match self {
+            Intrinsic::MemCpy {
+                destination,
+                source,
+                length,
+            } => destination.is_pointer() && source.is_pointer() && length.is_integer(),
+            Intrinsic::MemSet {
+                destination,
+                length,
+            } => destination.is_pointer() && length.is_integer(),
+            Intrinsic::WithOverflow(_, ty) | Intrinsic::CountPopulation(ty) => {
+                ty.scalar().is_integer()
+            }
+            Intrinsic::Trap => true,
+            Intrinsic::LifetimeStart(pointer) | Intrinsic::LifetimeEnd(pointer) => {
+                pointer.is_pointer()
+            }
+            Intrinsic::Sqrt(ty) | Intrinsic::Fma(ty) => ty.scalar().is_floating_point(),
+        };
+        match valid {
+            true => Ok(()),
+            false => Err(BuildError::InvalidIntrinsic(self.clone())),
+        }
+    }
+}
+
+/// Returns the declaration of a function of the name and function type.
+pub(crate) fn declaration(name: &str, signature: &Type) -> GlobalFunction {
+    let Type::Function(return_type, parameters, variadic) = signature else {
+        unreachable!("call signatures are always function types");
+    };
+    let function = parameters.iter().fold(
+        GlobalFunction::new(name, (**return_type).clone()),
+        |f, x| f.with_parameter((**x).clone()),
+    );
+    match variadic {
+        true => function.variadic(),
+        false => function,
+    }
+}
+
+/// Represents the arithmetic operation of an `llvm.*.with.overflow` intrinsic.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverflowOperator {
+    /// Signed addition, `sadd`.
+    SignedAdd,
+    /// Unsigned addition, `uadd`.
+    UnsignedAdd,
+    /// Signed subtraction, `ssub`.
+    SignedSub,
+    /// Unsigned subtraction, `usub`.
+    UnsignedSub,
+    /// Signed multiplication, `smul`.
+    SignedMul,
+    /// Unsigned multiplication, `umul`.
+    UnsignedMul,
+}
+
+impl IRComponent for OverflowOperator {
+    fn append_to_string(&self, string: &mut String) {
+        match self {
+            OverflowOperator::SignedAdd => string.push_str("sadd"),
+            OverflowOperator::UnsignedAdd => string.push_str("uadd"),
+            OverflowOperator::SignedSub => string.push_str("ssub"),
+            OverflowOperator::UnsignedSub => string.push_str("usub"),
+            OverflowOperator::SignedMul => string.push_str("smul"),
+            OverflowOperator::UnsignedMul => string.push_str("umul"),
+        }
+    }
+}
+
+impl SharedBasicBlock {
+    /// Calls the intrinsic with the provided arguments.
+    /// Returns the result of the call, or nothing if the intrinsic returns void.
+    pub fn call_intrinsic(&self, intrinsic: Intrinsic, arguments: Vec<Value>) -> Option<Value> {
+        self.try_call_intrinsic(intrinsic, arguments)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Calls the intrinsic with the provided arguments, or returns an error if the intrinsic
+    /// is not overloaded for its types, or the arguments do not match its parameters.
+    pub fn try_call_intrinsic(
+        &self,
+        intrinsic: Intrinsic,
+        arguments: Vec<Value>,
+    ) -> Result<Option<Value>, BuildError> {
+        intrinsic.check()?;
+        self.try_call(intrinsic.reference(), arguments)
+    }
+
+    /// Copies the number of bytes from the source to the destination, which must not overlap.
+    pub fn memcpy(&self, destination: Value, source: Value, length: Value, volatile: bool) {
+        self.try_memcpy(destination, source, length, volatile)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Copies the number of bytes from the source to the destination, or returns an error
+    /// if either is not a pointer, or the length is not an integer.
+    pub fn try_memcpy(
+        &self,
+        destination: Value,
+        source: Value,
+        length: Value,
+        volatile: bool,
+    ) -> Result<(), BuildError> {
+        let intrinsic = Intrinsic::MemCpy {
+            destination: destination.ty().clone(),
+            source: source.ty().clone(),
+            length: length.ty().clone(),
+        };
+        let volatile = Values::integer(&(volatile as u8).to_string(), 1);
+        self.try_call_intrinsic(intrinsic, vec![destination, source, length, volatile])?;
+        Ok(())
+    }
+
+    /// Sets the number of bytes at the destination to the `i8` value.
+    pub fn memset(&self, destination: Value, value: Value, length: Value, volatile: bool) {
+        self.try_memset(destination, value, length, volatile)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Sets the number of bytes at the destination to the `i8` value, or returns an error
+    /// if the destination is not a pointer, the value not an `i8` or the length not an integer.
+    pub fn try_memset(
+        &self,
+        destination: Value,
+        value: Value,
+        length: Value,
+        volatile: bool,
+    ) -> Result<(), BuildError> {
+        let intrinsic = Intrinsic::MemSet {
+            destination: destination.ty().clone(),
+            length: length.ty().clone(),
+        };
+        let volatile = Values::integer(&(volatile as u8).to_string(), 1);
+        self.try_call_intrinsic(intrinsic, vec![destination, value, length, volatile])?;
+        Ok(())
+    }
+
+    /// Computes the arithmetic operation on the integers, or vectors of integers.
+    /// Returns a structure of the result, which wraps around on overflow,
+    /// and an `i1` telling whether the operation overflowed.
+    pub fn with_overflow(&self, operator: OverflowOperator, lhs: Value, rhs: Value) -> Value {
+        self.try_with_overflow(operator, lhs, rhs)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Computes the arithmetic operation on the integers along with whether it overflowed,
+    /// or returns an error if the operands are not integers of the same type.
+    pub fn try_with_overflow(
+        &self,
+        operator: OverflowOperator,
+        lhs: Value,
+        rhs: Value,
+    ) -> Result<Value, BuildError> {
+        let intrinsic = Intrinsic::WithOverflow(operator, lhs.ty().clone());
+        let result = self.try_call_intrinsic(intrinsic, vec![lhs, rhs])?;
+        Ok(result.expect("arithmetic with overflow always returns a structure"))
+    }
+
+    /// Stops the program abnormally. The block should still end with `unreachable`.
+    pub fn trap(&self) {
+        self.call_intrinsic(Intrinsic::Trap, Vec::new());
+    }
+
+    /// Marks the number of bytes of memory allocated by an `alloca` as starting to be used.
+    /// Before this, and after `lifetime_end`, the memory holds no value.
+    pub fn lifetime_start(&self, size: u64, pointer: Value) {
+        self.try_lifetime_start(size, pointer)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Marks the memory as starting to be used, or returns an error if the value is not a pointer.
+    pub fn try_lifetime_start(&self, size: u64, pointer: Value) -> Result<(), BuildError> {
+        let intrinsic = Intrinsic::LifetimeStart(pointer.ty().clone());
+        let size = Values::integer(&size.to_string(), 64);
+        self.try_call_intrinsic(intrinsic, vec![size, pointer])?;
+        Ok(())
+    }
+
+    /// Marks the number of bytes of memory allocated by an `alloca` as no longer used.
+    pub fn lifetime_end(&self, size: u64, pointer: Value) {
+        self.try_lifetime_end(size, pointer)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Marks the memory as no longer used, or returns an error if the value is not a pointer.
+    pub fn try_lifetime_end(&self, size: u64, pointer: Value) -> Result<(), BuildError> {
+        let intrinsic = Intrinsic::LifetimeEnd(pointer.ty().clone());
+        let size = Values::integer(&size.to_string(), 64);
+        self.try_call_intrinsic(intrinsic, vec![size, pointer])?;
+        Ok(())
+    }
+
+    /// Counts the bits set in the integer, or in each integer of a vector.
+    pub fn ctpop(&self, value: Value) -> Value {
+        self.try_ctpop(value).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Counts the bits set in the integer, or returns an error if the value is not an integer.
+    pub fn try_ctpop(&self, value: Value) -> Result<Value, BuildError> {
+        let intrinsic = Intrinsic::CountPopulation(value.ty().clone());
+        let result = self.try_call_intrinsic(intrinsic, vec![value])?;
+        Ok(result.expect("ctpop always returns its operand type"))
+    }
+
+    /// Computes the square root of the floating point, or of each floating point of a vector.
+    pub fn sqrt(&self, value: Value) -> Value {
+        self.try_sqrt(value).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Computes the square root of the floating point,
+    /// or returns an error if the value is not a floating point.
+    pub fn try_sqrt(&self, value: Value) -> Result<Value, BuildError> {
+        let intrinsic = Intrinsic::Sqrt(value.ty().clone());
+        let result = self.try_call_intrinsic(intrinsic, vec![value])?;
+        Ok(result.expect("sqrt always returns its operand type"))
+    }
+
+    /// Computes `lhs * rhs + addend` on floating points, or vectors of floating points,
+    /// rounding only once.
+    pub fn fma(&self, lhs: Value, rhs: Value, addend: Value) -> Value {
+        self.try_fma(lhs, rhs, addend)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Computes `lhs * rhs + addend` rounding only once, or returns an error
+    /// if the operands are not floating points of the same type.
+    pub fn try_fma(&self, lhs: Value, rhs: Value, addend: Value) -> Result<Value, BuildError> {
+        let intrinsic = Intrinsic::Fma(lhs.ty().clone());
+        let result = self.try_call_intrinsic(intrinsic, vec![lhs, rhs, addend])?;
+        Ok(result.expect("fma always returns its operand type"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::BuildError;
+    use crate::intrinsic::{Intrinsic, OverflowOperator};
+    use crate::module::{GlobalFunction, Module};
+    use crate::types::Types;
+    use crate::value::Values;
+    use crate::IRComponent;
+
+    #[test]
+    fn build_intrinsic_calls() {
+        let mut module = Module::new();
+        let copy = GlobalFunction::new("copy", Types::void())
+            .with_parameter(Types::pointer(0))
+            .with_parameter(Types::pointer(0))
+            .build_body(|block, arguments| {
+                let buffer = block.alloca(Types::array(16, Types::integer(8)));
+                block.lifetime_start(16, buffer.clone());
+                let length = Values::integer("16", 64);
+                block.memcpy(buffer.clone(), arguments[1].clone(), length.clone(), false);
+                block.memset(
+                    arguments[1].clone(),
+                    Values::integer("0", 8),
+                    length.clone(),
+                    true,
+                );
+                block.memcpy(arguments[0].clone(), buffer.clone(), length, false);
+                block.lifetime_end(16, buffer);
+                block.ret_void();
+            });
+        let math = GlobalFunction::new("math", Types::fp64())
+            .with_parameter(Types::integer(32))
+            .with_parameter(Types::fp64())
+            .build_body(|block, arguments| {
+                let sum = block.with_overflow(
+                    OverflowOperator::SignedAdd,
+                    arguments[0].clone(),
+                    Values::integer("1", 32),
+                );
+                let (overflowed, fine) = block.br_if_returning(block.extractvalue(sum.clone(), 1));
+                overflowed.trap();
+                overflowed.unreachable();
+                let bits = fine.ctpop(fine.extractvalue(sum, 0));
+                let float = fine.sitofp(bits, Types::fp64());
+                let root = fine.sqrt(arguments[1].clone());
+                fine.ret(fine.fma(float, root.clone(), root));
+            });
+        module.add_function(copy);
+        module.add_function(math);
+        assert!(module.verify().is_ok());
+        assert_eq!(
+            module.emit(),
+            "define void @copy(ptr %arg0, ptr %arg1) { \
+                entry: \
+                    %r0 = alloca [ 16 x i8 ] \
+                    call void @llvm.lifetime.start.p0(i64 16, ptr %r0) \
+                    call void @llvm.memcpy.p0.p0.i64(ptr %r0, ptr %arg1, i64 16, i1 0) \
+                    call void @llvm.memset.p0.i64(ptr %arg1, i8 0, i64 16, i1 1) \
+                    call void @llvm.memcpy.p0.p0.i64(ptr %arg0, ptr %r0, i64 16, i1 0) \
+                    call void @llvm.lifetime.end.p0(i64 16, ptr %r0) \
+                    ret void \
+            }\n\n\
+            declare void @llvm.lifetime.start.p0(i64, ptr)\n\n\
+            declare void @llvm.memcpy.p0.p0.i64(ptr, ptr, i64, i1)\n\n\
+            declare void @llvm.memset.p0.i64(ptr, i8, i64, i1)\n\n\
+            declare void @llvm.lifetime.end.p0(i64, ptr)\n\n\
+            define double @math(i32 %arg0, double %arg1) { \
+                entry: \
+                    %r0 = call {i32, i1} @llvm.sadd.with.overflow.i32(i32 %arg0, i32 1) \
+                    %r1 = extractvalue {i32, i1} %r0, 1 \
+                    br i1 %r1, label %bb0, label %bb1 \
+                bb0: \
+                    call void @llvm.trap() \
+                    unreachable \
+                bb1: \
+                    %r2 = extractvalue {i32, i1} %r0, 0 \
+                    %r3 = call i32 @llvm.ctpop.i32(i32 %r2) \
+                    %r4 = sitofp i32 %r3 to double \
+                    %r5 = call double @llvm.sqrt.f64(double %arg1) \
+                    %r6 = call double @llvm.fma.f64(double %r4, double %r5, double %r5) \
+                    ret double %r6 \
+            }\n\n\
+            declare {i32, i1} @llvm.sadd.with.overflow.i32(i32, i32)\n\n\
+            declare void @llvm.trap()\n\n\
+            declare i32 @llvm.ctpop.i32(i32)\n\n\
+            declare double @llvm.sqrt.f64(double)\n\n\
+            declare double @llvm.fma.f64(double, double, double)\n\n"
+        );
+    }
+
+    #[test]
+    fn declare_intrinsics_of_pushed_functions() {
+        let mut module = Module::new();
+        let count = GlobalFunction::new("count", Types::integer(32))
+            .with_parameter(Types::integer(32))
+            .build_body(|block, arguments| {
+                block.ret(block.ctpop(arguments[0].clone()));
+            });
+        module.functions.push(count);
+        assert!(module.verify().is_ok());
+        assert_eq!(
+            module.emit(),
+            "define i32 @count(i32 %arg0) { \
+                entry: \
+                    %r0 = call i32 @llvm.ctpop.i32(i32 %arg0) \
+                    ret i32 %r0 \
+            }\n\n\
+            declare i32 @llvm.ctpop.i32(i32)\n\n"
+        );
+        assert_eq!(module.functions.len(), 1);
+    }
+
+    #[test]
+    fn name_vector_overloads() {
+        let vector = Types::vector(4, Types::integer(32));
+        let overflow = Intrinsic::WithOverflow(OverflowOperator::UnsignedMul, vector.clone());
+        assert_eq!(
+            overflow.declaration().emit(),
+            "declare {<4 x i32>, <4 x i1>} @llvm.umul.with.overflow.v4i32(<4 x i32>, <4 x i32>)"
+        );
+        let sqrt = Intrinsic::Sqrt(Types::vector(2, Types::fp32()));
+        assert_eq!(sqrt.name(), "llvm.sqrt.v2f32");
+    }
+
+    #[test]
+    fn reject_invalid_intrinsic_calls() {
+        GlobalFunction::new("main", Types::void()).build_body(|block, _| {
+            let integer = Values::integer("4", 32);
+            assert_eq!(
+                block.try_sqrt(integer.clone()),
+                Err(BuildError::InvalidIntrinsic(Intrinsic::Sqrt(
+                    Types::integer(32)
+                )))
+            );
+            assert_eq!(
+                block.try_lifetime_start(4, integer.clone()),
+                Err(BuildError::InvalidIntrinsic(Intrinsic::LifetimeStart(
+                    Types::integer(32)
+                )))
+            );
+            let pointer = block.alloca(Types::integer(32));
+            assert_eq!(
+                block.try_memset(pointer, integer, Values::integer("4", 64), false),
+                Err(BuildError::TypeMismatch {
+                    expected: Types::integer(8),
+                    found: Types::integer(32),
+                })
+            );
+            block.ret_void();
+        });
+    }
+}
@@ -9,14 +9,35 @@
 #![deny(missing_docs)]
 #![allow(dead_code)]
 
+/// The module for control flow, dominator and loop analyses of function bodies.
+pub mod analysis;
+/// The module for writing LLVM bitcode
+mod bitcode;
+/// The module for DWARF debug information.
+pub mod debug;
+/// The module for errors reported by the builders and the parser.
+pub mod error;
+/// The module for modules shared by the bitcode and text round trip tests
+#[cfg(test)]
+mod fixtures;
 /// The module holding LLVM instructions.
 pub mod instruction;
+/// The module for LLVM intrinsic functions
+pub mod intrinsic;
+/// The module for LLVM IR metadata
+pub mod metadata;
 /// The module for LLVM IR modules
 pub mod module;
+/// The module for parsing LLVM IR text
+mod parse;
+/// The module for optimization passes over function bodies
+pub mod pass;
 /// The module for LLVM IR types
 pub mod types;
 /// The module for LLVM IR values
 pub mod value;
+/// The module for verifying LLVM IR modules
+pub mod verify;
 
 use std::string::String;
 
@@ -0,0 +1,271 @@
+use crate::value::{append_escaped, Value};
+use crate::IRComponent;
+use std::string::{String, ToString};
+use std::sync::{Arc, Mutex};
+use std::vec::Vec;
+
+/// Represents a piece of metadata, which can be used as an operand of metadata nodes,
+/// or attached to instructions and globals.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Metadata {
+    /// Represents a metadata string, such as `!"foo"`.
+    #[non_exhaustive]
+    String(String),
+    /// Represents a constant value used as metadata, such as `i32 7`.
+    #[non_exhaustive]
+    Value(Value),
+    /// Represents a reference to a numbered metadata node in the module, such as `!0`.
+    #[non_exhaustive]
+    Reference(usize),
+    /// Represents a metadata tuple written inline, such as `!{!0, !"foo"}`.
+    #[non_exhaustive]
+    Tuple(Vec<Metadata>),
+    /// Represents a specialized metadata node written inline, such as `!DILocation(line: 1, scope: !0)`.
+    #[non_exhaustive]
+    Specialized(String, Vec<(String, Metadata)>),
+    /// Represents an untyped literal used as a field of specialized nodes, such as `42` or `DW_LANG_C`.
+    #[non_exhaustive]
+    Literal(String),
+    /// Represents an empty operand in a metadata node.
+    Null,
+}
+
+impl Metadata {
+    /// Creates a new metadata string.
+    pub fn string(contents: &str) -> Metadata {
+        Metadata::String(contents.to_string())
+    }
+
+    /// Creates a new untyped literal, used as a field of specialized nodes.
+    pub fn literal<T: ToString>(value: T) -> Metadata {
+        Metadata::Literal(value.to_string())
+    }
+}
+
+impl IRComponent for Metadata {
+    fn append_to_string(&self, string: &mut String) {
+        match self {
+            Metadata::String(contents) => {
+                string.push_str("!\"");
+                append_escaped(string, contents.as_bytes());
+                string.push('"');
+            }
+            Metadata::Value(value) => value.append_to_string(string),
+            Metadata::Reference(index) => {
+                string.push('!');
+                string.push_str(&index.to_string());
+            }
+            Metadata::Tuple(elements) => {
+                string.push_str("!{");
+                string.push_str(
+                    &elements
+                        .iter()
+                        .map(Metadata::emit)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                );
+                string.push('}');
+            }
+            Metadata::Specialized(name, fields) => {
+                string.push('!');
+                string.push_str(name);
+                string.push('(');
+                string.push_str(
+                    &fields
+                        .iter()
+                        .map(|(field, value)| {
+                            let mut out = field.clone();
+                            out.push_str(": ");
+                            // strings in specialized nodes are written without the leading `!`
+                            match value {
+                                Metadata::String(contents) => {
+                                    out.push('"');
+                                    append_escaped(&mut out, contents.as_bytes());
+                                    out.push('"');
+                                }
+                                value => value.append_to_string(&mut out),
+                            }
+                            out
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                );
+                string.push(')');
+            }
+            Metadata::Literal(value) => string.push_str(value),
+            Metadata::Null => string.push_str("null"),
+        }
+    }
+}
+
+/// Represents a numbered metadata node of a module, such as `!0 = !{i32 7, !"foo"}`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetadataNode {
+    distinct: bool,
+    body: Metadata,
+}
+
+impl MetadataNode {
+    /// Creates a new metadata node with the given elements.
+    pub fn new(elements: Vec<Metadata>) -> Self {
+        MetadataNode {
+            distinct: false,
+            body: Metadata::Tuple(elements),
+        }
+    }
+
+    /// Creates a new specialized metadata node, such as `!DIFile(filename: "main.lotl")`.
+    pub fn specialized(name: &str, fields: Vec<(&str, Metadata)>) -> Self {
+        MetadataNode {
+            distinct: false,
+            body: Metadata::Specialized(
+                name.to_string(),
+                fields
+                    .into_iter()
+                    .map(|(field, value)| (field.to_string(), value))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Marks the node as distinct, so it is never merged with nodes of the same contents.
+    pub fn distinct(mut self) -> Self {
+        self.distinct = true;
+        self
+    }
+}
+
+impl IRComponent for MetadataNode {
+    fn append_to_string(&self, string: &mut String) {
+        if self.distinct {
+            string.push_str("distinct ");
+        }
+        self.body.append_to_string(string);
+    }
+}
+
+/// The numbered metadata nodes of a module.
+///
+/// The table can be shared, so metadata can be added while building function bodies.
+#[derive(Clone, Debug, Default)]
+pub struct MetadataTable {
+    nodes: Arc<Mutex<Vec<MetadataNode>>>,
+}
+
+impl MetadataTable {
+    /// Creates a new empty metadata table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node to the table, and returns a reference to it.
+    pub fn add(&self, node: MetadataNode) -> Metadata {
+        let mut nodes = self.nodes.lock().unwrap();
+        nodes.push(node);
+        Metadata::Reference(nodes.len() - 1)
+    }
+
+    /// Returns the node a reference points to.
+    pub fn get(&self, reference: &Metadata) -> Option<MetadataNode> {
+        let Metadata::Reference(index) = reference else {
+            return None;
+        };
+        self.nodes.lock().unwrap().get(*index).cloned()
+    }
+
+    /// Returns the number of nodes in the table.
+    pub fn len(&self) -> usize {
+        self.nodes.lock().unwrap().len()
+    }
+
+    /// Returns true if the table has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl PartialEq for MetadataTable {
+    fn eq(&self, other: &Self) -> bool {
+        // tables are compared by their nodes, and a shared table is always equal to itself
+        Arc::ptr_eq(&self.nodes, &other.nodes)
+            || *self.nodes.lock().unwrap() == *other.nodes.lock().unwrap()
+    }
+}
+
+impl IRComponent for MetadataTable {
+    fn append_to_string(&self, string: &mut String) {
+        for (index, node) in self.nodes.lock().unwrap().iter().enumerate() {
+            string.push('!');
+            string.push_str(&index.to_string());
+            string.push_str(" = ");
+            node.append_to_string(string);
+            string.push('\n');
+        }
+    }
+}
+
+/// Represents how module flags of the same key are merged when linking modules together.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ModuleFlagBehavior {
+    /// Emits an error if the values disagree.
+    Error = 1,
+    /// Emits a warning if the values disagree, and uses the first value.
+    Warning = 2,
+    /// Requires another flag to have a specified value.
+    Require = 3,
+    /// Uses the specified value, overriding other values.
+    Override = 4,
+    /// Appends the values, which must be metadata nodes.
+    Append = 5,
+    /// Appends the values, dropping duplicates.
+    AppendUnique = 6,
+    /// Takes the largest of the values.
+    Max = 7,
+    /// Takes the smallest of the values.
+    Min = 8,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metadata::{Metadata, MetadataNode, MetadataTable};
+    use crate::value::Values;
+    use crate::IRComponent;
+    use std::vec;
+
+    #[test]
+    fn generate_metadata() {
+        let value = Metadata::Tuple(vec![
+            Metadata::Value(Values::integer("7", 32)),
+            Metadata::string("a \"quoted\" string"),
+            Metadata::Null,
+        ]);
+        assert_eq!(value.emit(), "!{i32 7, !\"a \\22quoted\\22 string\", null}");
+    }
+
+    #[test]
+    fn generate_metadata_table() {
+        let table = MetadataTable::new();
+        let first = table.add(MetadataNode::new(vec![Metadata::string("first")]));
+        let second = table.add(MetadataNode::new(vec![first.clone()]).distinct());
+        assert_eq!(first.emit(), "!0");
+        assert_eq!(second.emit(), "!1");
+        assert_eq!(table.emit(), "!0 = !{!\"first\"}\n!1 = distinct !{!0}\n");
+    }
+
+    #[test]
+    fn generate_specialized_metadata() {
+        let node = MetadataNode::specialized(
+            "DIBasicType",
+            vec![
+                ("name", Metadata::string("i32")),
+                ("size", Metadata::literal(32)),
+                ("encoding", Metadata::literal("DW_ATE_signed")),
+            ],
+        );
+        assert_eq!(
+            node.emit(),
+            "!DIBasicType(name: \"i32\", size: 32, encoding: DW_ATE_signed)"
+        );
+    }
+}
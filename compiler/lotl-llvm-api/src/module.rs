@@ -1,21 +1,46 @@
+use crate::instruction::InstructionKind;
+use crate::intrinsic::declaration;
+use crate::metadata::{Metadata, MetadataNode, MetadataTable, ModuleFlagBehavior};
+use crate::types::{NamedType, Type};
+use crate::value::{append_escaped, Value, Values};
+use crate::verify::{VerifierError, VerifierErrorKind};
 use crate::IRComponent;
-use std::string::String;
+use std::format;
+use std::string::{String, ToString};
+use std::vec::Vec;
 
 mod function;
 mod globals;
+mod target;
 
 pub use function::*;
 pub use globals::*;
+pub use target::*;
 
 /// Represents an LLVM module.
 ///
 /// LLVM programs are composed of Modules, each of which is a translation unit of the input programs.
 /// Each module consists of functions, global variables, and symbol table entries.
+#[derive(Clone, Debug)]
 pub struct Module {
+    /// The name of the source file the module was compiled from
+    pub source_filename: Option<String>,
+    /// The target triple the module is compiled for, such as `x86_64-unknown-linux-gnu`
+    pub target_triple: Option<String>,
+    /// The data layout of the target, describing how data is laid out in memory
+    pub data_layout: Option<String>,
+    /// The lines of module-level inline assembly, which are emitted as `module asm`
+    pub module_asm: Vec<String>,
+    /// The list of named types defined in the module
+    pub types: Vec<NamedType>,
     /// The list of global variables in the module
     pub vars: Vec<GlobalVariable>,
     /// The list of global functions in the module
     pub functions: Vec<GlobalFunction>,
+    /// The list of named metadata in the module, such as `!llvm.module.flags`
+    pub named_metadata: Vec<(String, Vec<Metadata>)>,
+    /// The numbered metadata nodes of the module
+    pub metadata: MetadataTable,
 }
 
 impl Default for Module {
@@ -24,31 +49,302 @@ impl Default for Module {
     }
 }
 
+impl PartialEq for Module {
+    fn eq(&self, other: &Self) -> bool {
+        // named types are equal by name alone, so their bodies are compared here
+        let types = |module: &Module| {
+            module
+                .types
+                .iter()
+                .map(|x| (x.name().to_string(), x.body()))
+                .collect::<Vec<_>>()
+        };
+        self.source_filename == other.source_filename
+            && self.target_triple == other.target_triple
+            && self.data_layout == other.data_layout
+            && self.module_asm == other.module_asm
+            && types(self) == types(other)
+            && self.vars == other.vars
+            && self.functions == other.functions
+            && self.named_metadata == other.named_metadata
+            && self.metadata == other.metadata
+    }
+}
+
 impl Module {
     /// Creates a new empty module
     pub fn new() -> Self {
         Module {
+            source_filename: None,
+            target_triple: None,
+            data_layout: None,
+            module_asm: Vec::new(),
+            types: Vec::new(),
             vars: Vec::new(),
             functions: Vec::new(),
+            named_metadata: Vec::new(),
+            metadata: MetadataTable::new(),
+        }
+    }
+
+    /// Provides the name of the source file the module was compiled from.
+    pub fn with_source_filename(mut self, name: &str) -> Self {
+        self.source_filename = Some(name.to_string());
+        self
+    }
+
+    /// Provides the target triple of the module.
+    /// If no data layout has been provided yet and the target is known, its data layout is used.
+    pub fn with_target_triple(mut self, triple: &str) -> Self {
+        self.target_triple = Some(triple.to_string());
+        if self.data_layout.is_none() {
+            self.data_layout = data_layout_for(triple).map(|x| x.to_string());
+        }
+        self
+    }
+
+    /// Provides the data layout of the module.
+    pub fn with_data_layout(mut self, layout: &str) -> Self {
+        self.data_layout = Some(layout.to_string());
+        self
+    }
+
+    /// Appends the assembly to the module-level inline assembly, line by line.
+    /// The assembly is placed as is into the object file, outside of any function.
+    pub fn append_module_asm(&mut self, asm: &str) {
+        self.module_asm.extend(asm.lines().map(|x| x.to_string()));
+    }
+
+    /// Adds a numbered metadata node to the module, and returns a reference to it.
+    pub fn add_metadata(&mut self, node: MetadataNode) -> Metadata {
+        self.metadata.add(node)
+    }
+
+    /// Adds an operand to the named metadata of the module, creating it if needed.
+    pub fn add_named_metadata(&mut self, name: &str, operand: Metadata) {
+        match self.named_metadata.iter_mut().find(|(x, _)| x == name) {
+            Some((_, operands)) => operands.push(operand),
+            None => self.named_metadata.push((name.to_string(), vec![operand])),
+        }
+    }
+
+    /// Adds a module flag, such as `!{i32 7, !"PIC Level", i32 2}`, to `!llvm.module.flags`.
+    pub fn add_flag(&mut self, behavior: ModuleFlagBehavior, key: &str, value: Metadata) {
+        let node = self.metadata.add(MetadataNode::new(vec![
+            Metadata::Value(Values::integer(&(behavior as u32).to_string(), 32)),
+            Metadata::string(key),
+            value,
+        ]));
+        self.add_named_metadata("llvm.module.flags", node);
+    }
+
+    /// Defines a named type with the given body in the module, and returns a reference to it.
+    pub fn define_type(&mut self, name: &str, body: Type) -> Type {
+        let named = NamedType::new(name, Some(body));
+        self.types.push(named.clone());
+        Type::Named(named)
+    }
+
+    /// Declares an opaque named type in the module, and returns a reference to it.
+    /// The body of the type can be provided later through `NamedType::set_body`.
+    pub fn declare_type(&mut self, name: &str) -> Type {
+        let named = NamedType::new(name, None);
+        self.types.push(named.clone());
+        Type::Named(named)
+    }
+
+    /// Adds a private constant holding the null-terminated string to the module,
+    /// and returns a pointer to it.
+    pub fn c_string(&mut self, contents: &str) -> Value {
+        let mut name = String::from(".str");
+        let mut count = 0;
+        while self.vars.iter().any(|x| x.name() == name) {
+            count += 1;
+            name = format!(".str.{count}");
+        }
+        let value = Values::c_string(contents);
+        let var = GlobalVariable::new(&name, value.ty().clone())
+            .with_linkage(LinkageType::Private)
+            .with_unnamed_addr(UnnamedAddr::Global)
+            .constant()
+            .with_value(value)
+            .with_alignment(1);
+        let reference = var.reference();
+        self.vars.push(var);
+        reference
+    }
+
+    /// Adds the function to the module, along with the declaration of every intrinsic
+    /// it calls that the module does not declare yet.
+    ///
+    /// Functions pushed to `functions` directly have their intrinsics declared
+    /// when the module is emitted or written as bitcode instead.
+    pub fn add_function(&mut self, function: GlobalFunction) {
+        self.functions.push(function);
+        let declarations = self.missing_intrinsics();
+        self.functions.extend(declarations);
+    }
+
+    /// Returns the declarations of the intrinsics called in the module
+    /// that the module does not declare.
+    pub(crate) fn missing_intrinsics(&self) -> Vec<GlobalFunction> {
+        let mut declarations: Vec<GlobalFunction> = Vec::new();
+        for (_, name, signature) in self.functions.iter().flat_map(calls) {
+            let declared = self
+                .functions
+                .iter()
+                .chain(&declarations)
+                .any(|x| x.name() == name);
+            if name.starts_with("llvm.") && !declared {
+                declarations.push(declaration(name, signature));
+            }
+        }
+        declarations
+    }
+
+    /// Checks that every function in the module is well-formed,
+    /// and that every function called directly is declared in the module.
+    /// Intrinsics are declared when the module is emitted, so they need no declaration.
+    /// Returns every problem found, instead of stopping at the first.
+    pub fn verify(&self) -> Result<(), Vec<VerifierError>> {
+        let mut errors = self
+            .functions
+            .iter()
+            .flat_map(GlobalFunction::verify)
+            .collect::<Vec<_>>();
+        for function in &self.functions {
+            for (label, name, _) in calls(function) {
+                if !name.starts_with("llvm.") && !self.functions.iter().any(|x| x.name() == name) {
+                    errors.push(VerifierError {
+                        function: function.name().to_string(),
+                        block: Some(label.to_string()),
+                        kind: VerifierErrorKind::UndeclaredFunction(name.to_string()),
+                    });
+                }
+            }
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// Looks up a named type defined in the module.
+    pub fn named_type(&self, name: &str) -> Option<Type> {
+        self.types
+            .iter()
+            .find(|x| x.name() == name)
+            .map(|x| Type::Named(x.clone()))
+    }
+}
+
+/// Returns the label of the block, the name and the signature of every direct call
+/// and invoke in the function.
+fn calls(function: &GlobalFunction) -> Vec<(&str, &str, &Type)> {
+    let Some(body) = function.get_body() else {
+        return Vec::new();
+    };
+    let mut calls = Vec::new();
+    for block in body.blocks() {
+        let block = body.block(block);
+        for id in block.instructions() {
+            if let InstructionKind::Call {
+                callee: Value::Function(name, signature),
+                ..
+            }
+            | InstructionKind::Invoke {
+                callee: Value::Function(name, signature),
+                ..
+            } = body.instruction(*id).kind()
+            {
+                calls.push((block.label(), name.as_str(), signature));
+            }
         }
     }
+    calls
 }
 
 impl IRComponent for Module {
     fn append_to_string(&self, string: &mut String) {
+        if let Some(name) = &self.source_filename {
+            string.push_str("source_filename = \"");
+            append_escaped(string, name.as_bytes());
+            string.push_str("\"\n");
+        }
+        if let Some(layout) = &self.data_layout {
+            string.push_str("target datalayout = \"");
+            string.push_str(layout);
+            string.push_str("\"\n");
+        }
+        if let Some(triple) = &self.target_triple {
+            string.push_str("target triple = \"");
+            string.push_str(triple);
+            string.push_str("\"\n");
+        }
+        if self.source_filename.is_some()
+            || self.data_layout.is_some()
+            || self.target_triple.is_some()
+        {
+            string.push('\n');
+        }
+        for line in &self.module_asm {
+            string.push_str("module asm \"");
+            append_escaped(string, line.as_bytes());
+            string.push_str("\"\n");
+        }
+        if !self.module_asm.is_empty() {
+            string.push('\n');
+        }
+        for ty in &self.types {
+            ty.append_to_string(string);
+            string.push_str("\n\n");
+        }
         for var in &self.vars {
             var.append_to_string(string);
             string.push_str("\n\n");
         }
-        for function in &self.functions {
-            function.append_to_string(string);
+        // functions sharing the same attributes refer to the same attribute group
+        let declarations = self.missing_intrinsics();
+        let mut groups: Vec<&[FunctionAttribute]> = Vec::new();
+        for function in self.functions.iter().chain(&declarations) {
+            let attributes = function.attributes();
+            let group = match groups.iter().position(|x| *x == attributes) {
+                _ if attributes.is_empty() => None,
+                Some(position) => Some(position),
+                None => {
+                    groups.push(attributes);
+                    Some(groups.len() - 1)
+                }
+            };
+            function.append_with_group(string, group);
             string.push_str("\n\n");
         }
+        for (group, attributes) in groups.iter().enumerate() {
+            string.push_str("attributes #");
+            string.push_str(&group.to_string());
+            string.push_str(" = {");
+            for attribute in *attributes {
+                string.push(' ');
+                attribute.append_to_string(string);
+            }
+            string.push_str(" }\n");
+        }
+        if !groups.is_empty() {
+            string.push('\n');
+        }
+        for (name, operands) in &self.named_metadata {
+            string.push('!');
+            string.push_str(name);
+            string.push_str(" = ");
+            Metadata::Tuple(operands.clone()).append_to_string(string);
+            string.push('\n');
+        }
+        self.metadata.append_to_string(string);
     }
 }
 
 /// Represents the Linkage Type of Global Variables and Function.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum LinkageType {
     /// Global values with this linkage are only directly accessible by objects in the current
     /// module.
@@ -76,17 +372,52 @@ impl IRComponent for LinkageType {
     }
 }
 
+/// Represents the calling convention of a function or a call.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CallingConvention {
+    /// The default C calling convention, supporting varargs function calls.
+    C,
+    /// Attempts to make calls as fast as possible, for example by passing things in registers.
+    Fast,
+    /// Attempts to make the code in the caller as efficient as possible,
+    /// under the assumption that the call is not commonly executed.
+    Cold,
+    /// A convention that supports guaranteed tail call optimization.
+    Tail,
+    /// A target-specific calling convention, referred to by number.
+    Numbered(u32),
+}
+
+impl IRComponent for CallingConvention {
+    fn append_to_string(&self, string: &mut String) {
+        match self {
+            CallingConvention::C => string.push_str("ccc"),
+            CallingConvention::Fast => string.push_str("fastcc"),
+            CallingConvention::Cold => string.push_str("coldcc"),
+            CallingConvention::Tail => string.push_str("tailcc"),
+            CallingConvention::Numbered(number) => {
+                string.push_str("cc ");
+                string.push_str(&number.to_string());
+            }
+        }
+    }
+}
+
 /// A marker trait, indicating this is a valid top-level component of a module.
 pub trait ModuleComponent: IRComponent {}
 
 #[cfg(test)]
 mod tests {
+    use crate::metadata::{Metadata, MetadataNode, ModuleFlagBehavior};
     use crate::module::GlobalVariable;
     use crate::module::LinkageType;
-    use crate::types::Types;
-    use crate::value::Value;
+    use crate::module::Module;
+    use crate::module::{FunctionBody, GlobalFunction};
+    use crate::types::{Type, Types};
+    use crate::value::{Value, Values};
     use crate::IRComponent;
     use std::string::ToString;
+    use std::vec;
 
     #[test]
     pub fn generate_simple_global_variable() {
@@ -95,4 +426,109 @@ mod tests {
             .with_value(Value::Number("1240".to_string(), Types::integer(32)));
         assert_eq!(var.emit(), "@foo = internal global i32 1240");
     }
+
+    #[test]
+    pub fn generate_string_literals() {
+        let mut module = Module::new();
+        let hello = module.c_string("Hello");
+        let world = module.c_string("world");
+        assert_eq!(hello.emit(), "ptr @.str");
+        assert_eq!(world.emit(), "ptr @.str.1");
+        assert_eq!(
+            module.emit(),
+            "@.str = private unnamed_addr constant [ 6 x i8 ] c\"Hello\\00\", align 1\n\n\
+             @.str.1 = private unnamed_addr constant [ 6 x i8 ] c\"world\\00\", align 1\n\n"
+        );
+    }
+
+    #[test]
+    pub fn generate_named_types() {
+        let mut module = Module::new();
+        let node = module.define_type(
+            "Node",
+            Types::structure(vec![Types::integer(32), Type::Ptr]),
+        );
+        let _handle = module.declare_type("Handle");
+        module.vars.push(
+            GlobalVariable::new("head", node.clone()).with_value(Value::ZeroInitializer(node)),
+        );
+        assert_eq!(
+            module.emit(),
+            "%Node = type {i32, ptr}\n\n%Handle = type opaque\n\n@head = global %Node zeroinitializer\n\n"
+        );
+    }
+
+    #[test]
+    pub fn generate_target_information() {
+        let module = Module::new()
+            .with_source_filename("main.lotl")
+            .with_target_triple("x86_64-unknown-linux-gnu");
+        assert_eq!(
+            module.emit(),
+            "source_filename = \"main.lotl\"\n\
+             target datalayout = \"e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-i128:128-f80:128-n8:16:32:64-S128\"\n\
+             target triple = \"x86_64-unknown-linux-gnu\"\n\n"
+        );
+
+        let module = Module::new()
+            .with_data_layout("e-p:64:64")
+            .with_target_triple("wasm32-unknown-unknown");
+        assert_eq!(module.data_layout.as_deref(), Some("e-p:64:64"));
+        let module = Module::new().with_target_triple("riscv64-unknown-elf");
+        assert_eq!(module.data_layout, None);
+    }
+
+    #[test]
+    pub fn generate_module_asm() {
+        let mut module = Module::new().with_target_triple("riscv64-unknown-elf");
+        module.append_module_asm(".globl answer\nanswer:\n");
+        module.append_module_asm("\tli a0, \"42\"");
+        assert_eq!(
+            module.emit(),
+            "target triple = \"riscv64-unknown-elf\"\n\n\
+             module asm \".globl answer\"\n\
+             module asm \"answer:\"\n\
+             module asm \"\\09li a0, \\2242\\22\"\n\n"
+        );
+    }
+
+    #[test]
+    pub fn generate_module_metadata() {
+        let mut module = Module::new();
+        module.add_flag(
+            ModuleFlagBehavior::Max,
+            "PIC Level",
+            Metadata::Value(Values::integer("2", 32)),
+        );
+        let range = module.add_metadata(MetadataNode::new(vec![
+            Metadata::Value(Values::integer("0", 32)),
+            Metadata::Value(Values::integer("10", 32)),
+        ]));
+        let ident = module.add_metadata(MetadataNode::new(vec![Metadata::string("lotl")]));
+        module.add_named_metadata("llvm.ident", ident);
+
+        let limit =
+            GlobalVariable::new("limit", Types::integer(32)).with_value(Values::integer("5", 32));
+        let body = FunctionBody::new(|block| {
+            let loaded = block.load(Types::integer(32), limit.reference());
+            block.attach_metadata("range", range.clone());
+            block.ret(loaded);
+        });
+        module
+            .vars
+            .push(limit.with_metadata("range", range.clone()));
+        module
+            .functions
+            .push(GlobalFunction::new("main", Types::integer(32)).body(body));
+        assert_eq!(
+            module.emit(),
+            "@limit = global i32 5, !range !1\n\n\
+             define i32 @main() { entry: %r0 = load i32, ptr @limit, !range !1 ret i32 %r0 }\n\n\
+             !llvm.module.flags = !{!0}\n\
+             !llvm.ident = !{!2}\n\
+             !0 = !{i32 7, !\"PIC Level\", i32 2}\n\
+             !1 = !{i32 0, i32 10}\n\
+             !2 = !{!\"lotl\"}\n"
+        );
+    }
 }
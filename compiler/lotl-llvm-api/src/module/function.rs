@@ -1,18 +1,45 @@
-use crate::instruction::{BasicBlock, SharedBasicBlock};
-use crate::module::{LinkageType, ModuleComponent};
+use crate::error::BuildError;
+use crate::instruction::{
+    BasicBlock, BlockId, InstId, Instruction, InstructionKind, SharedBasicBlock,
+};
+use crate::metadata::Metadata;
+use crate::module::{CallingConvention, LinkageType, ModuleComponent};
 use crate::types::Type;
+use crate::value::{append_local, Value};
+use crate::verify::{verify_function, VerifierError, VerifierErrorKind};
 use crate::IRComponent;
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::format;
 use std::string::{String, ToString};
+use std::sync::{Arc, Mutex};
 use std::vec::Vec;
 
 /// Global variables define regions of memory allocated at compilation time instead of run-time.
+#[derive(Clone, Debug, PartialEq)]
 pub struct GlobalFunction {
     /// The name of the function.
     name: String,
-    return_type: Type,
-    parameters: Vec<Type>,
+    pub(crate) return_type: Type,
+    pub(crate) parameters: Vec<Parameter>,
+    /// Whether the function accepts extra arguments after its parameters.
+    pub(crate) variadic: bool,
     /// The linkage type of the function, defaults to LinkageType::External
-    linkage: Option<LinkageType>,
+    pub(crate) linkage: Option<LinkageType>,
+    /// Whether the function is known to resolve to a definition within the same linkage unit.
+    pub(crate) dso_local: bool,
+    /// The visibility of the function, defaults to Visibility::Default
+    pub(crate) visibility: Option<Visibility>,
+    /// The calling convention of the function, defaults to the C calling convention.
+    pub(crate) calling_convention: Option<CallingConvention>,
+    /// The attributes of the function, such as `nounwind`.
+    pub(crate) attributes: Vec<FunctionAttribute>,
+    /// The section the function is placed in.
+    pub(crate) section: Option<String>,
+    /// The function called to handle exceptions unwinding through this function.
+    pub(crate) personality: Option<Value>,
+    /// The metadata attached to the function.
+    metadata: Vec<(String, Metadata)>,
     body: Option<FunctionBody>,
 }
 
@@ -23,20 +50,110 @@ impl GlobalFunction {
             name: name.to_string(),
             return_type,
             parameters: Vec::new(),
+            variadic: false,
             linkage: None,
+            dso_local: false,
+            visibility: None,
+            calling_convention: None,
+            attributes: Vec::new(),
+            section: None,
+            personality: None,
+            metadata: Vec::new(),
             body: None,
         }
     }
 
-    /// Adds a parameter to the function.
-    pub fn with_parameter(mut self, parameter: Type) -> Self {
+    /// Adds a parameter to the function. Unnamed parameters are named after their position.
+    pub fn with_parameter<P: Into<Parameter>>(self, parameter: P) -> Self {
+        self.try_with_parameter(parameter)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Adds a parameter to the function. Unnamed parameters are named after their position.
+    /// Fails if the name is taken by another parameter, or by a name the builder generates.
+    pub fn try_with_parameter<P: Into<Parameter>>(
+        mut self,
+        parameter: P,
+    ) -> Result<Self, BuildError> {
+        let mut parameter = parameter.into();
+        let position = format!("arg{}", self.parameters.len());
+        let name = parameter.name.get_or_insert(position.clone());
+        let generated = ["r", "bb", "arg"].iter().any(|prefix| {
+            name.strip_prefix(prefix)
+                .and_then(|number| number.parse::<u32>().ok())
+                .is_some_and(|number| *name == format!("{}{}", prefix, number))
+        });
+        let taken = self
+            .parameters
+            .iter()
+            .any(|x| x.name.as_ref() == Some(name));
+        if (generated && *name != position) || name == "entry" || taken {
+            return Err(BuildError::NameCollision(name.clone()));
+        }
         self.parameters.push(parameter);
+        Ok(self)
+    }
+
+    /// Marks the function as accepting a variable number of extra arguments, like `printf`.
+    pub fn variadic(mut self) -> Self {
+        self.variadic = true;
         self
     }
 
     /// Defines the linkage type of the global function.
+    /// Private and internal functions are always `dso_local`.
     pub fn linkage(mut self, linkage: LinkageType) -> Self {
         self.linkage = Some(linkage);
+        self.dso_local |= self.is_implicitly_dso_local();
+        self
+    }
+
+    /// Marks the function as resolving to a definition within the same linkage unit,
+    /// so it is never replaced by a definition from a shared library.
+    pub fn dso_local(mut self) -> Self {
+        self.dso_local = true;
+        self
+    }
+
+    /// Defines the visibility of the global function.
+    /// Hidden and protected functions are always `dso_local`.
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = Some(visibility);
+        self.dso_local |= self.is_implicitly_dso_local();
+        self
+    }
+
+    /// Defines the calling convention of the function. Calls to the function
+    /// must use the same calling convention.
+    pub fn with_calling_convention(mut self, convention: CallingConvention) -> Self {
+        self.calling_convention = Some(convention);
+        self
+    }
+
+    /// Adds an attribute to the function.
+    pub fn with_attribute(mut self, attribute: FunctionAttribute) -> Self {
+        if !self.attributes.contains(&attribute) {
+            self.attributes.push(attribute);
+        }
+        self
+    }
+
+    /// Places the function in the given section of the object file.
+    pub fn with_section(mut self, section: &str) -> Self {
+        self.section = Some(section.to_string());
+        self
+    }
+
+    /// Provides the personality function, which is called to handle exceptions
+    /// unwinding through this function.
+    pub fn with_personality(mut self, personality: Value) -> Self {
+        self.personality = Some(personality);
+        self
+    }
+
+    /// Attaches metadata of the given kind to the function, such as its `!dbg` subprogram.
+    pub fn with_metadata(mut self, kind: &str, metadata: Metadata) -> Self {
+        self.metadata.push((kind.to_string(), metadata));
         self
     }
 
@@ -45,11 +162,129 @@ impl GlobalFunction {
         self.body = Some(body);
         self
     }
+
+    /// Returns the body of the function so it can be transformed, or nothing for declarations.
+    pub fn body_mut(&mut self) -> Option<&mut FunctionBody> {
+        self.body.as_mut()
+    }
+
+    /// Returns the body of the function, or nothing for declarations.
+    pub fn get_body(&self) -> Option<&FunctionBody> {
+        self.body.as_ref()
+    }
+
+    /// Builds the body of the global function. This gives you a reference to the entry
+    /// Basic Block, and the values of the function's parameters.
+    pub fn build_body<F: FnOnce(SharedBasicBlock, Vec<Value>)>(self, handler: F) -> Self {
+        let arguments = self.arguments();
+        self.body(FunctionBody::new(|entry| handler(entry, arguments)))
+    }
+
+    /// Returns the values of the function's parameters, as seen from inside the function body.
+    pub fn arguments(&self) -> Vec<Value> {
+        self.parameters.iter().map(Parameter::value).collect()
+    }
+
+    /// Returns the function type of this global function.
+    pub fn signature(&self) -> Type {
+        Type::Function(
+            Box::new(self.return_type.clone()),
+            self.parameters
+                .iter()
+                .map(|p| Box::new(p.ty.clone()))
+                .collect(),
+            self.variadic,
+        )
+    }
+
+    /// Returns the name of the function.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the attributes of the function.
+    pub fn attributes(&self) -> &[FunctionAttribute] {
+        &self.attributes
+    }
+
+    /// Checks that the body of the function is well-formed, returning every problem found.
+    /// Declarations have no body, so only their attributes are checked.
+    pub fn verify(&self) -> Vec<VerifierError> {
+        let mut errors = match &self.body {
+            Some(body) => verify_function(&self.name, &self.return_type, &self.arguments(), body),
+            None => Vec::new(),
+        };
+        let inline = [FunctionAttribute::AlwaysInline, FunctionAttribute::NoInline];
+        if inline.iter().all(|x| self.attributes.contains(x)) {
+            errors.push(VerifierError {
+                function: self.name.clone(),
+                block: None,
+                kind: VerifierErrorKind::ConflictingAttributes(inline[0], inline[1]),
+            });
+        }
+        let landing_pad = self.body.iter().any(|body| {
+            body.instructions().into_iter().any(|x| {
+                matches!(
+                    body.instruction(x).kind(),
+                    InstructionKind::LandingPad { .. }
+                )
+            })
+        });
+        if landing_pad && self.personality.is_none() {
+            errors.push(VerifierError {
+                function: self.name.clone(),
+                block: None,
+                kind: VerifierErrorKind::MissingPersonality,
+            });
+        }
+        errors
+    }
+
+    /// Returns a value referring to this function, which can be called or stored.
+    pub fn reference(&self) -> Value {
+        Value::Function(self.name.clone(), self.signature())
+    }
 }
 
-impl IRComponent for GlobalFunction {
-    fn append_to_string(&self, string: &mut String) {
-        string.push_str("define ");
+impl GlobalFunction {
+    /// Returns true if the function can only be referred to from within its own linkage unit,
+    /// which makes it local without being marked `dso_local`.
+    pub(crate) fn is_implicitly_dso_local(&self) -> bool {
+        matches!(
+            self.linkage,
+            Some(LinkageType::Private) | Some(LinkageType::Internal)
+        ) || matches!(
+            self.visibility,
+            Some(Visibility::Hidden) | Some(Visibility::Protected)
+        )
+    }
+
+    /// Appends the function, referring to its attributes by the number of their attribute group
+    /// if it is given, or listing them in place otherwise.
+    pub(crate) fn append_with_group(&self, string: &mut String, group: Option<usize>) {
+        match self.body {
+            Some(_) => string.push_str("define "),
+            None => string.push_str("declare "),
+        }
+        self.linkage.iter().for_each(|e| {
+            if !matches!(e, LinkageType::External) {
+                e.append_to_string(string);
+                string.push(' ');
+            }
+        });
+        if self.dso_local && !self.is_implicitly_dso_local() {
+            string.push_str("dso_local ");
+        }
+        self.visibility.iter().for_each(|e| {
+            if *e != Visibility::Default {
+                e.append_to_string(string);
+                string.push(' ');
+            }
+        });
+        self.calling_convention.iter().for_each(|e| {
+            e.append_to_string(string);
+            string.push(' ');
+        });
         self.return_type.append_to_string(string);
         string.push(' ');
         string.push('@');
@@ -59,44 +294,427 @@ impl IRComponent for GlobalFunction {
             &self
                 .parameters
                 .iter()
-                .map(|p| p.emit())
+                .map(|p| {
+                    let mut parameter = p.emit();
+                    if self.body.is_some() {
+                        parameter.push(' ');
+                        append_local(&mut parameter, p.name.as_deref().unwrap_or_default());
+                    }
+                    parameter
+                })
                 .collect::<Vec<_>>()
-                .join(" "),
+                .join(", "),
         );
+        if self.variadic {
+            if !self.parameters.is_empty() {
+                string.push_str(", ");
+            }
+            string.push_str("...");
+        }
         string.push(')');
-        string.push(' ');
+        match group {
+            Some(group) => {
+                string.push_str(" #");
+                string.push_str(&group.to_string());
+            }
+            _ => self.attributes.iter().for_each(|e| {
+                string.push(' ');
+                e.append_to_string(string);
+            }),
+        }
+        self.section.iter().for_each(|e| {
+            string.push_str(" section \"");
+            string.push_str(e);
+            string.push('"');
+        });
+        self.personality.iter().for_each(|e| {
+            string.push_str(" personality ");
+            e.append_to_string(string);
+        });
+        for (kind, metadata) in &self.metadata {
+            string.push_str(" !");
+            string.push_str(kind);
+            string.push(' ');
+            metadata.append_to_string(string);
+        }
         self.body.iter().for_each(|body| {
+            string.push(' ');
             string.push('{');
             string.push(' ');
-            body.entry.unlock(|x| x.append_to_string(string));
+            body.append_blocks(string);
             string.push('}');
         })
     }
 }
 
+impl IRComponent for GlobalFunction {
+    fn append_to_string(&self, string: &mut String) {
+        self.append_with_group(string, None);
+    }
+}
+
 impl ModuleComponent for GlobalFunction {}
 
+/// Represents an attribute of a function, giving extra information about it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FunctionAttribute {
+    /// The function never raises an exception.
+    NoUnwind,
+    /// The function is never inlined into its callers.
+    NoInline,
+    /// The function is inlined into its callers whenever possible.
+    AlwaysInline,
+    /// The function is rarely called, so calls to it are optimized for size over speed.
+    Cold,
+    /// The function never returns normally.
+    NoReturn,
+}
+
+impl IRComponent for FunctionAttribute {
+    fn append_to_string(&self, string: &mut String) {
+        match self {
+            FunctionAttribute::NoUnwind => string.push_str("nounwind"),
+            FunctionAttribute::NoInline => string.push_str("noinline"),
+            FunctionAttribute::AlwaysInline => string.push_str("alwaysinline"),
+            FunctionAttribute::Cold => string.push_str("cold"),
+            FunctionAttribute::NoReturn => string.push_str("noreturn"),
+        }
+    }
+}
+
+/// Represents how visible a function is to other modules, once it is linked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Visibility {
+    /// The function is visible to other modules, and may be replaced by their definitions.
+    Default,
+    /// The function is not visible to other modules, once it is linked.
+    Hidden,
+    /// The function is visible to other modules, but never replaced by their definitions.
+    Protected,
+}
+
+impl IRComponent for Visibility {
+    fn append_to_string(&self, string: &mut String) {
+        match self {
+            Visibility::Default => string.push_str("default"),
+            Visibility::Hidden => string.push_str("hidden"),
+            Visibility::Protected => string.push_str("protected"),
+        }
+    }
+}
+
+/// Represents a parameter of a global function.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameter {
+    pub(crate) ty: Type,
+    pub(crate) name: Option<String>,
+    pub(crate) attributes: Vec<ParameterAttribute>,
+}
+
+impl Parameter {
+    /// Creates a new parameter of the given type.
+    pub fn new(ty: Type) -> Self {
+        Parameter {
+            ty,
+            name: None,
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Provides a name to the parameter, which it is referred to by inside the function body.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Adds an attribute to the parameter.
+    pub fn with_attribute(mut self, attribute: ParameterAttribute) -> Self {
+        self.attributes.push(attribute);
+        self
+    }
+
+    /// Returns the value of this parameter, as seen from inside the function body.
+    pub fn value(&self) -> Value {
+        Value::LocalIdentifier(self.name.clone().unwrap_or_default(), self.ty.clone())
+    }
+}
+
+impl From<Type> for Parameter {
+    fn from(ty: Type) -> Self {
+        Parameter::new(ty)
+    }
+}
+
+impl IRComponent for Parameter {
+    fn append_to_string(&self, string: &mut String) {
+        self.ty.append_to_string(string);
+        for attribute in &self.attributes {
+            string.push(' ');
+            attribute.append_to_string(string);
+        }
+    }
+}
+
+/// Represents an attribute on a function parameter, giving extra information about it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParameterAttribute {
+    /// The parameter is never undefined or poison.
+    NoUndef,
+    /// The parameter is a pointer that is never null.
+    NonNull,
+    /// The parameter is a pointer to a structure of the given type,
+    /// which the function writes its return value into.
+    StructRet(Type),
+    /// The parameter is a pointer to a value of the given type, which is passed by value.
+    /// A hidden copy is made between the caller and the callee.
+    ByVal(Type),
+}
+
+impl IRComponent for ParameterAttribute {
+    fn append_to_string(&self, string: &mut String) {
+        match self {
+            ParameterAttribute::NoUndef => string.push_str("noundef"),
+            ParameterAttribute::NonNull => string.push_str("nonnull"),
+            ParameterAttribute::StructRet(ty) => {
+                string.push_str("sret(");
+                ty.append_to_string(string);
+                string.push(')');
+            }
+            ParameterAttribute::ByVal(ty) => {
+                string.push_str("byval(");
+                ty.append_to_string(string);
+                string.push(')');
+            }
+        }
+    }
+}
+
 /// Represents a function body in LLVM IR.
+///
+/// The body owns every block and instruction of the function in an arena,
+/// where they are referred to by `BlockId` and `InstId`.
+#[derive(Clone, Debug)]
 pub struct FunctionBody {
-    entry: SharedBasicBlock,
+    pub(crate) blocks: Vec<BasicBlock>,
+    pub(crate) instructions: Vec<Instruction>,
+    pub(crate) block_index: usize,
+    pub(crate) register_index: usize,
+    /// The blocks in the order they are laid out, updated whenever a block is added or removed.
+    pub(crate) layout: Vec<BlockId>,
+    /// The instruction defining each register, by name.
+    pub(crate) definitions: HashMap<String, InstId>,
+    /// The instructions using each register, by name. Only instructions in a block are listed.
+    pub(crate) users: HashMap<String, Vec<InstId>>,
 }
 
 impl FunctionBody {
     /// Creates a new function body. This gives you a reference to a Basic Block, which is
     /// the entrypoint of the function body.
     pub fn new<F: FnOnce(SharedBasicBlock)>(handler: F) -> Self {
-        let f = FunctionBody {
-            entry: SharedBasicBlock::new(BasicBlock::entry("entry")),
+        let mut body = FunctionBody::empty();
+        body.append_block("entry");
+        let body = Arc::new(Mutex::new(body));
+        handler(SharedBasicBlock::entry(body.clone()));
+        // handles to the blocks may outlive the handler, such as a stored `PhiNode`
+        match Arc::try_unwrap(body) {
+            Ok(body) => body.into_inner().unwrap(),
+            Err(body) => body.lock().unwrap().clone(),
+        }
+    }
+}
+
+impl PartialEq for FunctionBody {
+    /// Compares the bodies as they are laid out, since the arena and the nesting
+    /// of the blocks depend on the order they were built in.
+    fn eq(&self, other: &Self) -> bool {
+        let (layout, other_layout) = (self.blocks(), other.blocks());
+        let (positions, other_positions) = (self.positions(), other.positions());
+        let same_instruction = |a: InstId, b: InstId| {
+            let (a, b) = (self.instruction(a), other.instruction(b));
+            let mut kind = a.kind().clone();
+            kind.blocks_mut()
+                .into_iter()
+                .for_each(|x| x.0 = positions[x.0]);
+            let mut other_kind = b.kind().clone();
+            other_kind
+                .blocks_mut()
+                .into_iter()
+                .for_each(|x| x.0 = other_positions[x.0]);
+            kind == other_kind && a.result() == b.result() && a.metadata() == b.metadata()
         };
-        handler(f.entry.clone());
-        f
+        layout.len() == other_layout.len()
+            && layout.iter().zip(&other_layout).all(|(a, b)| {
+                let (a, b) = (self.block(*a), other.block(*b));
+                a.label() == b.label()
+                    && a.instructions().len() == b.instructions().len()
+                    && a.instructions()
+                        .iter()
+                        .zip(b.instructions())
+                        .all(|(a, b)| same_instruction(*a, *b))
+            })
     }
 }
 
-impl IRComponent for FunctionBody {
-    fn append_to_string(&self, string: &mut String) {
-        string.push('{');
-        self.entry.unlock(|x| x.append_to_string(string));
-        string.push('}');
+impl FunctionBody {
+    /// Creates a function body without any block, to be filled by the readers.
+    pub(crate) fn empty() -> Self {
+        FunctionBody {
+            blocks: Vec::new(),
+            instructions: Vec::new(),
+            block_index: 0,
+            register_index: 0,
+            layout: Vec::new(),
+            definitions: HashMap::new(),
+            users: HashMap::new(),
+        }
+    }
+
+    /// Returns the position of every block of the arena in the layout,
+    /// with removed blocks placed after the end.
+    pub(crate) fn positions(&self) -> Vec<usize> {
+        let mut positions = vec![self.layout.len(); self.blocks.len()];
+        for (position, block) in self.layout.iter().enumerate() {
+            positions[block.0] = position;
+        }
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::BuildError;
+    use crate::module::{
+        CallingConvention, FunctionAttribute, GlobalFunction, LinkageType, Module, Parameter,
+        ParameterAttribute, Visibility,
+    };
+    use crate::types::{Type, Types};
+    use crate::verify::VerifierErrorKind;
+    use crate::IRComponent;
+    use std::vec;
+
+    #[test]
+    fn build_parameterized_function() {
+        let f = GlobalFunction::new("add", Types::integer(32))
+            .with_parameter(
+                Parameter::new(Types::integer(32))
+                    .with_name("lhs")
+                    .with_attribute(ParameterAttribute::NoUndef),
+            )
+            .with_parameter(Types::integer(32))
+            .build_body(|block, arguments| {
+                let summed = block.add(arguments[0].clone(), arguments[1].clone());
+                block.ret(summed);
+            });
+        assert_eq!(
+            f.emit(),
+            "define i32 @add(i32 noundef %lhs, i32 %arg1) { \
+                entry: \
+                    %r0 = add i32 %lhs, %arg1 \
+                    ret i32 %r0 \
+            }"
+        );
+    }
+
+    #[test]
+    fn name_parameters() {
+        let f = GlobalFunction::new("scale", Types::integer(32))
+            .with_parameter(Parameter::new(Types::integer(32)).with_name("the \"count\""))
+            .with_parameter(Parameter::new(Types::integer(32)).with_name("2x"))
+            .with_parameter(Types::integer(32))
+            .build_body(|block, arguments| {
+                let product = block.mul(arguments[0].clone(), arguments[1].clone());
+                block.ret(block.add(product, arguments[2].clone()));
+            });
+        assert_eq!(
+            f.emit(),
+            "define i32 @scale(i32 %\"the \\22count\\22\", i32 %\"2x\", i32 %arg2) { \
+                entry: \
+                    %r0 = mul i32 %\"the \\22count\\22\", %\"2x\" \
+                    %r1 = add i32 %r0, %arg2 \
+                    ret i32 %r1 \
+            }"
+        );
+        let mut module = Module::new();
+        module.functions.push(f);
+        assert_eq!(Module::parse(&module.emit()).unwrap(), module);
+
+        let f = GlobalFunction::new("f", Types::void()).with_parameter(Types::integer(32));
+        for name in ["r0", "bb1", "entry", "arg0", "arg2"] {
+            let parameter = Parameter::new(Types::integer(32)).with_name(name);
+            assert_eq!(
+                f.clone().try_with_parameter(parameter).err(),
+                Some(BuildError::NameCollision(name.to_string()))
+            );
+        }
+        let parameter = Parameter::new(Types::integer(32)).with_name("arg1");
+        assert!(f.clone().try_with_parameter(parameter).is_ok());
+        assert!(f
+            .try_with_parameter(Parameter::new(Types::integer(32)).with_name("r01"))
+            .is_ok());
+    }
+
+    #[test]
+    fn build_parameterized_declaration() {
+        let pair = Types::structure(vec![Types::integer(32), Types::integer(32)]);
+        let f = GlobalFunction::new("swap", Types::void())
+            .with_parameter(
+                Parameter::new(Type::Ptr)
+                    .with_attribute(ParameterAttribute::StructRet(pair.clone())),
+            )
+            .with_parameter(
+                Parameter::new(Type::Ptr)
+                    .with_attribute(ParameterAttribute::NonNull)
+                    .with_attribute(ParameterAttribute::ByVal(pair)),
+            );
+        assert_eq!(
+            f.emit(),
+            "declare void @swap(ptr sret({i32, i32}), ptr nonnull byval({i32, i32}))"
+        );
+    }
+
+    #[test]
+    fn build_function_with_attributes() {
+        let f = GlobalFunction::new("fast", Types::void())
+            .linkage(LinkageType::External)
+            .dso_local()
+            .with_visibility(Visibility::Default)
+            .with_calling_convention(CallingConvention::Fast)
+            .with_attribute(FunctionAttribute::NoUnwind)
+            .with_attribute(FunctionAttribute::NoInline)
+            .with_attribute(FunctionAttribute::NoUnwind)
+            .with_section(".text.fast")
+            .build_body(|block, _| block.ret_void());
+        assert_eq!(
+            f.emit(),
+            "define dso_local fastcc void @fast() nounwind noinline section \".text.fast\" { \
+                entry: \
+                    ret void \
+            }"
+        );
+        assert!(f.verify().is_empty());
+
+        // hidden functions are always local, so dso_local is left out
+        let f = GlobalFunction::new("hidden", Types::void())
+            .dso_local()
+            .with_visibility(Visibility::Hidden)
+            .with_attribute(FunctionAttribute::AlwaysInline)
+            .with_attribute(FunctionAttribute::NoInline);
+        assert_eq!(
+            f.emit(),
+            "declare hidden void @hidden() alwaysinline noinline"
+        );
+        let errors = f.verify();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].kind,
+            VerifierErrorKind::ConflictingAttributes(
+                FunctionAttribute::AlwaysInline,
+                FunctionAttribute::NoInline
+            )
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "in @hidden: attributes alwaysinline and noinline cannot be combined"
+        );
     }
 }
@@ -1,19 +1,34 @@
-use crate::IRComponent;
+use crate::metadata::Metadata;
 use crate::module::{LinkageType, ModuleComponent};
 use crate::types::Type;
 use crate::value::Value;
+use crate::IRComponent;
 use std::string::{String, ToString};
+use std::vec::Vec;
 
 /// Global variables define regions of memory allocated at compilation time instead of run-time.
+#[derive(Clone, Debug, PartialEq)]
 pub struct GlobalVariable {
     /// The name of the global variable.
     name: String,
     /// The linkage type of the value, defaults to LinkageType::External
-    linkage: Option<LinkageType>,
+    pub(crate) linkage: Option<LinkageType>,
+    /// The thread-local storage model of the variable, if it is thread-local.
+    pub(crate) thread_local: Option<ThreadLocalMode>,
+    /// Whether the address of the variable is significant.
+    pub(crate) unnamed_addr: Option<UnnamedAddr>,
+    /// Whether the variable is never modified.
+    constant: bool,
     /// The type of the global variable.
     ty: Type,
     /// The default value of the global variable.
-    value: Option<Value>,
+    pub(crate) value: Option<Value>,
+    /// The section the variable is placed in.
+    pub(crate) section: Option<String>,
+    /// The alignment of the variable, in bytes.
+    pub(crate) alignment: Option<u32>,
+    /// The metadata attached to the variable.
+    metadata: Vec<(String, Metadata)>,
 }
 
 impl GlobalVariable {
@@ -23,7 +38,13 @@ impl GlobalVariable {
             name: name.to_string(),
             ty,
             linkage: None,
+            thread_local: None,
+            unnamed_addr: None,
+            constant: false,
             value: None,
+            section: None,
+            alignment: None,
+            metadata: Vec::new(),
         }
     }
 
@@ -38,6 +59,70 @@ impl GlobalVariable {
         self.value = Some(value);
         self
     }
+
+    /// Marks the global variable as constant, so it is never modified.
+    pub fn constant(mut self) -> Self {
+        self.constant = true;
+        self
+    }
+
+    /// Marks the address of the global variable as insignificant, only its contents matter.
+    pub fn with_unnamed_addr(mut self, unnamed_addr: UnnamedAddr) -> Self {
+        self.unnamed_addr = Some(unnamed_addr);
+        self
+    }
+
+    /// Provides an alignment in bytes to the global variable. This must be a power of two.
+    pub fn with_alignment(mut self, alignment: u32) -> Self {
+        if !alignment.is_power_of_two() {
+            panic!("alignment must be a power of two, found {}", alignment);
+        }
+        self.alignment = Some(alignment);
+        self
+    }
+
+    /// Places the global variable in the given section of the object file.
+    pub fn with_section(mut self, section: &str) -> Self {
+        self.section = Some(section.to_string());
+        self
+    }
+
+    /// Makes the global variable thread-local, so each thread has its own copy.
+    pub fn with_thread_local(mut self, mode: ThreadLocalMode) -> Self {
+        self.thread_local = Some(mode);
+        self
+    }
+
+    /// Attaches metadata of the given kind to the global variable, such as `!dbg`.
+    pub fn with_metadata(mut self, kind: &str, metadata: Metadata) -> Self {
+        self.metadata.push((kind.to_string(), metadata));
+        self
+    }
+
+    /// Returns the name of the global variable.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the type of the global variable.
+    pub fn ty(&self) -> &Type {
+        &self.ty
+    }
+
+    /// Returns the initial value of the global variable, or nothing if it is defined elsewhere.
+    pub fn value(&self) -> Option<&Value> {
+        self.value.as_ref()
+    }
+
+    /// Returns true if the global variable is never modified.
+    pub fn is_constant(&self) -> bool {
+        self.constant
+    }
+
+    /// Returns a pointer to this global variable.
+    pub fn reference(&self) -> Value {
+        Value::GlobalIdentifier(self.name.clone(), Type::Ptr)
+    }
 }
 
 impl IRComponent for GlobalVariable {
@@ -45,17 +130,140 @@ impl IRComponent for GlobalVariable {
         string.push('@');
         string.push_str(&self.name);
         string.push_str(" = ");
-        self.linkage.iter().for_each(|e| {
+        // variables without an initializer are declarations, which must be marked `external`
+        match (&self.linkage, &self.value) {
+            (None | Some(LinkageType::External), None) => string.push_str("external "),
+            (None | Some(LinkageType::External), Some(_)) => {}
+            (Some(linkage), _) => {
+                linkage.append_to_string(string);
+                string.push(' ');
+            }
+        }
+        self.thread_local.iter().for_each(|e| {
+            e.append_to_string(string);
+            string.push(' ');
+        });
+        self.unnamed_addr.iter().for_each(|e| {
             e.append_to_string(string);
             string.push(' ');
         });
-        string.push_str("global ");
+        match self.constant {
+            true => string.push_str("constant "),
+            false => string.push_str("global "),
+        }
         self.ty.append_to_string(string);
         self.value.iter().for_each(|e| {
             string.push(' ');
             e.append_to_string_untyped(string);
         });
+        self.section.iter().for_each(|e| {
+            string.push_str(", section \"");
+            string.push_str(e);
+            string.push('"');
+        });
+        self.alignment.iter().for_each(|e| {
+            string.push_str(", align ");
+            string.push_str(&e.to_string());
+        });
+        for (kind, metadata) in &self.metadata {
+            string.push_str(", !");
+            string.push_str(kind);
+            string.push(' ');
+            metadata.append_to_string(string);
+        }
     }
 }
 
 impl ModuleComponent for GlobalVariable {}
+
+/// Represents whether the address of a global value is significant.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UnnamedAddr {
+    /// The address is not significant, only the contents are.
+    /// Constants with this marker can be merged with other constants of the same contents.
+    Global,
+    /// The address is not significant within the module, but may be outside of it.
+    Local,
+}
+
+impl IRComponent for UnnamedAddr {
+    fn append_to_string(&self, string: &mut String) {
+        match self {
+            UnnamedAddr::Global => string.push_str("unnamed_addr"),
+            UnnamedAddr::Local => string.push_str("local_unnamed_addr"),
+        }
+    }
+}
+
+/// Represents the thread-local storage model of a global variable.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ThreadLocalMode {
+    /// The general model, which works everywhere.
+    GeneralDynamic,
+    /// For variables only used within the current shared library.
+    LocalDynamic,
+    /// For variables in modules that will not be loaded dynamically.
+    InitialExec,
+    /// For variables defined in the executable and only used within it.
+    LocalExec,
+}
+
+impl IRComponent for ThreadLocalMode {
+    fn append_to_string(&self, string: &mut String) {
+        match self {
+            ThreadLocalMode::GeneralDynamic => string.push_str("thread_local"),
+            ThreadLocalMode::LocalDynamic => string.push_str("thread_local(localdynamic)"),
+            ThreadLocalMode::InitialExec => string.push_str("thread_local(initialexec)"),
+            ThreadLocalMode::LocalExec => string.push_str("thread_local(localexec)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::module::{GlobalVariable, LinkageType, ThreadLocalMode, UnnamedAddr};
+    use crate::types::Types;
+    use crate::value::Values;
+    use crate::IRComponent;
+
+    #[test]
+    pub fn generate_string_constant() {
+        let string = Values::c_string("Hello, world!");
+        let var = GlobalVariable::new("greeting", string.ty().clone())
+            .with_linkage(LinkageType::Private)
+            .with_unnamed_addr(UnnamedAddr::Global)
+            .constant()
+            .with_value(string)
+            .with_alignment(1);
+        assert_eq!(
+            var.emit(),
+            "@greeting = private unnamed_addr constant [ 14 x i8 ] c\"Hello, world!\\00\", align 1"
+        );
+    }
+
+    #[test]
+    pub fn generate_thread_local_variable() {
+        let var = GlobalVariable::new("counter", Types::integer(64))
+            .with_linkage(LinkageType::External)
+            .with_thread_local(ThreadLocalMode::InitialExec)
+            .with_value(Values::integer("0", 64))
+            .with_section(".tdata")
+            .with_alignment(8);
+        assert_eq!(
+            var.emit(),
+            "@counter = thread_local(initialexec) global i64 0, section \".tdata\", align 8"
+        );
+    }
+
+    #[test]
+    pub fn generate_declared_variables() {
+        let var = GlobalVariable::new("errno", Types::integer(32))
+            .with_thread_local(ThreadLocalMode::GeneralDynamic);
+        assert_eq!(var.emit(), "@errno = external thread_local global i32");
+        let var = GlobalVariable::new("table", Types::pointer(0))
+            .with_linkage(LinkageType::External)
+            .constant()
+            .with_alignment(8);
+        assert_eq!(var.emit(), "@table = external constant ptr, align 8");
+    }
+}
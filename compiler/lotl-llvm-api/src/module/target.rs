@@ -0,0 +1,18 @@
+/// Returns the default data layout of a target triple, if the target is known.
+///
+/// The layouts match the ones produced by clang for the same targets.
+pub fn data_layout_for(triple: &str) -> Option<&'static str> {
+    let mut components = triple.split('-');
+    let arch = components.next()?;
+    let rest = components.collect::<Vec<_>>();
+    match arch {
+        "x86_64" if rest.contains(&"linux") => {
+            Some("e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-i128:128-f80:128-n8:16:32:64-S128")
+        }
+        "aarch64" if rest.contains(&"linux") => {
+            Some("e-m:e-i8:8:32-i16:16:32-i64:64-i128:128-n32:64-S128")
+        }
+        "wasm32" => Some("e-m:e-p:32:32-p10:8:8-p20:8:8-i64:64-n32:64-S128-ni:1:10:20"),
+        _ => None,
+    }
+}
@@ -0,0 +1,2025 @@
+//! Parses LLVM IR text into modules, for the subset of LLVM IR this crate emits.
+//!
+//! https://llvm.org/docs/LangRef.html
+
+mod lexer;
+
+use crate::error::ParseError;
+use crate::instruction::{
+    check_shufflevector, cmpxchg_type, element_type, AtomicOrdering, AtomicRmwOperator,
+    BinaryFlags, BinaryOperator, BlockId, CallOptions, CastOperator, FastMathFlags,
+    InstructionKind, LandingPadClause, MemoryOptions, SyncScope, TailMarker,
+};
+use crate::metadata::{Metadata, MetadataNode};
+use crate::module::{
+    CallingConvention, FunctionAttribute, FunctionBody, GlobalFunction, GlobalVariable,
+    LinkageType, Module, Parameter, ParameterAttribute, ThreadLocalMode, UnnamedAddr, Visibility,
+};
+use crate::types::{Type, Types};
+use crate::value::{InlineAsm, Value, Values};
+use lexer::{tokenize, Token, TokenKind};
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::format;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+impl Module {
+    /// Parses LLVM IR text, such as the contents of a `.ll` file, into a module.
+    ///
+    /// Only the subset of LLVM IR this crate emits is understood,
+    /// so parsing the text of an emitted module gives back the same module.
+    /// Returns an error pointing at the first token that could not be parsed.
+    pub fn parse(source: &str) -> Result<Module, ParseError> {
+        let parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+            module: Module::new(),
+            nodes: Vec::new(),
+            groups: HashMap::new(),
+            group_uses: Vec::new(),
+        };
+        parser.module()
+    }
+}
+
+/// Describes a token for error messages.
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Word(word) | TokenKind::Number(word) => format!("'{}'", word),
+        TokenKind::Local(name) => format!("'%{}'", name),
+        TokenKind::Global(name) => format!("'@{}'", name),
+        TokenKind::Metadata(name) => format!("'!{}'", name),
+        TokenKind::String(_) => String::from("a string"),
+        TokenKind::CString(_) => String::from("a constant string"),
+        TokenKind::MetadataString(_) => String::from("a metadata string"),
+        TokenKind::Label(label) => format!("label '{}'", label),
+        TokenKind::Punctuation(character) => format!("'{}'", character),
+        TokenKind::End => String::from("the end of the input"),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    module: Module,
+    /// The numbered metadata nodes, which may be defined in any order.
+    nodes: Vec<(usize, MetadataNode)>,
+    /// The attribute groups, which may be referred to before their definition.
+    groups: HashMap<String, Vec<FunctionAttribute>>,
+    /// The attribute groups every function refers to, by the position of the function.
+    group_uses: Vec<(usize, Token)>,
+}
+
+impl Parser {
+    fn module(mut self) -> Result<Module, ParseError> {
+        self.declare_types();
+        loop {
+            let token = self.next();
+            match token.kind {
+                TokenKind::End => break,
+                TokenKind::Word(word) if word == "source_filename" => {
+                    self.expect_punctuation('=')?;
+                    self.module.source_filename = Some(self.string()?);
+                }
+                TokenKind::Word(word) if word == "target" => {
+                    let field = self.word()?;
+                    self.expect_punctuation('=')?;
+                    let contents = self.string()?;
+                    match field.as_str() {
+                        "datalayout" => self.module.data_layout = Some(contents),
+                        "triple" => self.module.target_triple = Some(contents),
+                        _ => return Err(self.error_before("expected 'datalayout' or 'triple'")),
+                    }
+                }
+                TokenKind::Word(word) if word == "module" => {
+                    self.expect_word("asm")?;
+                    let line = self.string()?;
+                    self.module.module_asm.push(line);
+                }
+                TokenKind::Word(word) if word == "define" || word == "declare" => {
+                    let function = self.function(word == "define")?;
+                    self.module.functions.push(function);
+                }
+                TokenKind::Word(word) if word == "attributes" => self.attribute_group()?,
+                TokenKind::Local(name) => self.type_definition(&name)?,
+                TokenKind::Global(name) => {
+                    let var = self.global_variable(&name)?;
+                    self.module.vars.push(var);
+                }
+                TokenKind::Metadata(name) => self.metadata_definition(&name)?,
+                kind => {
+                    return Err(self.error_at(
+                        &token.line,
+                        &token.column,
+                        format!("expected a top-level entity, found {}", describe(&kind)),
+                    ))
+                }
+            }
+        }
+        self.nodes.sort_by_key(|(index, _)| *index);
+        for (position, (index, node)) in self.nodes.iter().enumerate() {
+            if position != *index {
+                return Err(ParseError {
+                    line: 1,
+                    column: 1,
+                    message: format!("metadata !{} is not defined", position),
+                });
+            }
+            self.module.add_metadata(node.clone());
+        }
+        for (position, token) in std::mem::take(&mut self.group_uses) {
+            let TokenKind::Word(group) = &token.kind else {
+                continue;
+            };
+            let Some(attributes) = self.groups.get(group) else {
+                let message = format!("attribute group {} is not defined", group);
+                return Err(self.error_at(&token.line, &token.column, message));
+            };
+            let function = &mut self.module.functions[position];
+            for attribute in attributes {
+                if !function.attributes.contains(attribute) {
+                    function.attributes.push(*attribute);
+                }
+            }
+        }
+        self.resolve_functions();
+        Ok(self.module)
+    }
+
+    /// Declares every named type up front, since types can be referred to before their definition.
+    fn declare_types(&mut self) {
+        for window in self.tokens.windows(3) {
+            let (TokenKind::Local(name), TokenKind::Punctuation('='), TokenKind::Word(word)) =
+                (&window[0].kind, &window[1].kind, &window[2].kind)
+            else {
+                continue;
+            };
+            if word == "type" && self.module.named_type(name).is_none() {
+                self.module.declare_type(name);
+            }
+        }
+    }
+
+    /// Turns references to functions into function values, which carry the signature
+    /// of the function, now that every function is known.
+    fn resolve_functions(&mut self) {
+        let signatures = self
+            .module
+            .functions
+            .iter()
+            .map(|x| (x.name().to_string(), x.signature()))
+            .collect::<HashMap<_, _>>();
+        fn resolve(value: &mut Value, signatures: &HashMap<String, Type>) {
+            match value {
+                Value::GlobalIdentifier(name, _) => {
+                    if let Some(signature) = signatures.get(name) {
+                        *value = Value::Function(name.clone(), signature.clone());
+                    }
+                }
+                Value::Structure(elements, _)
+                | Value::Array(elements, _)
+                | Value::Vector(elements, _) => {
+                    elements.iter_mut().for_each(|x| resolve(x, signatures))
+                }
+                _ => {}
+            }
+        }
+        for var in &mut self.module.vars {
+            if let Some(value) = &mut var.value {
+                resolve(value, &signatures);
+            }
+        }
+        for function in &mut self.module.functions {
+            if let Some(personality) = &mut function.personality {
+                resolve(personality, &signatures);
+            }
+            let Some(body) = function.body_mut() else {
+                continue;
+            };
+            for id in body.instructions() {
+                body.modify_instruction(id, |kind| {
+                    for operand in kind.operands_mut() {
+                        resolve(operand, &signatures);
+                    }
+                });
+            }
+        }
+    }
+
+    fn type_definition(&mut self, name: &str) -> Result<(), ParseError> {
+        self.expect_punctuation('=')?;
+        self.expect_word("type")?;
+        if self.eat_word("opaque") {
+            return Ok(());
+        }
+        let body = self.ty()?;
+        if let Some(named) = self.module.types.iter().find(|x| x.name() == name) {
+            named.set_body(body);
+        }
+        Ok(())
+    }
+
+    fn global_variable(&mut self, name: &str) -> Result<GlobalVariable, ParseError> {
+        self.expect_punctuation('=')?;
+        let linkage = self.linkage();
+        let thread_local = match self.eat_word("thread_local") {
+            true => Some(self.thread_local_mode()?),
+            false => None,
+        };
+        let unnamed_addr = match self.peek() {
+            TokenKind::Word(word) if word == "unnamed_addr" => Some(UnnamedAddr::Global),
+            TokenKind::Word(word) if word == "local_unnamed_addr" => Some(UnnamedAddr::Local),
+            _ => None,
+        };
+        if unnamed_addr.is_some() {
+            self.next();
+        }
+        let constant = match self.word()?.as_str() {
+            "global" => false,
+            "constant" => true,
+            word => {
+                return Err(self.error_before(&format!(
+                    "expected 'global' or 'constant', found '{}'",
+                    word
+                )))
+            }
+        };
+        let ty = self.ty()?;
+        let mut var = GlobalVariable::new(name, ty.clone());
+        // declarations end with their type, which is the last token on the line
+        if self.on_same_line() && self.peek() != &TokenKind::Punctuation(',') {
+            var = var.with_value(self.value(&ty)?);
+        }
+        if let Some(linkage) = linkage {
+            var = var.with_linkage(linkage);
+        }
+        if let Some(mode) = thread_local {
+            var = var.with_thread_local(mode);
+        }
+        if let Some(unnamed_addr) = unnamed_addr {
+            var = var.with_unnamed_addr(unnamed_addr);
+        }
+        if constant {
+            var = var.constant();
+        }
+        while self.eat_punctuation(',') {
+            let token = self.next();
+            match token.kind {
+                TokenKind::Word(word) if word == "section" => {
+                    var = var.with_section(&self.string()?);
+                }
+                TokenKind::Word(word) if word == "align" => {
+                    let alignment = self.number::<u32>()?;
+                    if !alignment.is_power_of_two() {
+                        return Err(self.error_before("alignment must be a power of two"));
+                    }
+                    var = var.with_alignment(alignment);
+                }
+                TokenKind::Metadata(kind) => var = var.with_metadata(&kind, self.metadata()?),
+                kind => {
+                    return Err(self.error_at(
+                        &token.line,
+                        &token.column,
+                        format!(
+                            "expected 'section', 'align' or metadata, found {}",
+                            describe(&kind)
+                        ),
+                    ))
+                }
+            }
+        }
+        Ok(var)
+    }
+
+    fn linkage(&mut self) -> Option<LinkageType> {
+        // external linkage is the default, and declarations are marked with it
+        if self.eat_word("external") {
+            return None;
+        }
+        let linkage = match self.peek() {
+            TokenKind::Word(word) => match word.as_str() {
+                "private" => LinkageType::Private,
+                "internal" => LinkageType::Internal,
+                "available_externally" => LinkageType::AvailableExternally,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        self.next();
+        Some(linkage)
+    }
+
+    fn thread_local_mode(&mut self) -> Result<ThreadLocalMode, ParseError> {
+        if !self.eat_punctuation('(') {
+            return Ok(ThreadLocalMode::GeneralDynamic);
+        }
+        let mode = match self.word()?.as_str() {
+            "localdynamic" => ThreadLocalMode::LocalDynamic,
+            "initialexec" => ThreadLocalMode::InitialExec,
+            "localexec" => ThreadLocalMode::LocalExec,
+            word => return Err(self.error_before(&format!("unknown thread-local mode '{}'", word))),
+        };
+        self.expect_punctuation(')')?;
+        Ok(mode)
+    }
+
+    fn function(&mut self, define: bool) -> Result<GlobalFunction, ParseError> {
+        let linkage = self.linkage();
+        let dso_local = self.eat_word("dso_local");
+        let visibility = match self.peek() {
+            TokenKind::Word(word) => match word.as_str() {
+                "default" => Some(Visibility::Default),
+                "hidden" => Some(Visibility::Hidden),
+                "protected" => Some(Visibility::Protected),
+                _ => None,
+            },
+            _ => None,
+        };
+        if visibility.is_some() {
+            self.next();
+        }
+        let convention = self.calling_convention()?;
+        let return_type = self.ty()?;
+        let name = self.global()?;
+        let mut function = GlobalFunction::new(&name, return_type);
+        function.dso_local = dso_local;
+        function.visibility = visibility;
+        function.calling_convention = convention;
+        self.expect_punctuation('(')?;
+        if !self.eat_punctuation(')') {
+            loop {
+                if self.eat_word("...") {
+                    function = function.variadic();
+                    self.expect_punctuation(')')?;
+                    break;
+                }
+                let mut parameter = Parameter::new(self.ty()?);
+                while let Some(attribute) = self.parameter_attribute()? {
+                    parameter = parameter.with_attribute(attribute);
+                }
+                if let TokenKind::Local(name) = self.peek().clone() {
+                    self.next();
+                    parameter = parameter.with_name(&name);
+                }
+                function = function
+                    .try_with_parameter(parameter)
+                    .map_err(|e| self.error_before(&e.to_string()))?;
+                if !self.eat_punctuation(',') {
+                    self.expect_punctuation(')')?;
+                    break;
+                }
+            }
+        }
+        if let Some(linkage) = linkage {
+            function = function.linkage(linkage);
+        }
+        function.dso_local |= function.is_implicitly_dso_local();
+        loop {
+            if matches!(self.peek(), TokenKind::Word(word) if word.starts_with('#')) {
+                let token = self.next();
+                self.group_uses.push((self.module.functions.len(), token));
+                continue;
+            }
+            match self.function_attribute()? {
+                Some(attribute) => function = function.with_attribute(attribute),
+                None => break,
+            }
+        }
+        if self.eat_word("section") {
+            function = function.with_section(&self.string()?);
+        }
+        if self.eat_word("personality") {
+            function = function.with_personality(self.typed_value()?);
+        }
+        // attachments are told apart from the definition of a metadata node by the missing `=`
+        while let TokenKind::Metadata(kind) = self.peek().clone() {
+            if self.peek_at(1) == &TokenKind::Punctuation('=') {
+                break;
+            }
+            self.next();
+            function = function.with_metadata(&kind, self.metadata()?);
+        }
+        if define {
+            function = function.body(self.body()?);
+        }
+        Ok(function)
+    }
+
+    fn function_attribute(&mut self) -> Result<Option<FunctionAttribute>, ParseError> {
+        let TokenKind::Word(word) = self.peek() else {
+            return Ok(None);
+        };
+        let attribute = match word.as_str() {
+            "nounwind" => FunctionAttribute::NoUnwind,
+            "noinline" => FunctionAttribute::NoInline,
+            "alwaysinline" => FunctionAttribute::AlwaysInline,
+            "cold" => FunctionAttribute::Cold,
+            "noreturn" => FunctionAttribute::NoReturn,
+            _ => return Ok(None),
+        };
+        self.next();
+        Ok(Some(attribute))
+    }
+
+    /// Parses an attribute group such as `attributes #0 = { nounwind }`.
+    /// Attributes without a [FunctionAttribute], such as the `nofree` or
+    /// `"frame-pointer"="all"` that LLVM adds, are skipped.
+    fn attribute_group(&mut self) -> Result<(), ParseError> {
+        let group = self.word()?;
+        if !group.starts_with('#') {
+            return Err(self.error_before("expected an attribute group such as '#0'"));
+        }
+        self.expect_punctuation('=')?;
+        self.expect_punctuation('{')?;
+        let mut attributes = Vec::new();
+        while !self.eat_punctuation('}') {
+            match self.function_attribute()? {
+                Some(attribute) => attributes.push(attribute),
+                None => {
+                    let token = self.next();
+                    match token.kind {
+                        TokenKind::Word(_) => {
+                            if self.eat_punctuation('(') {
+                                self.skip_parenthesized()?;
+                            }
+                        }
+                        TokenKind::String(_) => {
+                            if self.eat_punctuation('=') {
+                                self.string()?;
+                            }
+                        }
+                        _ => return Err(self.expected("a function attribute", &token)),
+                    }
+                }
+            }
+        }
+        self.groups.insert(group, attributes);
+        Ok(())
+    }
+
+    /// Skips the tokens up to and including the `)` closing an already eaten `(`.
+    fn skip_parenthesized(&mut self) -> Result<(), ParseError> {
+        let mut depth = 1;
+        while depth > 0 {
+            let token = self.next();
+            match token.kind {
+                TokenKind::Punctuation('(') => depth += 1,
+                TokenKind::Punctuation(')') => depth -= 1,
+                TokenKind::End => return Err(self.expected("')'", &token)),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn calling_convention(&mut self) -> Result<Option<CallingConvention>, ParseError> {
+        let convention = match self.peek() {
+            TokenKind::Word(word) => match word.as_str() {
+                "ccc" => CallingConvention::C,
+                "fastcc" => CallingConvention::Fast,
+                "coldcc" => CallingConvention::Cold,
+                "tailcc" => CallingConvention::Tail,
+                "cc" => {
+                    self.next();
+                    return Ok(Some(CallingConvention::Numbered(self.number()?)));
+                }
+                // llvm-dis names the numbered conventions it knows
+                name => match numbered_convention(name) {
+                    Some(number) => CallingConvention::Numbered(number),
+                    None => return Ok(None),
+                },
+            },
+            _ => return Ok(None),
+        };
+        self.next();
+        Ok(Some(convention))
+    }
+
+    fn parameter_attribute(&mut self) -> Result<Option<ParameterAttribute>, ParseError> {
+        let TokenKind::Word(word) = self.peek().clone() else {
+            return Ok(None);
+        };
+        let attribute = match word.as_str() {
+            "noundef" => ParameterAttribute::NoUndef,
+            "nonnull" => ParameterAttribute::NonNull,
+            "sret" | "byval" => {
+                self.next();
+                self.expect_punctuation('(')?;
+                let ty = self.ty()?;
+                self.expect_punctuation(')')?;
+                return Ok(Some(match word.as_str() {
+                    "sret" => ParameterAttribute::StructRet(ty),
+                    _ => ParameterAttribute::ByVal(ty),
+                }));
+            }
+            _ => return Ok(None),
+        };
+        self.next();
+        Ok(Some(attribute))
+    }
+
+    fn body(&mut self) -> Result<FunctionBody, ParseError> {
+        self.expect_punctuation('{')?;
+        let mut body = FunctionBody::empty();
+        // blocks can be branched to before their label, so every label is looked up first
+        let mut blocks = HashMap::new();
+        let (mut braces, mut parentheses) = (1, 0);
+        for token in &self.tokens[self.position..] {
+            match &token.kind {
+                TokenKind::Punctuation('{') => braces += 1,
+                TokenKind::Punctuation('}') if braces == 1 => break,
+                TokenKind::Punctuation('}') => braces -= 1,
+                TokenKind::Punctuation('(') => parentheses += 1,
+                TokenKind::Punctuation(')') => parentheses -= 1,
+                TokenKind::Label(label) if parentheses == 0 => {
+                    if blocks.contains_key(label) {
+                        return Err(self.error_at(
+                            &token.line,
+                            &token.column,
+                            format!("block '{}' is defined twice", label),
+                        ));
+                    }
+                    blocks.insert(label.clone(), body.append_block(label));
+                }
+                _ => {}
+            }
+        }
+
+        let mut current = None;
+        let mut registers = 0;
+        loop {
+            match self.peek().clone() {
+                TokenKind::Punctuation('}') => {
+                    self.next();
+                    break;
+                }
+                TokenKind::Label(label) => {
+                    self.next();
+                    current = Some(blocks[&label]);
+                }
+                _ => {
+                    let Some(block) = current else {
+                        return Err(self.error("expected a block label"));
+                    };
+                    if self.instruction(&mut body, block, &blocks)? {
+                        registers += 1;
+                    }
+                }
+            }
+        }
+        // blocks other than the entry and registers are numbered from zero by the builders
+        body.block_index = body.blocks.len().saturating_sub(1);
+        body.register_index = registers;
+        Ok(body)
+    }
+
+    /// Parses an instruction at the end of the block, and returns true if it defines a register.
+    fn instruction(
+        &mut self,
+        body: &mut FunctionBody,
+        block: BlockId,
+        blocks: &HashMap<String, BlockId>,
+    ) -> Result<bool, ParseError> {
+        let result = match (self.peek().clone(), self.peek_at(1)) {
+            (TokenKind::Local(name), TokenKind::Punctuation('=')) => {
+                self.position += 2;
+                Some(name)
+            }
+            _ => None,
+        };
+        let start = self.position;
+        let (kind, ty) = self.operation(blocks)?;
+        let result = match (result, ty) {
+            (Some(name), Some(ty)) => Some(Value::LocalIdentifier(name, ty)),
+            (Some(name), None) => {
+                let token = &self.tokens[start];
+                return Err(self.error_at(
+                    &token.line,
+                    &token.column,
+                    format!("instruction defining '%{}' has no result", name),
+                ));
+            }
+            (None, _) => None,
+        };
+        let defines = result.is_some();
+        let index = body.block(block).instructions().len();
+        let id = body.insert_instruction(block, index, kind, result);
+        while self.peek() == &TokenKind::Punctuation(',') {
+            let TokenKind::Metadata(kind) = self.peek_at(1).clone() else {
+                break;
+            };
+            self.position += 2;
+            body.attach_metadata(id, &kind, self.metadata()?);
+        }
+        Ok(defines)
+    }
+
+    /// Parses the operation of an instruction, and returns it along with the type of its result.
+    fn operation(
+        &mut self,
+        blocks: &HashMap<String, BlockId>,
+    ) -> Result<(InstructionKind, Option<Type>), ParseError> {
+        let opcode = self.word()?;
+        if let Some(operator) = binary_operator(&opcode) {
+            let flags = self.binary_flags();
+            let lhs = self.typed_value()?;
+            self.expect_punctuation(',')?;
+            let rhs = self.value(&lhs.ty().clone())?;
+            let ty = lhs.ty().clone();
+            let kind = InstructionKind::Binary {
+                operator,
+                flags,
+                lhs,
+                rhs,
+            };
+            return Ok((kind, Some(ty)));
+        }
+        if let Some(operator) = cast_operator(&opcode) {
+            let value = self.typed_value()?;
+            self.expect_word("to")?;
+            let target = self.ty()?;
+            let kind = InstructionKind::Cast {
+                operator,
+                value,
+                target: target.clone(),
+            };
+            return Ok((kind, Some(target)));
+        }
+        Ok(match opcode.as_str() {
+            "fneg" => {
+                let value = self.typed_value()?;
+                let ty = value.ty().clone();
+                (InstructionKind::FloatNegate { value }, Some(ty))
+            }
+            "alloca" => {
+                let ty = self.ty()?;
+                let alignment = self.alignment()?;
+                (InstructionKind::Alloca { ty, alignment }, Some(Type::Ptr))
+            }
+            "load" => {
+                let atomic = self.eat_word("atomic");
+                let mut options = self.memory_options();
+                let ty = self.ty()?;
+                self.expect_punctuation(',')?;
+                let pointer = self.typed_value()?;
+                let ordering = match atomic {
+                    true => Some(self.atomic_ordering(&mut options)?),
+                    false => None,
+                };
+                options.alignment = self.alignment()?;
+                let kind = InstructionKind::Load {
+                    ty: ty.clone(),
+                    pointer,
+                    ordering,
+                    options,
+                };
+                (kind, Some(ty))
+            }
+            "store" => {
+                let atomic = self.eat_word("atomic");
+                let mut options = self.memory_options();
+                let value = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let pointer = self.typed_value()?;
+                let ordering = match atomic {
+                    true => Some(self.atomic_ordering(&mut options)?),
+                    false => None,
+                };
+                options.alignment = self.alignment()?;
+                let kind = InstructionKind::Store {
+                    value,
+                    pointer,
+                    ordering,
+                    options,
+                };
+                (kind, None)
+            }
+            "atomicrmw" => {
+                let mut options = self.memory_options();
+                let operator = self.word()?;
+                let operator = atomicrmw_operator(&operator).ok_or_else(|| {
+                    self.error_before(&format!("unknown atomicrmw operation '{}'", operator))
+                })?;
+                let pointer = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let value = self.typed_value()?;
+                let ordering = self.atomic_ordering(&mut options)?;
+                options.alignment = self.alignment()?;
+                let ty = value.ty().clone();
+                let kind = InstructionKind::AtomicRmw {
+                    operator,
+                    pointer,
+                    value,
+                    ordering,
+                    options,
+                };
+                (kind, Some(ty))
+            }
+            "cmpxchg" => {
+                let weak = self.eat_word("weak");
+                let mut options = self.memory_options();
+                let pointer = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let expected = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let replacement = self.typed_value()?;
+                let success = self.atomic_ordering(&mut options)?;
+                let failure = self.ordering()?;
+                options.alignment = self.alignment()?;
+                let ty = cmpxchg_type(expected.ty());
+                let kind = InstructionKind::CompareExchange {
+                    pointer,
+                    expected,
+                    replacement,
+                    success,
+                    failure,
+                    weak,
+                    options,
+                };
+                (kind, Some(ty))
+            }
+            "fence" => {
+                let sync_scope = self.sync_scope()?;
+                let ordering = self.ordering()?;
+                let kind = InstructionKind::Fence {
+                    ordering,
+                    sync_scope,
+                };
+                (kind, None)
+            }
+            "getelementptr" => {
+                let ty = self.ty()?;
+                self.expect_punctuation(',')?;
+                let base = self.typed_value()?;
+                let mut indices = Vec::new();
+                while self.peek() == &TokenKind::Punctuation(',')
+                    && !matches!(self.peek_at(1), TokenKind::Metadata(_))
+                {
+                    self.next();
+                    indices.push(self.typed_value()?);
+                }
+                let kind = InstructionKind::GetElementPtr { ty, base, indices };
+                (kind, Some(Type::Ptr))
+            }
+            "extractvalue" => {
+                let aggregate = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let index = self.number::<usize>()?;
+                let ty = element_type(aggregate.ty(), index)
+                    .map_err(|e| self.error_before(&e.to_string()))?;
+                (InstructionKind::ExtractValue { aggregate, index }, Some(ty))
+            }
+            "insertvalue" => {
+                let aggregate = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let value = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let index = self.number::<usize>()?;
+                let ty = aggregate.ty().clone();
+                let kind = InstructionKind::InsertValue {
+                    aggregate,
+                    value,
+                    index,
+                };
+                (kind, Some(ty))
+            }
+            "extractelement" => {
+                let vector = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let index = self.typed_value()?;
+                let ty = vector.ty().scalar().clone();
+                (InstructionKind::ExtractElement { vector, index }, Some(ty))
+            }
+            "insertelement" => {
+                let vector = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let value = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let index = self.typed_value()?;
+                let ty = vector.ty().clone();
+                let kind = InstructionKind::InsertElement {
+                    vector,
+                    value,
+                    index,
+                };
+                (kind, Some(ty))
+            }
+            "shufflevector" => {
+                let lhs = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let rhs = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let mask = self.typed_value()?;
+                let ty = check_shufflevector(&lhs, &rhs, &mask)
+                    .map_err(|e| self.error_before(&e.to_string()))?;
+                (InstructionKind::ShuffleVector { lhs, rhs, mask }, Some(ty))
+            }
+            "phi" => {
+                let ty = self.ty()?;
+                let mut incoming = Vec::new();
+                loop {
+                    self.expect_punctuation('[')?;
+                    let value = self.value(&ty)?;
+                    self.expect_punctuation(',')?;
+                    let block = self.label(blocks)?;
+                    self.expect_punctuation(']')?;
+                    incoming.push((value, block));
+                    if self.peek() != &TokenKind::Punctuation(',')
+                        || self.peek_at(1) != &TokenKind::Punctuation('[')
+                    {
+                        break;
+                    }
+                    self.next();
+                }
+                let kind = InstructionKind::Phi {
+                    ty: ty.clone(),
+                    incoming,
+                };
+                (kind, Some(ty))
+            }
+            "select" => {
+                let condition = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let if_true = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let if_false = self.typed_value()?;
+                let ty = if_true.ty().clone();
+                let kind = InstructionKind::Select {
+                    condition,
+                    if_true,
+                    if_false,
+                };
+                (kind, Some(ty))
+            }
+            "tail" | "musttail" | "notail" | "call" => self.call(&opcode)?,
+            "invoke" => self.invoke(blocks)?,
+            "landingpad" => {
+                let ty = self.ty()?;
+                let cleanup = self.eat_word("cleanup");
+                let mut clauses = Vec::new();
+                loop {
+                    if self.eat_word("catch") {
+                        clauses.push(LandingPadClause::Catch(self.typed_value()?));
+                    } else if self.eat_word("filter") {
+                        clauses.push(LandingPadClause::Filter(self.typed_value()?));
+                    } else {
+                        break;
+                    }
+                }
+                let kind = InstructionKind::LandingPad {
+                    ty: ty.clone(),
+                    cleanup,
+                    clauses,
+                };
+                (kind, Some(ty))
+            }
+            "resume" => {
+                let value = self.typed_value()?;
+                (InstructionKind::Resume { value }, None)
+            }
+            "#dbg_declare" => {
+                self.expect_punctuation('(')?;
+                let address = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                let variable = self.metadata()?;
+                self.expect_punctuation(',')?;
+                // the expression is always empty, since debug records are only made by the builders
+                self.metadata()?;
+                self.expect_punctuation(',')?;
+                let location = self.metadata()?;
+                self.expect_punctuation(')')?;
+                let kind = InstructionKind::DeclareVariable {
+                    address,
+                    variable,
+                    location,
+                };
+                (kind, None)
+            }
+            "ret" => {
+                let value = match self.eat_word("void") {
+                    true => None,
+                    false => Some(self.typed_value()?),
+                };
+                (InstructionKind::Return { value }, None)
+            }
+            "br" => {
+                if self.eat_word("label") {
+                    let target = self.label(blocks)?;
+                    return Ok((InstructionKind::Branch { target }, None));
+                }
+                let condition = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                self.expect_word("label")?;
+                let if_true = self.label(blocks)?;
+                self.expect_punctuation(',')?;
+                self.expect_word("label")?;
+                let if_false = self.label(blocks)?;
+                let kind = InstructionKind::ConditionalBranch {
+                    condition,
+                    if_true,
+                    if_false,
+                };
+                (kind, None)
+            }
+            "switch" => {
+                let value = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                self.expect_word("label")?;
+                let default = self.label(blocks)?;
+                self.expect_punctuation('[')?;
+                let mut cases = Vec::new();
+                while !self.eat_punctuation(']') {
+                    let case = self.typed_value()?;
+                    self.expect_punctuation(',')?;
+                    self.expect_word("label")?;
+                    cases.push((case, self.label(blocks)?));
+                }
+                let kind = InstructionKind::Switch {
+                    value,
+                    default,
+                    cases,
+                };
+                (kind, None)
+            }
+            "indirectbr" => {
+                let address = self.typed_value()?;
+                self.expect_punctuation(',')?;
+                self.expect_punctuation('[')?;
+                let mut destinations = Vec::new();
+                if !self.eat_punctuation(']') {
+                    loop {
+                        self.expect_word("label")?;
+                        destinations.push(self.label(blocks)?);
+                        if !self.eat_punctuation(',') {
+                            self.expect_punctuation(']')?;
+                            break;
+                        }
+                    }
+                }
+                let kind = InstructionKind::IndirectBranch {
+                    address,
+                    destinations,
+                };
+                (kind, None)
+            }
+            "unreachable" => (InstructionKind::Unreachable, None),
+            opcode => return Err(self.error_before(&format!("unknown instruction '{}'", opcode))),
+        })
+    }
+
+    /// Parses the `volatile` marker of a memory access, which comes before its operands.
+    fn memory_options(&mut self) -> MemoryOptions {
+        MemoryOptions {
+            volatile: self.eat_word("volatile"),
+            ..MemoryOptions::default()
+        }
+    }
+
+    /// Parses the sync scope and ordering of an atomic memory access, after its operands.
+    fn atomic_ordering(
+        &mut self,
+        options: &mut MemoryOptions,
+    ) -> Result<AtomicOrdering, ParseError> {
+        options.sync_scope = self.sync_scope()?;
+        self.ordering()
+    }
+
+    fn sync_scope(&mut self) -> Result<SyncScope, ParseError> {
+        if !self.eat_word("syncscope") {
+            return Ok(SyncScope::System);
+        }
+        self.expect_punctuation('(')?;
+        let name = self.string()?;
+        self.expect_punctuation(')')?;
+        Ok(match name.as_str() {
+            "" => SyncScope::System,
+            "singlethread" => SyncScope::SingleThread,
+            _ => SyncScope::Target(name),
+        })
+    }
+
+    fn ordering(&mut self) -> Result<AtomicOrdering, ParseError> {
+        Ok(match self.word()?.as_str() {
+            "unordered" => AtomicOrdering::Unordered,
+            "monotonic" => AtomicOrdering::Monotonic,
+            "acquire" => AtomicOrdering::Acquire,
+            "release" => AtomicOrdering::Release,
+            "acq_rel" => AtomicOrdering::AcquireRelease,
+            "seq_cst" => AtomicOrdering::SequentiallyConsistent,
+            word => return Err(self.error_before(&format!("unknown ordering '{}'", word))),
+        })
+    }
+
+    /// Parses the alignment at the end of a memory access, if there is one.
+    fn alignment(&mut self) -> Result<Option<u32>, ParseError> {
+        if self.peek() != &TokenKind::Punctuation(',')
+            || self.peek_at(1) != &TokenKind::Word(String::from("align"))
+        {
+            return Ok(None);
+        }
+        self.position += 2;
+        let alignment = self.number::<u32>()?;
+        if !alignment.is_power_of_two() {
+            return Err(self.error_before("alignment must be a power of two"));
+        }
+        Ok(Some(alignment))
+    }
+
+    fn binary_flags(&mut self) -> BinaryFlags {
+        let mut flags = BinaryFlags::new();
+        let mut fast_math = FastMathFlags::new();
+        while let TokenKind::Word(word) = self.peek().clone() {
+            match word.as_str() {
+                "nuw" => flags = flags.no_unsigned_wrap(),
+                "nsw" => flags = flags.no_signed_wrap(),
+                "exact" => flags = flags.exact(),
+                "fast" => fast_math = FastMathFlags::fast(),
+                "nnan" => fast_math = fast_math.no_nans(),
+                "ninf" => fast_math = fast_math.no_infs(),
+                "nsz" => fast_math = fast_math.no_signed_zeros(),
+                "arcp" => fast_math = fast_math.allow_reciprocal(),
+                "contract" => fast_math = fast_math.allow_contract(),
+                "afn" => fast_math = fast_math.approximate_functions(),
+                "reassoc" => fast_math = fast_math.allow_reassociation(),
+                _ => break,
+            }
+            self.next();
+        }
+        match fast_math == FastMathFlags::new() {
+            true => flags,
+            false => flags.fast_math(fast_math),
+        }
+    }
+
+    /// Parses a call, starting at its tail marker or the `call` keyword.
+    fn call(&mut self, opcode: &str) -> Result<(InstructionKind, Option<Type>), ParseError> {
+        let mut options = CallOptions::new();
+        let marker = match opcode {
+            "tail" => Some(TailMarker::Tail),
+            "musttail" => Some(TailMarker::MustTail),
+            "notail" => Some(TailMarker::NoTail),
+            _ => None,
+        };
+        if let Some(marker) = marker {
+            options = options.with_tail_marker(marker);
+            self.expect_word("call")?;
+        }
+        let (signature, callee, arguments, result) = self.call_site(&mut options)?;
+        let kind = InstructionKind::Call {
+            options,
+            signature,
+            callee,
+            arguments,
+        };
+        Ok((kind, result))
+    }
+
+    /// Parses an `invoke`, starting after its keyword.
+    fn invoke(
+        &mut self,
+        blocks: &HashMap<String, BlockId>,
+    ) -> Result<(InstructionKind, Option<Type>), ParseError> {
+        let mut options = CallOptions::new();
+        let (signature, callee, arguments, result) = self.call_site(&mut options)?;
+        self.expect_word("to")?;
+        self.expect_word("label")?;
+        let normal = self.label(blocks)?;
+        self.expect_word("unwind")?;
+        self.expect_word("label")?;
+        let unwind = self.label(blocks)?;
+        let kind = InstructionKind::Invoke {
+            options,
+            signature,
+            callee,
+            arguments,
+            normal,
+            unwind,
+        };
+        Ok((kind, result))
+    }
+
+    /// Parses the calling convention, callee and arguments shared by calls and invokes,
+    /// returning the signature, callee, arguments and type of the result.
+    fn call_site(
+        &mut self,
+        options: &mut CallOptions,
+    ) -> Result<(Type, Value, Vec<Value>, Option<Type>), ParseError> {
+        options.convention = self.calling_convention()?;
+        // only variadic callees are written with their function type
+        let ty = self.ty()?;
+        let mut callee = match self.eat_word("asm") {
+            true => Value::InlineAsm(self.inline_asm()?),
+            false => self.value(&Type::Ptr)?,
+        };
+        self.expect_punctuation('(')?;
+        let mut arguments = Vec::new();
+        if !self.eat_punctuation(')') {
+            loop {
+                arguments.push(self.typed_value()?);
+                if !self.eat_punctuation(',') {
+                    self.expect_punctuation(')')?;
+                    break;
+                }
+            }
+        }
+        let signature = match ty {
+            Type::Function(..) => ty,
+            _ => Types::function(ty, arguments.iter().map(|x| x.ty().clone()).collect()),
+        };
+        // inline assembly is called with the signature of the call
+        if let Value::InlineAsm(asm) = &mut callee {
+            asm.signature = signature.clone();
+        }
+        let Type::Function(return_type, _, _) = &signature else {
+            unreachable!("call signatures are always function types");
+        };
+        let result = match **return_type {
+            Type::Void => None,
+            ref ty => Some(ty.clone()),
+        };
+        Ok((signature, callee, arguments, result))
+    }
+
+    /// Parses inline assembly, starting after its `asm` keyword.
+    /// Its signature is only known once the call around it has been parsed.
+    fn inline_asm(&mut self) -> Result<InlineAsm, ParseError> {
+        let side_effects = self.eat_word("sideeffect");
+        let align_stack = self.eat_word("alignstack");
+        let intel_dialect = self.eat_word("inteldialect");
+        let unwind = self.eat_word("unwind");
+        let template = self.string()?;
+        self.expect_punctuation(',')?;
+        let constraints = self.string()?;
+        Ok(InlineAsm {
+            template,
+            constraints,
+            signature: Types::void(),
+            side_effects,
+            align_stack,
+            intel_dialect,
+            unwind,
+        })
+    }
+
+    fn label(&mut self, blocks: &HashMap<String, BlockId>) -> Result<BlockId, ParseError> {
+        let name = self.local()?;
+        blocks
+            .get(&name)
+            .copied()
+            .ok_or_else(|| self.error_before(&format!("undefined block '%{}'", name)))
+    }
+
+    fn ty(&mut self) -> Result<Type, ParseError> {
+        let token = self.next();
+        let mut ty = match &token.kind {
+            TokenKind::Word(word) => match word.as_str() {
+                "void" => Type::Void,
+                "half" => Type::Half,
+                "float" => Type::Float,
+                "double" => Type::Double,
+                "fp128" => Type::FP128,
+                "ptr" if self.eat_word("addrspace") => {
+                    self.expect_punctuation('(')?;
+                    let address_space = self.number()?;
+                    self.expect_punctuation(')')?;
+                    Types::pointer(address_space)
+                }
+                "ptr" => Type::Ptr,
+                word => match word.strip_prefix('i').map(|x| x.parse::<u32>()) {
+                    Some(Ok(width)) => Types::integer(width),
+                    _ => return Err(self.expected("a type", &token)),
+                },
+            },
+            TokenKind::Punctuation('[') => {
+                let length = self.number()?;
+                self.expect_word("x")?;
+                let element = self.ty()?;
+                self.expect_punctuation(']')?;
+                Types::array(length, element)
+            }
+            TokenKind::Punctuation('{') => Types::structure(self.type_list()?),
+            TokenKind::Punctuation('<') if self.eat_punctuation('{') => {
+                let fields = self.type_list()?;
+                self.expect_punctuation('>')?;
+                Types::packed_structure(fields)
+            }
+            TokenKind::Punctuation('<') => {
+                let scalable = self.eat_word("vscale");
+                if scalable {
+                    self.expect_word("x")?;
+                }
+                let length = self.number()?;
+                self.expect_word("x")?;
+                let element = self.ty()?;
+                self.expect_punctuation('>')?;
+                match scalable {
+                    true => Types::scalable_vector(length, element),
+                    false => Types::vector(length, element),
+                }
+            }
+            TokenKind::Local(name) => match self.module.named_type(name) {
+                Some(ty) => ty,
+                None => {
+                    return Err(self.error_at(
+                        &token.line,
+                        &token.column,
+                        format!("undefined type '%{}'", name),
+                    ))
+                }
+            },
+            _ => return Err(self.expected("a type", &token)),
+        };
+        // a parenthesis after a type makes it the return type of a function type
+        while self.peek() == &TokenKind::Punctuation('(') {
+            self.next();
+            let mut parameters = Vec::new();
+            let mut variadic = false;
+            if !self.eat_punctuation(')') {
+                loop {
+                    if self.eat_word("...") {
+                        variadic = true;
+                        self.expect_punctuation(')')?;
+                        break;
+                    }
+                    parameters.push(self.ty()?);
+                    if !self.eat_punctuation(',') {
+                        self.expect_punctuation(')')?;
+                        break;
+                    }
+                }
+            }
+            ty = match variadic {
+                true => Types::variadic_function(ty, parameters),
+                false => Types::function(ty, parameters),
+            };
+        }
+        Ok(ty)
+    }
+
+    /// Parses the fields of a structure type, after its opening brace.
+    fn type_list(&mut self) -> Result<Vec<Type>, ParseError> {
+        let mut types = Vec::new();
+        if self.eat_punctuation('}') {
+            return Ok(types);
+        }
+        loop {
+            types.push(self.ty()?);
+            if !self.eat_punctuation(',') {
+                self.expect_punctuation('}')?;
+                return Ok(types);
+            }
+        }
+    }
+
+    fn typed_value(&mut self) -> Result<Value, ParseError> {
+        let ty = self.ty()?;
+        self.value(&ty)
+    }
+
+    /// Parses a value written without its type, which is given by the context.
+    fn value(&mut self, ty: &Type) -> Result<Value, ParseError> {
+        let token = self.next();
+        let ty = ty.clone();
+        Ok(match token.kind {
+            // floating points written with an exponent, such as `5.000000e-01`, are made decimal
+            TokenKind::Number(number)
+                if ty.is_floating_point()
+                    && !number.starts_with("0x")
+                    && number.contains(['e', 'E']) =>
+            {
+                let decimal = number
+                    .parse::<f64>()
+                    .map(|x| x.to_string())
+                    .map_err(|_| self.error_before(&format!("invalid number '{}'", number)))?;
+                Values::try_float(&decimal, ty).map_err(|e| self.error_before(&e.to_string()))?
+            }
+            TokenKind::Number(number) => Value::Number(number, ty),
+            TokenKind::Word(word) if word == "true" || word == "false" => Value::Number(word, ty),
+            TokenKind::Word(word) if word == "zeroinitializer" => Value::ZeroInitializer(ty),
+            TokenKind::Word(word) if word == "blockaddress" => {
+                self.expect_punctuation('(')?;
+                let function = self.global()?;
+                self.expect_punctuation(',')?;
+                let label = self.local()?;
+                self.expect_punctuation(')')?;
+                Value::BlockAddress(function, label)
+            }
+            TokenKind::Word(word) if word == "null" => Value::Null(ty),
+            TokenKind::Word(word) if word == "undef" => Value::Undef(ty),
+            TokenKind::Word(word) if word == "poison" => Value::Poison(ty),
+            TokenKind::Word(word) if word == "getelementptr" => {
+                let inbounds = self.eat_word("inbounds");
+                self.expect_punctuation('(')?;
+                let source = self.ty()?;
+                self.expect_punctuation(',')?;
+                let base = self.typed_value()?;
+                let mut indices = Vec::new();
+                while self.eat_punctuation(',') {
+                    indices.push(self.typed_value()?);
+                }
+                self.expect_punctuation(')')?;
+                Value::GetElementPtr(source, Box::new(base), indices, inbounds)
+            }
+            TokenKind::Word(word) if cast_operator(&word).is_some() => {
+                self.expect_punctuation('(')?;
+                let value = self.typed_value()?;
+                self.expect_word("to")?;
+                let target = self.ty()?;
+                self.expect_punctuation(')')?;
+                let operator = cast_operator(&word).expect("the operator was matched");
+                Value::Cast(operator, Box::new(value), target)
+            }
+            TokenKind::Local(name) => Value::LocalIdentifier(name, ty),
+            TokenKind::Global(name) => Value::GlobalIdentifier(name, ty),
+            TokenKind::CString(bytes) => Value::CString(bytes, ty),
+            TokenKind::Punctuation('{') => Value::Structure(self.value_list('}')?, ty),
+            TokenKind::Punctuation('<') if self.eat_punctuation('{') => {
+                let elements = self.value_list('}')?;
+                self.expect_punctuation('>')?;
+                Value::Structure(elements, ty)
+            }
+            TokenKind::Punctuation('<') => Value::Vector(self.value_list('>')?, ty),
+            TokenKind::Punctuation('[') => Value::Array(self.value_list(']')?, ty),
+            _ => return Err(self.expected("a value", &token)),
+        })
+    }
+
+    /// Parses the typed elements of a constant aggregate, after its opening bracket.
+    fn value_list(&mut self, close: char) -> Result<Vec<Value>, ParseError> {
+        let mut values = Vec::new();
+        if self.eat_punctuation(close) {
+            return Ok(values);
+        }
+        loop {
+            values.push(self.typed_value()?);
+            if !self.eat_punctuation(',') {
+                self.expect_punctuation(close)?;
+                return Ok(values);
+            }
+        }
+    }
+
+    fn metadata_definition(&mut self, name: &str) -> Result<(), ParseError> {
+        self.expect_punctuation('=')?;
+        let Ok(index) = name.parse::<usize>() else {
+            self.expect_punctuation('!')?;
+            self.expect_punctuation('{')?;
+            let operands = self.metadata_list()?;
+            self.module
+                .named_metadata
+                .push((name.to_string(), operands));
+            return Ok(());
+        };
+        let distinct = self.eat_word("distinct");
+        let node = match self.metadata()? {
+            Metadata::Tuple(elements) => MetadataNode::new(elements),
+            Metadata::Specialized(name, fields) => MetadataNode::specialized(
+                &name,
+                fields
+                    .iter()
+                    .map(|(field, value)| (field.as_str(), value.clone()))
+                    .collect(),
+            ),
+            _ => return Err(self.error_before("expected a metadata node")),
+        };
+        if self.nodes.iter().any(|(x, _)| *x == index) {
+            return Err(self.error_before(&format!("metadata !{} is defined twice", index)));
+        }
+        self.nodes.push((
+            index,
+            match distinct {
+                true => node.distinct(),
+                false => node,
+            },
+        ));
+        Ok(())
+    }
+
+    fn metadata(&mut self) -> Result<Metadata, ParseError> {
+        match self.peek().clone() {
+            TokenKind::Metadata(name) => {
+                self.next();
+                if let Ok(index) = name.parse::<usize>() {
+                    return Ok(Metadata::Reference(index));
+                }
+                self.expect_punctuation('(')?;
+                let mut fields = Vec::new();
+                if !self.eat_punctuation(')') {
+                    loop {
+                        let field = match self.next().kind {
+                            TokenKind::Label(field) => field,
+                            _ => return Err(self.error_before("expected a field name")),
+                        };
+                        fields.push((field, self.field()?));
+                        if !self.eat_punctuation(',') {
+                            self.expect_punctuation(')')?;
+                            break;
+                        }
+                    }
+                }
+                Ok(Metadata::Specialized(name, fields))
+            }
+            TokenKind::MetadataString(bytes) => {
+                self.next();
+                Ok(Metadata::String(
+                    String::from_utf8_lossy(&bytes).into_owned(),
+                ))
+            }
+            TokenKind::Punctuation('!') => {
+                self.next();
+                self.expect_punctuation('{')?;
+                Ok(Metadata::Tuple(self.metadata_list()?))
+            }
+            TokenKind::Word(word) if word == "null" => {
+                self.next();
+                Ok(Metadata::Null)
+            }
+            _ => Ok(Metadata::Value(self.typed_value()?)),
+        }
+    }
+
+    /// Parses the value of a field of a specialized node, where strings have no leading `!`
+    /// and anything else that is not metadata is an untyped literal.
+    fn field(&mut self) -> Result<Metadata, ParseError> {
+        match self.peek().clone() {
+            TokenKind::String(bytes) => {
+                self.next();
+                Ok(Metadata::String(
+                    String::from_utf8_lossy(&bytes).into_owned(),
+                ))
+            }
+            TokenKind::Metadata(_) | TokenKind::MetadataString(_) | TokenKind::Punctuation('!') => {
+                self.metadata()
+            }
+            TokenKind::Word(word) if word == "null" => self.metadata(),
+            _ => {
+                let mut parts = Vec::new();
+                loop {
+                    match self.peek().clone() {
+                        TokenKind::Punctuation(',' | ')') | TokenKind::End => break,
+                        TokenKind::Word(part) | TokenKind::Number(part) => parts.push(part),
+                        TokenKind::Punctuation(character) => parts.push(character.to_string()),
+                        kind => return Err(self.expected_kind("a literal", &kind)),
+                    }
+                    self.next();
+                }
+                if parts.is_empty() {
+                    return Err(self.error("expected a literal"));
+                }
+                Ok(Metadata::Literal(parts.join(" ")))
+            }
+        }
+    }
+
+    /// Parses the operands of a metadata tuple, after its opening brace.
+    fn metadata_list(&mut self) -> Result<Vec<Metadata>, ParseError> {
+        let mut operands = Vec::new();
+        if self.eat_punctuation('}') {
+            return Ok(operands);
+        }
+        loop {
+            operands.push(self.metadata()?);
+            if !self.eat_punctuation(',') {
+                self.expect_punctuation('}')?;
+                return Ok(operands);
+            }
+        }
+    }
+
+    fn peek(&self) -> &TokenKind {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> &TokenKind {
+        let index = (self.position + offset).min(self.tokens.len() - 1);
+        &self.tokens[index].kind
+    }
+
+    /// Returns the next token and moves past it, staying at the end of the input once reached.
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.position].clone();
+        if token.kind != TokenKind::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    /// Returns true if the next token is on the same line as the previous one.
+    fn on_same_line(&self) -> bool {
+        let previous = &self.tokens[self.position.saturating_sub(1)];
+        self.tokens[self.position].line == previous.line
+            && self.tokens[self.position].kind != TokenKind::End
+    }
+
+    fn eat_punctuation(&mut self, character: char) -> bool {
+        match self.peek() == &TokenKind::Punctuation(character) {
+            true => {
+                self.next();
+                true
+            }
+            false => false,
+        }
+    }
+
+    fn expect_punctuation(&mut self, character: char) -> Result<(), ParseError> {
+        match self.eat_punctuation(character) {
+            true => Ok(()),
+            false => Err(self.expected_kind(&format!("'{}'", character), &self.peek().clone())),
+        }
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        match self.peek() {
+            TokenKind::Word(next) if next == word => {
+                self.next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<(), ParseError> {
+        match self.eat_word(word) {
+            true => Ok(()),
+            false => Err(self.expected_kind(&format!("'{}'", word), &self.peek().clone())),
+        }
+    }
+
+    fn word(&mut self) -> Result<String, ParseError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Word(word) => Ok(word),
+            _ => Err(self.expected("a keyword", &token)),
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> Result<T, ParseError> {
+        let token = self.next();
+        match &token.kind {
+            TokenKind::Number(number) => number
+                .parse()
+                .map_err(|_| self.expected("a number", &token)),
+            _ => Err(self.expected("a number", &token)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::String(bytes) => Ok(String::from_utf8_lossy(&bytes).into_owned()),
+            _ => Err(self.expected("a string", &token)),
+        }
+    }
+
+    fn local(&mut self) -> Result<String, ParseError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Local(name) => Ok(name),
+            _ => Err(self.expected("a local name", &token)),
+        }
+    }
+
+    fn global(&mut self) -> Result<String, ParseError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Global(name) => Ok(name),
+            _ => Err(self.expected("a global name", &token)),
+        }
+    }
+
+    /// Returns an error at the next token.
+    fn error(&self, message: &str) -> ParseError {
+        let token = &self.tokens[self.position];
+        self.error_at(&token.line, &token.column, message.to_string())
+    }
+
+    /// Returns an error at the token that was just parsed.
+    fn error_before(&self, message: &str) -> ParseError {
+        let token = &self.tokens[self.position.saturating_sub(1)];
+        self.error_at(&token.line, &token.column, message.to_string())
+    }
+
+    fn error_at(&self, line: &usize, column: &usize, message: String) -> ParseError {
+        ParseError {
+            line: *line,
+            column: *column,
+            message,
+        }
+    }
+
+    /// Returns an error at the token, which is not what was expected.
+    fn expected(&self, what: &str, token: &Token) -> ParseError {
+        self.error_at(
+            &token.line,
+            &token.column,
+            format!("expected {}, found {}", what, describe(&token.kind)),
+        )
+    }
+
+    /// Returns an error at the next token, which is not what was expected.
+    fn expected_kind(&self, what: &str, kind: &TokenKind) -> ParseError {
+        self.error(&format!("expected {}, found {}", what, describe(kind)))
+    }
+}
+
+fn binary_operator(opcode: &str) -> Option<BinaryOperator> {
+    Some(match opcode {
+        "add" => BinaryOperator::IntegerAdd,
+        "sub" => BinaryOperator::IntegerSub,
+        "mul" => BinaryOperator::IntegerMul,
+        "sdiv" => BinaryOperator::IntegerSignedDiv,
+        "udiv" => BinaryOperator::IntegerUnsignedDiv,
+        "srem" => BinaryOperator::IntegerSignedRem,
+        "urem" => BinaryOperator::IntegerUnsignedRem,
+        "shl" => BinaryOperator::ShiftLeft,
+        "lshr" => BinaryOperator::LogicalShiftRight,
+        "ashr" => BinaryOperator::ArithmeticShiftRight,
+        "and" => BinaryOperator::And,
+        "or" => BinaryOperator::Or,
+        "xor" => BinaryOperator::Xor,
+        "fadd" => BinaryOperator::FloatAdd,
+        "fsub" => BinaryOperator::FloatSub,
+        "fmul" => BinaryOperator::FloatMul,
+        "fdiv" => BinaryOperator::FloatDiv,
+        "frem" => BinaryOperator::FloatRem,
+        _ => return None,
+    })
+}
+
+fn atomicrmw_operator(operation: &str) -> Option<AtomicRmwOperator> {
+    Some(match operation {
+        "xchg" => AtomicRmwOperator::Exchange,
+        "add" => AtomicRmwOperator::Add,
+        "sub" => AtomicRmwOperator::Sub,
+        "and" => AtomicRmwOperator::And,
+        "nand" => AtomicRmwOperator::Nand,
+        "or" => AtomicRmwOperator::Or,
+        "xor" => AtomicRmwOperator::Xor,
+        "max" => AtomicRmwOperator::Max,
+        "min" => AtomicRmwOperator::Min,
+        "umax" => AtomicRmwOperator::UnsignedMax,
+        "umin" => AtomicRmwOperator::UnsignedMin,
+        "fadd" => AtomicRmwOperator::FloatAdd,
+        "fsub" => AtomicRmwOperator::FloatSub,
+        "fmax" => AtomicRmwOperator::FloatMax,
+        "fmin" => AtomicRmwOperator::FloatMin,
+        "uinc_wrap" => AtomicRmwOperator::UnsignedIncrementWrap,
+        "udec_wrap" => AtomicRmwOperator::UnsignedDecrementWrap,
+        "usub_cond" => AtomicRmwOperator::UnsignedSubtractConditional,
+        "usub_sat" => AtomicRmwOperator::UnsignedSubtractSaturate,
+        _ => return None,
+    })
+}
+
+fn numbered_convention(name: &str) -> Option<u32> {
+    Some(match name {
+        "ghccc" => 10,
+        "webkit_jscc" => 12,
+        "anyregcc" => 13,
+        "preserve_mostcc" => 14,
+        "preserve_allcc" => 15,
+        "swiftcc" => 16,
+        "cxx_fast_tlscc" => 17,
+        "cfguard_checkcc" => 19,
+        "swifttailcc" => 20,
+        "x86_stdcallcc" => 64,
+        "x86_fastcallcc" => 65,
+        "arm_apcscc" => 66,
+        "arm_aapcscc" => 67,
+        "arm_aapcs_vfpcc" => 68,
+        "x86_thiscallcc" => 70,
+        "x86_64_sysvcc" => 78,
+        "win64cc" => 79,
+        "x86_vectorcallcc" => 80,
+        _ => return None,
+    })
+}
+
+fn cast_operator(opcode: &str) -> Option<CastOperator> {
+    Some(match opcode {
+        "trunc" => CastOperator::Trunc,
+        "zext" => CastOperator::ZeroExtend,
+        "sext" => CastOperator::SignExtend,
+        "fptrunc" => CastOperator::FloatTrunc,
+        "fpext" => CastOperator::FloatExtend,
+        "fptoui" => CastOperator::FloatToUnsigned,
+        "fptosi" => CastOperator::FloatToSigned,
+        "uitofp" => CastOperator::UnsignedToFloat,
+        "sitofp" => CastOperator::SignedToFloat,
+        "ptrtoint" => CastOperator::PtrToInt,
+        "inttoptr" => CastOperator::IntToPtr,
+        "bitcast" => CastOperator::BitCast,
+        "addrspacecast" => CastOperator::AddrSpaceCast,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debug::{DIBuilder, DwarfEncoding};
+    use crate::error::ParseError;
+    use crate::fixtures::{self, llvm_tool};
+    use crate::metadata::{Metadata, MetadataNode};
+    use crate::module::{FunctionBody, GlobalFunction, Module};
+    use crate::types::Types;
+    use crate::value::{Value, Values};
+    use crate::IRComponent;
+    use std::string::String;
+    use std::vec;
+
+    #[test]
+    fn parse_disassembled_module() {
+        // written by llvm-dis, which prints floating points with an exponent
+        let text = "; ModuleID = 'shim.bc'
+source_filename = \"shim.ll\"
+
+@.str = private unnamed_addr constant [6 x i8] c\"Hello\\00\", align 1
+@half = global double 5.000000e-01
+@big = global float 1.000000e+10
+@first = global ptr getelementptr inbounds ([6 x i8], ptr @.str, i64 0, i64 1)
+@errno = external thread_local global i32
+@vec = global <2 x double> <double 1.000000e+00, double 2.500000e+00>
+
+define double @scale(double %x) {
+entry:
+  %y = fmul double %x, 1.500000e+00
+  ret double %y
+}
+";
+        let module = Module::parse(text).unwrap();
+        assert_eq!(
+            module.vars[1].value(),
+            Some(&Values::float("0.5", Types::fp64()))
+        );
+        assert_eq!(module.vars[2].emit(), "@big = global float 10000000000.0");
+        assert_eq!(
+            module.vars[3].value(),
+            Some(&Values::inbounds_getelementptr(
+                Types::array(6, Types::integer(8)),
+                module.vars[0].reference(),
+                vec![Values::integer("0", 64), Values::integer("1", 64)],
+            ))
+        );
+        assert_eq!(
+            module.vars[4].emit(),
+            "@errno = external thread_local global i32"
+        );
+        assert_eq!(
+            module.vars[5].emit(),
+            "@vec = global <2 x double> <double 1.0, double 2.5>"
+        );
+        assert_eq!(
+            module.functions[0].emit(),
+            "define double @scale(double %x) { entry: %y = fmul double %x, 1.5 ret double %y }"
+        );
+    }
+
+    /// Parses the text of the module, then checks that it gives the module again.
+    fn round_trip(module: &Module) -> Module {
+        let parsed = Module::parse(&module.emit()).unwrap();
+        assert_eq!(parsed.emit(), module.emit());
+        assert_eq!(&parsed, module);
+        parsed
+    }
+
+    #[test]
+    fn round_trip_globals() {
+        let mut module = fixtures::globals_module();
+        let flag = module.add_metadata(MetadataNode::new(vec![
+            Metadata::String(String::from("flag")),
+            Metadata::Value(Values::integer("1", 1)),
+        ]));
+        let pointers = module.vars.remove(3).with_metadata("flag", flag);
+        module.vars.insert(3, pointers);
+
+        let parsed = round_trip(&module);
+        assert_eq!(parsed.vars[5].value(), module.vars[5].value());
+    }
+
+    #[test]
+    fn round_trip_atomics() {
+        round_trip(&fixtures::atomics_module());
+    }
+
+    #[test]
+    fn round_trip_vectors() {
+        round_trip(&fixtures::vectors_module());
+    }
+
+    #[test]
+    fn round_trip_function_attributes() {
+        let module = fixtures::function_attributes_module();
+        let text = module.emit();
+        assert!(text.contains("define internal fastcc i32 @helper(i32 %arg0) #1 section"));
+        assert!(text.contains("attributes #0 = { noreturn nounwind cold }"));
+        round_trip(&module);
+    }
+
+    #[test]
+    fn round_trip_exceptions() {
+        let module = fixtures::exceptions_module();
+        assert!(module.verify().is_ok());
+        let text = module.emit();
+        assert!(text.contains("invoke fastcc void @cleanup() to label %bb2 unwind label %bb3"));
+        round_trip(&module);
+    }
+
+    #[test]
+    fn round_trip_jump_tables() {
+        round_trip(&fixtures::jump_tables_module());
+    }
+
+    #[test]
+    fn round_trip_constant_expressions() {
+        let module = fixtures::constant_expressions_module();
+        let parsed = round_trip(&module);
+        assert_eq!(parsed.vars[1].value(), module.vars[1].value());
+        assert_eq!(parsed.vars[2].value(), module.vars[2].value());
+    }
+
+    #[test]
+    fn round_trip_inline_asm() {
+        round_trip(&fixtures::inline_asm_module());
+    }
+
+    #[test]
+    fn round_trip_functions() {
+        let module = fixtures::functions_module();
+        let parsed = round_trip(&module);
+        let (body, original) = (
+            parsed.functions[3].get_body().unwrap(),
+            module.functions[3].get_body().unwrap(),
+        );
+        for (id, original_id) in body.instructions().into_iter().zip(original.instructions()) {
+            assert_eq!(
+                body.instruction(id).kind(),
+                original.instruction(original_id).kind()
+            );
+        }
+    }
+
+    #[test]
+    fn parse_disassembled_fixtures() {
+        let modules = [
+            fixtures::globals_module(),
+            fixtures::functions_module(),
+            fixtures::atomics_module(),
+            fixtures::vectors_module(),
+            fixtures::function_attributes_module(),
+            fixtures::exceptions_module(),
+            fixtures::jump_tables_module(),
+            fixtures::constant_expressions_module(),
+            fixtures::inline_asm_module(),
+        ];
+        for module in modules {
+            let Some(assembled) = llvm_tool("llvm-as", module.emit().as_bytes()) else {
+                return;
+            };
+            let text = String::from_utf8(llvm_tool("llvm-dis", &assembled).unwrap()).unwrap();
+            let parsed = Module::parse(&text).unwrap_or_else(|e| panic!("{}\n{}", e, text));
+            // LLVM must read the parsed module as the one it wrote
+            let reassembled = llvm_tool("llvm-as", parsed.emit().as_bytes()).unwrap();
+            let disassembled = llvm_tool("llvm-dis", &reassembled).unwrap();
+            assert_eq!(String::from_utf8(disassembled).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn round_trip_debug_info() {
+        let mut module = Module::new();
+        let builder = DIBuilder::new(&mut module, "main.lotl", "/src", "lotl");
+        let int = builder.basic_type("i32", 32, DwarfEncoding::Signed);
+        let ty = builder.subroutine_type(Some(int.clone()), vec![]);
+        let subprogram = builder.subprogram("main", 1, ty);
+        let variable = builder.local_variable("x", subprogram.clone(), 2, int);
+        let location = builder.location(2, 5, subprogram.clone());
+
+        let body = FunctionBody::new(|block| {
+            block.set_debug_location(Some(location.clone()));
+            let x = block.alloca(Types::integer(32));
+            block.declare_variable(x.clone(), variable, location);
+            block.ret(Values::integer("0", 32));
+        });
+        module.functions.push(
+            GlobalFunction::new("main", Types::integer(32))
+                .with_metadata("dbg", subprogram)
+                .body(body),
+        );
+
+        let parsed = Module::parse(&module.emit()).unwrap();
+        assert_eq!(parsed.emit(), module.emit());
+        assert_eq!(parsed, module);
+    }
+
+    #[test]
+    fn parse_written_ir() {
+        let module = Module::parse(
+            "; ModuleID = 'count.ll'\n\
+            %\"Pair\" = type { i32, i32 }\n\
+            \n\
+            declare i32 @puts(ptr)\n\
+            \n\
+            define i32 @count(i32 %n) {\n\
+            entry:\n\
+            \x20 br label %loop\n\
+            loop: ; the counter\n\
+            \x20 %i = phi i32 [ 0, %entry ], [ %next, %loop ]\n\
+            \x20 %next = add i32 %i, 1\n\
+            \x20 %done = icmp_is_not_supported\n\
+            }\n",
+        );
+        assert_eq!(
+            module.map(|_| ()),
+            Err(ParseError {
+                line: 12,
+                column: 11,
+                message: String::from("unknown instruction 'icmp_is_not_supported'"),
+            })
+        );
+
+        let module = Module::parse(
+            "%\"Pair\" = type { i32, i32 }\n\
+            declare i32 @puts(ptr)\n\
+            define i32 @count(i32 %n) {\n\
+            entry:\n\
+            \x20 br label %loop\n\
+            loop: ; the counter\n\
+            \x20 %i = phi i32 [ 0, %entry ], [ %next, %loop ]\n\
+            \x20 %next = add i32 %i, 1\n\
+            \x20 %call = call i32 @puts(ptr null_is_a_word)\n\
+            }\n",
+        );
+        assert_eq!(
+            module.err().unwrap().message,
+            "expected a value, found 'null_is_a_word'"
+        );
+
+        let module = Module::parse(
+            "%\"Pair\" = type { i32, i32 }\n\
+            declare i32 @puts(ptr)\n\
+            define i32 @count(i32 %n) {\n\
+            entry:\n\
+            \x20 br label %loop\n\
+            loop: ; the counter\n\
+            \x20 %i = phi i32 [ 0, %entry ], [ %next, %loop ]\n\
+            \x20 %next = add i32 %i, 1\n\
+            \x20 %call = call i32 @puts(ptr @puts)\n\
+            \x20 ret i32 %next\n\
+            }\n",
+        )
+        .unwrap();
+        assert!(module.named_type("Pair").is_some());
+        assert_eq!(
+            module.emit(),
+            "%Pair = type {i32, i32}\n\n\
+            declare i32 @puts(ptr)\n\n\
+            define i32 @count(i32 %n) { \
+                entry: \
+                    br label %loop \
+                loop: \
+                    %i = phi i32 [ 0, %entry ], [ %next, %loop ] \
+                    %next = add i32 %i, 1 \
+                    %call = call i32 @puts(ptr @puts) \
+                    ret i32 %next \
+            }\n\n"
+        );
+        let body = module.functions[1].get_body().unwrap();
+        let call = body.block(body.blocks()[1]).instructions()[2];
+        assert_eq!(
+            body.instruction(call).kind().operands()[0],
+            &Value::Function(
+                String::from("puts"),
+                Types::function(Types::integer(32), vec![Types::pointer(0)])
+            )
+        );
+    }
+
+    #[test]
+    fn report_errors() {
+        let error = Module::parse("@x = global i32 1\n@y = globel i32 2\n")
+            .err()
+            .unwrap();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.column, 6);
+        assert_eq!(
+            error.to_string(),
+            "2:6: expected 'global' or 'constant', found 'globel'"
+        );
+
+        let error = Module::parse("@x = global %Missing zeroinitializer")
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "1:13: undefined type '%Missing'");
+
+        let error = Module::parse("define void @f() {\nentry:\n  br label %exit\n}")
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "3:12: undefined block '%exit'");
+
+        let error = Module::parse("@s = constant [1 x i8] c\"\\zz\"")
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "1:26: invalid escape in string");
+    }
+}
@@ -0,0 +1,220 @@
+use crate::error::ParseError;
+use std::format;
+use std::string::String;
+use std::vec::Vec;
+
+/// The kinds of tokens of LLVM IR text.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TokenKind {
+    /// A keyword, type or literal such as `define`, `i32` or `DW_ATE_signed`.
+    Word(String),
+    /// A number, kept as written, such as `-12` or `1.5e+00`.
+    Number(String),
+    /// A local name such as `%r0`, without the `%`.
+    Local(String),
+    /// A global name such as `@main`, without the `@`.
+    Global(String),
+    /// A metadata name such as `!dbg`, `!0` or `!DILocation`, without the `!`.
+    Metadata(String),
+    /// A quoted string, with its escapes resolved.
+    String(Vec<u8>),
+    /// A constant array of bytes, such as `c"Hello\00"`.
+    CString(Vec<u8>),
+    /// A metadata string, such as `!"foo"`.
+    MetadataString(Vec<u8>),
+    /// A block label or field name followed by a colon, such as `entry:`.
+    Label(String),
+    /// A single punctuation character, such as `=` or `{`.
+    Punctuation(char),
+    /// The end of the text.
+    End,
+}
+
+/// A token, along with where it starts.
+#[derive(Clone, Debug)]
+pub(crate) struct Token {
+    pub(crate) kind: TokenKind,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+}
+
+/// Splits LLVM IR text into tokens, leaving out whitespace and `;` comments.
+pub(crate) fn tokenize(source: &str) -> Result<Vec<Token>, ParseError> {
+    let mut lexer = Lexer {
+        bytes: source.as_bytes(),
+        position: 0,
+        line: 1,
+        line_start: 0,
+    };
+    let mut tokens = Vec::new();
+    loop {
+        lexer.skip_whitespace();
+        let (line, column) = (lexer.line, lexer.position - lexer.line_start + 1);
+        let kind = lexer.token()?;
+        let end = kind == TokenKind::End;
+        tokens.push(Token { kind, line, column });
+        if end {
+            return Ok(tokens);
+        }
+    }
+}
+
+struct Lexer<'s> {
+    bytes: &'s [u8],
+    position: usize,
+    line: usize,
+    line_start: usize,
+}
+
+/// Returns true if the byte can be part of a name or keyword.
+fn is_word(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'.' | b'-' | b'$')
+}
+
+impl Lexer<'_> {
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.position + offset).copied()
+    }
+
+    fn error(&self, message: String) -> ParseError {
+        ParseError {
+            line: self.line,
+            column: self.position - self.line_start + 1,
+            message,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.peek(0) {
+            match byte {
+                b'\n' => {
+                    self.position += 1;
+                    self.line += 1;
+                    self.line_start = self.position;
+                }
+                b';' => {
+                    while !matches!(self.peek(0), Some(b'\n') | None) {
+                        self.position += 1;
+                    }
+                }
+                byte if byte.is_ascii_whitespace() => self.position += 1,
+                _ => return,
+            }
+        }
+    }
+
+    fn token(&mut self) -> Result<TokenKind, ParseError> {
+        let Some(byte) = self.peek(0) else {
+            return Ok(TokenKind::End);
+        };
+        Ok(match byte {
+            b'%' | b'@' => {
+                self.position += 1;
+                let name = match self.peek(0) {
+                    Some(b'"') => String::from_utf8_lossy(&self.string()?).into_owned(),
+                    _ => self.word(),
+                };
+                if name.is_empty() {
+                    return Err(self.error(format!("expected a name after '{}'", byte as char)));
+                }
+                match byte {
+                    b'%' => TokenKind::Local(name),
+                    _ => TokenKind::Global(name),
+                }
+            }
+            b'!' => {
+                self.position += 1;
+                match self.peek(0) {
+                    Some(b'"') => TokenKind::MetadataString(self.string()?),
+                    Some(next) if is_word(next) => TokenKind::Metadata(self.word()),
+                    _ => TokenKind::Punctuation('!'),
+                }
+            }
+            b'"' => TokenKind::String(self.string()?),
+            b'c' if self.peek(1) == Some(b'"') => {
+                self.position += 1;
+                TokenKind::CString(self.string()?)
+            }
+            b'#' => {
+                self.position += 1;
+                TokenKind::Word(format!("#{}", self.word()))
+            }
+            byte if byte.is_ascii_digit()
+                || (byte == b'-' && self.peek(1).is_some_and(|x| x.is_ascii_digit())) =>
+            {
+                let mut number = self.word();
+                // exponents of floating points are signed, such as in `1.000000e+00`
+                if number.ends_with(['e', 'E'])
+                    && !number.starts_with("0x")
+                    && self.peek(0) == Some(b'+')
+                {
+                    self.position += 1;
+                    number.push('+');
+                    number.push_str(&self.word());
+                }
+                self.label_or(number, TokenKind::Number)
+            }
+            byte if is_word(byte) => {
+                let word = self.word();
+                self.label_or(word, TokenKind::Word)
+            }
+            b'=' | b',' | b'(' | b')' | b'{' | b'}' | b'[' | b']' | b'<' | b'>' | b'*' | b'|' => {
+                self.position += 1;
+                TokenKind::Punctuation(byte as char)
+            }
+            byte => return Err(self.error(format!("unexpected character '{}'", byte as char))),
+        })
+    }
+
+    /// Turns the word into a label if a colon follows it.
+    fn label_or(&mut self, word: String, kind: fn(String) -> TokenKind) -> TokenKind {
+        match self.peek(0) {
+            Some(b':') => {
+                self.position += 1;
+                TokenKind::Label(word)
+            }
+            _ => kind(word),
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let start = self.position;
+        while self.peek(0).is_some_and(is_word) {
+            self.position += 1;
+        }
+        String::from_utf8_lossy(&self.bytes[start..self.position]).into_owned()
+    }
+
+    /// Reads a quoted string, where `\XX` escapes a byte by its hexadecimal value.
+    fn string(&mut self) -> Result<Vec<u8>, ParseError> {
+        self.position += 1;
+        let mut bytes = Vec::new();
+        loop {
+            match self.peek(0) {
+                None | Some(b'\n') => return Err(self.error(String::from("unterminated string"))),
+                Some(b'"') => {
+                    self.position += 1;
+                    return Ok(bytes);
+                }
+                Some(b'\\') if self.peek(1) == Some(b'\\') => {
+                    bytes.push(b'\\');
+                    self.position += 2;
+                }
+                Some(b'\\') => {
+                    let digits = self
+                        .bytes
+                        .get(self.position + 1..self.position + 3)
+                        .and_then(|x| std::str::from_utf8(x).ok())
+                        .and_then(|x| u8::from_str_radix(x, 16).ok())
+                        .ok_or_else(|| self.error(String::from("invalid escape in string")))?;
+                    bytes.push(digits);
+                    self.position += 3;
+                }
+                Some(byte) => {
+                    bytes.push(byte);
+                    self.position += 1;
+                }
+            }
+        }
+    }
+}
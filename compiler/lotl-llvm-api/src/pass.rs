@@ -0,0 +1,123 @@
+mod dce;
+mod fold;
+mod mem2reg;
+mod simplify;
+
+pub use dce::DeadCodeElimination;
+pub use fold::ConstantFolding;
+pub use mem2reg::Mem2Reg;
+pub use simplify::SimplifyCfg;
+
+pub(crate) use fold::Integer;
+
+use crate::module::{FunctionBody, Module};
+use std::boxed::Box;
+use std::vec::Vec;
+
+/// Represents a transformation of function bodies, such as an optimization.
+pub trait Pass {
+    /// Returns the name of the pass, such as `mem2reg`.
+    fn name(&self) -> &'static str;
+
+    /// Runs the pass over the function body, returning true if anything changed.
+    fn run(&self, body: &mut FunctionBody) -> bool;
+}
+
+/// Runs a sequence of passes over every function of a module.
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl PassManager {
+    /// Creates a new pass manager without any passes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a pass manager running every optimization, in an order where each pass
+    /// cleans up after the ones before it.
+    pub fn optimizations() -> Self {
+        PassManager::new()
+            .with_pass(Mem2Reg)
+            .with_pass(ConstantFolding)
+            .with_pass(SimplifyCfg)
+            .with_pass(DeadCodeElimination)
+    }
+
+    /// Adds a pass, which runs after every pass added before it.
+    pub fn with_pass<P: Pass + 'static>(mut self, pass: P) -> Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    /// Returns the names of the passes, in the order they run.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.passes.iter().map(|x| x.name()).collect()
+    }
+
+    /// Runs every pass over every function body in the module, returning true if anything changed.
+    pub fn run(&self, module: &mut Module) -> bool {
+        let mut changed = false;
+        for function in &mut module.functions {
+            if let Some(body) = function.body_mut() {
+                changed |= self.run_on_function(body);
+            }
+        }
+        changed
+    }
+
+    /// Runs every pass over the function body, returning true if anything changed.
+    pub fn run_on_function(&self, body: &mut FunctionBody) -> bool {
+        let mut changed = false;
+        for pass in &self.passes {
+            changed |= pass.run(body);
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::module::{FunctionBody, GlobalFunction, Module};
+    use crate::pass::PassManager;
+    use crate::types::Types;
+    use crate::value::Values;
+    use crate::IRComponent;
+    use std::vec;
+
+    #[test]
+    fn optimize_module() {
+        let body = FunctionBody::new(|block| {
+            let counter = block.alloca(Types::integer(32));
+            block.store(Values::integer("10", 32), counter.clone());
+            let (if_true, if_false) = block.br_if_returning(Values::integer("1", 1));
+            let loaded = if_true.load(Types::integer(32), counter.clone());
+            let doubled = if_true.mul(loaded, Values::integer("2", 32));
+            if_true.store(doubled, counter.clone());
+            let merge = if_true.br_returning();
+            if_false.goto(&merge);
+            let result = merge.load(Types::integer(32), counter);
+            merge.ret(result);
+        });
+        let mut module = Module::new();
+        module
+            .functions
+            .push(GlobalFunction::new("main", Types::integer(32)).body(body));
+
+        let passes = PassManager::optimizations();
+        assert_eq!(
+            passes.names(),
+            vec!["mem2reg", "constfold", "simplifycfg", "dce"]
+        );
+        assert!(passes.run(&mut module));
+        assert_eq!(module.verify(), Ok(()));
+        assert_eq!(
+            module.emit(),
+            "define i32 @main() { \
+                entry: \
+                    ret i32 20 \
+            }\n\n"
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use crate::analysis::ControlFlowGraph;
+use crate::module::FunctionBody;
+use crate::pass::Pass;
+use crate::value::Value;
+use std::collections::{HashMap, HashSet};
+use std::vec::Vec;
+
+/// Removes blocks control never reaches, and instructions whose results are never used.
+///
+/// Instructions with side effects, such as `store` and `call`, are always kept,
+/// along with everything they use.
+pub struct DeadCodeElimination;
+
+impl Pass for DeadCodeElimination {
+    fn name(&self) -> &'static str {
+        "dce"
+    }
+
+    fn run(&self, body: &mut FunctionBody) -> bool {
+        let removed_blocks = remove_unreachable_blocks(body);
+        remove_dead_instructions(body) || removed_blocks
+    }
+}
+
+/// Removes every block unreachable from the entry, returning true if any were removed.
+pub(crate) fn remove_unreachable_blocks(body: &mut FunctionBody) -> bool {
+    let cfg = ControlFlowGraph::new(body);
+    let unreachable = body
+        .blocks()
+        .into_iter()
+        .filter(|x| !cfg.is_reachable(*x))
+        .collect::<Vec<_>>();
+    for block in &unreachable {
+        for successor in cfg.successors(*block) {
+            body.remove_incoming(*successor, *block);
+        }
+        body.remove_block(*block);
+    }
+    !unreachable.is_empty()
+}
+
+/// Removes every instruction that has no side effects and is not used by a live instruction.
+fn remove_dead_instructions(body: &mut FunctionBody) -> bool {
+    let instructions = body.instructions();
+    let mut definitions = HashMap::new();
+    for id in &instructions {
+        if let Some(Value::LocalIdentifier(name, _)) = body.instruction(*id).result() {
+            definitions.insert(name.clone(), *id);
+        }
+    }
+
+    // mark everything reachable through operands from an instruction with side effects
+    let mut live = HashSet::new();
+    let mut pending = instructions
+        .iter()
+        .copied()
+        .filter(|x| !body.instruction(*x).kind().is_pure())
+        .collect::<Vec<_>>();
+    while let Some(id) = pending.pop() {
+        if !live.insert(id) {
+            continue;
+        }
+        for operand in body.instruction(id).kind().operands() {
+            if let Value::LocalIdentifier(name, _) = operand {
+                pending.extend(definitions.get(name));
+            }
+        }
+    }
+
+    let mut changed = false;
+    for id in instructions {
+        if !live.contains(&id) {
+            body.remove_instruction(id);
+            changed = true;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::module::{FunctionBody, GlobalFunction};
+    use crate::pass::{DeadCodeElimination, Pass};
+    use crate::types::Types;
+    use crate::value::Values;
+    use crate::IRComponent;
+    use std::vec;
+
+    #[test]
+    fn remove_dead_code() {
+        let mut body = FunctionBody::new(|block| {
+            let pointer = block.alloca(Types::integer(32));
+            let unused = block.add(Values::integer("1", 32), Values::integer("2", 32));
+            block.mul(unused, Values::integer("3", 32));
+            let stored = block.sub(Values::integer("4", 32), Values::integer("5", 32));
+            block.store(stored, pointer);
+            let exit = block.br_returning();
+            let orphan = block.child();
+            let merged = exit.phi(
+                Types::integer(32),
+                vec![
+                    (Values::integer("0", 32), &block),
+                    (Values::integer("1", 32), &orphan),
+                ],
+            );
+            exit.ret(merged.value());
+            orphan.goto(&exit);
+        });
+        let before = GlobalFunction::new("main", Types::integer(32)).body(body.clone());
+        assert_eq!(
+            before.emit(),
+            "define i32 @main() { \
+                entry: \
+                    %r0 = alloca i32 \
+                    %r1 = add i32 1, 2 \
+                    %r2 = mul i32 %r1, 3 \
+                    %r3 = sub i32 4, 5 \
+                    store i32 %r3, ptr %r0 \
+                    br label %bb0 \
+                bb0: \
+                    %r4 = phi i32 [ 0, %entry ], [ 1, %bb1 ] \
+                    ret i32 %r4 \
+                bb1: \
+                    br label %bb0 \
+            }"
+        );
+
+        assert!(DeadCodeElimination.run(&mut body));
+        let after = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(
+            after.emit(),
+            "define i32 @main() { \
+                entry: \
+                    %r0 = alloca i32 \
+                    %r3 = sub i32 4, 5 \
+                    store i32 %r3, ptr %r0 \
+                    br label %bb0 \
+                bb0: \
+                    %r4 = phi i32 [ 0, %entry ] \
+                    ret i32 %r4 \
+            }"
+        );
+    }
+}
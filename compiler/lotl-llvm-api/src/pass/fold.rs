@@ -0,0 +1,528 @@
+use crate::instruction::{
+    element_type, BinaryFlags, BinaryOperator, CastOperator, FastMathFlags, InstructionKind,
+};
+use crate::module::FunctionBody;
+use crate::pass::Pass;
+use crate::types::Type;
+use crate::value::{half_bits, half_value, is_decimal, Value};
+use std::format;
+use std::string::ToString;
+
+/// Replaces instructions operating on constants by their result, such as `add i32 10, 20`.
+///
+/// Binary operations, casts between integer and floating-point types, `extractvalue`,
+/// `extractelement` and `select` are folded.
+/// Operations producing poison or undefined behavior, like dividing by zero, are left alone,
+/// as are operations on `fp128`, which cannot be computed exactly.
+pub struct ConstantFolding;
+
+impl Pass for ConstantFolding {
+    fn name(&self) -> &'static str {
+        "constfold"
+    }
+
+    fn run(&self, body: &mut FunctionBody) -> bool {
+        let mut changed = false;
+        for id in body.instructions() {
+            let instruction = body.instruction(id);
+            let (Some(result), Some(folded)) = (instruction.result(), fold(instruction.kind()))
+            else {
+                continue;
+            };
+            let result = result.clone();
+            // later instructions see the constant right away, so chains fold in a single run
+            body.replace_uses(&result, &folded);
+            body.remove_instruction(id);
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Returns the constant an instruction evaluates to, if it can be computed.
+fn fold(kind: &InstructionKind) -> Option<Value> {
+    match kind {
+        InstructionKind::Binary {
+            operator,
+            flags,
+            lhs,
+            rhs,
+        } if operator.is_floating_point() => fold_float_binary(
+            *operator,
+            flags.fast_math,
+            Float::new(lhs)?,
+            Float::new(rhs)?,
+        )
+        .map(|x| x.value()),
+        InstructionKind::Binary {
+            operator,
+            flags,
+            lhs,
+            rhs,
+        } => fold_binary(*operator, *flags, Integer::new(lhs)?, Integer::new(rhs)?)
+            .map(|x| x.value()),
+        InstructionKind::Cast {
+            operator: CastOperator::FloatTrunc | CastOperator::FloatExtend,
+            value,
+            target,
+        } => Some(Float::rounded(Float::new(value)?.value, target)?.value()),
+        InstructionKind::Cast {
+            operator: operator @ (CastOperator::FloatToUnsigned | CastOperator::FloatToSigned),
+            value,
+            target,
+        } => fold_float_to_integer(*operator, Float::new(value)?, target).map(|x| x.value()),
+        InstructionKind::Cast {
+            operator: operator @ (CastOperator::UnsignedToFloat | CastOperator::SignedToFloat),
+            value,
+            target,
+        } => {
+            let value = Integer::new(value)?;
+            // converting straight to the narrower types avoids rounding twice
+            let value = match (operator, target) {
+                (CastOperator::UnsignedToFloat, Type::Half | Type::Float) => {
+                    value.bits as f32 as f64
+                }
+                (CastOperator::UnsignedToFloat, _) => value.bits as f64,
+                (_, Type::Half | Type::Float) => value.signed() as f32 as f64,
+                _ => value.signed() as f64,
+            };
+            Some(Float::rounded(value, target)?.value())
+        }
+        InstructionKind::Cast {
+            operator,
+            value,
+            target,
+        } => fold_cast(*operator, Integer::new(value)?, target).map(|x| x.value()),
+        InstructionKind::ExtractValue { aggregate, index } => match aggregate {
+            Value::Structure(elements, _) | Value::Array(elements, _) => {
+                elements.get(*index).cloned()
+            }
+            Value::CString(bytes, _) => Some(
+                Integer {
+                    bits: *bytes.get(*index)? as u128,
+                    width: 8,
+                }
+                .value(),
+            ),
+            Value::ZeroInitializer(ty) => match element_type(ty, *index).ok()? {
+                Type::Integer(width) => Some(Integer { bits: 0, width }.value()),
+                element => Some(Value::ZeroInitializer(element)),
+            },
+            _ => None,
+        },
+        InstructionKind::ExtractElement { vector, index } => {
+            let index = usize::try_from(Integer::new(index)?.bits).ok()?;
+            match vector {
+                Value::Vector(elements, _) => elements.get(index).cloned(),
+                _ => None,
+            }
+        }
+        InstructionKind::Select {
+            condition,
+            if_true,
+            if_false,
+        } => match Integer::new(condition)?.bits {
+            0 => Some(if_false.clone()),
+            _ => Some(if_true.clone()),
+        },
+        _ => None,
+    }
+}
+
+/// An integer constant of at most 128 bits, holding the bits of its two's complement form.
+#[derive(Clone, Copy)]
+pub(crate) struct Integer {
+    pub(crate) bits: u128,
+    width: u32,
+}
+
+impl Integer {
+    /// Reads an integer constant, or returns nothing if the value is not one.
+    pub(crate) fn new(value: &Value) -> Option<Integer> {
+        let Value::Number(contents, Type::Integer(width)) = value else {
+            return None;
+        };
+        if *width == 0 || *width > 128 {
+            return None;
+        }
+        let bits = match contents.parse::<i128>() {
+            Ok(signed) => signed as u128,
+            Err(_) => contents.parse::<u128>().ok()?,
+        };
+        Some(Integer::from_bits(bits, *width))
+    }
+
+    fn from_bits(bits: u128, width: u32) -> Integer {
+        Integer {
+            bits: bits & mask(width),
+            width,
+        }
+    }
+
+    /// Interprets the bits as a signed integer.
+    fn signed(&self) -> i128 {
+        let unused = 128 - self.width;
+        ((self.bits << unused) as i128) >> unused
+    }
+
+    /// Writes the integer as a constant. Booleans are written as 0 or 1,
+    /// every other width as a signed number, like LLVM does.
+    fn value(&self) -> Value {
+        let contents = match self.width {
+            1 => self.bits.to_string(),
+            _ => self.signed().to_string(),
+        };
+        Value::Number(contents, Type::Integer(self.width))
+    }
+
+    /// Returns true if the exact unsigned result of an operation fits in the width.
+    fn fits_unsigned(&self, result: Option<u128>) -> bool {
+        result.is_some_and(|x| x <= mask(self.width))
+    }
+
+    /// Returns true if the exact signed result of an operation fits in the width.
+    fn fits_signed(&self, result: Option<i128>) -> bool {
+        let min = i128::MIN >> (128 - self.width);
+        let max = i128::MAX >> (128 - self.width);
+        result.is_some_and(|x| x >= min && x <= max)
+    }
+}
+
+fn mask(width: u32) -> u128 {
+    u128::MAX >> (128 - width)
+}
+
+/// Computes a binary operation on integers, or returns nothing if the result would be poison
+/// or the operation has undefined behavior.
+fn fold_binary(
+    operator: BinaryOperator,
+    flags: BinaryFlags,
+    lhs: Integer,
+    rhs: Integer,
+) -> Option<Integer> {
+    let width = lhs.width;
+    let (a, b) = (lhs.bits, rhs.bits);
+    let (signed_a, signed_b) = (lhs.signed(), rhs.signed());
+    let signed_overflow = signed_a == i128::MIN >> (128 - width) && signed_b == -1;
+    let bits = match operator {
+        BinaryOperator::IntegerAdd => {
+            if (flags.has_no_unsigned_wrap() && !lhs.fits_unsigned(a.checked_add(b)))
+                || (flags.has_no_signed_wrap() && !lhs.fits_signed(signed_a.checked_add(signed_b)))
+            {
+                return None;
+            }
+            a.wrapping_add(b)
+        }
+        BinaryOperator::IntegerSub => {
+            if (flags.has_no_unsigned_wrap() && a < b)
+                || (flags.has_no_signed_wrap() && !lhs.fits_signed(signed_a.checked_sub(signed_b)))
+            {
+                return None;
+            }
+            a.wrapping_sub(b)
+        }
+        BinaryOperator::IntegerMul => {
+            if (flags.has_no_unsigned_wrap() && !lhs.fits_unsigned(a.checked_mul(b)))
+                || (flags.has_no_signed_wrap() && !lhs.fits_signed(signed_a.checked_mul(signed_b)))
+            {
+                return None;
+            }
+            a.wrapping_mul(b)
+        }
+        BinaryOperator::IntegerUnsignedDiv | BinaryOperator::IntegerUnsignedRem if b == 0 => {
+            return None
+        }
+        BinaryOperator::IntegerSignedDiv | BinaryOperator::IntegerSignedRem
+            if b == 0 || signed_overflow =>
+        {
+            return None
+        }
+        BinaryOperator::IntegerUnsignedDiv => {
+            if flags.is_exact() && a % b != 0 {
+                return None;
+            }
+            a / b
+        }
+        BinaryOperator::IntegerSignedDiv => {
+            if flags.is_exact() && signed_a % signed_b != 0 {
+                return None;
+            }
+            (signed_a / signed_b) as u128
+        }
+        BinaryOperator::IntegerUnsignedRem => a % b,
+        BinaryOperator::IntegerSignedRem => (signed_a % signed_b) as u128,
+        // shifting by the width or more produces poison
+        BinaryOperator::ShiftLeft
+        | BinaryOperator::LogicalShiftRight
+        | BinaryOperator::ArithmeticShiftRight
+            if b >= width as u128 =>
+        {
+            return None
+        }
+        BinaryOperator::ShiftLeft => {
+            let shifted = Integer::from_bits(a << b, width);
+            if (flags.has_no_unsigned_wrap() && shifted.bits >> b != a)
+                || (flags.has_no_signed_wrap() && shifted.signed() >> b != signed_a)
+            {
+                return None;
+            }
+            shifted.bits
+        }
+        BinaryOperator::LogicalShiftRight | BinaryOperator::ArithmeticShiftRight
+            if flags.is_exact() && b > 0 && a & mask(b as u32) != 0 =>
+        {
+            return None
+        }
+        BinaryOperator::LogicalShiftRight => a >> b,
+        BinaryOperator::ArithmeticShiftRight => (signed_a >> b) as u128,
+        BinaryOperator::And => a & b,
+        BinaryOperator::Or => a | b,
+        BinaryOperator::Xor => a ^ b,
+        _ => return None,
+    };
+    Some(Integer::from_bits(bits, width))
+}
+
+/// Computes a conversion between integer types.
+fn fold_cast(operator: CastOperator, value: Integer, target: &Type) -> Option<Integer> {
+    let Type::Integer(width) = *target else {
+        return None;
+    };
+    if width == 0 || width > 128 {
+        return None;
+    }
+    match operator {
+        CastOperator::Trunc | CastOperator::ZeroExtend => {
+            Some(Integer::from_bits(value.bits, width))
+        }
+        CastOperator::SignExtend => Some(Integer::from_bits(value.signed() as u128, width)),
+        _ => None,
+    }
+}
+
+/// A `half`, `float` or `double` constant, held as a `double` rounded to its type.
+#[derive(Clone)]
+struct Float {
+    value: f64,
+    ty: Type,
+}
+
+impl Float {
+    /// Reads a floating-point constant, or returns nothing if the value is not one
+    /// of a type that can be computed exactly.
+    fn new(value: &Value) -> Option<Float> {
+        let Value::Number(_, ty) = value else {
+            return None;
+        };
+        Float::rounded(value.float_value()?, ty)
+    }
+
+    /// Rounds the number to the nearest one of the type.
+    ///
+    /// Results of arithmetic on `double` are rounded to `float` and `half` without
+    /// changing them, as each format has more than twice the precision of the next.
+    fn rounded(value: f64, ty: &Type) -> Option<Float> {
+        let value = match ty {
+            Type::Half => half_value(half_bits(value as f32) as u16) as f64,
+            Type::Float => value as f32 as f64,
+            Type::Double => value,
+            _ => return None,
+        };
+        Some(Float {
+            value,
+            ty: ty.clone(),
+        })
+    }
+
+    /// Writes the number as a constant, in decimal when it has an exact short form,
+    /// and in hexadecimal otherwise.
+    fn value(&self) -> Value {
+        let decimal = format!("{:?}", self.value);
+        let contents = match &self.ty {
+            Type::Half => format!("0xH{:04X}", half_bits(self.value as f32)),
+            _ if is_decimal(&decimal) => decimal,
+            _ => format!("0x{:016X}", self.value.to_bits()),
+        };
+        Value::Number(contents, self.ty.clone())
+    }
+}
+
+/// Computes a binary operation on floating-point numbers, or returns nothing if the result
+/// would be poison because of the `nnan` or `ninf` fast-math flags.
+fn fold_float_binary(
+    operator: BinaryOperator,
+    fast_math: FastMathFlags,
+    lhs: Float,
+    rhs: Float,
+) -> Option<Float> {
+    let (a, b) = (lhs.value, rhs.value);
+    let result = match operator {
+        BinaryOperator::FloatAdd => a + b,
+        BinaryOperator::FloatSub => a - b,
+        BinaryOperator::FloatMul => a * b,
+        BinaryOperator::FloatDiv => a / b,
+        BinaryOperator::FloatRem => a % b,
+        _ => return None,
+    };
+    let result = Float::rounded(result, &lhs.ty)?;
+    let values = [a, b, result.value];
+    if (fast_math.no_nans && values.iter().any(|x| x.is_nan()))
+        || (fast_math.no_infs && values.iter().any(|x| x.is_infinite()))
+    {
+        return None;
+    }
+    Some(result)
+}
+
+/// Converts a floating-point number to an integer, rounding towards zero,
+/// or returns nothing if the result would be poison because it does not fit.
+fn fold_float_to_integer(operator: CastOperator, value: Float, target: &Type) -> Option<Integer> {
+    let Type::Integer(width) = *target else {
+        return None;
+    };
+    if width == 0 || width > 128 || !value.value.is_finite() {
+        return None;
+    }
+    let truncated = value.value.trunc();
+    let bits = match operator {
+        CastOperator::FloatToUnsigned => {
+            if truncated < 0.0 || truncated >= 2f64.powi(width as i32) {
+                return None;
+            }
+            truncated as u128
+        }
+        _ => {
+            let min = (i128::MIN >> (128 - width)) as f64;
+            if truncated < min || truncated >= -min {
+                return None;
+            }
+            truncated as i128 as u128
+        }
+    };
+    Some(Integer::from_bits(bits, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::instruction::{BinaryFlags, BinaryOperator, FastMathFlags};
+    use crate::module::{FunctionBody, GlobalFunction};
+    use crate::pass::{ConstantFolding, Pass};
+    use crate::types::Types;
+    use crate::value::Values;
+    use crate::IRComponent;
+    use std::vec;
+
+    #[test]
+    fn fold_constants() {
+        let mut body = FunctionBody::new(|block| {
+            let sum = block.add(Values::integer("10", 32), Values::integer("20", 32));
+            let product = block.mul(sum, Values::integer("9", 32));
+            let truncated = block.trunc(product, Types::integer(8));
+            block.binop_with_flags(
+                BinaryOperator::IntegerAdd,
+                truncated.clone(),
+                Values::integer("120", 8),
+                BinaryFlags::new().no_signed_wrap(),
+            );
+            let wrapped = block.add(truncated, Values::integer("120", 8));
+            let extended = block.sext(wrapped, Types::integer(32));
+            let element = block.extractvalue(
+                Values::structure(vec![Values::integer("10", 32), Values::integer("20", 64)]),
+                0,
+            );
+            let quotient = block.sdiv(extended, element);
+            let undefined = block.udiv(quotient, Values::integer("0", 32));
+            block.ret(undefined);
+        });
+        let before = GlobalFunction::new("main", Types::integer(32)).body(body.clone());
+        assert_eq!(
+            before.emit(),
+            "define i32 @main() { \
+                entry: \
+                    %r0 = add i32 10, 20 \
+                    %r1 = mul i32 %r0, 9 \
+                    %r2 = trunc i32 %r1 to i8 \
+                    %r3 = add nsw i8 %r2, 120 \
+                    %r4 = add i8 %r2, 120 \
+                    %r5 = sext i8 %r4 to i32 \
+                    %r6 = extractvalue {i32, i64} {i32 10, i64 20}, 0 \
+                    %r7 = sdiv i32 %r5, %r6 \
+                    %r8 = udiv i32 %r7, 0 \
+                    ret i32 %r8 \
+            }"
+        );
+
+        assert!(ConstantFolding.run(&mut body));
+        let after = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(
+            after.emit(),
+            "define i32 @main() { \
+                entry: \
+                    %r3 = add nsw i8 14, 120 \
+                    %r8 = udiv i32 -12, 0 \
+                    ret i32 %r8 \
+            }"
+        );
+
+        let mut body = FunctionBody::new(|block| {
+            let sum = block.fadd(
+                Values::float("1.5", Types::fp64()),
+                Values::float("2.25", Types::fp64()),
+            );
+            let product = block.fmul(sum, Values::float("2.0", Types::fp64()));
+            let truncated = block.fptrunc(product, Types::fp32());
+            let zero = Values::float("0.0", Types::fp32());
+            let infinite = block.fdiv(truncated.clone(), zero.clone());
+            block.binop_with_flags(
+                BinaryOperator::FloatDiv,
+                truncated.clone(),
+                zero,
+                BinaryFlags::new().fast_math(FastMathFlags::new().no_infs()),
+            );
+            let slot = block.alloca(Types::fp64());
+            block.store(block.fpext(infinite, Types::fp64()), slot.clone());
+            let integer = block.fptosi(truncated, Types::integer(32));
+            block.fptoui(Values::float("-1.0", Types::fp64()), Types::integer(32));
+            block.store(block.uitofp(Values::integer("-1", 8), Types::fp16()), slot);
+            block.ret(block.sitofp(integer, Types::fp64()));
+        });
+        assert!(ConstantFolding.run(&mut body));
+        let after = GlobalFunction::new("main", Types::fp64()).body(body);
+        assert_eq!(
+            after.emit(),
+            "define double @main() { \
+                entry: \
+                    %r4 = fdiv ninf float 7.5, 0.0 \
+                    %r5 = alloca double \
+                    store double 0x7FF0000000000000, ptr %r5 \
+                    %r8 = fptoui double -1.0 to i32 \
+                    store half 0xH5BF8, ptr %r5 \
+                    ret double 7.0 \
+            }"
+        );
+    }
+
+    #[test]
+    fn fold_vector_elements() {
+        let mut body = FunctionBody::new(|block| {
+            let vector = Values::vector(
+                Types::integer(16),
+                vec![Values::integer("7", 16), Values::integer("-3", 16)],
+            );
+            let element = block.extractelement(vector.clone(), Values::integer("1", 64));
+            let past_end = block.extractelement(vector, Values::integer("2", 64));
+            block.ret(block.add(element, past_end));
+        });
+        assert!(ConstantFolding.run(&mut body));
+        let after = GlobalFunction::new("main", Types::integer(16)).body(body);
+        assert_eq!(
+            after.emit(),
+            "define i16 @main() { \
+                entry: \
+                    %r1 = extractelement <2 x i16> <i16 7, i16 -3>, i64 2 \
+                    %r2 = add i16 -3, %r1 \
+                    ret i16 %r2 \
+            }"
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use crate::analysis::{ControlFlowGraph, DominatorTree};
+use crate::instruction::{InstId, InstructionKind};
+use crate::module::FunctionBody;
+use crate::pass::Pass;
+use crate::types::Type;
+use crate::value::{Value, Values};
+use std::collections::{HashMap, HashSet};
+use std::vec;
+use std::vec::Vec;
+
+/// Promotes stack allocations to registers, inserting `phi` instructions where control flow joins.
+///
+/// Only allocations that are just loaded from and stored to with their own type are promoted,
+/// by accesses that are neither atomic nor volatile.
+/// Loading before any store reads zero.
+pub struct Mem2Reg;
+
+impl Pass for Mem2Reg {
+    fn name(&self) -> &'static str {
+        "mem2reg"
+    }
+
+    fn run(&self, body: &mut FunctionBody) -> bool {
+        let cfg = ControlFlowGraph::new(body);
+        let dominators = DominatorTree::new(&cfg);
+        let allocas = body
+            .instructions()
+            .into_iter()
+            .filter_map(|x| promotable(body, &cfg, x))
+            .collect::<Vec<_>>();
+        if allocas.is_empty() {
+            return false;
+        }
+
+        // place phis at the iterated dominance frontier of the blocks storing to each allocation
+        let mut phis = HashMap::new();
+        for (index, (id, _, ty)) in allocas.iter().enumerate() {
+            let mut stores = Vec::new();
+            for user in body.users(*id) {
+                let instruction = body.instruction(user);
+                if let InstructionKind::Store { .. } = instruction.kind() {
+                    stores.extend(instruction.block());
+                }
+            }
+            let mut placed = HashSet::new();
+            while let Some(block) = stores.pop() {
+                for join in dominators.frontier(&cfg, block) {
+                    if !placed.insert(join) {
+                        continue;
+                    }
+                    let result = body.create_register(ty.clone());
+                    let phi = InstructionKind::Phi {
+                        ty: ty.clone(),
+                        incoming: Vec::new(),
+                    };
+                    phis.insert(body.insert_instruction(join, 0, phi, Some(result)), index);
+                    stores.push(join);
+                }
+            }
+        }
+
+        // rename along the dominator tree, carrying the current value of every allocation
+        let initial = allocas
+            .iter()
+            .map(|(_, _, ty)| zero(ty))
+            .collect::<Vec<_>>();
+        let mut pending = vec![(cfg.entry(), initial)];
+        while let Some((block, mut values)) = pending.pop() {
+            for id in body.block(block).instructions().to_vec() {
+                let instruction = body.instruction(id);
+                if let Some(index) = phis.get(&id) {
+                    values[*index] = instruction.result().unwrap().clone();
+                    continue;
+                }
+                let find = |pointer: &Value| allocas.iter().position(|(_, x, _)| x == pointer);
+                match instruction.kind() {
+                    InstructionKind::Load { pointer, .. } => {
+                        let Some(index) = find(pointer) else {
+                            continue;
+                        };
+                        let result = instruction.result().unwrap().clone();
+                        body.replace_uses(&result, &values[index]);
+                    }
+                    InstructionKind::Store { value, pointer, .. } => {
+                        let Some(index) = find(pointer) else {
+                            continue;
+                        };
+                        values[index] = value.clone();
+                    }
+                    _ => continue,
+                }
+                body.remove_instruction(id);
+            }
+            for successor in cfg.successors(block) {
+                for id in body.block(*successor).instructions().to_vec() {
+                    let Some(index) = phis.get(&id) else {
+                        continue;
+                    };
+                    body.modify_instruction(id, |kind| {
+                        if let InstructionKind::Phi { incoming, .. } = kind {
+                            incoming.push((values[*index].clone(), block));
+                        }
+                    });
+                }
+            }
+            // children are pushed in reverse, so they are renamed in layout order
+            for child in dominators.children(block).into_iter().rev() {
+                pending.push((child, values.clone()));
+            }
+        }
+
+        // unreachable predecessors are never renamed, but every edge still needs an incoming value
+        for (id, index) in &phis {
+            let block = body.instruction(*id).block().unwrap();
+            for predecessor in cfg.predecessors(block) {
+                if cfg.is_reachable(*predecessor) {
+                    continue;
+                }
+                body.modify_instruction(*id, |kind| {
+                    if let InstructionKind::Phi { incoming, .. } = kind {
+                        incoming.push((zero(&allocas[*index].2), *predecessor));
+                    }
+                });
+            }
+        }
+
+        for (id, _, _) in allocas {
+            body.remove_instruction(id);
+        }
+        true
+    }
+}
+
+/// Returns the allocation with its pointer and allocated type, if every use of the pointer
+/// is a reachable, non-atomic and non-volatile `load` or `store` of the allocated type.
+fn promotable(
+    body: &FunctionBody,
+    cfg: &ControlFlowGraph,
+    id: InstId,
+) -> Option<(InstId, Value, Type)> {
+    let instruction = body.instruction(id);
+    let (InstructionKind::Alloca { ty, .. }, Some(pointer)) =
+        (instruction.kind(), instruction.result())
+    else {
+        return None;
+    };
+    let promotable = body.users(id).into_iter().all(|user| {
+        let instruction = body.instruction(user);
+        let valid = match instruction.kind() {
+            InstructionKind::Load {
+                ty: loaded,
+                pointer: source,
+                ordering: None,
+                options,
+            } => loaded == ty && source == pointer && !options.volatile,
+            InstructionKind::Store {
+                value,
+                pointer: destination,
+                ordering: None,
+                options,
+            } => {
+                destination == pointer && value != pointer && value.ty() == ty && !options.volatile
+            }
+            _ => false,
+        };
+        valid && instruction.block().is_some_and(|x| cfg.is_reachable(x))
+    });
+    promotable.then(|| (id, pointer.clone(), ty.clone()))
+}
+
+/// Returns the value of an allocation that has not been stored to yet.
+fn zero(ty: &Type) -> Value {
+    match ty {
+        Type::Integer(width) => Values::integer("0", *width),
+        _ => Values::zeroinitializer(ty.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::instruction::{AtomicOrdering, MemoryOptions};
+    use crate::module::{FunctionBody, GlobalFunction};
+    use crate::pass::{Mem2Reg, Pass};
+    use crate::types::Types;
+    use crate::value::Values;
+    use crate::IRComponent;
+
+    #[test]
+    fn promote_allocations() {
+        let mut body = FunctionBody::new(|block| {
+            let counter = block.alloca(Types::integer(32));
+            let flag = block.alloca(Types::integer(1));
+            block.store(Values::integer("10", 32), counter.clone());
+            let (if_true, if_false) = block.br_if_returning(Values::integer("1", 1));
+            let loaded = if_true.load(Types::integer(32), counter.clone());
+            let doubled = if_true.mul(loaded, Values::integer("2", 32));
+            if_true.store(doubled, counter.clone());
+            let merge = if_true.br_returning();
+            if_false.goto(&merge);
+            let unset = merge.load(Types::integer(1), flag);
+            let result = merge.load(Types::integer(32), counter);
+            let selected = merge.select(unset, result, Values::integer("0", 32));
+            merge.ret(selected);
+        });
+        let before = GlobalFunction::new("main", Types::integer(32)).body(body.clone());
+        assert_eq!(
+            before.emit(),
+            "define i32 @main() { \
+                entry: \
+                    %r0 = alloca i32 \
+                    %r1 = alloca i1 \
+                    store i32 10, ptr %r0 \
+                    br i1 1, label %bb0, label %bb1 \
+                bb0: \
+                    %r2 = load i32, ptr %r0 \
+                    %r3 = mul i32 %r2, 2 \
+                    store i32 %r3, ptr %r0 \
+                    br label %bb2 \
+                bb2: \
+                    %r4 = load i1, ptr %r1 \
+                    %r5 = load i32, ptr %r0 \
+                    %r6 = select i1 %r4, i32 %r5, i32 0 \
+                    ret i32 %r6 \
+                bb1: \
+                    br label %bb2 \
+            }"
+        );
+
+        assert!(Mem2Reg.run(&mut body));
+        let after = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(
+            after.emit(),
+            "define i32 @main() { \
+                entry: \
+                    br i1 1, label %bb0, label %bb1 \
+                bb0: \
+                    %r3 = mul i32 10, 2 \
+                    br label %bb2 \
+                bb2: \
+                    %r7 = phi i32 [ %r3, %bb0 ], [ 10, %bb1 ] \
+                    %r6 = select i1 0, i32 %r7, i32 0 \
+                    ret i32 %r6 \
+                bb1: \
+                    br label %bb2 \
+            }"
+        );
+    }
+
+    #[test]
+    fn keep_volatile_and_atomic_allocations() {
+        let mut body = FunctionBody::new(|block| {
+            let volatile = block.alloca(Types::integer(32));
+            let atomic = block.alloca(Types::integer(32));
+            block.store_with(
+                Values::integer("1", 32),
+                volatile.clone(),
+                MemoryOptions::new().volatile(),
+            );
+            block.store(Values::integer("2", 32), atomic.clone());
+            let loaded = block.load_atomic(
+                Types::integer(32),
+                atomic,
+                AtomicOrdering::Acquire,
+                MemoryOptions::new().with_alignment(4),
+            );
+            let result = block.load(Types::integer(32), volatile);
+            block.ret(block.add(loaded, result));
+        });
+        let before = GlobalFunction::new("main", Types::integer(32)).body(body.clone());
+        assert!(!Mem2Reg.run(&mut body));
+        let after = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(after.emit(), before.emit());
+    }
+}
@@ -0,0 +1,236 @@
+use crate::analysis::ControlFlowGraph;
+use crate::instruction::{BlockId, InstructionKind};
+use crate::module::FunctionBody;
+use crate::pass::dce::remove_unreachable_blocks;
+use crate::pass::{Integer, Pass};
+use crate::value::Value;
+
+/// Simplifies the control flow graph, until nothing more can be simplified.
+///
+/// Branches and switches on constant conditions become unconditional,
+/// unreachable blocks are removed, `phi` instructions selecting a single value are replaced by it,
+/// and blocks are merged into their only predecessor when it has no other successor.
+pub struct SimplifyCfg;
+
+impl Pass for SimplifyCfg {
+    fn name(&self) -> &'static str {
+        "simplifycfg"
+    }
+
+    fn run(&self, body: &mut FunctionBody) -> bool {
+        let mut changed = false;
+        loop {
+            let folded = fold_branches(body);
+            let removed = remove_unreachable_blocks(body);
+            let simplified = simplify_phis(body);
+            let merged = merge_blocks(body);
+            if !(folded || removed || simplified || merged) {
+                return changed;
+            }
+            changed = true;
+        }
+    }
+}
+
+/// Turns conditional branches and switches with a constant condition, or with the same block
+/// on every side, into unconditional branches.
+fn fold_branches(body: &mut FunctionBody) -> bool {
+    let mut changed = false;
+    for block in body.blocks() {
+        let Some(terminator) = body.block(block).terminator(body) else {
+            continue;
+        };
+        let kind = body.instruction(terminator).kind();
+        let successors = kind.successors();
+        let target = match kind {
+            InstructionKind::ConditionalBranch {
+                condition,
+                if_true,
+                if_false,
+            } => match condition {
+                _ if if_true == if_false => *if_true,
+                Value::Number(contents, _) if contents == "0" => *if_false,
+                Value::Number(_, _) => *if_true,
+                _ => continue,
+            },
+            InstructionKind::Switch {
+                value,
+                default,
+                cases,
+            } => match Integer::new(value) {
+                Some(value) => cases
+                    .iter()
+                    .find(|(x, _)| Integer::new(x).is_some_and(|x| x.bits == value.bits))
+                    .map_or(*default, |(_, x)| *x),
+                None if successors.iter().all(|x| x == default) => *default,
+                None => continue,
+            },
+            _ => continue,
+        };
+        body.modify_instruction(terminator, |kind| {
+            *kind = InstructionKind::Branch { target }
+        });
+        let mut dropped = successors;
+        dropped.retain(|x| *x != target);
+        for dropped in dropped {
+            body.remove_incoming(dropped, block);
+        }
+        changed = true;
+    }
+    changed
+}
+
+/// Replaces `phi` instructions whose incoming values are all the same by that value.
+fn simplify_phis(body: &mut FunctionBody) -> bool {
+    let mut changed = false;
+    for id in body.instructions() {
+        let instruction = body.instruction(id);
+        let (Some(result), InstructionKind::Phi { incoming, .. }) =
+            (instruction.result(), instruction.kind())
+        else {
+            continue;
+        };
+        // a phi may select itself along a back-edge, which does not introduce another value
+        let mut values = incoming.iter().map(|(x, _)| x).filter(|x| *x != result);
+        let Some(value) = values.next() else {
+            continue;
+        };
+        if values.any(|x| x != value) {
+            continue;
+        }
+        let (result, value) = (result.clone(), value.clone());
+        body.replace_uses(&result, &value);
+        body.remove_instruction(id);
+        changed = true;
+    }
+    changed
+}
+
+/// Merges a block into its only predecessor, if the predecessor only branches to it.
+fn merge_blocks(body: &mut FunctionBody) -> bool {
+    let cfg = ControlFlowGraph::new(body);
+    for block in body.blocks() {
+        let [predecessor] = cfg.predecessors(block) else {
+            continue;
+        };
+        if *predecessor == block || block == body.entry() {
+            continue;
+        }
+        let Some(terminator) = body.block(*predecessor).terminator(body) else {
+            continue;
+        };
+        if !matches!(
+            body.instruction(terminator).kind(),
+            InstructionKind::Branch { .. }
+        ) {
+            continue;
+        }
+        merge(body, &cfg, *predecessor, block);
+        return true;
+    }
+    false
+}
+
+fn merge(body: &mut FunctionBody, cfg: &ControlFlowGraph, into: BlockId, block: BlockId) {
+    let terminator = body.block(into).terminator(body).unwrap();
+    body.remove_instruction(terminator);
+    for id in body.block(block).instructions().to_vec() {
+        let instruction = body.instruction(id);
+        // with a single predecessor, every phi selects the value flowing in from it
+        match (instruction.result(), instruction.kind()) {
+            (Some(result), InstructionKind::Phi { incoming, .. }) if incoming.len() == 1 => {
+                let (result, value) = (result.clone(), incoming[0].0.clone());
+                body.replace_uses(&result, &value);
+                body.remove_instruction(id);
+            }
+            _ => body.move_instruction(id, into),
+        }
+    }
+    for successor in cfg.successors(block) {
+        body.replace_incoming(*successor, block, into);
+    }
+    body.remove_block(block);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::module::{FunctionBody, GlobalFunction};
+    use crate::pass::{Pass, SimplifyCfg};
+    use crate::types::Types;
+    use crate::value::Values;
+    use crate::IRComponent;
+    use std::vec;
+
+    #[test]
+    fn simplify_control_flow() {
+        let mut body = FunctionBody::new(|block| {
+            let (if_true, if_false) = block.br_if_returning(Values::integer("1", 1));
+            let sum = if_true.add(Values::integer("1", 32), Values::integer("2", 32));
+            let merge = if_true.br_returning();
+            if_false.goto(&merge);
+            let merged = merge.phi(
+                Types::integer(32),
+                vec![(sum, &if_true), (Values::integer("0", 32), &if_false)],
+            );
+            merge.ret(merged.value());
+        });
+        let before = GlobalFunction::new("main", Types::integer(32)).body(body.clone());
+        assert_eq!(
+            before.emit(),
+            "define i32 @main() { \
+                entry: \
+                    br i1 1, label %bb0, label %bb1 \
+                bb0: \
+                    %r0 = add i32 1, 2 \
+                    br label %bb2 \
+                bb2: \
+                    %r1 = phi i32 [ %r0, %bb0 ], [ 0, %bb1 ] \
+                    ret i32 %r1 \
+                bb1: \
+                    br label %bb2 \
+            }"
+        );
+
+        assert!(SimplifyCfg.run(&mut body));
+        let after = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(
+            after.emit(),
+            "define i32 @main() { \
+                entry: \
+                    %r0 = add i32 1, 2 \
+                    ret i32 %r0 \
+            }"
+        );
+    }
+
+    #[test]
+    fn fold_constant_switches() {
+        let mut body = FunctionBody::new(|block| {
+            let (default, cases) = block.switch_returning(
+                Values::integer("255", 8),
+                vec![Values::integer("0", 8), Values::integer("-1", 8)],
+            );
+            let merge = default.br_returning();
+            cases[0].goto(&merge);
+            cases[1].goto(&merge);
+            let merged = merge.phi(
+                Types::integer(32),
+                vec![
+                    (Values::integer("1", 32), &default),
+                    (Values::integer("2", 32), &cases[0]),
+                    (Values::integer("3", 32), &cases[1]),
+                ],
+            );
+            merge.ret(merged.value());
+        });
+        assert!(SimplifyCfg.run(&mut body));
+        let after = GlobalFunction::new("main", Types::integer(32)).body(body);
+        assert_eq!(
+            after.emit(),
+            "define i32 @main() { \
+                entry: \
+                    ret i32 3 \
+            }"
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use crate::IRComponent;
 use std::boxed::Box;
+use std::fmt::{Debug, Formatter};
+use std::format;
 use std::string::{String, ToString};
+use std::sync::{Arc, RwLock};
 use std::vec::Vec;
 
 /// Represents an LLVM IR Type.
@@ -13,18 +16,37 @@ pub enum Type {
     /// Represents the LLVM array type, with the size and element type specified.
     #[non_exhaustive]
     Array(u32, Box<Type>),
+    /// Represents the LLVM vector type, with the number of elements and element type specified.
+    /// Vector elements are integers, floating points or pointers.
+    #[non_exhaustive]
+    Vector(u32, Box<Type>),
+    /// Represents the LLVM vector type whose number of elements is a multiple of a constant
+    /// only known at run-time, `vscale`, with the multiplier and element type specified.
+    #[non_exhaustive]
+    ScalableVector(u32, Box<Type>),
     /// Represents the LLVM structure type, with the element types specified.
     #[non_exhaustive]
     Structure(Vec<Type>),
+    /// Represents the LLVM structure type without any padding between elements,
+    /// with the element types specified.
+    #[non_exhaustive]
+    PackedStructure(Vec<Type>),
+    /// Represents a type defined by name at the module level.
+    #[non_exhaustive]
+    Named(NamedType),
     /// Represents a pointer into memory.
     #[non_exhaustive]
     Ptr,
+    /// Represents a pointer into memory, in a numbered address space other than the default.
+    #[non_exhaustive]
+    AddressSpacePtr(u32),
     /// Represents the empty type with no size and value.
     #[non_exhaustive]
     Void,
-    /// Represents a function that can be called.
+    /// Represents a function that can be called, with the return type, parameter types,
+    /// and whether it accepts a variable number of extra arguments.
     #[non_exhaustive]
-    Function(Box<Type>, Vec<Box<Type>>),
+    Function(Box<Type>, Vec<Box<Type>>, bool),
     /// Represents a basic 16-bit floating point.
     Half,
     /// A 32-bit floating point.
@@ -51,15 +73,43 @@ impl IRComponent for Type {
                 string.push(' ');
                 string.push(']');
             }
+            Type::Vector(size, subtype) => {
+                string.push('<');
+                string.push_str(&size.to_string());
+                string.push_str(" x ");
+                subtype.append_to_string(string);
+                string.push('>');
+            }
+            Type::ScalableVector(size, subtype) => {
+                string.push_str("<vscale x ");
+                string.push_str(&size.to_string());
+                string.push_str(" x ");
+                subtype.append_to_string(string);
+                string.push('>');
+            }
             Type::Ptr => {
                 string.push_str("ptr");
             }
+            Type::AddressSpacePtr(address_space) => {
+                string.push_str("ptr addrspace(");
+                string.push_str(&address_space.to_string());
+                string.push(')');
+            }
             Type::Void => {
                 string.push_str("void");
             }
-            Type::Function(return_type, parameters) => {
+            Type::Function(return_type, parameters, variadic) => {
                 return_type.append_to_string(string);
-                string.push('(');
+                string.push_str(" (");
+                let mut parameters = parameters.iter().map(|x| x.emit()).collect::<Vec<_>>();
+                if *variadic {
+                    parameters.push(String::from("..."));
+                }
+                string.push_str(&parameters.join(", "));
+                string.push(')');
+            }
+            Type::Structure(parameters) => {
+                string.push('{');
                 string.push_str(
                     &parameters
                         .iter()
@@ -67,10 +117,10 @@ impl IRComponent for Type {
                         .collect::<Vec<_>>()
                         .join(", "),
                 );
-                string.push(')');
+                string.push('}');
             }
-            Type::Structure(parameters) => {
-                string.push('{');
+            Type::PackedStructure(parameters) => {
+                string.push_str("<{");
                 string.push_str(
                     &parameters
                         .iter()
@@ -78,7 +128,11 @@ impl IRComponent for Type {
                         .collect::<Vec<_>>()
                         .join(", "),
                 );
-                string.push('}');
+                string.push_str("}>");
+            }
+            Type::Named(named) => {
+                string.push('%');
+                string.push_str(named.name());
             }
             Type::Half => string.push_str("half"),
             Type::Float => string.push_str("float"),
@@ -88,6 +142,149 @@ impl IRComponent for Type {
     }
 }
 
+/// A type defined by name at the module level, such as `%Node = type { i32, ptr }`.
+///
+/// The body of the type is shared between every copy of the handle, so a type can be declared
+/// before its body is known. A named type without a body is opaque.
+#[derive(Clone)]
+pub struct NamedType {
+    name: String,
+    body: Arc<RwLock<Option<Type>>>,
+}
+
+impl NamedType {
+    /// Creates a new named type, with an optional body.
+    pub(crate) fn new(name: &str, body: Option<Type>) -> Self {
+        NamedType {
+            name: name.to_string(),
+            body: Arc::new(RwLock::new(body)),
+        }
+    }
+
+    /// Returns the name of this type.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the body of this type, or nothing if the type is opaque.
+    pub fn body(&self) -> Option<Type> {
+        self.body.read().unwrap().clone()
+    }
+
+    /// Provides the body of this type, replacing the existing body if there is one.
+    pub fn set_body(&self, body: Type) {
+        *self.body.write().unwrap() = Some(body);
+    }
+}
+
+impl PartialEq for NamedType {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Debug for NamedType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "%{}", self.name)
+    }
+}
+
+impl IRComponent for NamedType {
+    fn append_to_string(&self, string: &mut String) {
+        string.push('%');
+        string.push_str(&self.name);
+        string.push_str(" = type ");
+        match self.body() {
+            Some(body) => body.append_to_string(string),
+            None => string.push_str("opaque"),
+        }
+    }
+}
+
+impl Type {
+    /// Returns the underlying layout of this type, looking through named types.
+    /// Opaque named types are returned as they are.
+    pub fn resolved(&self) -> Type {
+        match self {
+            Type::Named(named) => match named.body() {
+                Some(body) => body.resolved(),
+                None => self.clone(),
+            },
+            _ => self.clone(),
+        }
+    }
+
+    /// Returns true if this is an integer type.
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Type::Integer(_))
+    }
+
+    /// Returns true if this is a floating-point type.
+    pub fn is_floating_point(&self) -> bool {
+        matches!(self, Type::Half | Type::Float | Type::Double | Type::FP128)
+    }
+
+    /// Returns true if this is a pointer type, in any address space.
+    pub fn is_pointer(&self) -> bool {
+        matches!(self, Type::Ptr | Type::AddressSpacePtr(_))
+    }
+
+    /// Returns true if this is a vector type, of fixed or scalable length.
+    pub fn is_vector(&self) -> bool {
+        matches!(self, Type::Vector(..) | Type::ScalableVector(..))
+    }
+
+    /// Returns the element type of a vector type, or this type itself if it is not a vector.
+    /// Element-wise operations check their operands through this type.
+    pub fn scalar(&self) -> &Type {
+        match self {
+            Type::Vector(_, element) | Type::ScalableVector(_, element) => element,
+            _ => self,
+        }
+    }
+
+    /// Returns the name this type is given in the names of overloaded intrinsics,
+    /// such as `v4i32` in `llvm.vector.reduce.add.v4i32`.
+    pub(crate) fn mangled(&self) -> String {
+        match self {
+            Type::Integer(width) => format!("i{}", width),
+            Type::Half => String::from("f16"),
+            Type::Float => String::from("f32"),
+            Type::Double => String::from("f64"),
+            Type::FP128 => String::from("f128"),
+            Type::Ptr => String::from("p0"),
+            Type::AddressSpacePtr(address_space) => format!("p{}", address_space),
+            Type::Vector(length, element) => format!("v{}{}", length, element.mangled()),
+            Type::ScalableVector(length, element) => {
+                format!("nxv{}{}", length, element.mangled())
+            }
+            _ => self.emit(),
+        }
+    }
+
+    /// Returns the address space of a pointer type.
+    pub fn address_space(&self) -> Option<u32> {
+        match self {
+            Type::Ptr => Some(0),
+            Type::AddressSpacePtr(address_space) => Some(*address_space),
+            _ => None,
+        }
+    }
+
+    /// Returns the width in bits of an integer or floating-point type.
+    /// The width of pointers depends on the data layout, so they have no known width here.
+    pub fn bit_width(&self) -> Option<u32> {
+        match self {
+            Type::Integer(width) => Some(*width),
+            Type::Half => Some(16),
+            Type::Float => Some(32),
+            Type::Double => Some(64),
+            Type::FP128 => Some(128),
+            _ => None,
+        }
+    }
+}
+
 /// A structure with implementations to generate type instances.
 pub struct Types;
 
@@ -101,11 +298,53 @@ impl Types {
         Type::Array(length, Box::new(subtype))
     }
 
+    /// Generates a new vector type, with the specified length and element type.
+    pub fn vector(length: u32, subtype: Type) -> Type {
+        Type::Vector(length, Box::new(subtype))
+    }
+
+    /// Generates a new scalable vector type, holding `vscale` times the specified length
+    /// of elements of the element type.
+    pub fn scalable_vector(length: u32, subtype: Type) -> Type {
+        Type::ScalableVector(length, Box::new(subtype))
+    }
+
     /// Generates a new structure type, with the provided element types.
     pub fn structure(subtypes: Vec<Type>) -> Type {
         Type::Structure(subtypes)
     }
 
+    /// Generates a new packed structure type, with the provided element types.
+    pub fn packed_structure(subtypes: Vec<Type>) -> Type {
+        Type::PackedStructure(subtypes)
+    }
+
+    /// Generates a new function type, with the provided return and parameter types.
+    pub fn function(return_type: Type, parameters: Vec<Type>) -> Type {
+        Type::Function(
+            Box::new(return_type),
+            parameters.into_iter().map(Box::new).collect(),
+            false,
+        )
+    }
+
+    /// Generates a new function type that accepts extra arguments after the provided parameters.
+    pub fn variadic_function(return_type: Type, parameters: Vec<Type>) -> Type {
+        Type::Function(
+            Box::new(return_type),
+            parameters.into_iter().map(Box::new).collect(),
+            true,
+        )
+    }
+
+    /// Generates a new pointer type, in the given address space.
+    pub fn pointer(address_space: u32) -> Type {
+        match address_space {
+            0 => Type::Ptr,
+            _ => Type::AddressSpacePtr(address_space),
+        }
+    }
+
     /// Generates a new void type, with no size or value.
     pub fn void() -> Type {
         Type::Void
@@ -134,7 +373,7 @@ impl Types {
 
 #[cfg(test)]
 mod tests {
-    use crate::types::{Type, Types};
+    use crate::types::{NamedType, Type, Types};
     use crate::IRComponent;
     use std::vec;
 
@@ -149,9 +388,52 @@ mod tests {
         let int = Types::array(4, Types::integer(32));
         assert_eq!(int.emit(), "[ 4 x i32 ]");
     }
+    #[test]
+    pub fn test_vectors() {
+        let vector = Types::vector(4, Types::fp32());
+        assert_eq!(vector.emit(), "<4 x float>");
+        assert_eq!(vector.scalar(), &Types::fp32());
+        let scalable = Types::scalable_vector(2, Types::integer(64));
+        assert_eq!(scalable.emit(), "<vscale x 2 x i64>");
+        assert_eq!(scalable.mangled(), "nxv2i64");
+    }
+
     #[test]
     pub fn test_structures() {
         let int = Type::Structure(vec![Type::Integer(32), Type::Integer(64)]);
         assert_eq!(int.emit(), "{i32, i64}");
     }
+
+    #[test]
+    pub fn test_packed_structures() {
+        let int = Types::packed_structure(vec![Type::Integer(32), Type::Integer(8)]);
+        assert_eq!(int.emit(), "<{i32, i8}>");
+    }
+
+    #[test]
+    pub fn test_named_types() {
+        let node = NamedType::new("Node", None);
+        assert_eq!(node.emit(), "%Node = type opaque");
+        node.set_body(Types::structure(vec![Types::integer(32), Type::Ptr]));
+        assert_eq!(node.emit(), "%Node = type {i32, ptr}");
+        assert_eq!(Type::Named(node.clone()).emit(), "%Node");
+        assert_eq!(
+            Type::Named(node).resolved(),
+            Types::structure(vec![Types::integer(32), Type::Ptr])
+        );
+    }
+
+    #[test]
+    pub fn test_pointers() {
+        assert_eq!(Types::pointer(0).emit(), "ptr");
+        assert_eq!(Types::pointer(3).emit(), "ptr addrspace(3)");
+    }
+
+    #[test]
+    pub fn test_functions() {
+        let function = Types::function(Types::integer(32), vec![Type::Ptr, Types::integer(64)]);
+        assert_eq!(function.emit(), "i32 (ptr, i64)");
+        let variadic = Types::variadic_function(Types::integer(32), vec![Type::Ptr]);
+        assert_eq!(variadic.emit(), "i32 (ptr, ...)");
+    }
 }
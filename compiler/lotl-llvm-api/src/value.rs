@@ -1,10 +1,25 @@
+use crate::error::BuildError;
+use crate::instruction::{check_cast, check_getelementptr, CastOperator, SharedBasicBlock};
 use crate::types::{Type, Types};
 use crate::IRComponent;
-use std::string::String;
+use std::boxed::Box;
+use std::format;
+use std::string::{String, ToString};
 use std::vec::Vec;
 
+mod asm;
+mod float;
+
+pub use asm::InlineAsm;
+
+pub(crate) use asm::check_inline_asm;
+use float::append_float;
+#[cfg(test)]
+pub(crate) use float::quad_value;
+pub(crate) use float::{float_value, half_bits, half_value, is_decimal, quad_bits};
+
 /// Represents a valid LLVM value.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 #[non_exhaustive]
 pub enum Value {
     /// Represents a constant numeric value. The value is of either integer or floating-point type.
@@ -16,14 +31,55 @@ pub enum Value {
     /// Represents a LLVM constant structure.
     #[non_exhaustive]
     Structure(Vec<Value>, Type),
+    /// Represents a LLVM constant array.
+    #[non_exhaustive]
+    Array(Vec<Value>, Type),
+    /// Represents a LLVM constant vector.
+    #[non_exhaustive]
+    Vector(Vec<Value>, Type),
+    /// Represents a LLVM constant array of bytes, written as a string.
+    #[non_exhaustive]
+    CString(Vec<u8>, Type),
     /// Represents a global identifier. This is always of pointer type.
     #[non_exhaustive]
     GlobalIdentifier(String, Type),
     /// Represents a local identifier in a function.
     #[non_exhaustive]
     LocalIdentifier(String, Type),
+    /// Represents a reference to a global function, with the function's signature.
+    /// This is always of pointer type, the signature is only used to call the function.
+    #[non_exhaustive]
+    Function(String, Type),
+    /// Represents the address of a basic block, `blockaddress`, with the name of its function
+    /// and its label. This is always of pointer type, and can only be branched to with `indirectbr`.
+    #[non_exhaustive]
+    BlockAddress(String, String),
+    /// Represents the null pointer of a pointer type.
+    #[non_exhaustive]
+    Null(Type),
+    /// Represents an undefined value, which may be any value of its type at every use.
+    #[non_exhaustive]
+    Undef(Type),
+    /// Represents a poison value, which makes the result of any instruction using it poison.
+    #[non_exhaustive]
+    Poison(Type),
+    /// Represents a constant `getelementptr` expression, with the type the indices step into,
+    /// the base pointer, the indices and whether the result stays in bounds of the base,
+    /// `inbounds`. This is always of the type of its base pointer.
+    #[non_exhaustive]
+    GetElementPtr(Type, Box<Value>, Vec<Value>, bool),
+    /// Represents a constant conversion expression, such as `ptrtoint`,
+    /// with the converted value and the target type.
+    #[non_exhaustive]
+    Cast(CastOperator, Box<Value>, Type),
+    /// Represents a fragment of inline assembly. This is always of pointer type,
+    /// and can only be the callee of a `call` or `invoke`.
+    #[non_exhaustive]
+    InlineAsm(InlineAsm),
 }
 
+static POINTER: Type = Type::Ptr;
+
 impl Value {
     /// Returns the type associated with this value.
     pub fn ty(&self) -> &Type {
@@ -32,7 +88,49 @@ impl Value {
             Value::GlobalIdentifier(_, ty) => ty,
             Value::LocalIdentifier(_, ty) => ty,
             Value::Structure(_, ty) => ty,
+            Value::Array(_, ty) => ty,
+            Value::Vector(_, ty) => ty,
+            Value::CString(_, ty) => ty,
             Value::ZeroInitializer(ty) => ty,
+            Value::Function(_, _) | Value::BlockAddress(_, _) | Value::InlineAsm(_) => &POINTER,
+            Value::Null(ty) | Value::Undef(ty) | Value::Poison(ty) => ty,
+            Value::GetElementPtr(_, base, _, _) => base.ty(),
+            Value::Cast(_, _, ty) => ty,
+        }
+    }
+
+    /// Returns true if this value is a constant, which does not depend on any local value
+    /// of a function. Only constants can initialize globals or be operands of constant expressions.
+    pub fn is_constant(&self) -> bool {
+        match self {
+            Value::LocalIdentifier(_, _) => false,
+            Value::Structure(elements, _)
+            | Value::Array(elements, _)
+            | Value::Vector(elements, _) => elements.iter().all(Value::is_constant),
+            Value::GetElementPtr(_, base, indices, _) => {
+                base.is_constant() && indices.iter().all(Value::is_constant)
+            }
+            Value::Cast(_, value, _) => value.is_constant(),
+            _ => true,
+        }
+    }
+
+    /// Returns the function type the value is called with,
+    /// if it is a function reference or inline assembly.
+    pub(crate) fn signature(&self) -> Option<&Type> {
+        match self {
+            Value::Function(_, signature) => Some(signature),
+            Value::InlineAsm(asm) => Some(asm.signature()),
+            _ => None,
+        }
+    }
+
+    /// Returns the number held by a floating-point constant, written in decimal
+    /// or in one of the hexadecimal forms of LLVM, or `None` for other values.
+    pub fn float_value(&self) -> Option<f64> {
+        match self {
+            Value::Number(contents, ty) if ty.is_floating_point() => float_value(contents),
+            _ => None,
         }
     }
 }
@@ -43,25 +141,51 @@ pub struct Values;
 impl Values {
     /// Generates a new integer constant, with a maximum width of (2^22 - 1).
     pub fn integer(contents: &str, size: u32) -> Value {
+        Values::try_integer(contents, size).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Generates a new integer constant, or returns an error if the width is zero or too big,
+    /// or the contents are not an integer.
+    pub fn try_integer(contents: &str, size: u32) -> Result<Value, BuildError> {
+        if size == 0 {
+            return Err(BuildError::ZeroWidthInteger);
+        }
         if size > 8388607 {
-            panic!("too big integer, must be i8388607 or smaller")
+            return Err(BuildError::IntegerTooWide(size));
         }
-        for (idx, ch) in contents.chars().enumerate() {
-            if !(ch.is_ascii_digit() || (ch == '-' && idx == 0)) {
-                panic!("invalid character in integer: '{}'", contents);
-            }
+        let digits = contents.strip_prefix('-').unwrap_or(contents);
+        if digits.is_empty() || !digits.chars().all(|ch| ch.is_ascii_digit()) {
+            return Err(BuildError::InvalidNumber(contents.to_string()));
         }
-        Value::Number(contents.parse().unwrap(), Types::integer(size))
+        Ok(Value::Number(contents.to_string(), Types::integer(size)))
     }
 
     /// Generates a new floating-point constant, with the specified type.
     pub fn float(contents: &str, ty: Type) -> Value {
-        for (idx, ch) in contents.chars().enumerate() {
-            if !(ch.is_ascii_digit() || (ch == '-' && idx == 0) || (ch == '.')) {
-                panic!("invalid character in floating-point: '{}'", contents);
-            }
+        Values::try_float(contents, ty).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Generates a new floating-point constant, or returns an error if the type is not
+    /// a floating-point type or the contents are not a number.
+    ///
+    /// The contents are either a decimal, such as `-0.5`, or the bits of the number in one of
+    /// the hexadecimal forms of LLVM, such as `0x3FB999999999999A` for a `double`.
+    /// Decimals that the type cannot hold exactly are stored in hexadecimal,
+    /// so the constant is equal to the one read back from the emitted module.
+    pub fn try_float(contents: &str, ty: Type) -> Result<Value, BuildError> {
+        if !ty.is_floating_point() {
+            return Err(BuildError::TypeMismatch {
+                expected: Types::fp64(),
+                found: ty,
+            });
         }
-        Value::Number(contents.parse().unwrap(), ty)
+        let valid = is_decimal(contents) || contents.starts_with("0x");
+        if !valid || float_value(contents).is_none() {
+            return Err(BuildError::InvalidNumber(contents.to_string()));
+        }
+        let mut number = String::new();
+        append_float(&mut number, contents, &ty);
+        Ok(Value::Number(number, ty))
     }
 
     /// Creates a new constant structure value, with the provided values as elements
@@ -72,10 +196,172 @@ impl Values {
         )
     }
 
+    /// Creates a new constant packed structure value, with the provided values as elements.
+    pub fn packed_structure(contents: Vec<Value>) -> Value {
+        Value::Structure(
+            contents.clone(),
+            Types::packed_structure(contents.iter().map(|x| x.ty().clone()).collect()),
+        )
+    }
+
+    /// Creates a new constant array value, with the provided values as elements.
+    /// All elements must be of the element type.
+    pub fn array(element: Type, contents: Vec<Value>) -> Value {
+        Values::try_array(element, contents).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Creates a new constant array value, or returns an error if an element is not
+    /// of the element type.
+    pub fn try_array(element: Type, contents: Vec<Value>) -> Result<Value, BuildError> {
+        if let Some(value) = contents.iter().find(|x| *x.ty() != element) {
+            return Err(BuildError::TypeMismatch {
+                expected: element,
+                found: value.ty().clone(),
+            });
+        }
+        let ty = Types::array(contents.len() as u32, element);
+        Ok(Value::Array(contents, ty))
+    }
+
+    /// Creates a new constant vector value, with the provided values as elements.
+    /// All elements must be of the element type.
+    pub fn vector(element: Type, contents: Vec<Value>) -> Value {
+        Values::try_vector(element, contents).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Creates a new constant vector value, or returns an error if there are no elements,
+    /// the element type cannot be held by vectors, or an element is not of the element type.
+    pub fn try_vector(element: Type, contents: Vec<Value>) -> Result<Value, BuildError> {
+        let ty = Types::vector(contents.len() as u32, element.clone());
+        check_vector_type(&ty)?;
+        if let Some(value) = contents.iter().find(|x| *x.ty() != element) {
+            return Err(BuildError::TypeMismatch {
+                expected: element,
+                found: value.ty().clone(),
+            });
+        }
+        Ok(Value::Vector(contents, ty))
+    }
+
+    /// Creates a new constant null-terminated string, as an array of bytes.
+    pub fn c_string(contents: &str) -> Value {
+        let mut bytes = contents.as_bytes().to_vec();
+        bytes.push(0);
+        Values::bytes(bytes)
+    }
+
+    /// Creates a new constant array of bytes.
+    pub fn bytes(contents: Vec<u8>) -> Value {
+        let ty = Types::array(contents.len() as u32, Types::integer(8));
+        Value::CString(contents, ty)
+    }
+
     /// Creates a new zero-initialized value
     pub fn zeroinitializer(ty: Type) -> Value {
         Value::ZeroInitializer(ty)
     }
+
+    /// Creates the address of a basic block of the function with the given name,
+    /// which `indirectbr` can branch to.
+    pub fn block_address(function: &str, block: &SharedBasicBlock) -> Value {
+        Value::BlockAddress(function.to_string(), block.label())
+    }
+
+    /// Creates the null pointer of the pointer type.
+    pub fn null(ty: Type) -> Value {
+        Values::try_null(ty).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Creates the null pointer of the pointer type, or returns an error if the type is not a pointer.
+    pub fn try_null(ty: Type) -> Result<Value, BuildError> {
+        if !ty.is_pointer() {
+            return Err(BuildError::NotPointer(ty));
+        }
+        Ok(Value::Null(ty))
+    }
+
+    /// Creates an undefined value of the type.
+    pub fn undef(ty: Type) -> Value {
+        Value::Undef(ty)
+    }
+
+    /// Creates a poison value of the type.
+    pub fn poison(ty: Type) -> Value {
+        Value::Poison(ty)
+    }
+
+    /// Creates a constant pointer to the element of the aggregate at the indices,
+    /// which can initialize a global.
+    ///
+    /// The first index steps over whole values of type `ty` behind the base pointer,
+    /// every following index steps into the aggregate.
+    pub fn getelementptr(ty: Type, base: Value, indices: Vec<Value>) -> Value {
+        Values::try_getelementptr(ty, base, indices).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Creates a constant pointer to the element of the aggregate at the indices, or returns
+    /// an error if an operand is not a constant, or the indices do not lead into the aggregate.
+    pub fn try_getelementptr(
+        ty: Type,
+        base: Value,
+        indices: Vec<Value>,
+    ) -> Result<Value, BuildError> {
+        check_constants([&base].into_iter().chain(&indices))?;
+        check_getelementptr(&ty, &base, &indices)?;
+        Ok(Value::GetElementPtr(ty, Box::new(base), indices, false))
+    }
+
+    /// Creates a constant pointer to the element of the aggregate at the indices,
+    /// marked `inbounds`, so the result is poison if it is not within the base value.
+    pub fn inbounds_getelementptr(ty: Type, base: Value, indices: Vec<Value>) -> Value {
+        Values::try_inbounds_getelementptr(ty, base, indices).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Creates a constant `inbounds` pointer to the element of the aggregate at the indices,
+    /// or returns an error if an operand is not a constant, or the indices do not lead
+    /// into the aggregate.
+    pub fn try_inbounds_getelementptr(
+        ty: Type,
+        base: Value,
+        indices: Vec<Value>,
+    ) -> Result<Value, BuildError> {
+        match Values::try_getelementptr(ty, base, indices)? {
+            Value::GetElementPtr(ty, base, indices, _) => {
+                Ok(Value::GetElementPtr(ty, base, indices, true))
+            }
+            _ => unreachable!("getelementptr builds a getelementptr expression"),
+        }
+    }
+
+    /// Creates a constant conversion of the value into the target type.
+    pub fn cast(operator: CastOperator, value: Value, target: Type) -> Value {
+        Values::try_cast(operator, value, target).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Creates a constant conversion of the value into the target type, or returns an error if
+    /// the value is not a constant, or the conversion cannot be made between the two types.
+    pub fn try_cast(
+        operator: CastOperator,
+        value: Value,
+        target: Type,
+    ) -> Result<Value, BuildError> {
+        check_constants([&value])?;
+        check_cast(operator, &value, &target)?;
+        Ok(Value::Cast(operator, Box::new(value), target))
+    }
+
+    /// Creates a constant conversion of the pointer into an integer of the target type.
+    pub fn ptrtoint(value: Value, target: Type) -> Value {
+        Values::cast(CastOperator::PtrToInt, value, target)
+    }
+}
+
+/// Checks that the operands of a constant expression are constants.
+fn check_constants<'v>(operands: impl IntoIterator<Item = &'v Value>) -> Result<(), BuildError> {
+    match operands.into_iter().find(|x| !x.is_constant()) {
+        Some(operand) => Err(BuildError::NonConstantOperand(operand.clone())),
+        None => Ok(()),
+    }
 }
 
 impl IRComponent for Value {
@@ -89,18 +375,20 @@ impl IRComponent for Value {
 impl Value {
     pub(crate) fn append_to_string_untyped(&self, string: &mut String) {
         match self {
+            Value::Number(value, ty) if ty.is_floating_point() => append_float(string, value, ty),
             Value::Number(value, _) => {
                 string.push_str(value);
             }
-            Value::GlobalIdentifier(name, _) => {
+            Value::GlobalIdentifier(name, _) | Value::Function(name, _) => {
                 string.push('@');
                 string.push_str(name);
             }
-            Value::LocalIdentifier(name, _) => {
-                string.push('%');
-                string.push_str(name);
-            }
-            Value::Structure(elements, _) => {
+            Value::LocalIdentifier(name, _) => append_local(string, name),
+            Value::Structure(elements, ty) => {
+                let packed = matches!(ty, Type::PackedStructure(_));
+                if packed {
+                    string.push('<');
+                }
                 string.push('{');
                 string.push_str(
                     elements
@@ -111,15 +399,125 @@ impl Value {
                         .as_str(),
                 );
                 string.push('}');
+                if packed {
+                    string.push('>');
+                }
+            }
+            Value::Array(elements, _) => {
+                string.push('[');
+                string.push_str(
+                    elements
+                        .iter()
+                        .map(Value::emit)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                        .as_str(),
+                );
+                string.push(']');
+            }
+            Value::Vector(elements, _) => {
+                string.push('<');
+                string.push_str(
+                    elements
+                        .iter()
+                        .map(Value::emit)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                        .as_str(),
+                );
+                string.push('>');
+            }
+            Value::CString(bytes, _) => {
+                string.push_str("c\"");
+                append_escaped(string, bytes);
+                string.push('"');
             }
             Value::ZeroInitializer(_) => string.push_str("zeroinitializer"),
+            Value::BlockAddress(function, label) => {
+                string.push_str("blockaddress(@");
+                string.push_str(function);
+                string.push_str(", %");
+                string.push_str(label);
+                string.push(')');
+            }
+            Value::InlineAsm(asm) => asm.append_to_string(string),
+            Value::Null(_) => string.push_str("null"),
+            Value::Undef(_) => string.push_str("undef"),
+            Value::Poison(_) => string.push_str("poison"),
+            Value::GetElementPtr(ty, base, indices, inbounds) => {
+                string.push_str("getelementptr ");
+                if *inbounds {
+                    string.push_str("inbounds ");
+                }
+                string.push('(');
+                ty.append_to_string(string);
+                string.push_str(", ");
+                base.append_to_string(string);
+                for index in indices {
+                    string.push_str(", ");
+                    index.append_to_string(string);
+                }
+                string.push(')');
+            }
+            Value::Cast(operator, value, target) => {
+                operator.append_to_string(string);
+                string.push_str(" (");
+                value.append_to_string(string);
+                string.push_str(" to ");
+                target.append_to_string(string);
+                string.push(')');
+            }
+        }
+    }
+}
+
+/// Checks that a vector type has elements, and that they are integers, floating points or pointers.
+pub(crate) fn check_vector_type(ty: &Type) -> Result<(), BuildError> {
+    let (Type::Vector(length, element) | Type::ScalableVector(length, element)) = ty else {
+        return Err(BuildError::NotVector(ty.clone()));
+    };
+    if *length == 0 {
+        return Err(BuildError::EmptyVector(ty.clone()));
+    }
+    if !element.is_integer() && !element.is_floating_point() && !element.is_pointer() {
+        return Err(BuildError::InvalidVectorElement(*element.clone()));
+    }
+    Ok(())
+}
+
+/// Appends the name of a local value, quoting it if it is not a plain identifier.
+pub(crate) fn append_local(string: &mut String, name: &str) {
+    let plain =
+        |byte: u8| byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'.' | b'-' | b'$');
+    string.push('%');
+    match name.bytes().next() {
+        Some(first) if !first.is_ascii_digit() && name.bytes().all(plain) => string.push_str(name),
+        _ => {
+            string.push('"');
+            append_escaped(string, name.as_bytes());
+            string.push('"');
+        }
+    }
+}
+
+/// Appends the bytes as the contents of a quoted LLVM string, escaping them where needed.
+pub(crate) fn append_escaped(string: &mut String, bytes: &[u8]) {
+    for byte in bytes {
+        // printable characters are kept, except for the quote and the escape itself
+        let printable = byte.is_ascii_graphic() || *byte == b' ';
+        if printable && *byte != b'"' && *byte != b'\\' {
+            string.push(*byte as char);
+        } else {
+            string.push_str(&format!("\\{:02X}", byte));
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::types::Type;
+    use crate::error::BuildError;
+    use crate::instruction::CastOperator;
+    use crate::types::{Type, Types};
     use crate::value::{Value, Values};
     use crate::IRComponent;
     use std::string::ToString;
@@ -136,6 +534,11 @@ mod tests {
         assert_eq!(value.emit(), "ptr @foo");
     }
     #[test]
+    pub fn test_function_references() {
+        let value = Value::Function("foo".to_string(), Types::function(Types::void(), vec![]));
+        assert_eq!(value.emit(), "ptr @foo");
+    }
+    #[test]
     pub fn test_int_constants() {
         let value = Values::integer("1256", 32);
         assert_eq!(value.emit(), "i32 1256");
@@ -145,4 +548,132 @@ mod tests {
         let value = Values::structure(vec![Values::integer("1256", 32)]);
         assert_eq!(value.emit(), "{i32} {i32 1256}");
     }
+    #[test]
+    pub fn test_array_constants() {
+        let value = Values::array(
+            Types::integer(32),
+            vec![Values::integer("1", 32), Values::integer("2", 32)],
+        );
+        assert_eq!(value.emit(), "[ 2 x i32 ] [i32 1, i32 2]");
+    }
+    #[test]
+    pub fn test_vector_constants() {
+        let value = Values::vector(
+            Types::integer(32),
+            vec![Values::integer("1", 32), Values::integer("2", 32)],
+        );
+        assert_eq!(value.emit(), "<2 x i32> <i32 1, i32 2>");
+        assert_eq!(
+            Values::try_vector(Types::integer(32), vec![]),
+            Err(BuildError::EmptyVector(Types::vector(
+                0,
+                Types::integer(32)
+            )))
+        );
+        assert_eq!(
+            Values::try_vector(
+                Types::array(1, Types::integer(8)),
+                vec![Values::c_string("")]
+            ),
+            Err(BuildError::InvalidVectorElement(Types::array(
+                1,
+                Types::integer(8)
+            )))
+        );
+    }
+    #[test]
+    pub fn test_string_constants() {
+        let value = Values::c_string("Hello, world!");
+        assert_eq!(value.emit(), "[ 14 x i8 ] c\"Hello, world!\\00\"");
+        let value = Values::c_string("say \"hi\"\\\n");
+        assert_eq!(value.emit(), "[ 11 x i8 ] c\"say \\22hi\\22\\5C\\0A\\00\"");
+    }
+
+    #[test]
+    pub fn test_float_constants() {
+        assert_eq!(Values::float("0.5", Types::fp32()).emit(), "float 0.5");
+        assert_eq!(Values::float("2", Types::fp64()).emit(), "double 2.0");
+        assert_eq!(Values::float("0.1", Types::fp64()).emit(), "double 0.1");
+        assert_eq!(
+            Values::float("0.1", Types::fp32()).emit(),
+            "float 0x3FB99999A0000000"
+        );
+        assert_eq!(Values::float("0.1", Types::fp16()).emit(), "half 0xH2E66");
+        assert_eq!(
+            Values::float("1024.5", Types::fp128()).emit(),
+            "fp128 0xL00000000000000004009002000000000"
+        );
+        let value = Values::float("0xH3E00", Types::fp16());
+        assert_eq!(value.emit(), "half 0xH3E00");
+        assert_eq!(value.float_value(), Some(1.5));
+        assert!(Values::try_float("0x", Types::fp64()).is_err());
+    }
+
+    #[test]
+    pub fn test_constant_expressions() {
+        let table = Value::GlobalIdentifier("table".to_string(), Type::Ptr);
+        let table_type = Types::array(4, Types::integer(32));
+        let element = Values::getelementptr(
+            table_type.clone(),
+            table.clone(),
+            vec![Values::integer("0", 64), Values::integer("1", 64)],
+        );
+        assert_eq!(
+            element.emit(),
+            "ptr getelementptr ([ 4 x i32 ], ptr @table, i64 0, i64 1)"
+        );
+        assert_eq!(
+            Values::ptrtoint(element, Types::integer(64)).emit(),
+            "i64 ptrtoint (ptr getelementptr ([ 4 x i32 ], ptr @table, i64 0, i64 1) to i64)"
+        );
+        assert_eq!(
+            Values::null(Types::pointer(1)).emit(),
+            "ptr addrspace(1) null"
+        );
+        assert_eq!(Values::undef(Types::integer(8)).emit(), "i8 undef");
+        assert_eq!(Values::poison(Types::fp64()).emit(), "double poison");
+
+        let local = Value::LocalIdentifier("index".to_string(), Types::integer(64));
+        assert_eq!(
+            Values::try_getelementptr(table_type, table, vec![local.clone()]),
+            Err(BuildError::NonConstantOperand(local))
+        );
+        assert_eq!(
+            Values::try_null(Types::integer(64)),
+            Err(BuildError::NotPointer(Types::integer(64)))
+        );
+        assert!(Values::try_cast(
+            CastOperator::PtrToInt,
+            Values::integer("1", 32),
+            Types::integer(64)
+        )
+        .is_err());
+    }
+
+    #[test]
+    pub fn test_invalid_constants() {
+        assert_eq!(
+            Values::try_integer("10", 8388608),
+            Err(BuildError::IntegerTooWide(8388608))
+        );
+        assert_eq!(
+            Values::try_integer("1-0", 32),
+            Err(BuildError::InvalidNumber("1-0".to_string()))
+        );
+        assert_eq!(
+            Values::try_integer("-", 32),
+            Err(BuildError::InvalidNumber("-".to_string()))
+        );
+        assert_eq!(
+            Values::try_integer("", 32),
+            Err(BuildError::InvalidNumber("".to_string()))
+        );
+        assert_eq!(
+            Values::try_integer("5", 0),
+            Err(BuildError::ZeroWidthInteger)
+        );
+        assert!(Values::try_integer("-5", 1).is_ok());
+        assert!(Values::try_float("1.5", Types::integer(32)).is_err());
+        assert!(Values::try_array(Types::integer(8), vec![Values::integer("1", 32)]).is_err());
+    }
 }
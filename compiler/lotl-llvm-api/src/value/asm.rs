@@ -0,0 +1,124 @@
+use crate::error::BuildError;
+use crate::types::Type;
+use crate::value::{append_escaped, Value};
+use crate::IRComponent;
+use std::string::{String, ToString};
+
+/// Represents a fragment of inline assembly, which is called like a function.
+///
+/// The constraints describe how the operands of the assembly are passed, separated by commas:
+/// outputs start with `=` and are returned, clobbers start with `~`,
+/// and every other constraint takes an argument of the call.
+/// A single output is returned as is, while multiple outputs are returned as a structure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InlineAsm {
+    pub(crate) template: String,
+    pub(crate) constraints: String,
+    pub(crate) signature: Type,
+    pub(crate) side_effects: bool,
+    pub(crate) align_stack: bool,
+    pub(crate) intel_dialect: bool,
+    pub(crate) unwind: bool,
+}
+
+impl InlineAsm {
+    /// Creates a fragment of inline assembly with the template and constraints,
+    /// called with the function type.
+    pub fn new(template: &str, constraints: &str, signature: Type) -> Self {
+        InlineAsm {
+            template: template.to_string(),
+            constraints: constraints.to_string(),
+            signature,
+            side_effects: false,
+            align_stack: false,
+            intel_dialect: false,
+            unwind: false,
+        }
+    }
+
+    /// Marks the assembly as having side effects not described by its constraints,
+    /// so it is never removed or reordered, such as for a `syscall`.
+    pub fn with_side_effects(mut self) -> Self {
+        self.side_effects = true;
+        self
+    }
+
+    /// Marks the assembly as needing the stack to be aligned, as it calls other functions.
+    pub fn with_aligned_stack(mut self) -> Self {
+        self.align_stack = true;
+        self
+    }
+
+    /// Marks the template as written in the Intel dialect, instead of AT&T.
+    pub fn with_intel_dialect(mut self) -> Self {
+        self.intel_dialect = true;
+        self
+    }
+
+    /// Marks the assembly as able to raise exceptions, so it can be called with `invoke`.
+    pub fn with_unwind(mut self) -> Self {
+        self.unwind = true;
+        self
+    }
+
+    /// Returns the function type the assembly is called with.
+    pub fn signature(&self) -> &Type {
+        &self.signature
+    }
+
+    /// Returns a value of the assembly, which can be used as the callee of a `call`.
+    pub fn reference(&self) -> Value {
+        Value::InlineAsm(self.clone())
+    }
+}
+
+/// Checks that the outputs of the constraints match the return type of the signature,
+/// and that the other constraints match its parameters.
+pub(crate) fn check_inline_asm(asm: &InlineAsm) -> Result<(), BuildError> {
+    let Type::Function(return_type, parameters, false) = &asm.signature else {
+        return Err(BuildError::InvalidConstraints(asm.clone()));
+    };
+    let (mut outputs, mut inputs) = (0, 0);
+    for constraint in asm.constraints.split(',').filter(|x| !x.is_empty()) {
+        if constraint.starts_with('~') {
+            continue;
+        }
+        // indirect outputs are written through a pointer argument
+        match constraint.starts_with('=') && !constraint.starts_with("=*") {
+            true => outputs += 1,
+            false => inputs += 1,
+        }
+    }
+    let returns = match return_type.resolved() {
+        Type::Void => 0,
+        Type::Structure(elements) if outputs > 1 => elements.len(),
+        _ => 1,
+    };
+    if returns != outputs || parameters.len() != inputs {
+        return Err(BuildError::InvalidConstraints(asm.clone()));
+    }
+    Ok(())
+}
+
+impl IRComponent for InlineAsm {
+    fn append_to_string(&self, string: &mut String) {
+        string.push_str("asm ");
+        if self.side_effects {
+            string.push_str("sideeffect ");
+        }
+        if self.align_stack {
+            string.push_str("alignstack ");
+        }
+        if self.intel_dialect {
+            string.push_str("inteldialect ");
+        }
+        if self.unwind {
+            string.push_str("unwind ");
+        }
+        string.push('"');
+        append_escaped(string, self.template.as_bytes());
+        string.push_str("\", \"");
+        append_escaped(string, self.constraints.as_bytes());
+        string.push('"');
+    }
+}
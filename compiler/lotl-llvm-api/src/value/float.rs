@@ -0,0 +1,152 @@
+use crate::types::Type;
+use std::format;
+use std::string::String;
+
+/// Returns the number of a floating-point constant, written in decimal or in one of the
+/// hexadecimal forms of LLVM: `0x` for the bits of a `double`, `0xH` for the bits of a `half`
+/// and `0xL` for the bits of an `fp128`, lower word first.
+pub(crate) fn float_value(contents: &str) -> Option<f64> {
+    if let Some(digits) = contents.strip_prefix("0xH") {
+        let bits = u16::from_str_radix(digits, 16).ok()?;
+        return Some(half_value(bits) as f64);
+    }
+    if let Some(digits) = contents.strip_prefix("0xL") {
+        let bits = u128::from_str_radix(digits, 16).ok()?;
+        return Some(quad_value((bits >> 64) as u64, bits as u64));
+    }
+    if let Some(digits) = contents.strip_prefix("0x") {
+        return Some(f64::from_bits(u64::from_str_radix(digits, 16).ok()?));
+    }
+    contents.parse().ok()
+}
+
+/// Appends the floating-point constant of the type, in decimal if LLVM reads it back as written,
+/// and in hexadecimal otherwise.
+///
+/// LLVM only accepts decimals with a fraction, which must be exact unless the type is `double`.
+/// Hexadecimal `half` and `fp128` constants are kept as written, since they are always exact.
+pub(crate) fn append_float(string: &mut String, contents: &str, ty: &Type) {
+    let Some(value) = float_value(contents) else {
+        string.push_str(contents);
+        return;
+    };
+    let decimal = is_decimal(contents);
+    let hexadecimal = match ty {
+        Type::Half if contents.starts_with("0xH") => None,
+        Type::Half => {
+            let bits = half_bits(value as f32);
+            let exact = decimal && half_value(bits as u16) as f64 == value;
+            Some(format!("0xH{:04X}", bits)).filter(|_| !exact)
+        }
+        Type::Float => {
+            let rounded = value as f32 as f64;
+            let exact = decimal && rounded == value;
+            Some(format!("0x{:016X}", rounded.to_bits())).filter(|_| !exact)
+        }
+        Type::FP128 if contents.starts_with("0xL") => None,
+        Type::FP128 => {
+            let bits = quad_bits(value);
+            Some(format!(
+                "0xL{:016X}{:016X}",
+                bits as u64,
+                (bits >> 64) as u64
+            ))
+        }
+        _ => Some(format!("0x{:016X}", value.to_bits())).filter(|_| !decimal),
+    };
+    match hexadecimal {
+        Some(hexadecimal) => string.push_str(&hexadecimal),
+        None if decimal && !contents.contains('.') => {
+            string.push_str(contents);
+            string.push_str(".0");
+        }
+        None => string.push_str(contents),
+    }
+}
+
+/// Returns true if the contents are a plain decimal number, such as `-12` or `0.5`.
+pub(crate) fn is_decimal(contents: &str) -> bool {
+    let digits = contents.strip_prefix('-').unwrap_or(contents);
+    let mut parts = digits.splitn(2, '.');
+    let whole = parts.next().unwrap_or_default();
+    let fraction = parts.next().unwrap_or_default();
+    !whole.is_empty()
+        && whole.bytes().all(|x| x.is_ascii_digit())
+        && fraction.bytes().all(|x| x.is_ascii_digit())
+}
+
+/// Rounds a `float` to the nearest `half`, returning its bits.
+pub(crate) fn half_bits(value: f32) -> u64 {
+    let bits = value.to_bits();
+    let sign = (bits >> 16) & 0x8000;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127;
+    let mantissa = bits & 0x7f_ffff;
+    let magnitude = match exponent {
+        128 if mantissa != 0 => 0x7e00,
+        16.. => 0x7c00,
+        // subnormal halves keep fewer bits of the mantissa, along with its leading one
+        -25..=-15 => round_shifted(mantissa | 0x80_0000, (-1 - exponent) as u32),
+        ..=-26 => 0,
+        _ => (((exponent + 15) as u32) << 10) + round_shifted(mantissa, 13),
+    };
+    (sign | magnitude) as u64
+}
+
+/// Shifts the bits right, rounding to the nearest value and to even on ties.
+/// Rounding may carry into the exponent above the bits, which is still correct.
+fn round_shifted(bits: u32, shift: u32) -> u32 {
+    let kept = bits >> shift;
+    let rest = bits & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    match rest > halfway || (rest == halfway && kept & 1 == 1) {
+        true => kept + 1,
+        false => kept,
+    }
+}
+
+/// Converts a `double` into the bits of an `fp128`, which holds it exactly.
+pub(crate) fn quad_bits(value: f64) -> u128 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 63) as u128) << 127;
+    let exponent = ((bits >> 52) & 0x7ff) as i64;
+    let mantissa = (bits & ((1 << 52) - 1)) as u128;
+    match exponent {
+        0 if mantissa == 0 => sign,
+        0x7ff => sign | (0x7fff << 112) | (mantissa << 60),
+        // subnormal doubles are normal in fp128, so the mantissa is shifted up to its leading one
+        0 => {
+            let shift = mantissa.leading_zeros() - (128 - 52) + 1;
+            let normalized = (mantissa << shift) & ((1 << 52) - 1);
+            let exponent = (16383 - 1022 - shift as i64) as u128;
+            sign | (exponent << 112) | (normalized << 60)
+        }
+        _ => sign | (((exponent - 1023 + 16383) as u128) << 112) | (mantissa << 60),
+    }
+}
+
+/// Converts the bits of a `half` into a `float`, which holds it exactly.
+pub(crate) fn half_value(bits: u16) -> f32 {
+    let sign = if bits >> 15 == 1 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// Converts the words of an `fp128` into the nearest `double`, truncating the mantissa.
+pub(crate) fn quad_value(low: u64, high: u64) -> f64 {
+    let bits = (high as u128) << 64 | low as u128;
+    let sign = (bits >> 127) as u64;
+    let exponent = ((bits >> 112) & 0x7fff) as i64;
+    let mantissa = ((bits >> 60) & ((1 << 52) - 1)) as u64;
+    let exponent = match exponent {
+        0 => 0,
+        0x7fff => 0x7ff,
+        _ => (exponent - 16383 + 1023).clamp(0, 0x7ff),
+    };
+    f64::from_bits(sign << 63 | (exponent as u64) << 52 | mantissa)
+}
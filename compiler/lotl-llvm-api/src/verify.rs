@@ -0,0 +1,632 @@
+use crate::analysis::{ControlFlowGraph, DominatorTree};
+use crate::instruction::{BlockId, InstId, InstructionKind};
+use crate::module::{FunctionAttribute, FunctionBody};
+use crate::types::Type;
+use crate::value::Value;
+use crate::IRComponent;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+/// Represents a problem found while verifying a module.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerifierError {
+    /// The name of the function the problem is in.
+    pub function: String,
+    /// The label of the block the problem is in, if it is inside of a block.
+    pub block: Option<String>,
+    /// The kind of problem.
+    pub kind: VerifierErrorKind,
+}
+
+/// Represents the kind of problem found while verifying a module.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum VerifierErrorKind {
+    /// The block does not end with a terminator.
+    MissingTerminator,
+    /// A terminator appears before the end of the block.
+    TerminatorNotLast,
+    /// A `ret` returns a value of another type than the function does.
+    ReturnTypeMismatch {
+        /// The return type of the function.
+        expected: Type,
+        /// The type returned by the instruction.
+        found: Type,
+    },
+    /// The operands of an instruction have types it does not accept.
+    InvalidOperands(String),
+    /// A register is used with another type than it was defined with.
+    RegisterTypeMismatch {
+        /// The name of the register.
+        register: String,
+        /// The type the register was defined with.
+        expected: Type,
+        /// The type the register is used with.
+        found: Type,
+    },
+    /// A register is used, but never defined.
+    UndefinedRegister(String),
+    /// A register is defined more than once.
+    RedefinedRegister(String),
+    /// A register is used in a place its definition does not dominate.
+    UseNotDominated(String),
+    /// Two blocks of the function have the same label.
+    DuplicateBlock(String),
+    /// The entry block is the target of a branch.
+    EntryHasPredecessors,
+    /// A `phi` instruction appears after an instruction that is not a `phi`.
+    PhiNotAtStart,
+    /// A `phi` instruction has an incoming value from a block that is not a predecessor.
+    PhiIncomingNotPredecessor(String),
+    /// A `phi` instruction has no incoming value for a predecessor.
+    PhiMissingIncoming(String),
+    /// The function has two attributes which contradict each other.
+    ConflictingAttributes(FunctionAttribute, FunctionAttribute),
+    /// A `landingpad` instruction is not the first instruction of its block after any `phi`.
+    LandingPadNotFirst,
+    /// An `invoke` unwinds to a block that does not start with a `landingpad`.
+    InvalidUnwindDestination(String),
+    /// A block starting with a `landingpad` is reached by an edge that does not unwind.
+    LandingPadNotUnwindDestination,
+    /// The function has a `landingpad`, but no personality function.
+    MissingPersonality,
+    /// A function is called, but not declared in the module.
+    UndeclaredFunction(String),
+}
+
+impl Display for VerifierError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "in @{}", self.function)?;
+        if let Some(block) = &self.block {
+            write!(f, ", block %{}", block)?;
+        }
+        write!(f, ": ")?;
+        match &self.kind {
+            VerifierErrorKind::MissingTerminator => {
+                write!(f, "block does not end with a terminator")
+            }
+            VerifierErrorKind::TerminatorNotLast => {
+                write!(f, "terminator appears before the end of the block")
+            }
+            VerifierErrorKind::ReturnTypeMismatch { expected, found } => {
+                write!(f, "expected return type {:?}, found {:?}", expected, found)
+            }
+            VerifierErrorKind::InvalidOperands(message) => write!(f, "{}", message),
+            VerifierErrorKind::RegisterTypeMismatch {
+                register,
+                expected,
+                found,
+            } => write!(
+                f,
+                "register %{} is of type {:?}, but used as {:?}",
+                register, expected, found
+            ),
+            VerifierErrorKind::UndefinedRegister(name) => {
+                write!(f, "register %{} is never defined", name)
+            }
+            VerifierErrorKind::RedefinedRegister(name) => {
+                write!(f, "register %{} is defined more than once", name)
+            }
+            VerifierErrorKind::UseNotDominated(name) => {
+                write!(f, "definition of %{} does not dominate its use", name)
+            }
+            VerifierErrorKind::DuplicateBlock(label) => {
+                write!(f, "block %{} is defined more than once", label)
+            }
+            VerifierErrorKind::EntryHasPredecessors => {
+                write!(f, "the entry block cannot be branched to")
+            }
+            VerifierErrorKind::PhiNotAtStart => {
+                write!(f, "phi instructions must be at the start of the block")
+            }
+            VerifierErrorKind::PhiIncomingNotPredecessor(label) => {
+                write!(
+                    f,
+                    "phi has an incoming value from %{}, which is not a predecessor",
+                    label
+                )
+            }
+            VerifierErrorKind::PhiMissingIncoming(label) => {
+                write!(
+                    f,
+                    "phi has no incoming value for the predecessor %{}",
+                    label
+                )
+            }
+            VerifierErrorKind::ConflictingAttributes(first, second) => {
+                write!(
+                    f,
+                    "attributes {} and {} cannot be combined",
+                    first.emit(),
+                    second.emit()
+                )
+            }
+            VerifierErrorKind::LandingPadNotFirst => {
+                write!(f, "landingpad must be the first instruction after the phis")
+            }
+            VerifierErrorKind::InvalidUnwindDestination(label) => {
+                write!(
+                    f,
+                    "invoke unwinds to %{}, which is not a landing pad",
+                    label
+                )
+            }
+            VerifierErrorKind::LandingPadNotUnwindDestination => {
+                write!(f, "landing pads can only be reached by unwinding")
+            }
+            VerifierErrorKind::MissingPersonality => {
+                write!(f, "landingpad needs the function to have a personality")
+            }
+            VerifierErrorKind::UndeclaredFunction(name) => {
+                write!(f, "@{} is called, but not declared in the module", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifierError {}
+
+/// The instruction defining a register, or nothing for parameters,
+/// along with the type of the register.
+type Definition = (Option<InstId>, Type);
+
+/// Verifies the body of a function, returning every problem found.
+pub(crate) fn verify_function(
+    name: &str,
+    return_type: &Type,
+    arguments: &[Value],
+    body: &FunctionBody,
+) -> Vec<VerifierError> {
+    let mut errors = Errors {
+        function: name.to_string(),
+        list: Vec::new(),
+    };
+
+    // check the structure of each block
+    let blocks = body.blocks();
+    let mut labels = HashSet::new();
+    for block in &blocks {
+        let label = body.block(*block).label();
+        if !labels.insert(label) {
+            errors.add(
+                Some(label),
+                VerifierErrorKind::DuplicateBlock(label.to_string()),
+            );
+        }
+        let instructions = body.block(*block).instructions();
+        let mut seen_non_phi = false;
+        for (index, id) in instructions.iter().enumerate() {
+            let kind = body.instruction(*id).kind();
+            if kind.is_terminator() && index + 1 != instructions.len() {
+                errors.add(Some(label), VerifierErrorKind::TerminatorNotLast);
+            }
+            if let Err(message) = kind.check_types() {
+                errors.add(Some(label), VerifierErrorKind::InvalidOperands(message));
+            }
+            if let InstructionKind::Return { value } = kind {
+                let found = value.as_ref().map_or(Type::Void, |x| x.ty().clone());
+                if found != *return_type {
+                    errors.add(
+                        Some(label),
+                        VerifierErrorKind::ReturnTypeMismatch {
+                            expected: return_type.clone(),
+                            found,
+                        },
+                    );
+                }
+            }
+            match kind {
+                InstructionKind::Phi { .. } if seen_non_phi => {
+                    errors.add(Some(label), VerifierErrorKind::PhiNotAtStart)
+                }
+                InstructionKind::Phi { .. } => {}
+                InstructionKind::LandingPad { .. } if seen_non_phi => {
+                    errors.add(Some(label), VerifierErrorKind::LandingPadNotFirst);
+                }
+                _ => seen_non_phi = true,
+            }
+        }
+        if body.block(*block).terminator(body).is_none() {
+            errors.add(Some(label), VerifierErrorKind::MissingTerminator);
+        }
+    }
+
+    let cfg = ControlFlowGraph::new(body);
+    let dominators = DominatorTree::new(&cfg);
+    for block in &blocks {
+        if cfg.successors(*block).contains(&body.entry()) {
+            let label = body.block(*block).label();
+            errors.add(Some(label), VerifierErrorKind::EntryHasPredecessors);
+        }
+    }
+
+    // landing pads are reached exactly by the unwinding edges of invokes
+    for block in &blocks {
+        let Some(terminator) = body.block(*block).terminator(body) else {
+            continue;
+        };
+        let label = body.block(*block).label();
+        let kind = body.instruction(terminator).kind();
+        for successor in kind.successors() {
+            let unwinds =
+                matches!(kind, InstructionKind::Invoke { unwind, .. } if *unwind == successor);
+            let destination = body.block(successor).label();
+            match (unwinds, is_landing_pad(body, successor)) {
+                (true, false) => errors.add(
+                    Some(label),
+                    VerifierErrorKind::InvalidUnwindDestination(destination.to_string()),
+                ),
+                (false, true) => errors.add(
+                    Some(destination),
+                    VerifierErrorKind::LandingPadNotUnwindDestination,
+                ),
+                _ => {}
+            }
+        }
+    }
+
+    // find where every register is defined
+    let mut definitions: HashMap<String, Definition> = HashMap::new();
+    for argument in arguments {
+        if let Value::LocalIdentifier(name, ty) = argument {
+            definitions.insert(name.clone(), (None, ty.clone()));
+        }
+    }
+    for id in body.instructions() {
+        let Some(Value::LocalIdentifier(name, ty)) = body.instruction(id).result() else {
+            continue;
+        };
+        if definitions
+            .insert(name.clone(), (Some(id), ty.clone()))
+            .is_some()
+        {
+            let label = body.block(body.instruction(id).block().unwrap()).label();
+            errors.add(
+                Some(label),
+                VerifierErrorKind::RedefinedRegister(name.clone()),
+            );
+        }
+    }
+
+    // check every use against its definition
+    let uses = Uses {
+        body,
+        cfg: &cfg,
+        definitions: &definitions,
+        dominators: &dominators,
+    };
+    for block in &blocks {
+        let label = body.block(*block).label();
+        for (index, id) in body.block(*block).instructions().iter().enumerate() {
+            let InstructionKind::Phi { incoming, .. } = body.instruction(*id).kind() else {
+                for operand in body.instruction(*id).kind().operands() {
+                    uses.check(&mut errors, operand, *block, Some(index));
+                }
+                continue;
+            };
+            let predecessors = cfg.predecessors(*block);
+            for (value, from) in incoming {
+                match predecessors.contains(from) {
+                    // values flowing along an edge only need to be available at its end
+                    true => uses.check(&mut errors, value, *from, None),
+                    false => errors.add(
+                        Some(label),
+                        VerifierErrorKind::PhiIncomingNotPredecessor(
+                            body.block(*from).label().to_string(),
+                        ),
+                    ),
+                }
+            }
+            for predecessor in predecessors {
+                if !incoming.iter().any(|(_, from)| from == predecessor) {
+                    errors.add(
+                        Some(label),
+                        VerifierErrorKind::PhiMissingIncoming(
+                            body.block(*predecessor).label().to_string(),
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    errors.list
+}
+
+/// Returns whether the first instruction of the block after any `phi` is a `landingpad`.
+fn is_landing_pad(body: &FunctionBody, block: BlockId) -> bool {
+    body.block(block)
+        .instructions()
+        .iter()
+        .map(|x| body.instruction(*x).kind())
+        .find(|x| !matches!(x, InstructionKind::Phi { .. }))
+        .is_some_and(|x| matches!(x, InstructionKind::LandingPad { .. }))
+}
+
+/// The problems found in a function.
+struct Errors {
+    function: String,
+    list: Vec<VerifierError>,
+}
+
+impl Errors {
+    fn add(&mut self, block: Option<&str>, kind: VerifierErrorKind) {
+        self.list.push(VerifierError {
+            function: self.function.clone(),
+            block: block.map(str::to_string),
+            kind,
+        });
+    }
+}
+
+/// Where every register of a function is defined, and how its blocks dominate each other.
+struct Uses<'a> {
+    body: &'a FunctionBody,
+    cfg: &'a ControlFlowGraph,
+    definitions: &'a HashMap<String, Definition>,
+    dominators: &'a DominatorTree,
+}
+
+impl Uses<'_> {
+    /// Checks a use of the value in the block, at the instruction index if given,
+    /// or at the end of the block otherwise.
+    fn check(&self, errors: &mut Errors, value: &Value, block: BlockId, position: Option<usize>) {
+        let Value::LocalIdentifier(name, found) = value else {
+            return;
+        };
+        let label = Some(self.body.block(block).label());
+        let Some((definition, expected)) = self.definitions.get(name) else {
+            errors.add(label, VerifierErrorKind::UndefinedRegister(name.clone()));
+            return;
+        };
+        if expected != found {
+            errors.add(
+                label,
+                VerifierErrorKind::RegisterTypeMismatch {
+                    register: name.clone(),
+                    expected: expected.clone(),
+                    found: found.clone(),
+                },
+            );
+        }
+        // uses in unreachable blocks are never executed, so they cannot violate dominance
+        let Some(definition) = definition.filter(|_| self.dominators.is_reachable(block)) else {
+            return;
+        };
+        let definition_block = self.body.instruction(definition).block().unwrap();
+        let dominates = match (position, self.body.instruction(definition).kind()) {
+            // the result of an invoke is only available once it returns to the normal block,
+            // which must be reached only from the invoke for the result to dominate its uses
+            (Some(_), InstructionKind::Invoke { normal, .. }) => {
+                self.cfg.predecessors(*normal).len() == 1
+                    && self.dominators.dominates(*normal, block)
+            }
+            (Some(index), _) if definition_block == block => {
+                let instructions = self.body.block(block).instructions();
+                instructions[..index].contains(&definition)
+            }
+            _ => self.dominators.dominates(definition_block, block),
+        };
+        if !dominates {
+            errors.add(label, VerifierErrorKind::UseNotDominated(name.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::module::{FunctionBody, GlobalFunction, Module};
+    use crate::types::Types;
+    use crate::value::{Value, Values};
+    use crate::verify::{VerifierError, VerifierErrorKind};
+    use std::string::ToString;
+    use std::vec;
+    use std::vec::Vec;
+
+    fn verify(return_type: crate::types::Type, body: FunctionBody) -> Vec<VerifierError> {
+        verify_with(Vec::new(), return_type, body)
+    }
+
+    fn verify_with(
+        declarations: Vec<GlobalFunction>,
+        return_type: crate::types::Type,
+        body: FunctionBody,
+    ) -> Vec<VerifierError> {
+        let mut module = Module::new();
+        module
+            .functions
+            .push(GlobalFunction::new("main", return_type).body(body));
+        module.functions.extend(declarations);
+        module.verify().err().unwrap_or_default()
+    }
+
+    fn kinds(errors: Vec<VerifierError>) -> Vec<VerifierErrorKind> {
+        errors.into_iter().map(|x| x.kind).collect()
+    }
+
+    #[test]
+    fn verify_valid_function() {
+        let errors = verify(
+            Types::integer(32),
+            FunctionBody::new(|block| {
+                let header = block.br_returning();
+                let counter =
+                    header.phi(Types::integer(32), vec![(Values::integer("0", 32), &block)]);
+                let incremented = header.add(counter.value(), Values::integer("1", 32));
+                let (repeat, exit) = header.br_if_returning(Values::integer("1", 1));
+                counter.add_incoming(incremented.clone(), &repeat);
+                repeat.goto(&header);
+                exit.ret(incremented);
+            }),
+        );
+        assert_eq!(errors, vec![]);
+    }
+
+    #[test]
+    fn reject_missing_terminator() {
+        let errors = verify(
+            Types::void(),
+            FunctionBody::new(|block| {
+                block.add(Values::integer("1", 32), Values::integer("2", 32));
+            }),
+        );
+        assert_eq!(
+            errors,
+            vec![VerifierError {
+                function: "main".to_string(),
+                block: Some("entry".to_string()),
+                kind: VerifierErrorKind::MissingTerminator,
+            }]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "in @main, block %entry: block does not end with a terminator"
+        );
+    }
+
+    #[test]
+    fn reject_misplaced_terminator() {
+        let errors = verify(
+            Types::void(),
+            FunctionBody::new(|block| {
+                block.ret_void();
+                block.ret_void();
+            }),
+        );
+        assert_eq!(kinds(errors), vec![VerifierErrorKind::TerminatorNotLast]);
+    }
+
+    #[test]
+    fn reject_mismatched_return() {
+        let errors = verify(
+            Types::void(),
+            FunctionBody::new(|block| {
+                block.ret(Values::integer("0", 32));
+            }),
+        );
+        assert_eq!(
+            kinds(errors),
+            vec![VerifierErrorKind::ReturnTypeMismatch {
+                expected: Types::void(),
+                found: Types::integer(32),
+            }]
+        );
+    }
+
+    #[test]
+    fn reject_undefined_and_mistyped_registers() {
+        let errors = verify(
+            Types::integer(32),
+            FunctionBody::new(|block| {
+                let sum = block.add(Values::integer("1", 32), Values::integer("2", 32));
+                let Value::LocalIdentifier(name, _) = sum else {
+                    unreachable!()
+                };
+                block.ret(Value::LocalIdentifier(name, Types::integer(64)));
+                let missing = Value::LocalIdentifier("missing".to_string(), Types::integer(32));
+                let next = block.br_returning();
+                next.ret(missing);
+            }),
+        );
+        assert!(errors
+            .iter()
+            .any(|x| matches!(x.kind, VerifierErrorKind::RegisterTypeMismatch { .. })));
+        assert!(errors
+            .iter()
+            .any(|x| x.kind == VerifierErrorKind::UndefinedRegister("missing".to_string())));
+    }
+
+    #[test]
+    fn reject_undominated_use() {
+        let errors = verify(
+            Types::integer(32),
+            FunctionBody::new(|block| {
+                let (if_true, if_false) = block.br_if_returning(Values::integer("1", 1));
+                let sum = if_true.add(Values::integer("1", 32), Values::integer("2", 32));
+                if_true.ret(sum.clone());
+                if_false.ret(sum);
+            }),
+        );
+        assert_eq!(
+            errors,
+            vec![VerifierError {
+                function: "main".to_string(),
+                block: Some("bb1".to_string()),
+                kind: VerifierErrorKind::UseNotDominated("r0".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn reject_phi_from_non_predecessor() {
+        let errors = verify(
+            Types::integer(32),
+            FunctionBody::new(|block| {
+                let (if_true, if_false) = block.br_if_returning(Values::integer("1", 1));
+                if_false.ret(Values::integer("0", 32));
+                let merge = if_true.br_returning();
+                let merged = merge.phi(
+                    Types::integer(32),
+                    vec![(Values::integer("10", 32), &if_false)],
+                );
+                merge.ret(merged.value());
+            }),
+        );
+        assert_eq!(
+            kinds(errors),
+            vec![
+                VerifierErrorKind::PhiIncomingNotPredecessor("bb1".to_string()),
+                VerifierErrorKind::PhiMissingIncoming("bb0".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_undeclared_calls() {
+        let callee = GlobalFunction::new("answer", Types::integer(32));
+        let body = FunctionBody::new(|block| {
+            let answer = block.call(callee.reference(), vec![]).unwrap();
+            let counted = block.ctpop(answer);
+            block.ret(counted);
+        });
+        assert_eq!(
+            verify(Types::integer(32), body.clone()),
+            vec![VerifierError {
+                function: "main".to_string(),
+                block: Some("entry".to_string()),
+                kind: VerifierErrorKind::UndeclaredFunction("answer".to_string()),
+            }]
+        );
+        assert!(verify_with(vec![callee], Types::integer(32), body).is_empty());
+    }
+
+    #[test]
+    fn reject_invalid_unwinding() {
+        let callee = GlobalFunction::new("may_throw", Types::integer(32));
+        let errors = verify_with(
+            vec![callee.clone()],
+            Types::integer(32),
+            FunctionBody::new(|block| {
+                let (result, normal, unwind) = block.invoke_returning(callee.reference(), vec![]);
+                normal.ret(result.clone().unwrap());
+                unwind.add(Values::integer("1", 32), Values::integer("2", 32));
+                unwind.landingpad(Types::pointer(0), true, vec![]);
+                let cleanup = unwind.br_returning();
+                cleanup.landingpad(Types::pointer(0), true, vec![]);
+                cleanup.ret(result.unwrap());
+            }),
+        );
+        assert_eq!(
+            kinds(errors),
+            vec![
+                VerifierErrorKind::LandingPadNotFirst,
+                VerifierErrorKind::InvalidUnwindDestination("bb1".to_string()),
+                VerifierErrorKind::LandingPadNotUnwindDestination,
+                VerifierErrorKind::UseNotDominated("r0".to_string()),
+                VerifierErrorKind::MissingPersonality,
+            ]
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use crate::integer::Integer;
+use crate::interpreter::{HostFunction, Interpreter};
+use crate::trap::Trap;
+use crate::value::RuntimeValue;
+use std::slice::Iter;
+
+/// Returns the host functions every interpreter starts out with.
+pub(crate) fn functions() -> [(&'static str, HostFunction); 2] {
+    [("printf", printf), ("puts", puts)]
+}
+
+/// Prints a string formatted with the arguments, like the C function,
+/// and returns the number of bytes printed.
+fn printf(
+    interpreter: &mut Interpreter<'_>,
+    arguments: &[RuntimeValue],
+) -> Result<RuntimeValue, Trap> {
+    let Some(RuntimeValue::Pointer(format)) = arguments.first() else {
+        return Err(Trap::Host("printf expects a format string".to_string()));
+    };
+    let format = interpreter.read_c_string(*format)?;
+    let printed = self::format(interpreter, &format, &mut arguments[1..].iter())?;
+    interpreter.print(&printed);
+    Ok(RuntimeValue::integer(printed.len() as i64, 32))
+}
+
+/// Prints a string followed by a newline, like the C function.
+fn puts(
+    interpreter: &mut Interpreter<'_>,
+    arguments: &[RuntimeValue],
+) -> Result<RuntimeValue, Trap> {
+    let Some(RuntimeValue::Pointer(string)) = arguments.first() else {
+        return Err(Trap::Host("puts expects a string".to_string()));
+    };
+    let mut printed = interpreter.read_c_string(*string)?;
+    printed.push(b'\n');
+    interpreter.print(&printed);
+    Ok(RuntimeValue::integer(printed.len() as i64, 32))
+}
+
+/// The options of a single conversion, such as `%-08.3ld`.
+#[derive(Default)]
+struct Specification {
+    left: bool,
+    plus: bool,
+    space: bool,
+    zero: bool,
+    alternate: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+fn format(
+    interpreter: &Interpreter<'_>,
+    format: &[u8],
+    arguments: &mut Iter<RuntimeValue>,
+) -> Result<Vec<u8>, Trap> {
+    let mut output = Vec::new();
+    let mut bytes = format.iter().copied().peekable();
+    while let Some(byte) = bytes.next() {
+        if byte != b'%' {
+            output.push(byte);
+            continue;
+        }
+        let mut specification = Specification::default();
+        while let Some(flag) = bytes.next_if(|x| b"-+ 0#".contains(x)) {
+            match flag {
+                b'-' => specification.left = true,
+                b'+' => specification.plus = true,
+                b' ' => specification.space = true,
+                b'0' => specification.zero = true,
+                _ => specification.alternate = true,
+            }
+        }
+        if bytes.next_if_eq(&b'*').is_some() {
+            let width = integer(next(arguments)?)?.to_i64();
+            specification.left |= width < 0;
+            specification.width = width.unsigned_abs() as usize;
+        } else {
+            specification.width = digits(&mut bytes);
+        }
+        if bytes.next_if_eq(&b'.').is_some() {
+            specification.precision = match bytes.next_if_eq(&b'*') {
+                Some(_) => usize::try_from(integer(next(arguments)?)?.to_i64()).ok(),
+                None => Some(digits(&mut bytes)),
+            };
+        }
+        // arguments are passed with their own width, the length only truncates them
+        let mut bits = 32;
+        while let Some(length) = bytes.next_if(|x| b"hlLqjzt".contains(x)) {
+            bits = match (length, bits) {
+                (b'h', 16) => 8,
+                (b'h', _) => 16,
+                _ => 64,
+            };
+        }
+
+        let Some(conversion) = bytes.next() else {
+            return Err(Trap::Host("printf format ends in a conversion".to_string()));
+        };
+        match conversion {
+            b'%' => output.push(b'%'),
+            b'd' | b'i' => {
+                let value = resize(integer(next(arguments)?)?, bits, true);
+                let sign = sign(&specification, value.is_negative());
+                let magnitude = match value.is_negative() {
+                    true => value.neg().to_unsigned_string(),
+                    false => value.to_unsigned_string(),
+                };
+                pad_integer(&mut output, &specification, &sign, magnitude);
+            }
+            b'u' | b'x' | b'X' | b'o' => {
+                let value = resize(integer(next(arguments)?)?, bits, false).to_u64();
+                let (digits, prefix) = match conversion {
+                    b'u' => (value.to_string(), ""),
+                    b'x' => (format!("{:x}", value), "0x"),
+                    b'X' => (format!("{:X}", value), "0X"),
+                    _ => (format!("{:o}", value), "0"),
+                };
+                let prefix = match specification.alternate && value != 0 {
+                    true => prefix,
+                    false => "",
+                };
+                pad_integer(&mut output, &specification, prefix, digits);
+            }
+            b'c' => {
+                let byte = integer(next(arguments)?)?.to_u64() as u8;
+                pad(&mut output, &specification, "", &[byte], false);
+            }
+            b's' => {
+                let RuntimeValue::Pointer(address) = next(arguments)? else {
+                    return Err(Trap::Host("printf expects a string for %s".to_string()));
+                };
+                let mut string = interpreter.read_c_string(*address)?;
+                string.truncate(specification.precision.unwrap_or(usize::MAX));
+                pad(&mut output, &specification, "", &string, false);
+            }
+            b'p' => {
+                let RuntimeValue::Pointer(address) = next(arguments)? else {
+                    return Err(Trap::Host("printf expects a pointer for %p".to_string()));
+                };
+                let text = format!("0x{:x}", address);
+                pad(&mut output, &specification, "", text.as_bytes(), false);
+            }
+            b'f' | b'F' | b'e' | b'E' | b'g' | b'G' => {
+                let RuntimeValue::Float(value, _) = next(arguments)? else {
+                    return Err(Trap::Host("printf expects a floating point".to_string()));
+                };
+                let sign = sign(&specification, value.is_sign_negative());
+                let text = float(value.abs(), conversion, &specification);
+                let zero = specification.zero && value.is_finite();
+                pad(&mut output, &specification, &sign, text.as_bytes(), zero);
+            }
+            _ => {
+                let conversion = conversion as char;
+                return Err(Trap::Unsupported(format!(
+                    "printf conversion %{}",
+                    conversion
+                )));
+            }
+        }
+    }
+    Ok(output)
+}
+
+fn next<'a>(arguments: &mut Iter<'a, RuntimeValue>) -> Result<&'a RuntimeValue, Trap> {
+    arguments
+        .next()
+        .ok_or_else(|| Trap::Host("printf is missing an argument".to_string()))
+}
+
+fn integer(argument: &RuntimeValue) -> Result<Integer, Trap> {
+    match argument {
+        RuntimeValue::Integer(integer) => Ok(integer.clone()),
+        _ => Err(Trap::Host("printf expects an integer".to_string())),
+    }
+}
+
+fn digits<I: Iterator<Item = u8>>(bytes: &mut std::iter::Peekable<I>) -> usize {
+    let mut number = 0;
+    while let Some(digit) = bytes.next_if(u8::is_ascii_digit) {
+        number = number * 10 + (digit - b'0') as usize;
+    }
+    number
+}
+
+/// Converts an integer argument to the width of the length modifier.
+fn resize(integer: Integer, bits: u32, signed: bool) -> Integer {
+    match integer.width() >= bits {
+        true => integer.trunc(bits),
+        false if signed => integer.sext(bits),
+        false => integer.zext(bits),
+    }
+}
+
+fn sign(specification: &Specification, negative: bool) -> String {
+    match (negative, specification.plus, specification.space) {
+        (true, _, _) => "-",
+        (false, true, _) => "+",
+        (false, false, true) => " ",
+        _ => "",
+    }
+    .to_string()
+}
+
+/// Pads an integer to its precision with zeros, then to its width.
+fn pad_integer(output: &mut Vec<u8>, specification: &Specification, prefix: &str, digits: String) {
+    let digits = match specification.precision {
+        Some(0) if digits == "0" => String::new(),
+        Some(precision) => format!("{:0>1$}", digits, precision),
+        None => digits,
+    };
+    let zero = specification.zero && specification.precision.is_none();
+    pad(output, specification, prefix, digits.as_bytes(), zero);
+}
+
+/// Pads the text to the width of the conversion, after its sign or prefix when padding with zeros.
+fn pad(output: &mut Vec<u8>, specification: &Specification, prefix: &str, text: &[u8], zero: bool) {
+    let padding = specification
+        .width
+        .saturating_sub(prefix.len() + text.len());
+    if specification.left {
+        output.extend_from_slice(prefix.as_bytes());
+        output.extend_from_slice(text);
+        output.extend(std::iter::repeat_n(b' ', padding));
+    } else if zero {
+        output.extend_from_slice(prefix.as_bytes());
+        output.extend(std::iter::repeat_n(b'0', padding));
+        output.extend_from_slice(text);
+    } else {
+        output.extend(std::iter::repeat_n(b' ', padding));
+        output.extend_from_slice(prefix.as_bytes());
+        output.extend_from_slice(text);
+    }
+}
+
+/// Formats the magnitude of a floating point for `%f`, `%e` or `%g`.
+fn float(value: f64, conversion: u8, specification: &Specification) -> String {
+    let upper = conversion.is_ascii_uppercase();
+    let text = match conversion.to_ascii_lowercase() {
+        _ if value.is_nan() => "nan".to_string(),
+        _ if value.is_infinite() => "inf".to_string(),
+        b'f' => format!("{:.*}", specification.precision.unwrap_or(6), value),
+        b'e' => exponential(value, specification.precision.unwrap_or(6)),
+        _ => {
+            // %g picks the shorter style for the number of significant digits
+            let significant = specification.precision.unwrap_or(6).max(1);
+            let exponent = exponential(value, significant - 1)
+                .split_once('e')
+                .map_or(0, |(_, x)| x.parse::<i32>().unwrap());
+            let text = match exponent < -4 || exponent >= significant as i32 {
+                true => exponential(value, significant - 1),
+                false => format!("{:.*}", (significant as i32 - 1 - exponent) as usize, value),
+            };
+            match specification.alternate {
+                true => text,
+                false => strip_zeros(&text),
+            }
+        }
+    };
+    match upper {
+        true => text.to_ascii_uppercase(),
+        false => text,
+    }
+}
+
+/// Formats a number like `1.500000e+00`.
+fn exponential(value: f64, precision: usize) -> String {
+    let text = format!("{:.*e}", precision, value);
+    let (mantissa, exponent) = text.split_once('e').unwrap();
+    let exponent = exponent.parse::<i32>().unwrap();
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+}
+
+/// Removes the trailing zeros of the fraction, and the decimal point if nothing is left of it.
+fn strip_zeros(text: &str) -> String {
+    let (number, exponent) = match text.find('e') {
+        Some(index) => text.split_at(index),
+        None => (text, ""),
+    };
+    let number = match number.contains('.') {
+        true => number.trim_end_matches('0').trim_end_matches('.'),
+        false => number,
+    };
+    format!("{}{}", number, exponent)
+}
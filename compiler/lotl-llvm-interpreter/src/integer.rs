@@ -0,0 +1,464 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+/// An integer of any width, holding the bits of its two's complement form.
+///
+/// The bits are stored in 64-bit words, least significant first.
+/// Bits above the width are always zero.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Integer {
+    width: u32,
+    words: Vec<u64>,
+}
+
+impl Integer {
+    /// Creates the integer zero of the given width.
+    pub fn zero(width: u32) -> Integer {
+        Integer {
+            width,
+            words: vec![0; width.div_ceil(64).max(1) as usize],
+        }
+    }
+
+    /// Creates an integer from an unsigned value, keeping only the bits that fit in the width.
+    pub fn from_u64(value: u64, width: u32) -> Integer {
+        let mut integer = Integer::zero(width);
+        integer.words[0] = value;
+        integer.normalized()
+    }
+
+    /// Creates an integer from a signed value, keeping only the bits that fit in the width.
+    pub fn from_i64(value: i64, width: u32) -> Integer {
+        let mut integer = Integer::zero(width);
+        let fill = if value < 0 { u64::MAX } else { 0 };
+        integer.words.fill(fill);
+        integer.words[0] = value as u64;
+        integer.normalized()
+    }
+
+    /// Reads a decimal integer, which may be negative, wrapping it around the width.
+    pub fn parse(contents: &str, width: u32) -> Option<Integer> {
+        let (negative, digits) = match contents.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, contents),
+        };
+        if digits.is_empty() {
+            return None;
+        }
+        // parsed one bit wider, so the most negative value can be negated back in range
+        let mut integer = Integer::zero(width + 1);
+        for digit in digits.chars() {
+            let digit = digit.to_digit(10)?;
+            integer.multiply_small(10, digit as u64);
+        }
+        if negative {
+            integer = integer.neg();
+        }
+        Some(integer.trunc(width))
+    }
+
+    /// Returns the width of the integer in bits.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns true if every bit is zero.
+    pub fn is_zero(&self) -> bool {
+        self.words.iter().all(|x| *x == 0)
+    }
+
+    /// Returns true if the sign bit is set.
+    pub fn is_negative(&self) -> bool {
+        self.bit(self.width - 1)
+    }
+
+    /// Returns the lowest 64 bits, as an unsigned value.
+    pub fn to_u64(&self) -> u64 {
+        self.words[0]
+    }
+
+    /// Returns the lowest 64 bits of the sign-extended integer, as a signed value.
+    pub fn to_i64(&self) -> i64 {
+        self.sext(self.width.max(64)).words[0] as i64
+    }
+
+    /// Returns the unsigned value, or nothing if it needs more than 64 bits.
+    pub fn to_u64_checked(&self) -> Option<u64> {
+        match self.words[1..].iter().all(|x| *x == 0) {
+            true => Some(self.words[0]),
+            false => None,
+        }
+    }
+
+    fn bit(&self, index: u32) -> bool {
+        (self.words[(index / 64) as usize] >> (index % 64)) & 1 == 1
+    }
+
+    fn set_bit(&mut self, index: u32) {
+        self.words[(index / 64) as usize] |= 1 << (index % 64);
+    }
+
+    /// Clears the bits above the width.
+    fn normalized(mut self) -> Integer {
+        let used = self.width % 64;
+        if used != 0 {
+            let last = self.words.len() - 1;
+            self.words[last] &= u64::MAX >> (64 - used);
+        }
+        self
+    }
+
+    /// Multiplies the integer by a small factor and adds a small value, in place.
+    fn multiply_small(&mut self, factor: u64, addend: u64) {
+        let mut carry = addend as u128;
+        for word in &mut self.words {
+            let product = *word as u128 * factor as u128 + carry;
+            *word = product as u64;
+            carry = product >> 64;
+        }
+        *self = self.clone().normalized();
+    }
+
+    /// Divides the integer by a small divisor in place, returning the remainder.
+    fn divide_small(&mut self, divisor: u64) -> u64 {
+        let mut remainder = 0u128;
+        for word in self.words.iter_mut().rev() {
+            let current = (remainder << 64) | *word as u128;
+            *word = (current / divisor as u128) as u64;
+            remainder = current % divisor as u128;
+        }
+        remainder as u64
+    }
+
+    /// Adds two integers of the same width, wrapping around on overflow.
+    pub fn add(&self, other: &Integer) -> Integer {
+        let mut result = Integer::zero(self.width);
+        let mut carry = false;
+        for (index, word) in result.words.iter_mut().enumerate() {
+            let (sum, first) = self.words[index].overflowing_add(other.words[index]);
+            let (sum, second) = sum.overflowing_add(carry as u64);
+            *word = sum;
+            carry = first || second;
+        }
+        result.normalized()
+    }
+
+    /// Subtracts two integers of the same width, wrapping around on overflow.
+    pub fn sub(&self, other: &Integer) -> Integer {
+        self.add(&other.neg())
+    }
+
+    /// Multiplies two integers of the same width, wrapping around on overflow.
+    pub fn mul(&self, other: &Integer) -> Integer {
+        let mut result = Integer::zero(self.width);
+        let count = result.words.len();
+        for i in 0..count {
+            let mut carry = 0u128;
+            for j in 0..count - i {
+                let current = result.words[i + j] as u128
+                    + self.words[i] as u128 * other.words[j] as u128
+                    + carry;
+                result.words[i + j] = current as u64;
+                carry = current >> 64;
+            }
+        }
+        result.normalized()
+    }
+
+    /// Divides two unsigned integers, returning the quotient and remainder,
+    /// or nothing when dividing by zero.
+    pub fn udivrem(&self, other: &Integer) -> Option<(Integer, Integer)> {
+        if other.is_zero() {
+            return None;
+        }
+        // long division, one bit at a time, with a remainder one bit wider than the operands
+        let divisor = other.zext(self.width + 1);
+        let mut quotient = Integer::zero(self.width);
+        let mut remainder = Integer::zero(self.width + 1);
+        for index in (0..self.width).rev() {
+            remainder = remainder.shl(1);
+            if self.bit(index) {
+                remainder.set_bit(0);
+            }
+            if remainder.cmp_unsigned(&divisor) != Ordering::Less {
+                remainder = remainder.sub(&divisor);
+                quotient.set_bit(index);
+            }
+        }
+        Some((quotient, remainder.trunc(self.width)))
+    }
+
+    /// Divides two signed integers, rounding towards zero, returning the quotient and remainder.
+    /// The remainder has the sign of the dividend. Returns nothing when dividing by zero.
+    pub fn sdivrem(&self, other: &Integer) -> Option<(Integer, Integer)> {
+        let (quotient, remainder) = self.abs().udivrem(&other.abs())?;
+        let quotient = match self.is_negative() != other.is_negative() {
+            true => quotient.neg(),
+            false => quotient,
+        };
+        let remainder = match self.is_negative() {
+            true => remainder.neg(),
+            false => remainder,
+        };
+        Some((quotient, remainder))
+    }
+
+    /// Returns true if this is the most negative value of its width, which cannot be negated.
+    pub fn is_signed_min(&self) -> bool {
+        self.is_negative() && !self.sub(&Integer::from_u64(1, self.width)).is_negative()
+    }
+
+    fn abs(&self) -> Integer {
+        match self.is_negative() {
+            true => self.neg(),
+            false => self.clone(),
+        }
+    }
+
+    /// Negates the integer, wrapping around for the most negative value.
+    pub fn neg(&self) -> Integer {
+        self.not().add(&Integer::from_u64(1, self.width))
+    }
+
+    /// Flips every bit of the integer.
+    pub fn not(&self) -> Integer {
+        Integer {
+            width: self.width,
+            words: self.words.iter().map(|x| !x).collect(),
+        }
+        .normalized()
+    }
+
+    /// Combines the bits of two integers of the same width.
+    pub fn and(&self, other: &Integer) -> Integer {
+        self.zip(other, |a, b| a & b)
+    }
+
+    /// Combines the bits of two integers of the same width.
+    pub fn or(&self, other: &Integer) -> Integer {
+        self.zip(other, |a, b| a | b)
+    }
+
+    /// Combines the bits of two integers of the same width.
+    pub fn xor(&self, other: &Integer) -> Integer {
+        self.zip(other, |a, b| a ^ b)
+    }
+
+    fn zip<F: Fn(u64, u64) -> u64>(&self, other: &Integer, f: F) -> Integer {
+        Integer {
+            width: self.width,
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| f(*a, *b))
+                .collect(),
+        }
+    }
+
+    /// Shifts the bits towards the most significant bit, filling in zeros.
+    pub fn shl(&self, amount: u64) -> Integer {
+        let mut result = Integer::zero(self.width);
+        if amount >= self.width as u64 {
+            return result;
+        }
+        for index in 0..self.width - amount as u32 {
+            if self.bit(index) {
+                result.set_bit(index + amount as u32);
+            }
+        }
+        result
+    }
+
+    /// Shifts the bits towards the least significant bit, filling in zeros.
+    pub fn lshr(&self, amount: u64) -> Integer {
+        self.shift_right(amount, false)
+    }
+
+    /// Shifts the bits towards the least significant bit, filling in copies of the sign bit.
+    pub fn ashr(&self, amount: u64) -> Integer {
+        self.shift_right(amount, self.is_negative())
+    }
+
+    fn shift_right(&self, amount: u64, fill: bool) -> Integer {
+        let mut result = Integer::zero(self.width);
+        for index in 0..self.width {
+            let source = index as u64 + amount;
+            let set = match source < self.width as u64 {
+                true => self.bit(source as u32),
+                false => fill,
+            };
+            if set {
+                result.set_bit(index);
+            }
+        }
+        result
+    }
+
+    /// Compares two integers of the same width as unsigned values.
+    pub fn cmp_unsigned(&self, other: &Integer) -> Ordering {
+        self.words.iter().rev().cmp(other.words.iter().rev())
+    }
+
+    /// Compares two integers of the same width as signed values.
+    pub fn cmp_signed(&self, other: &Integer) -> Ordering {
+        match (self.is_negative(), other.is_negative()) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            _ => self.cmp_unsigned(other),
+        }
+    }
+
+    /// Keeps only the lowest bits of the integer.
+    pub fn trunc(&self, width: u32) -> Integer {
+        let mut result = Integer::zero(width);
+        let count = result.words.len().min(self.words.len());
+        result.words[..count].copy_from_slice(&self.words[..count]);
+        result.normalized()
+    }
+
+    /// Widens the integer, filling in zeros.
+    pub fn zext(&self, width: u32) -> Integer {
+        self.trunc(width)
+    }
+
+    /// Widens the integer, filling in copies of the sign bit.
+    pub fn sext(&self, width: u32) -> Integer {
+        let mut result = self.zext(width);
+        if self.is_negative() {
+            for index in self.width..width {
+                result.set_bit(index);
+            }
+        }
+        result
+    }
+
+    /// Converts the integer into the nearest floating point, as an unsigned or signed value.
+    pub fn to_f64(&self, signed: bool) -> f64 {
+        if signed && self.is_negative() {
+            return -self.neg().to_f64(false);
+        }
+        self.words.iter().rev().fold(0.0, |total, word| {
+            total * 18446744073709551616.0 + *word as f64
+        })
+    }
+
+    /// Converts a floating point into an integer, rounding towards zero and wrapping around
+    /// the width. Values that are not finite become zero.
+    pub fn from_f64(value: f64, width: u32) -> Integer {
+        if !value.is_finite() {
+            return Integer::zero(width);
+        }
+        let magnitude = value.abs().trunc();
+        let bits = magnitude.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i64 - 1075;
+        let mantissa = match magnitude == 0.0 {
+            true => 0,
+            false => (bits & ((1 << 52) - 1)) | (1 << 52),
+        };
+        // the magnitude is `mantissa * 2^exponent`, computed one bit wider than needed
+        let wide = Integer::from_u64(mantissa, width.max(64) + 1);
+        let shifted = match exponent >= 0 {
+            true => wide.shl(exponent as u64),
+            false => wide.lshr(exponent.unsigned_abs()),
+        };
+        let result = shifted.trunc(width);
+        match value < 0.0 {
+            true => result.neg(),
+            false => result,
+        }
+    }
+
+    /// Returns the integer as little-endian bytes, padded with zeros to the length.
+    pub fn to_bytes(&self, length: usize) -> Vec<u8> {
+        let mut bytes = self
+            .words
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect::<Vec<_>>();
+        bytes.resize(length, 0);
+        bytes
+    }
+
+    /// Reads an integer of the given width from little-endian bytes.
+    pub fn from_bytes(bytes: &[u8], width: u32) -> Integer {
+        let mut result = Integer::zero(width);
+        for (index, byte) in bytes.iter().enumerate().take(result.words.len() * 8) {
+            result.words[index / 8] |= (*byte as u64) << (index % 8 * 8);
+        }
+        result.normalized()
+    }
+
+    /// Writes the integer as an unsigned decimal number.
+    pub fn to_unsigned_string(&self) -> String {
+        let mut remaining = self.clone();
+        let mut digits = Vec::new();
+        loop {
+            digits.push(b'0' + remaining.divide_small(10) as u8);
+            if remaining.is_zero() {
+                break;
+            }
+        }
+        digits.reverse();
+        String::from_utf8(digits).unwrap()
+    }
+}
+
+/// Writes the integer as a signed decimal number, except for `i1`, which is written as 0 or 1.
+impl Display for Integer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.width != 1 && self.is_negative() {
+            true => write!(f, "-{}", self.neg().to_unsigned_string()),
+            false => write!(f, "{}", self.to_unsigned_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::integer::Integer;
+    use std::string::ToString;
+
+    #[test]
+    fn wrap_around_any_width() {
+        let a = Integer::parse("100", 7).unwrap();
+        assert_eq!(a.to_string(), "-28");
+        assert_eq!(a.add(&a).to_string(), "-56");
+        assert_eq!(Integer::parse("-1", 1).unwrap().to_string(), "1");
+
+        let big = Integer::parse("340282366920938463463374607431768211455", 256).unwrap();
+        let squared = big.mul(&big);
+        assert_eq!(
+            squared.to_unsigned_string(),
+            "115792089237316195423570985008687907852589419931798687112530834793049593217025"
+        );
+        let (quotient, remainder) = squared.udivrem(&big).unwrap();
+        assert_eq!(quotient, big);
+        assert!(remainder.is_zero());
+    }
+
+    #[test]
+    fn divide_signed_integers() {
+        let a = Integer::from_i64(-7, 32);
+        let b = Integer::from_i64(2, 32);
+        let (quotient, remainder) = a.sdivrem(&b).unwrap();
+        assert_eq!(quotient.to_i64(), -3);
+        assert_eq!(remainder.to_i64(), -1);
+        assert!(a.sdivrem(&Integer::zero(32)).is_none());
+        assert!(Integer::from_i64(-128, 8).is_signed_min());
+        assert!(!Integer::from_i64(-127, 8).is_signed_min());
+    }
+
+    #[test]
+    fn shift_and_convert() {
+        let a = Integer::from_i64(-16, 12);
+        assert_eq!(a.ashr(2).to_i64(), -4);
+        assert_eq!(a.lshr(2).to_u64(), 1020);
+        assert_eq!(a.shl(20).to_u64(), 0);
+        assert_eq!(a.sext(70).to_string(), "-16");
+        assert_eq!(a.zext(70).to_string(), "4080");
+        assert_eq!(Integer::from_f64(-3.75, 8).to_i64(), -3);
+        assert_eq!(Integer::from_f64(1e30, 128).to_f64(false), 1e30);
+        assert_eq!(Integer::from_bytes(&a.to_bytes(2), 12), a);
+    }
+}
@@ -0,0 +1,887 @@
+use crate::host;
+use crate::integer::Integer;
+use crate::intrinsic;
+use crate::layout::{align_of, field_offsets, size_of, store_size};
+use crate::memory::Memory;
+use crate::trap::Trap;
+use crate::value::RuntimeValue;
+use lotl_llvm_api::instruction::{
+    AtomicRmwOperator, BinaryOperator, BlockId, CastOperator, Instruction, InstructionKind,
+};
+use lotl_llvm_api::module::{FunctionBody, Module};
+use lotl_llvm_api::types::Type;
+use lotl_llvm_api::value::Value;
+use lotl_llvm_api::IRComponent;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// The deepest calls can be nested before execution traps.
+const MAX_DEPTH: usize = 512;
+
+/// A function provided by the host, which is called whenever the module calls
+/// a function of its name that the module does not define.
+/// Returning [`Trap::Unwind`] raises an exception, which unwinds to the nearest `invoke`.
+pub type HostFunction = fn(&mut Interpreter<'_>, &[RuntimeValue]) -> Result<RuntimeValue, Trap>;
+
+/// Executes the functions of a module directly, without compiling it.
+///
+/// Global variables are allocated and initialized when the interpreter is created,
+/// and keep their contents between calls to `run`.
+pub struct Interpreter<'m> {
+    module: &'m Module,
+    memory: Memory,
+    /// The addresses of every global variable and function, by name.
+    addresses: HashMap<String, u64>,
+    /// The names of every function, by address.
+    functions: HashMap<u64, String>,
+    /// The addresses of every block, by the name of its function and its label.
+    block_addresses: HashMap<(String, String), u64>,
+    /// The blocks, by address.
+    blocks: HashMap<u64, BlockId>,
+    host: HashMap<String, HostFunction>,
+    output: String,
+    depth: usize,
+}
+
+/// The registers and stack memory of a function being executed.
+struct Frame {
+    registers: HashMap<String, RuntimeValue>,
+    allocations: Vec<u64>,
+    /// The exception unwinding to the landing pad the frame is about to execute.
+    exception: Option<RuntimeValue>,
+}
+
+/// Where control continues after an instruction.
+enum Flow {
+    Next,
+    Jump(BlockId),
+    Return(RuntimeValue),
+}
+
+impl<'m> Interpreter<'m> {
+    /// Creates an interpreter for the module, with `printf` and `puts` provided by the host.
+    /// Fails if a global variable does not fit in memory, or is initialized with a value
+    /// that cannot be computed.
+    pub fn new(module: &'m Module) -> Result<Self, Trap> {
+        let mut interpreter = Interpreter {
+            module,
+            memory: Memory::new(),
+            addresses: HashMap::new(),
+            functions: HashMap::new(),
+            block_addresses: HashMap::new(),
+            blocks: HashMap::new(),
+            host: HashMap::new(),
+            output: String::new(),
+            depth: 0,
+        };
+        for var in &module.vars {
+            let address = interpreter
+                .memory
+                .allocate(size_of(var.ty()), align_of(var.ty()))?;
+            interpreter
+                .addresses
+                .insert(var.name().to_string(), address);
+        }
+        for function in &module.functions {
+            interpreter.add_function(function.name());
+            let Some(body) = function.get_body() else {
+                continue;
+            };
+            for block in body.blocks() {
+                let address = interpreter.memory.reserve();
+                let key = (
+                    function.name().to_string(),
+                    body.block(block).label().to_string(),
+                );
+                interpreter.block_addresses.insert(key, address);
+                interpreter.blocks.insert(address, block);
+            }
+        }
+        for (name, function) in host::functions() {
+            interpreter = interpreter.with_host_function(name, function);
+        }
+
+        // every address is known by now, so initializers can point to any global
+        for var in &module.vars {
+            let address = interpreter.addresses[var.name()];
+            if let Some(value) = var.value() {
+                let value = interpreter.evaluate(&HashMap::new(), value)?;
+                interpreter.store(address, var.ty(), &value)?;
+            }
+            if var.is_constant() {
+                interpreter.memory.protect(address);
+            }
+        }
+        Ok(interpreter)
+    }
+
+    /// Provides a host function, which is called whenever the module calls a function
+    /// of this name that it does not define. Replaces the existing host function of that name.
+    pub fn with_host_function(mut self, name: &str, function: HostFunction) -> Self {
+        self.add_function(name);
+        self.host.insert(name.to_string(), function);
+        self
+    }
+
+    /// Gives the function an address, so it can be referred to by pointers.
+    fn add_function(&mut self, name: &str) {
+        if !self.addresses.contains_key(name) {
+            let address = self.memory.reserve();
+            self.addresses.insert(name.to_string(), address);
+            self.functions.insert(address, name.to_string());
+        }
+    }
+
+    /// Calls the function of the given name, and returns its result.
+    pub fn run(&mut self, name: &str, arguments: &[RuntimeValue]) -> Result<RuntimeValue, Trap> {
+        self.call(name, arguments.to_vec())
+    }
+
+    /// Returns everything the host functions printed so far.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Prints bytes to the output. Invalid UTF-8 is replaced.
+    pub fn print(&mut self, bytes: &[u8]) {
+        self.output.push_str(&String::from_utf8_lossy(bytes));
+    }
+
+    /// Returns the address of a global variable or function.
+    pub fn address(&self, name: &str) -> Option<u64> {
+        self.addresses.get(name).copied()
+    }
+
+    /// Reads a value of the type from memory.
+    pub fn load(&self, address: u64, ty: &Type) -> Result<RuntimeValue, Trap> {
+        let bytes = self.memory.read(address, store_size(ty))?;
+        Ok(RuntimeValue::decode(ty, bytes))
+    }
+
+    /// Writes a value of the type into memory.
+    pub fn store(&mut self, address: u64, ty: &Type, value: &RuntimeValue) -> Result<(), Trap> {
+        // the bytes are read first, so padding inside of aggregates keeps its contents
+        let mut bytes = self.memory.read(address, store_size(ty))?.to_vec();
+        value.encode(ty, &mut bytes);
+        self.memory.write(address, &bytes)
+    }
+
+    /// Reads the bytes of a null-terminated string from memory, without the terminator.
+    pub fn read_c_string(&self, address: u64) -> Result<Vec<u8>, Trap> {
+        let mut bytes = Vec::new();
+        loop {
+            match self.memory.read(address + bytes.len() as u64, 1)?[0] {
+                0 => return Ok(bytes),
+                byte => bytes.push(byte),
+            }
+        }
+    }
+
+    fn call(&mut self, name: &str, arguments: Vec<RuntimeValue>) -> Result<RuntimeValue, Trap> {
+        let module = self.module;
+        let defined = module
+            .functions
+            .iter()
+            .find(|x| x.name() == name)
+            .and_then(|x| Some((x, x.get_body()?)));
+        let Some((function, body)) = defined else {
+            if let Some(host) = self.host.get(name).copied() {
+                return host(self, &arguments);
+            }
+            return intrinsic::call(name, &arguments)
+                .unwrap_or_else(|| Err(Trap::UndefinedFunction(name.to_string())));
+        };
+
+        let parameters = function.arguments();
+        if arguments.len() < parameters.len() {
+            return Err(Trap::ArgumentCount {
+                function: name.to_string(),
+                expected: parameters.len(),
+                found: arguments.len(),
+            });
+        }
+        if self.depth == MAX_DEPTH {
+            return Err(Trap::StackOverflow);
+        }
+        let mut frame = Frame {
+            registers: HashMap::new(),
+            allocations: Vec::new(),
+            exception: None,
+        };
+        for (parameter, argument) in parameters.iter().zip(arguments) {
+            if let Value::LocalIdentifier { 0: name, .. } = parameter {
+                frame.registers.insert(name.clone(), argument);
+            }
+        }
+
+        self.depth += 1;
+        let result = self.execute(body, &mut frame);
+        self.depth -= 1;
+        for address in frame.allocations {
+            self.memory.free(address);
+        }
+        result
+    }
+
+    fn execute(&mut self, body: &'m FunctionBody, frame: &mut Frame) -> Result<RuntimeValue, Trap> {
+        let mut block = body.entry();
+        let mut previous = None;
+        loop {
+            let instructions = body.block(block).instructions();
+            let phis = instructions
+                .iter()
+                .take_while(|x| matches!(body.instruction(**x).kind(), InstructionKind::Phi { .. }))
+                .count();
+
+            // every phi reads the values from before the block was entered, so they are
+            // all computed before any of them is assigned
+            let mut selected = Vec::new();
+            for id in &instructions[..phis] {
+                let instruction = body.instruction(*id);
+                let InstructionKind::Phi { incoming, .. } = instruction.kind() else {
+                    continue;
+                };
+                let Some((value, _)) = incoming.iter().find(|(_, from)| Some(*from) == previous)
+                else {
+                    let result = instruction.result().map(|x| x.emit()).unwrap_or_default();
+                    return Err(Trap::UndefinedValue(result));
+                };
+                selected.push((instruction, self.evaluate(&frame.registers, value)?));
+            }
+            for (instruction, value) in selected {
+                define(frame, instruction, value);
+            }
+
+            let mut next = None;
+            for id in &instructions[phis..] {
+                match self.step(frame, body.instruction(*id))? {
+                    Flow::Next => {}
+                    Flow::Jump(target) => {
+                        next = Some(target);
+                        break;
+                    }
+                    Flow::Return(value) => return Ok(value),
+                }
+            }
+            let Some(target) = next else {
+                return Err(Trap::MissingTerminator(
+                    body.block(block).label().to_string(),
+                ));
+            };
+            previous = Some(block);
+            block = target;
+        }
+    }
+
+    fn step(&mut self, frame: &mut Frame, instruction: &Instruction) -> Result<Flow, Trap> {
+        let registers = &frame.registers;
+        let result = match instruction.kind() {
+            InstructionKind::Binary {
+                operator, lhs, rhs, ..
+            } => binary(
+                *operator,
+                self.evaluate(registers, lhs)?,
+                self.evaluate(registers, rhs)?,
+            )?,
+            InstructionKind::FloatNegate { value } => negate(self.evaluate(registers, value)?)
+                .ok_or_else(|| Trap::Unsupported(format!("fneg {}", value.emit())))?,
+            InstructionKind::Cast {
+                operator,
+                value,
+                target,
+            } => cast(
+                *operator,
+                self.evaluate(registers, value)?,
+                value.ty(),
+                target,
+            )?,
+            InstructionKind::Alloca { ty, alignment } => {
+                let alignment = alignment.map_or(align_of(ty), |x| align_of(ty).max(x as u64));
+                let address = self.memory.allocate(size_of(ty), alignment)?;
+                frame.allocations.push(address);
+                RuntimeValue::Pointer(address)
+            }
+            InstructionKind::Load { ty, pointer, .. } => {
+                let address = self.pointer(registers, pointer)?;
+                self.load(address, ty)?
+            }
+            InstructionKind::Store { value, pointer, .. } => {
+                let address = self.pointer(registers, pointer)?;
+                let stored = self.evaluate(registers, value)?;
+                self.store(address, value.ty(), &stored)?;
+                RuntimeValue::Void
+            }
+            // execution is single threaded, so atomic operations only need to read and write
+            InstructionKind::AtomicRmw {
+                operator,
+                pointer,
+                value,
+                ..
+            } => {
+                let address = self.pointer(registers, pointer)?;
+                let old = self.load(address, value.ty())?;
+                let new = atomicrmw(*operator, old.clone(), self.evaluate(registers, value)?)?;
+                self.store(address, value.ty(), &new)?;
+                old
+            }
+            InstructionKind::CompareExchange {
+                pointer,
+                expected,
+                replacement,
+                ..
+            } => {
+                let address = self.pointer(registers, pointer)?;
+                let old = self.load(address, expected.ty())?;
+                let equal = old == self.evaluate(registers, expected)?;
+                if equal {
+                    let stored = self.evaluate(registers, replacement)?;
+                    self.store(address, replacement.ty(), &stored)?;
+                }
+                RuntimeValue::Aggregate(vec![old, RuntimeValue::integer(equal as i64, 1)])
+            }
+            InstructionKind::Fence { .. } => RuntimeValue::Void,
+            InstructionKind::GetElementPtr { ty, base, indices } => {
+                RuntimeValue::Pointer(self.element_pointer(registers, ty, base, indices)?)
+            }
+            InstructionKind::ExtractValue { aggregate, index } => {
+                match self.evaluate(registers, aggregate)? {
+                    RuntimeValue::Aggregate(mut elements) if *index < elements.len() => {
+                        elements.swap_remove(*index)
+                    }
+                    _ => {
+                        return Err(Trap::Unsupported(format!(
+                            "extractvalue {}",
+                            aggregate.emit()
+                        )))
+                    }
+                }
+            }
+            InstructionKind::InsertValue {
+                aggregate,
+                value,
+                index,
+            } => match self.evaluate(registers, aggregate)? {
+                RuntimeValue::Aggregate(mut elements) if *index < elements.len() => {
+                    elements[*index] = self.evaluate(registers, value)?;
+                    RuntimeValue::Aggregate(elements)
+                }
+                _ => {
+                    return Err(Trap::Unsupported(format!(
+                        "insertvalue {}",
+                        aggregate.emit()
+                    )))
+                }
+            },
+            // an index past the end produces poison, which is computed as zero
+            InstructionKind::ExtractElement { vector, index } => {
+                let index = self.index(registers, index)?;
+                match self.evaluate(registers, vector)? {
+                    RuntimeValue::Aggregate(mut elements) if index < elements.len() => {
+                        elements.swap_remove(index)
+                    }
+                    RuntimeValue::Aggregate(_) => RuntimeValue::zero(vector.ty().scalar()),
+                    _ => {
+                        return Err(Trap::Unsupported(format!(
+                            "extractelement {}",
+                            vector.emit()
+                        )))
+                    }
+                }
+            }
+            InstructionKind::InsertElement {
+                vector,
+                value,
+                index,
+            } => {
+                let index = self.index(registers, index)?;
+                match self.evaluate(registers, vector)? {
+                    RuntimeValue::Aggregate(mut elements) => {
+                        if index < elements.len() {
+                            elements[index] = self.evaluate(registers, value)?;
+                        }
+                        RuntimeValue::Aggregate(elements)
+                    }
+                    _ => {
+                        return Err(Trap::Unsupported(format!(
+                            "insertelement {}",
+                            vector.emit()
+                        )))
+                    }
+                }
+            }
+            // the mask picks from the elements of both vectors, one after the other
+            InstructionKind::ShuffleVector { lhs, rhs, mask } => {
+                let (
+                    RuntimeValue::Aggregate(mut elements),
+                    RuntimeValue::Aggregate(second),
+                    RuntimeValue::Aggregate(mask),
+                ) = (
+                    self.evaluate(registers, lhs)?,
+                    self.evaluate(registers, rhs)?,
+                    self.evaluate(registers, mask)?,
+                )
+                else {
+                    return Err(Trap::Unsupported(format!("shufflevector {}", lhs.emit())));
+                };
+                elements.extend(second);
+                let element = lhs.ty().scalar();
+                let picked = mask.iter().map(|x| match x {
+                    RuntimeValue::Integer(index) => index
+                        .to_u64_checked()
+                        .and_then(|x| elements.get(x as usize))
+                        .cloned()
+                        .unwrap_or_else(|| RuntimeValue::zero(element)),
+                    _ => RuntimeValue::zero(element),
+                });
+                RuntimeValue::Aggregate(picked.collect())
+            }
+            InstructionKind::Select {
+                condition,
+                if_true,
+                if_false,
+            } => match self.condition(registers, condition)? {
+                true => self.evaluate(registers, if_true)?,
+                false => self.evaluate(registers, if_false)?,
+            },
+            InstructionKind::Call {
+                callee, arguments, ..
+            } => self.call_value(registers, callee, arguments)?,
+            // the personality function is never run, so every landing pad receives every exception
+            InstructionKind::Invoke {
+                callee,
+                arguments,
+                normal,
+                unwind,
+                ..
+            } => {
+                return match self.call_value(registers, callee, arguments) {
+                    Ok(value) => {
+                        define(frame, instruction, value);
+                        Ok(Flow::Jump(*normal))
+                    }
+                    Err(Trap::Unwind(exception)) => {
+                        frame.exception = Some(exception);
+                        Ok(Flow::Jump(*unwind))
+                    }
+                    Err(trap) => Err(trap),
+                };
+            }
+            InstructionKind::LandingPad { .. } => frame.exception.take().ok_or_else(|| {
+                let result = instruction.result().map(|x| x.emit()).unwrap_or_default();
+                Trap::UndefinedValue(result)
+            })?,
+            InstructionKind::Resume { value } => {
+                return Err(Trap::Unwind(self.evaluate(registers, value)?));
+            }
+            InstructionKind::DeclareVariable { .. } => RuntimeValue::Void,
+            InstructionKind::Return { value } => {
+                return Ok(Flow::Return(match value {
+                    Some(value) => self.evaluate(registers, value)?,
+                    None => RuntimeValue::Void,
+                }));
+            }
+            InstructionKind::Branch { target } => return Ok(Flow::Jump(*target)),
+            InstructionKind::ConditionalBranch {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                return Ok(Flow::Jump(match self.condition(registers, condition)? {
+                    true => *if_true,
+                    false => *if_false,
+                }));
+            }
+            InstructionKind::Switch {
+                value,
+                default,
+                cases,
+            } => {
+                let value = self.evaluate(registers, value)?;
+                let mut target = *default;
+                for (case, block) in cases {
+                    if self.evaluate(registers, case)? == value {
+                        target = *block;
+                        break;
+                    }
+                }
+                return Ok(Flow::Jump(target));
+            }
+            InstructionKind::IndirectBranch {
+                address,
+                destinations,
+            } => {
+                let address = self.pointer(registers, address)?;
+                return match self.blocks.get(&address) {
+                    Some(block) if destinations.contains(block) => Ok(Flow::Jump(*block)),
+                    _ => Err(Trap::InvalidDestination(address)),
+                };
+            }
+            InstructionKind::Unreachable => return Err(Trap::Unreachable),
+            kind => return Err(Trap::Unsupported(format!("{:?}", kind))),
+        };
+        define(frame, instruction, result);
+        Ok(Flow::Next)
+    }
+
+    /// Calls the function the callee points to with the values of the arguments.
+    fn call_value(
+        &mut self,
+        registers: &HashMap<String, RuntimeValue>,
+        callee: &Value,
+        arguments: &[Value],
+    ) -> Result<RuntimeValue, Trap> {
+        let address = self.pointer(registers, callee)?;
+        let Some(name) = self.functions.get(&address).cloned() else {
+            return Err(Trap::NotFunction(address));
+        };
+        let arguments = arguments
+            .iter()
+            .map(|x| self.evaluate(registers, x))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.call(&name, arguments)
+    }
+
+    /// Computes the value of an operand.
+    fn evaluate(
+        &self,
+        registers: &HashMap<String, RuntimeValue>,
+        value: &Value,
+    ) -> Result<RuntimeValue, Trap> {
+        let unsupported = || Trap::Unsupported(value.emit());
+        match value {
+            Value::Number { 0: contents, .. } => match value.ty() {
+                Type::Integer { 0: width, .. } => Integer::parse(contents, *width)
+                    .map(RuntimeValue::Integer)
+                    .ok_or_else(unsupported),
+                ty => value
+                    .float_value()
+                    .map(|x| RuntimeValue::float(x, ty.clone()))
+                    .ok_or_else(unsupported),
+            },
+            Value::ZeroInitializer { 0: ty, .. } => Ok(RuntimeValue::zero(ty)),
+            Value::Structure { 0: elements, .. }
+            | Value::Array { 0: elements, .. }
+            | Value::Vector { 0: elements, .. } => elements
+                .iter()
+                .map(|x| self.evaluate(registers, x))
+                .collect::<Result<Vec<_>, _>>()
+                .map(RuntimeValue::Aggregate),
+            Value::CString { 0: bytes, .. } => Ok(RuntimeValue::Aggregate(
+                bytes
+                    .iter()
+                    .map(|x| RuntimeValue::integer(*x as i64, 8))
+                    .collect(),
+            )),
+            Value::GlobalIdentifier { 0: name, .. } | Value::Function { 0: name, .. } => self
+                .address(name)
+                .map(RuntimeValue::Pointer)
+                .ok_or_else(|| Trap::UndefinedValue(format!("@{}", name))),
+            Value::BlockAddress {
+                0: function,
+                1: label,
+                ..
+            } => self
+                .block_addresses
+                .get(&(function.clone(), label.clone()))
+                .copied()
+                .map(RuntimeValue::Pointer)
+                .ok_or_else(|| Trap::UndefinedValue(value.emit())),
+            Value::LocalIdentifier { 0: name, .. } => registers
+                .get(name)
+                .cloned()
+                .ok_or_else(|| Trap::UndefinedValue(format!("%{}", name))),
+            Value::Null { .. } => Ok(RuntimeValue::Pointer(0)),
+            // undefined and poison values may be anything, so they are computed as zero
+            Value::Undef { 0: ty, .. } | Value::Poison { 0: ty, .. } => Ok(RuntimeValue::zero(ty)),
+            Value::GetElementPtr {
+                0: ty,
+                1: base,
+                2: indices,
+                ..
+            } => self
+                .element_pointer(registers, ty, base, indices)
+                .map(RuntimeValue::Pointer),
+            Value::Cast {
+                0: operator,
+                1: operand,
+                2: target,
+                ..
+            } => cast(
+                *operator,
+                self.evaluate(registers, operand)?,
+                operand.ty(),
+                target,
+            ),
+            _ => Err(unsupported()),
+        }
+    }
+
+    fn pointer(
+        &self,
+        registers: &HashMap<String, RuntimeValue>,
+        value: &Value,
+    ) -> Result<u64, Trap> {
+        match self.evaluate(registers, value)? {
+            RuntimeValue::Pointer(address) => Ok(address),
+            _ => Err(Trap::Unsupported(format!("{} as a pointer", value.emit()))),
+        }
+    }
+
+    /// Computes an element index, where an index too large for the address space is past the end.
+    fn index(
+        &self,
+        registers: &HashMap<String, RuntimeValue>,
+        value: &Value,
+    ) -> Result<usize, Trap> {
+        match self.evaluate(registers, value)? {
+            RuntimeValue::Integer(index) => Ok(index
+                .to_u64_checked()
+                .and_then(|x| usize::try_from(x).ok())
+                .unwrap_or(usize::MAX)),
+            _ => Err(Trap::Unsupported(format!("index {}", value.emit()))),
+        }
+    }
+
+    fn condition(
+        &self,
+        registers: &HashMap<String, RuntimeValue>,
+        value: &Value,
+    ) -> Result<bool, Trap> {
+        match self.evaluate(registers, value)? {
+            RuntimeValue::Integer(condition) => Ok(!condition.is_zero()),
+            _ => Err(Trap::Unsupported(format!(
+                "{} as a condition",
+                value.emit()
+            ))),
+        }
+    }
+
+    /// Computes the address of `getelementptr`. The address may point outside of the allocation,
+    /// which only traps once it is accessed.
+    fn element_pointer(
+        &self,
+        registers: &HashMap<String, RuntimeValue>,
+        ty: &Type,
+        base: &Value,
+        indices: &[Value],
+    ) -> Result<u64, Trap> {
+        let mut address = self.pointer(registers, base)?;
+        let mut current = ty.clone();
+        for (position, index) in indices.iter().enumerate() {
+            let step = match self.evaluate(registers, index)? {
+                RuntimeValue::Integer(step) => step.to_i64(),
+                _ => return Err(Trap::Unsupported(format!("index {}", index.emit()))),
+            };
+            // the first index steps over whole values of the type, the others into its elements
+            let offset = match current.resolved() {
+                _ if position == 0 => step.wrapping_mul(size_of(&current) as i64),
+                Type::Array { 1: element, .. } => {
+                    current = *element;
+                    step.wrapping_mul(size_of(&current) as i64)
+                }
+                Type::Structure { 0: fields, .. } | Type::PackedStructure { 0: fields, .. } => {
+                    let Some(field) = fields.get(step as usize) else {
+                        return Err(Trap::Unsupported(format!("index {}", index.emit())));
+                    };
+                    let offset = field_offsets(&current)[step as usize];
+                    current = field.clone();
+                    offset as i64
+                }
+                _ => return Err(Trap::Unsupported(format!("index {}", index.emit()))),
+            };
+            address = address.wrapping_add(offset as u64);
+        }
+        Ok(address)
+    }
+}
+
+/// Assigns the result of an instruction to its register.
+fn define(frame: &mut Frame, instruction: &Instruction, value: RuntimeValue) {
+    if let Some(Value::LocalIdentifier { 0: name, .. }) = instruction.result() {
+        frame.registers.insert(name.clone(), value);
+    }
+}
+
+/// Negates a float, or each float of a vector.
+fn negate(value: RuntimeValue) -> Option<RuntimeValue> {
+    match value {
+        RuntimeValue::Float(value, ty) => Some(RuntimeValue::Float(-value, ty)),
+        RuntimeValue::Aggregate(elements) => elements
+            .into_iter()
+            .map(negate)
+            .collect::<Option<Vec<_>>>()
+            .map(RuntimeValue::Aggregate),
+        _ => None,
+    }
+}
+
+/// Computes the value `atomicrmw` writes from the value in memory and its operand.
+pub(crate) fn atomicrmw(
+    operator: AtomicRmwOperator,
+    old: RuntimeValue,
+    operand: RuntimeValue,
+) -> Result<RuntimeValue, Trap> {
+    let result = match (old, operand) {
+        (_, operand) if operator == AtomicRmwOperator::Exchange => operand,
+        (RuntimeValue::Integer(a), RuntimeValue::Integer(b)) => {
+            let zero = Integer::zero(a.width());
+            let one = Integer::from_u64(1, a.width());
+            let signed = a.cmp_signed(&b);
+            let unsigned = a.cmp_unsigned(&b);
+            RuntimeValue::Integer(match operator {
+                AtomicRmwOperator::Add => a.add(&b),
+                AtomicRmwOperator::Sub => a.sub(&b),
+                AtomicRmwOperator::And => a.and(&b),
+                AtomicRmwOperator::Nand => a.and(&b).not(),
+                AtomicRmwOperator::Or => a.or(&b),
+                AtomicRmwOperator::Xor => a.xor(&b),
+                AtomicRmwOperator::Max if signed == Ordering::Less => b,
+                AtomicRmwOperator::Min if signed == Ordering::Greater => b,
+                AtomicRmwOperator::UnsignedMax if unsigned == Ordering::Less => b,
+                AtomicRmwOperator::UnsignedMin if unsigned == Ordering::Greater => b,
+                AtomicRmwOperator::Max
+                | AtomicRmwOperator::Min
+                | AtomicRmwOperator::UnsignedMax
+                | AtomicRmwOperator::UnsignedMin => a,
+                AtomicRmwOperator::UnsignedIncrementWrap if unsigned == Ordering::Less => {
+                    a.add(&one)
+                }
+                AtomicRmwOperator::UnsignedIncrementWrap => zero,
+                AtomicRmwOperator::UnsignedDecrementWrap
+                    if a.is_zero() || unsigned == Ordering::Greater =>
+                {
+                    b
+                }
+                AtomicRmwOperator::UnsignedDecrementWrap => a.sub(&one),
+                AtomicRmwOperator::UnsignedSubtractConditional
+                | AtomicRmwOperator::UnsignedSubtractSaturate
+                    if unsigned != Ordering::Less =>
+                {
+                    a.sub(&b)
+                }
+                AtomicRmwOperator::UnsignedSubtractConditional => a,
+                AtomicRmwOperator::UnsignedSubtractSaturate => zero,
+                _ => return Err(Trap::Unsupported(operator.emit())),
+            })
+        }
+        (RuntimeValue::Float(a, ty), RuntimeValue::Float(b, _)) => {
+            let result = match operator {
+                AtomicRmwOperator::FloatAdd => a + b,
+                AtomicRmwOperator::FloatSub => a - b,
+                AtomicRmwOperator::FloatMax => a.max(b),
+                AtomicRmwOperator::FloatMin => a.min(b),
+                _ => return Err(Trap::Unsupported(operator.emit())),
+            };
+            RuntimeValue::float(result, ty)
+        }
+        _ => return Err(Trap::Unsupported(operator.emit())),
+    };
+    Ok(result)
+}
+
+pub(crate) fn binary(
+    operator: BinaryOperator,
+    lhs: RuntimeValue,
+    rhs: RuntimeValue,
+) -> Result<RuntimeValue, Trap> {
+    let result = match (lhs, rhs) {
+        (RuntimeValue::Integer(a), RuntimeValue::Integer(b)) => {
+            // shifting by the width or more is poison, which is computed as if it were not
+            let amount = b.to_u64_checked().unwrap_or(u64::MAX);
+            let result = match operator {
+                BinaryOperator::IntegerAdd => a.add(&b),
+                BinaryOperator::IntegerSub => a.sub(&b),
+                BinaryOperator::IntegerMul => a.mul(&b),
+                BinaryOperator::IntegerUnsignedDiv => a.udivrem(&b).ok_or(Trap::DivisionByZero)?.0,
+                BinaryOperator::IntegerUnsignedRem => a.udivrem(&b).ok_or(Trap::DivisionByZero)?.1,
+                BinaryOperator::IntegerSignedDiv | BinaryOperator::IntegerSignedRem => {
+                    if a.is_signed_min() && b.not().is_zero() {
+                        return Err(Trap::DivisionOverflow);
+                    }
+                    let (quotient, remainder) = a.sdivrem(&b).ok_or(Trap::DivisionByZero)?;
+                    match operator {
+                        BinaryOperator::IntegerSignedDiv => quotient,
+                        _ => remainder,
+                    }
+                }
+                BinaryOperator::ShiftLeft => a.shl(amount),
+                BinaryOperator::LogicalShiftRight => a.lshr(amount),
+                BinaryOperator::ArithmeticShiftRight => a.ashr(amount),
+                BinaryOperator::And => a.and(&b),
+                BinaryOperator::Or => a.or(&b),
+                BinaryOperator::Xor => a.xor(&b),
+                _ => return Err(Trap::Unsupported(operator.emit())),
+            };
+            RuntimeValue::Integer(result)
+        }
+        (RuntimeValue::Float(a, ty), RuntimeValue::Float(b, _)) => {
+            let result = match operator {
+                BinaryOperator::FloatAdd => a + b,
+                BinaryOperator::FloatSub => a - b,
+                BinaryOperator::FloatMul => a * b,
+                BinaryOperator::FloatDiv => a / b,
+                BinaryOperator::FloatRem => a % b,
+                _ => return Err(Trap::Unsupported(operator.emit())),
+            };
+            RuntimeValue::float(result, ty)
+        }
+        // vectors are operated on element-wise
+        (RuntimeValue::Aggregate(a), RuntimeValue::Aggregate(b)) => RuntimeValue::Aggregate(
+            a.into_iter()
+                .zip(b)
+                .map(|(a, b)| binary(operator, a, b))
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        _ => return Err(Trap::Unsupported(operator.emit())),
+    };
+    Ok(result)
+}
+
+fn cast(
+    operator: CastOperator,
+    value: RuntimeValue,
+    source: &Type,
+    target: &Type,
+) -> Result<RuntimeValue, Trap> {
+    let width = match target.resolved() {
+        Type::Integer { 0: width, .. } => width,
+        _ => 64,
+    };
+    let result = match (operator, value) {
+        (CastOperator::Trunc, RuntimeValue::Integer(x)) => RuntimeValue::Integer(x.trunc(width)),
+        (CastOperator::ZeroExtend, RuntimeValue::Integer(x)) => {
+            RuntimeValue::Integer(x.zext(width))
+        }
+        (CastOperator::SignExtend, RuntimeValue::Integer(x)) => {
+            RuntimeValue::Integer(x.sext(width))
+        }
+        (CastOperator::FloatTrunc | CastOperator::FloatExtend, RuntimeValue::Float(x, _)) => {
+            RuntimeValue::float(x, target.clone())
+        }
+        (
+            CastOperator::FloatToUnsigned | CastOperator::FloatToSigned,
+            RuntimeValue::Float(x, _),
+        ) => RuntimeValue::Integer(Integer::from_f64(x, width)),
+        (CastOperator::UnsignedToFloat, RuntimeValue::Integer(x)) => {
+            RuntimeValue::float(x.to_f64(false), target.clone())
+        }
+        (CastOperator::SignedToFloat, RuntimeValue::Integer(x)) => {
+            RuntimeValue::float(x.to_f64(true), target.clone())
+        }
+        (CastOperator::PtrToInt, RuntimeValue::Pointer(address)) => {
+            RuntimeValue::Integer(Integer::from_u64(address, width))
+        }
+        (CastOperator::IntToPtr, RuntimeValue::Integer(x)) => RuntimeValue::Pointer(x.to_u64()),
+        (CastOperator::AddrSpaceCast, RuntimeValue::Pointer(address)) => {
+            RuntimeValue::Pointer(address)
+        }
+        // the bits are reinterpreted by writing the value out and reading it back
+        (CastOperator::BitCast, value) => {
+            let mut bytes = vec![0; size_of(source).max(size_of(target)) as usize];
+            value.encode(source, &mut bytes);
+            RuntimeValue::decode(target, &bytes)
+        }
+        _ => return Err(Trap::Unsupported(operator.emit())),
+    };
+    Ok(result)
+}
@@ -0,0 +1,60 @@
+//! The LLVM intrinsics the interpreter executes itself, when the module declares them
+//! without the host providing a function of their name.
+
+use crate::interpreter::{atomicrmw, binary};
+use crate::trap::Trap;
+use crate::value::RuntimeValue;
+use lotl_llvm_api::instruction::{AtomicRmwOperator, BinaryOperator};
+
+/// Calls the intrinsic of the given name, or returns nothing if it is not one the interpreter knows.
+pub(crate) fn call(name: &str, arguments: &[RuntimeValue]) -> Option<Result<RuntimeValue, Trap>> {
+    let reduction = name.strip_prefix("llvm.vector.reduce.")?;
+    let (reduction, _) = reduction.split_once('.')?;
+    // the maximum and minimum are computed the same way as by `atomicrmw`
+    let combine = match reduction {
+        "add" => Combine::Binary(BinaryOperator::IntegerAdd),
+        "mul" => Combine::Binary(BinaryOperator::IntegerMul),
+        "and" => Combine::Binary(BinaryOperator::And),
+        "or" => Combine::Binary(BinaryOperator::Or),
+        "xor" => Combine::Binary(BinaryOperator::Xor),
+        "fadd" => Combine::Binary(BinaryOperator::FloatAdd),
+        "fmul" => Combine::Binary(BinaryOperator::FloatMul),
+        "smax" => Combine::AtomicRmw(AtomicRmwOperator::Max),
+        "smin" => Combine::AtomicRmw(AtomicRmwOperator::Min),
+        "umax" => Combine::AtomicRmw(AtomicRmwOperator::UnsignedMax),
+        "umin" => Combine::AtomicRmw(AtomicRmwOperator::UnsignedMin),
+        "fmax" => Combine::AtomicRmw(AtomicRmwOperator::FloatMax),
+        "fmin" => Combine::AtomicRmw(AtomicRmwOperator::FloatMin),
+        _ => return None,
+    };
+    Some(reduce(name, combine, arguments))
+}
+
+/// How two elements of a vector are combined by a reduction.
+#[derive(Clone, Copy)]
+enum Combine {
+    Binary(BinaryOperator),
+    AtomicRmw(AtomicRmwOperator),
+}
+
+/// Combines the elements of a vector in order, starting from the first element,
+/// or from the start value of `fadd` and `fmul`.
+fn reduce(name: &str, combine: Combine, arguments: &[RuntimeValue]) -> Result<RuntimeValue, Trap> {
+    let invalid = || Trap::Host(format!("{} expects a vector", name));
+    let (start, elements) = match arguments {
+        [start, RuntimeValue::Aggregate(elements)] => (Some(start), elements),
+        [RuntimeValue::Aggregate(elements)] => (None, elements),
+        _ => return Err(invalid()),
+    };
+    let mut elements = elements.iter().cloned();
+    let Some(mut result) = start.cloned().or_else(|| elements.next()) else {
+        return Err(invalid());
+    };
+    for element in elements {
+        result = match combine {
+            Combine::Binary(operator) => binary(operator, result, element)?,
+            Combine::AtomicRmw(operator) => atomicrmw(operator, result, element)?,
+        };
+    }
+    Ok(result)
+}
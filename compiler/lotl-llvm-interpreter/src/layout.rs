@@ -0,0 +1,99 @@
+//! The memory layout of types, following the usual 64-bit data layout:
+//! pointers take 8 bytes, and integers are aligned to the next power of two up to 16 bytes.
+//!
+//! Vectors are laid out like arrays and aligned to their size, and scalable vectors
+//! are executed with `vscale` as one.
+//!
+//! Sizes saturate at `u64::MAX` for types larger than the address space,
+//! which no allocation can hold.
+
+use lotl_llvm_api::types::Type;
+
+/// Returns the number of bytes a `load` or `store` of the type touches.
+pub(crate) fn store_size(ty: &Type) -> u64 {
+    match ty.resolved() {
+        Type::Integer { 0: width, .. } => width.div_ceil(8) as u64,
+        Type::Vector {
+            0: length,
+            1: element,
+            ..
+        }
+        | Type::ScalableVector {
+            0: length,
+            1: element,
+            ..
+        } => (length as u64).saturating_mul(size_of(&element)),
+        resolved => size_of(&resolved),
+    }
+}
+
+/// Returns the number of bytes between consecutive values of the type in an array,
+/// including the padding needed for alignment.
+pub(crate) fn size_of(ty: &Type) -> u64 {
+    match ty.resolved() {
+        Type::Integer { .. } | Type::Vector { .. } | Type::ScalableVector { .. } => {
+            round_up(store_size(ty), align_of(ty))
+        }
+        Type::Ptr { .. } | Type::AddressSpacePtr { .. } => 8,
+        Type::Half => 2,
+        Type::Float => 4,
+        Type::Double => 8,
+        Type::FP128 => 16,
+        Type::Array {
+            0: length,
+            1: element,
+            ..
+        } => (length as u64).saturating_mul(size_of(&element)),
+        Type::Structure { 0: fields, .. } => {
+            let end = fields
+                .last()
+                .zip(field_offsets(ty).last())
+                .map(|(field, offset)| offset.saturating_add(size_of(field)))
+                .unwrap_or(0);
+            round_up(end, align_of(ty))
+        }
+        Type::PackedStructure { 0: fields, .. } => fields
+            .iter()
+            .map(size_of)
+            .fold(0, |size, field| size.saturating_add(field)),
+        _ => 0,
+    }
+}
+
+/// Returns the alignment of the type in bytes.
+pub(crate) fn align_of(ty: &Type) -> u64 {
+    match ty.resolved() {
+        Type::Integer { 0: width, .. } => (width.div_ceil(8) as u64).next_power_of_two().min(16),
+        Type::Array { 1: element, .. } => align_of(&element),
+        Type::Vector { .. } | Type::ScalableVector { .. } => store_size(ty)
+            .checked_next_power_of_two()
+            .unwrap_or(1 << 63),
+        Type::Structure { 0: fields, .. } => fields.iter().map(align_of).max().unwrap_or(1),
+        Type::PackedStructure { .. } => 1,
+        resolved => size_of(&resolved).max(1),
+    }
+}
+
+/// Returns the offset in bytes of every field of a structure type.
+pub(crate) fn field_offsets(ty: &Type) -> Vec<u64> {
+    let (fields, packed) = match ty.resolved() {
+        Type::Structure { 0: fields, .. } => (fields, false),
+        Type::PackedStructure { 0: fields, .. } => (fields, true),
+        _ => return Vec::new(),
+    };
+    let mut offsets = Vec::new();
+    let mut offset = 0u64;
+    for field in &fields {
+        if !packed {
+            offset = round_up(offset, align_of(field));
+        }
+        offsets.push(offset);
+        offset = offset.saturating_add(size_of(field));
+    }
+    offsets
+}
+
+/// Rounds the size up to a multiple of the alignment, saturating like the sizes do.
+fn round_up(size: u64, align: u64) -> u64 {
+    size.checked_next_multiple_of(align).unwrap_or(u64::MAX)
+}
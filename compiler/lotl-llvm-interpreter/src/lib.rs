@@ -0,0 +1,612 @@
+//! Lotl-LLVM-Interpreter executes modules built with `lotl-llvm-api` directly,
+//! so the emitted IR can be tested without LLVM installed.
+//!
+//! Memory is modeled as separate allocations in a flat address space,
+//! and accessing memory outside of a live allocation traps instead of corrupting it.
+
+#![deny(missing_docs)]
+
+mod host;
+mod integer;
+mod interpreter;
+mod intrinsic;
+mod layout;
+mod memory;
+mod trap;
+mod value;
+
+pub use integer::Integer;
+pub use interpreter::{HostFunction, Interpreter};
+pub use trap::Trap;
+pub use value::RuntimeValue;
+
+#[cfg(test)]
+mod tests {
+    use crate::{Interpreter, RuntimeValue, Trap};
+    use lotl_llvm_api::instruction::{
+        AtomicOrdering, AtomicRmwOperator, MemoryOptions, SyncScope, VectorReduction,
+    };
+    use lotl_llvm_api::module::{GlobalFunction, GlobalVariable, Module};
+    use lotl_llvm_api::types::Types;
+    use lotl_llvm_api::value::{Value, Values};
+
+    /// Builds an `i1` that is true while the value is below the limit, for small values.
+    fn below(
+        block: &lotl_llvm_api::instruction::SharedBasicBlock,
+        value: Value,
+        limit: &str,
+    ) -> Value {
+        let difference = block.sub(value, Values::integer(limit, 32));
+        let sign = block.lshr(difference, Values::integer("31", 32));
+        block.trunc(sign, Types::integer(1))
+    }
+
+    #[test]
+    fn execute_loops_and_calls() {
+        let double = GlobalFunction::new("double", Types::integer(32))
+            .with_parameter(Types::integer(32))
+            .build_body(|block, arguments| {
+                let doubled = block.mul(arguments[0].clone(), Values::integer("2", 32));
+                block.ret(doubled);
+            });
+        let reference = double.reference();
+        let main = GlobalFunction::new("main", Types::integer(32)).build_body(|block, _| {
+            let header = block.br_returning();
+            let counter = header.phi(Types::integer(32), vec![(Values::integer("0", 32), &block)]);
+            let sum = header.phi(Types::integer(32), vec![(Values::integer("0", 32), &block)]);
+            let repeat = below(&header, counter.value(), "10");
+            let (body, exit) = header.br_if_returning(repeat);
+            let added = body.add(sum.value(), counter.value());
+            let incremented = body.add(counter.value(), Values::integer("1", 32));
+            counter.add_incoming(incremented, &body);
+            sum.add_incoming(added, &body);
+            body.goto(&header);
+            let result = exit.call(reference, vec![sum.value()]).unwrap();
+            exit.ret(result);
+        });
+        let mut module = Module::new();
+        module.functions.push(double);
+        module.functions.push(main);
+
+        let mut interpreter = Interpreter::new(&module).unwrap();
+        assert_eq!(
+            interpreter.run("main", &[]),
+            Ok(RuntimeValue::integer(90, 32))
+        );
+        assert_eq!(
+            interpreter.run("double", &[RuntimeValue::integer(-4, 32)]),
+            Ok(RuntimeValue::integer(-8, 32))
+        );
+        assert_eq!(
+            interpreter.run("missing", &[]),
+            Err(Trap::UndefinedFunction("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn model_memory() {
+        let pair = Types::structure(vec![Types::integer(8), Types::array(3, Types::integer(16))]);
+        let mut module = Module::new();
+        let base =
+            GlobalVariable::new("base", Types::integer(16)).with_value(Values::integer("100", 16));
+        let reference = base.reference();
+        module.vars.push(base);
+        module
+            .functions
+            .push(
+                GlobalFunction::new("main", Types::integer(16)).build_body(|block, _| {
+                    let pointer = block.alloca(pair.clone());
+                    let field = block.getelementptr(
+                        pair.clone(),
+                        pointer.clone(),
+                        vec![Values::integer("0", 32), Values::integer("0", 32)],
+                    );
+                    block.store(Values::integer("7", 8), field);
+                    for index in 0..3 {
+                        let element = block.getelementptr(
+                            pair.clone(),
+                            pointer.clone(),
+                            vec![
+                                Values::integer("0", 32),
+                                Values::integer("1", 32),
+                                Values::integer(&index.to_string(), 64),
+                            ],
+                        );
+                        let offset = block.load(Types::integer(16), reference.clone());
+                        let value = block.add(offset, Values::integer(&index.to_string(), 16));
+                        block.store(value, element);
+                    }
+                    let loaded = block.load(pair.clone(), pointer);
+                    let small = block.extractvalue(loaded.clone(), 0);
+                    let elements = block.extractvalue(loaded, 1);
+                    let last = block.extractvalue(elements, 2);
+                    let widened = block.zext(small, Types::integer(16));
+                    let result = block.add(widened, last);
+                    block.ret(result);
+                }),
+            );
+
+        let mut interpreter = Interpreter::new(&module).unwrap();
+        assert_eq!(
+            interpreter.run("main", &[]),
+            Ok(RuntimeValue::integer(109, 16))
+        );
+        let address = interpreter.address("base").unwrap();
+        assert_eq!(
+            interpreter.load(address, &Types::integer(16)),
+            Ok(RuntimeValue::integer(100, 16))
+        );
+    }
+
+    #[test]
+    fn trap_invalid_programs() {
+        let array = Types::array(4, Types::integer(32));
+        let escape = GlobalFunction::new("escape", Types::pointer(0)).build_body(|block, _| {
+            let local = block.alloca(Types::integer(32));
+            block.ret(local);
+        });
+        let escaped = escape.reference();
+        let mut module = Module::new();
+        module.functions.push(escape);
+        module.functions.push(
+            GlobalFunction::new("overflow", Types::integer(32)).build_body(|block, _| {
+                let pointer = block.alloca(array.clone());
+                let past = block.getelementptr(
+                    array.clone(),
+                    pointer,
+                    vec![Values::integer("0", 32), Values::integer("4", 32)],
+                );
+                let loaded = block.load(Types::integer(32), past);
+                block.ret(loaded);
+            }),
+        );
+        module.functions.push(
+            GlobalFunction::new("dangling", Types::integer(32)).build_body(|block, _| {
+                let pointer = block.call(escaped, vec![]).unwrap();
+                let loaded = block.load(Types::integer(32), pointer);
+                block.ret(loaded);
+            }),
+        );
+        module.functions.push(
+            GlobalFunction::new("divide", Types::integer(32))
+                .with_parameter(Types::integer(32))
+                .build_body(|block, arguments| {
+                    let quotient = block.sdiv(Values::integer("1", 32), arguments[0].clone());
+                    block.ret(quotient);
+                }),
+        );
+        module.functions.push(
+            GlobalFunction::new("never", Types::void()).build_body(|block, _| block.unreachable()),
+        );
+        let huge = Types::array(u32::MAX, Types::array(u32::MAX, Types::integer(64)));
+        module.functions.push(
+            GlobalFunction::new("huge", Types::void()).build_body(|block, _| {
+                block.alloca(huge.clone());
+                block.ret_void();
+            }),
+        );
+        module
+            .functions
+            .push(
+                GlobalFunction::new("large", Types::void()).build_body(|block, _| {
+                    block.alloca(Types::array(u32::MAX, Types::integer(64)));
+                    block.ret_void();
+                }),
+            );
+
+        let mut interpreter = Interpreter::new(&module).unwrap();
+        assert!(matches!(
+            interpreter.run("overflow", &[]),
+            Err(Trap::OutOfBounds { size: 4, .. })
+        ));
+        assert!(matches!(
+            interpreter.run("dangling", &[]),
+            Err(Trap::DanglingPointer(_))
+        ));
+        assert_eq!(
+            interpreter.run("divide", &[RuntimeValue::integer(0, 32)]),
+            Err(Trap::DivisionByZero)
+        );
+        assert_eq!(interpreter.run("never", &[]), Err(Trap::Unreachable));
+        assert_eq!(
+            interpreter.run("divide", &[]),
+            Err(Trap::ArgumentCount {
+                function: "divide".to_string(),
+                expected: 1,
+                found: 0
+            })
+        );
+        assert_eq!(
+            interpreter.run("huge", &[]),
+            Err(Trap::OutOfMemory(u64::MAX))
+        );
+        assert_eq!(
+            interpreter.run("large", &[]),
+            Err(Trap::OutOfMemory(8 * u32::MAX as u64))
+        );
+
+        module.vars.push(GlobalVariable::new("everything", huge));
+        assert_eq!(
+            Interpreter::new(&module).err(),
+            Some(Trap::OutOfMemory(u64::MAX))
+        );
+    }
+
+    #[test]
+    fn compute_any_width() {
+        let mut module = Module::new();
+        module
+            .functions
+            .push(
+                GlobalFunction::new("wide", Types::integer(128)).build_body(|block, _| {
+                    let product = block.mul(
+                        Values::integer("18446744073709551616", 128),
+                        Values::integer("-3", 128),
+                    );
+                    let shifted = block.ashr(product, Values::integer("1", 128));
+                    block.ret(shifted);
+                }),
+            );
+        module
+            .functions
+            .push(
+                GlobalFunction::new("narrow", Types::integer(5)).build_body(|block, _| {
+                    let sum = block.add(Values::integer("15", 5), Values::integer("3", 5));
+                    block.ret(sum);
+                }),
+            );
+        module
+            .functions
+            .push(
+                GlobalFunction::new("float", Types::integer(32)).build_body(|block, _| {
+                    let tenth = block.fadd(
+                        Values::float("0.1", Types::fp32()),
+                        Values::float("0.2", Types::fp32()),
+                    );
+                    let widened = block.fpext(tenth, Types::fp64());
+                    let scaled = block.fmul(widened, Values::float("-100000000", Types::fp64()));
+                    let converted = block.fptosi(scaled, Types::integer(32));
+                    block.ret(converted);
+                }),
+            );
+
+        let mut interpreter = Interpreter::new(&module).unwrap();
+        let wide = interpreter.run("wide", &[]).unwrap();
+        assert!(
+            matches!(&wide, RuntimeValue::Integer(x) if x.to_string() == "-27670116110564327424")
+        );
+        assert_eq!(
+            interpreter.run("narrow", &[]),
+            Ok(RuntimeValue::integer(-14, 5))
+        );
+        assert_eq!(
+            interpreter.run("float", &[]),
+            Ok(RuntimeValue::integer(-30000001, 32))
+        );
+    }
+
+    #[test]
+    fn print_with_host_functions() {
+        let mut module = Module::new();
+        let format = module.c_string("%d apples and %-6s|%5.2f%% %x %e\n");
+        let name = module.c_string("pears");
+        let done = module.c_string("done");
+        let printf = GlobalFunction::new("printf", Types::integer(32))
+            .with_parameter(Types::pointer(0))
+            .variadic();
+        let puts =
+            GlobalFunction::new("puts", Types::integer(32)).with_parameter(Types::pointer(0));
+        let (printf_reference, puts_reference) = (printf.reference(), puts.reference());
+        module.functions.push(printf);
+        module.functions.push(puts);
+        module
+            .functions
+            .push(
+                GlobalFunction::new("main", Types::integer(32)).build_body(|block, _| {
+                    let printed = block.call(
+                        printf_reference,
+                        vec![
+                            format,
+                            Values::integer("3", 32),
+                            name,
+                            Values::float("12.345", Types::fp64()),
+                            Values::integer("255", 8),
+                            Values::float("1500", Types::fp64()),
+                        ],
+                    );
+                    block.call(puts_reference, vec![done]);
+                    block.ret(printed.unwrap());
+                }),
+            );
+
+        let mut interpreter = Interpreter::new(&module).unwrap();
+        assert_eq!(
+            interpreter.run("main", &[]),
+            Ok(RuntimeValue::integer(43, 32))
+        );
+        assert_eq!(
+            interpreter.output(),
+            "3 apples and pears |12.35% ff 1.500000e+03\ndone\n"
+        );
+    }
+
+    #[test]
+    fn execute_atomics() {
+        let mut module = Module::new();
+        module
+            .functions
+            .push(
+                GlobalFunction::new("main", Types::integer(32)).build_body(|block, _| {
+                    let counter = block.alloca_with_alignment(Types::integer(32), 16);
+                    block.store(Values::integer("10", 32), counter.clone());
+                    let mut sum = Values::integer("0", 32);
+                    for (operator, operand) in [
+                        (AtomicRmwOperator::Sub, "3"),
+                        (AtomicRmwOperator::UnsignedMin, "5"),
+                        (AtomicRmwOperator::UnsignedIncrementWrap, "5"),
+                        (AtomicRmwOperator::UnsignedDecrementWrap, "9"),
+                    ] {
+                        let old = block.atomicrmw(
+                            operator,
+                            counter.clone(),
+                            Values::integer(operand, 32),
+                            AtomicOrdering::SequentiallyConsistent,
+                            MemoryOptions::new(),
+                        );
+                        sum = block.add(sum, old);
+                    }
+                    let exchanged = block.cmpxchg(
+                        counter.clone(),
+                        Values::integer("9", 32),
+                        Values::integer("100", 32),
+                        AtomicOrdering::AcquireRelease,
+                        AtomicOrdering::Acquire,
+                        MemoryOptions::new(),
+                    );
+                    let failed = block.cmpxchg(
+                        counter.clone(),
+                        Values::integer("9", 32),
+                        Values::integer("1", 32),
+                        AtomicOrdering::AcquireRelease,
+                        AtomicOrdering::Acquire,
+                        MemoryOptions::new(),
+                    );
+                    block.fence(AtomicOrdering::SequentiallyConsistent, SyncScope::System);
+                    let swapped = block.extractvalue(exchanged, 1);
+                    let kept = block.extractvalue(failed, 1);
+                    let valid = block.xor(swapped, kept);
+                    let loaded = block.load_atomic(
+                        Types::integer(32),
+                        counter,
+                        AtomicOrdering::Acquire,
+                        MemoryOptions::new().with_alignment(4),
+                    );
+                    let sum = block.add(sum, loaded);
+                    block.ret(block.select(valid, sum, Values::integer("0", 32)));
+                }),
+            );
+
+        let mut interpreter = Interpreter::new(&module).unwrap();
+        assert_eq!(
+            interpreter.run("main", &[]),
+            Ok(RuntimeValue::integer(122, 32))
+        );
+    }
+
+    #[test]
+    fn execute_switches() {
+        let mut module = Module::new();
+        module.functions.push(
+            GlobalFunction::new("classify", Types::integer(32))
+                .with_parameter(Types::integer(8))
+                .build_body(|block, arguments| {
+                    let (default, cases) = block.switch_returning(
+                        arguments[0].clone(),
+                        vec![Values::integer("1", 8), Values::integer("-1", 8)],
+                    );
+                    default.ret(Values::integer("0", 32));
+                    cases[0].ret(Values::integer("10", 32));
+                    let (first, second) = (cases[1].child(), cases[1].child());
+                    let target = Values::block_address("classify", &second);
+                    cases[1].indirectbr(target, vec![&first, &second]);
+                    first.ret(Values::integer("20", 32));
+                    second.ret(Values::integer("30", 32));
+                }),
+        );
+
+        let mut interpreter = Interpreter::new(&module).unwrap();
+        for (argument, result) in [(1, 10), (255, 30), (7, 0)] {
+            assert_eq!(
+                interpreter.run("classify", &[RuntimeValue::integer(argument, 8)]),
+                Ok(RuntimeValue::integer(result, 32))
+            );
+        }
+    }
+
+    #[test]
+    fn execute_vectors() {
+        let vector = Types::vector(4, Types::integer(32));
+        let doubles = Types::vector(2, Types::fp64());
+        let mut module = Module::new();
+        module
+            .functions
+            .push(VectorReduction::Add.declaration(&vector));
+        module
+            .functions
+            .push(VectorReduction::SignedMax.declaration(&vector));
+        module
+            .functions
+            .push(VectorReduction::FloatAdd.declaration(&doubles));
+        module
+            .functions
+            .push(
+                GlobalFunction::new("main", Types::integer(32)).build_body(|block, _| {
+                    let elements = (1..=4)
+                        .map(|x| Values::integer(&x.to_string(), 32))
+                        .collect();
+                    let pointer = block.alloca(vector.clone());
+                    block.store(
+                        Values::vector(Types::integer(32), elements),
+                        pointer.clone(),
+                    );
+                    let loaded = block.load(vector.clone(), pointer);
+                    let doubled = block.add(loaded.clone(), loaded.clone());
+                    let shuffled = block.shufflevector(doubled, loaded, vec![7, 0, 5, 2]);
+                    let inserted = block.insertelement(
+                        shuffled,
+                        Values::integer("10", 32),
+                        Values::integer("1", 32),
+                    );
+                    let last = block.extractelement(inserted.clone(), Values::integer("3", 32));
+                    let past_end = block.extractelement(inserted.clone(), Values::integer("4", 32));
+                    let sum = block.vector_reduce(VectorReduction::Add, inserted.clone());
+                    let max = block.vector_reduce(VectorReduction::SignedMax, inserted);
+                    let result = block.add(block.add(sum, last), block.add(max, past_end));
+                    block.ret(result);
+                }),
+            );
+        module
+            .functions
+            .push(
+                GlobalFunction::new("floats", Types::fp64()).build_body(|block, _| {
+                    let splat = block.splat(doubles.clone(), Values::float("1.5", Types::fp64()));
+                    let negated = block.fneg(splat);
+                    block.ret(block.vector_reduce(VectorReduction::FloatAdd, negated));
+                }),
+            );
+
+        // the elements are [4, 10, 2, 6]
+        let mut interpreter = Interpreter::new(&module).unwrap();
+        assert_eq!(
+            interpreter.run("main", &[]),
+            Ok(RuntimeValue::integer(38, 32))
+        );
+        assert_eq!(
+            interpreter.run("floats", &[]),
+            Ok(RuntimeValue::float(-3.0, Types::fp64()))
+        );
+    }
+
+    #[test]
+    fn execute_constant_expressions() {
+        let table_type = Types::array(4, Types::integer(32));
+        let elements = ["10", "20", "30", "40"].map(|x| Values::integer(x, 32));
+        let table = GlobalVariable::new("table", table_type.clone())
+            .with_value(Values::array(Types::integer(32), elements.to_vec()));
+        let third = Values::getelementptr(
+            table_type,
+            table.reference(),
+            vec![Values::integer("0", 64), Values::integer("2", 64)],
+        );
+        let slot = GlobalVariable::new("third", Types::pointer(0)).with_value(third.clone());
+        let nothing = GlobalVariable::new("nothing", Types::pointer(0))
+            .with_value(Values::null(Types::pointer(0)));
+        let (slot_reference, nothing_reference) = (slot.reference(), nothing.reference());
+        let start = Values::ptrtoint(table.reference(), Types::integer(64));
+        let mut module = Module::new();
+        module.vars.push(table);
+        module.vars.push(slot);
+        module.vars.push(nothing);
+        module
+            .functions
+            .push(
+                GlobalFunction::new("main", Types::integer(64)).build_body(|block, _| {
+                    let pointer = block.load(Types::pointer(0), slot_reference);
+                    let loaded = block.load(Types::integer(32), pointer);
+                    let widened = block.zext(loaded, Types::integer(64));
+                    let end = Values::ptrtoint(third, Types::integer(64));
+                    let offset = block.sub(end, start);
+                    let null = block.load(Types::pointer(0), nothing_reference);
+                    let zero = block.ptrtoint(null, Types::integer(64));
+                    let truncated = block.fptosi(
+                        Values::float("0x4004000000000000", Types::fp64()),
+                        Types::integer(64),
+                    );
+                    let sum = block.add(widened, offset);
+                    let sum = block.add(sum, zero);
+                    block.ret(block.add(sum, truncated));
+                }),
+            );
+
+        let mut interpreter = Interpreter::new(&module).unwrap();
+        assert_eq!(
+            interpreter.run("main", &[]),
+            Ok(RuntimeValue::integer(40, 64))
+        );
+    }
+
+    #[test]
+    fn execute_exceptions() {
+        /// Doubles its argument, or raises an exception carrying the argument if it is zero.
+        fn raise(
+            _: &mut Interpreter<'_>,
+            arguments: &[RuntimeValue],
+        ) -> Result<RuntimeValue, Trap> {
+            let Some(RuntimeValue::Integer(value)) = arguments.first() else {
+                return Err(Trap::Host("raise expects an integer".to_string()));
+            };
+            match value.to_i64() {
+                0 => Err(Trap::Unwind(RuntimeValue::Aggregate(vec![
+                    RuntimeValue::Pointer(0),
+                    RuntimeValue::integer(1, 32),
+                ]))),
+                value => Ok(RuntimeValue::integer(value * 2, 32)),
+            }
+        }
+
+        let mut module = Module::new();
+        let exception = Types::structure(vec![Types::pointer(0), Types::integer(32)]);
+        let personality =
+            GlobalFunction::new("__gxx_personality_v0", Types::integer(32)).variadic();
+        let raise_function =
+            GlobalFunction::new("raise", Types::integer(32)).with_parameter(Types::integer(32));
+        let raise_reference = raise_function.reference();
+        let inner = GlobalFunction::new("inner", Types::integer(32))
+            .with_parameter(Types::integer(32))
+            .with_personality(personality.reference())
+            .build_body(|block, arguments| {
+                let (result, normal, unwind) =
+                    block.invoke_returning(raise_reference, vec![arguments[0].clone()]);
+                normal.ret(result.unwrap());
+                let caught = unwind.landingpad(exception.clone(), true, vec![]);
+                unwind.resume(caught);
+            });
+        let inner_reference = inner.reference();
+        let main = GlobalFunction::new("main", Types::integer(32))
+            .with_parameter(Types::integer(32))
+            .with_personality(personality.reference())
+            .build_body(|block, arguments| {
+                let (result, normal, unwind) =
+                    block.invoke_returning(inner_reference, vec![arguments[0].clone()]);
+                normal.ret(result.unwrap());
+                let caught = unwind.landingpad(exception.clone(), true, vec![]);
+                let code = unwind.extractvalue(caught, 1);
+                unwind.ret(unwind.add(code, Values::integer("100", 32)));
+            });
+        module.functions.push(personality);
+        module.functions.push(raise_function);
+        module.functions.push(inner);
+        module.functions.push(main);
+        assert!(module.verify().is_ok());
+
+        let mut interpreter = Interpreter::new(&module)
+            .unwrap()
+            .with_host_function("raise", raise);
+        let argument = |x| [RuntimeValue::integer(x, 32)];
+        assert_eq!(
+            interpreter.run("main", &argument(5)),
+            Ok(RuntimeValue::integer(10, 32))
+        );
+        assert_eq!(
+            interpreter.run("main", &argument(0)),
+            Ok(RuntimeValue::integer(101, 32))
+        );
+        assert_eq!(
+            interpreter.run("inner", &argument(0)),
+            Err(Trap::Unwind(RuntimeValue::Aggregate(vec![
+                RuntimeValue::Pointer(0),
+                RuntimeValue::integer(1, 32),
+            ])))
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use crate::trap::Trap;
+use std::collections::BTreeMap;
+
+/// The first address handed out, so that null and small offsets from it never point anywhere.
+const FIRST_ADDRESS: u64 = 0x1000;
+
+/// The unused bytes left after every allocation, so a pointer one past the end of an allocation
+/// never points into the next one.
+const GAP: u64 = 16;
+
+/// The most bytes all allocations together may hold, so a program allocating more
+/// traps instead of exhausting the memory of the host.
+const LIMIT: u64 = 1 << 32;
+
+/// A flat address space of separate allocations, where every access is checked
+/// to stay inside a single live allocation.
+pub(crate) struct Memory {
+    allocations: BTreeMap<u64, Allocation>,
+    next: u64,
+    used: u64,
+}
+
+struct Allocation {
+    bytes: Vec<u8>,
+    live: bool,
+    read_only: bool,
+}
+
+impl Memory {
+    pub(crate) fn new() -> Memory {
+        Memory {
+            allocations: BTreeMap::new(),
+            next: FIRST_ADDRESS,
+            used: 0,
+        }
+    }
+
+    /// Allocates zeroed memory of the given size and alignment, returning its address.
+    /// Fails if the allocations would hold more than [LIMIT] bytes.
+    pub(crate) fn allocate(&mut self, size: u64, align: u64) -> Result<u64, Trap> {
+        let used = self
+            .used
+            .checked_add(size)
+            .filter(|used| *used <= LIMIT)
+            .ok_or(Trap::OutOfMemory(size))?;
+        let address = self
+            .next
+            .checked_next_multiple_of(align.max(GAP))
+            .filter(|address| address.checked_add(size + GAP).is_some())
+            .ok_or(Trap::OutOfMemory(size))?;
+        self.next = address + size + GAP;
+        self.used = used;
+        self.allocations.insert(
+            address,
+            Allocation {
+                bytes: vec![0; size as usize],
+                live: true,
+                read_only: false,
+            },
+        );
+        Ok(address)
+    }
+
+    /// Reserves an address that holds no bytes, such as the address of a function.
+    pub(crate) fn reserve(&mut self) -> u64 {
+        let address = self.next.next_multiple_of(GAP);
+        self.next = address + GAP;
+        self.allocations.insert(
+            address,
+            Allocation {
+                bytes: Vec::new(),
+                live: true,
+                read_only: false,
+            },
+        );
+        address
+    }
+
+    /// Frees the allocation at the address, so every later access to it traps.
+    pub(crate) fn free(&mut self, address: u64) {
+        if let Some(allocation) = self.allocations.get_mut(&address) {
+            allocation.live = false;
+        }
+    }
+
+    /// Makes the allocation at the address read-only, so every later write to it traps.
+    pub(crate) fn protect(&mut self, address: u64) {
+        if let Some(allocation) = self.allocations.get_mut(&address) {
+            allocation.read_only = true;
+        }
+    }
+
+    /// Reads bytes from memory.
+    pub(crate) fn read(&self, address: u64, size: u64) -> Result<&[u8], Trap> {
+        let (base, allocation) = self.find(address, size)?;
+        let start = (address - base) as usize;
+        Ok(&allocation.bytes[start..start + size as usize])
+    }
+
+    /// Writes bytes into memory.
+    pub(crate) fn write(&mut self, address: u64, bytes: &[u8]) -> Result<(), Trap> {
+        let (base, allocation) = self.find(address, bytes.len() as u64)?;
+        if allocation.read_only {
+            return Err(Trap::ReadOnly(address));
+        }
+        let start = (address - base) as usize;
+        let allocation = self.allocations.get_mut(&base).unwrap();
+        allocation.bytes[start..start + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Finds the live allocation holding every accessed byte.
+    fn find(&self, address: u64, size: u64) -> Result<(u64, &Allocation), Trap> {
+        if address < FIRST_ADDRESS {
+            return Err(Trap::NullPointer);
+        }
+        let out_of_bounds = Trap::OutOfBounds { address, size };
+        let Some((base, allocation)) = self.allocations.range(..=address).next_back() else {
+            return Err(out_of_bounds);
+        };
+        let end = address.checked_add(size).ok_or(out_of_bounds.clone())?;
+        if end > base + allocation.bytes.len() as u64 {
+            return Err(out_of_bounds);
+        }
+        if !allocation.live {
+            return Err(Trap::DanglingPointer(address));
+        }
+        Ok((*base, allocation))
+    }
+}
@@ -0,0 +1,109 @@
+use crate::value::RuntimeValue;
+use std::fmt::{Display, Formatter};
+
+/// Represents a reason execution stopped before the function returned.
+///
+/// Most traps are undefined behavior in LLVM IR, which a compiled program
+/// would not report at all.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Trap {
+    /// Memory was accessed through a null pointer.
+    NullPointer,
+    /// Memory was accessed outside of every allocation.
+    OutOfBounds {
+        /// The address accessed.
+        address: u64,
+        /// The number of bytes accessed.
+        size: u64,
+    },
+    /// Memory was accessed after the function that allocated it returned.
+    DanglingPointer(u64),
+    /// A constant global variable was written to.
+    ReadOnly(u64),
+    /// An `unreachable` instruction was executed.
+    Unreachable,
+    /// An integer was divided by zero.
+    DivisionByZero,
+    /// The most negative integer was divided by minus one, which overflows.
+    DivisionOverflow,
+    /// A function was called that is neither defined in the module nor provided by the host.
+    UndefinedFunction(String),
+    /// A pointer that does not point to a function was called.
+    NotFunction(u64),
+    /// A function was called with fewer arguments than it has parameters.
+    ArgumentCount {
+        /// The name of the function called.
+        function: String,
+        /// The number of parameters of the function.
+        expected: usize,
+        /// The number of arguments given.
+        found: usize,
+    },
+    /// A value was used that was never defined.
+    UndefinedValue(String),
+    /// Control reached the end of a block without a terminator.
+    MissingTerminator(String),
+    /// An `indirectbr` branched to an address that is not one of its destinations.
+    InvalidDestination(u64),
+    /// Calls were nested too deeply.
+    StackOverflow,
+    /// An allocation of the given number of bytes did not fit in the memory of the interpreter.
+    OutOfMemory(u64),
+    /// An exception was raised by `resume` or a host function, and unwound out of
+    /// every function without reaching an `invoke`.
+    Unwind(RuntimeValue),
+    /// A host function was given arguments it does not accept.
+    Host(String),
+    /// The instruction or value is not supported by the interpreter.
+    Unsupported(String),
+}
+
+impl Display for Trap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Trap::NullPointer => write!(f, "dereferenced a null pointer"),
+            Trap::OutOfBounds { address, size } => write!(
+                f,
+                "accessed {} bytes at {:#x}, out of bounds of every allocation",
+                size, address
+            ),
+            Trap::DanglingPointer(address) => {
+                write!(f, "accessed {:#x} after its allocation was freed", address)
+            }
+            Trap::ReadOnly(address) => write!(f, "wrote to constant memory at {:#x}", address),
+            Trap::Unreachable => write!(f, "executed unreachable"),
+            Trap::DivisionByZero => write!(f, "divided by zero"),
+            Trap::DivisionOverflow => write!(f, "divided the most negative integer by -1"),
+            Trap::UndefinedFunction(name) => write!(f, "call to undefined function @{}", name),
+            Trap::NotFunction(address) => {
+                write!(f, "called {:#x}, which is not a function", address)
+            }
+            Trap::ArgumentCount {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "@{} expects {} arguments, found {}",
+                function, expected, found
+            ),
+            Trap::UndefinedValue(name) => write!(f, "use of undefined value {}", name),
+            Trap::MissingTerminator(label) => {
+                write!(f, "reached the end of block {} without a terminator", label)
+            }
+            Trap::InvalidDestination(address) => write!(
+                f,
+                "indirectbr to {:#x}, which is not one of its destinations",
+                address
+            ),
+            Trap::StackOverflow => write!(f, "calls nested too deeply"),
+            Trap::OutOfMemory(size) => write!(f, "ran out of memory allocating {} bytes", size),
+            Trap::Unwind(exception) => write!(f, "uncaught exception {:?}", exception),
+            Trap::Host(message) => write!(f, "{}", message),
+            Trap::Unsupported(what) => write!(f, "unsupported by the interpreter: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for Trap {}
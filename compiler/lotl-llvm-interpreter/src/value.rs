@@ -0,0 +1,284 @@
+use crate::integer::Integer;
+use crate::layout::{field_offsets, size_of, store_size};
+use lotl_llvm_api::types::Type;
+
+/// Represents a value computed while executing a function.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuntimeValue {
+    /// An integer of any width.
+    Integer(Integer),
+    /// A floating point of the given type. Values of `half` and `float` are rounded
+    /// to their precision, while `fp128` is computed with the precision of a `double`.
+    Float(f64, Type),
+    /// An address in memory. Null is zero.
+    Pointer(u64),
+    /// The elements of a structure, array or vector.
+    Aggregate(Vec<RuntimeValue>),
+    /// The result of a function returning `void`.
+    Void,
+}
+
+impl RuntimeValue {
+    /// Creates an integer of the given width from a signed value.
+    pub fn integer(value: i64, width: u32) -> RuntimeValue {
+        RuntimeValue::Integer(Integer::from_i64(value, width))
+    }
+
+    /// Creates a floating point of the given type, rounding it to the precision of the type.
+    pub fn float(value: f64, ty: Type) -> RuntimeValue {
+        let rounded = match ty {
+            Type::Half => half_to_f32(f32_to_half(value as f32)) as f64,
+            Type::Float => value as f32 as f64,
+            _ => value,
+        };
+        RuntimeValue::Float(rounded, ty)
+    }
+
+    /// Creates the value of the type with every bit set to zero, like `zeroinitializer`.
+    pub fn zero(ty: &Type) -> RuntimeValue {
+        match ty.resolved() {
+            Type::Integer { 0: width, .. } => RuntimeValue::Integer(Integer::zero(width)),
+            resolved if resolved.is_floating_point() => RuntimeValue::Float(0.0, resolved),
+            Type::Ptr { .. } | Type::AddressSpacePtr { .. } => RuntimeValue::Pointer(0),
+            Type::Array {
+                0: length,
+                1: element,
+                ..
+            }
+            | Type::Vector {
+                0: length,
+                1: element,
+                ..
+            }
+            | Type::ScalableVector {
+                0: length,
+                1: element,
+                ..
+            } => RuntimeValue::Aggregate(vec![RuntimeValue::zero(&element); length as usize]),
+            Type::Structure { 0: fields, .. } | Type::PackedStructure { 0: fields, .. } => {
+                RuntimeValue::Aggregate(fields.iter().map(RuntimeValue::zero).collect())
+            }
+            _ => RuntimeValue::Void,
+        }
+    }
+
+    /// Writes the value as the type into memory bytes, which span at least its store size.
+    pub(crate) fn encode(&self, ty: &Type, bytes: &mut [u8]) {
+        let ty = ty.resolved();
+        match (self, &ty) {
+            (RuntimeValue::Integer(integer), _) => {
+                let size = store_size(&ty) as usize;
+                bytes[..size].copy_from_slice(&integer.to_bytes(size));
+            }
+            (RuntimeValue::Float(value, _), Type::Half) => {
+                bytes[..2].copy_from_slice(&f32_to_half(*value as f32).to_le_bytes());
+            }
+            (RuntimeValue::Float(value, _), Type::Float) => {
+                bytes[..4].copy_from_slice(&(*value as f32).to_le_bytes());
+            }
+            (RuntimeValue::Float(value, _), Type::Double) => {
+                bytes[..8].copy_from_slice(&value.to_le_bytes());
+            }
+            (RuntimeValue::Float(value, _), Type::FP128) => {
+                bytes[..16].copy_from_slice(&f64_to_quad(*value).to_le_bytes());
+            }
+            (RuntimeValue::Pointer(address), _) => {
+                bytes[..8].copy_from_slice(&address.to_le_bytes());
+            }
+            (
+                RuntimeValue::Aggregate(elements),
+                Type::Array { 1: element, .. }
+                | Type::Vector { 1: element, .. }
+                | Type::ScalableVector { 1: element, .. },
+            ) => {
+                let size = size_of(element) as usize;
+                for (index, value) in elements.iter().enumerate() {
+                    value.encode(element, &mut bytes[index * size..]);
+                }
+            }
+            (
+                RuntimeValue::Aggregate(elements),
+                Type::Structure { 0: fields, .. } | Type::PackedStructure { 0: fields, .. },
+            ) => {
+                for ((value, field), offset) in elements.iter().zip(fields).zip(field_offsets(&ty))
+                {
+                    value.encode(field, &mut bytes[offset as usize..]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Reads a value of the type from memory bytes, which span at least its store size.
+    pub(crate) fn decode(ty: &Type, bytes: &[u8]) -> RuntimeValue {
+        let ty = ty.resolved();
+        match &ty {
+            Type::Integer { 0: width, .. } => {
+                let size = store_size(&ty) as usize;
+                RuntimeValue::Integer(Integer::from_bytes(&bytes[..size], *width))
+            }
+            Type::Half => {
+                let bits = u16::from_le_bytes(bytes[..2].try_into().unwrap());
+                RuntimeValue::Float(half_to_f32(bits) as f64, ty)
+            }
+            Type::Float => {
+                let value = f32::from_le_bytes(bytes[..4].try_into().unwrap());
+                RuntimeValue::Float(value as f64, ty)
+            }
+            Type::Double => {
+                let value = f64::from_le_bytes(bytes[..8].try_into().unwrap());
+                RuntimeValue::Float(value, ty)
+            }
+            Type::FP128 => {
+                let bits = u128::from_le_bytes(bytes[..16].try_into().unwrap());
+                RuntimeValue::Float(quad_to_f64(bits), ty)
+            }
+            Type::Ptr { .. } | Type::AddressSpacePtr { .. } => {
+                RuntimeValue::Pointer(u64::from_le_bytes(bytes[..8].try_into().unwrap()))
+            }
+            Type::Array {
+                0: length,
+                1: element,
+                ..
+            }
+            | Type::Vector {
+                0: length,
+                1: element,
+                ..
+            }
+            | Type::ScalableVector {
+                0: length,
+                1: element,
+                ..
+            } => {
+                let size = size_of(element) as usize;
+                RuntimeValue::Aggregate(
+                    (0..*length as usize)
+                        .map(|index| RuntimeValue::decode(element, &bytes[index * size..]))
+                        .collect(),
+                )
+            }
+            Type::Structure { 0: fields, .. } | Type::PackedStructure { 0: fields, .. } => {
+                RuntimeValue::Aggregate(
+                    fields
+                        .iter()
+                        .zip(field_offsets(&ty))
+                        .map(|(field, offset)| {
+                            RuntimeValue::decode(field, &bytes[offset as usize..])
+                        })
+                        .collect(),
+                )
+            }
+            _ => RuntimeValue::Void,
+        }
+    }
+}
+
+/// Rounds a `float` to the nearest `half`, returning its bits.
+fn f32_to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+    let unbiased = exponent - 127;
+    if unbiased > 15 {
+        return sign | 0x7c00;
+    }
+    // normal halves keep the top 10 bits of the mantissa, subnormal halves shift out more
+    let (full, shift, base) = match unbiased >= -14 {
+        true => (mantissa, 13, ((unbiased + 15) as u32) << 10),
+        false if unbiased < -25 => return sign,
+        false => (mantissa | 0x80_0000, (-1 - unbiased) as u32, 0),
+    };
+    let kept = full >> shift;
+    let rest = full & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    // rounding to nearest even may carry into the exponent, which is still correct
+    let rounded = match rest > halfway || (rest == halfway && kept & 1 == 1) {
+        true => base + kept + 1,
+        false => base + kept,
+    };
+    sign | rounded as u16
+}
+
+/// Converts the bits of a `half` into a `float`, which holds it exactly.
+fn half_to_f32(bits: u16) -> f32 {
+    let sign = match bits & 0x8000 {
+        0 => 1.0,
+        _ => -1.0,
+    };
+    let exponent = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+    match exponent {
+        0 => sign * mantissa as f32 * 2f32.powi(-24),
+        0x1f => f32::from_bits(((bits as u32 & 0x8000) << 16) | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(
+            ((bits as u32 & 0x8000) << 16) | ((exponent + 112) << 23) | (mantissa << 13),
+        ),
+    }
+}
+
+/// Converts a `double` into the bits of an `fp128`, which holds it exactly.
+fn f64_to_quad(value: f64) -> u128 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 63) as u128) << 127;
+    let exponent = ((bits >> 52) & 0x7ff) as i64;
+    let mantissa = (bits & ((1 << 52) - 1)) as u128;
+    match exponent {
+        0 if mantissa == 0 => sign,
+        0x7ff => sign | (0x7fff << 112) | (mantissa << 60),
+        // subnormal doubles are normal in fp128, so the mantissa is shifted up to its leading one
+        0 => {
+            let shift = mantissa.leading_zeros() - (128 - 52) + 1;
+            let normalized = (mantissa << shift) & ((1 << 52) - 1);
+            let exponent = (16383 - 1022 - shift as i64) as u128;
+            sign | (exponent << 112) | (normalized << 60)
+        }
+        _ => sign | (((exponent - 1023 + 16383) as u128) << 112) | (mantissa << 60),
+    }
+}
+
+/// Converts the bits of an `fp128` into a `double`, truncating the extra precision.
+fn quad_to_f64(bits: u128) -> f64 {
+    let sign = ((bits >> 127) as u64) << 63;
+    let exponent = ((bits >> 112) & 0x7fff) as i64;
+    let mantissa = ((bits >> 60) & ((1 << 52) - 1)) as u64;
+    let magnitude = match exponent - 16383 + 1023 {
+        _ if exponent == 0 => 0,
+        _ if exponent == 0x7fff && bits & ((1 << 112) - 1) != 0 => (0xfff << 51) | mantissa,
+        _ if exponent == 0x7fff => 0x7ff << 52,
+        2047.. => 0x7ff << 52,
+        exponent @ 1.. => ((exponent as u64) << 52) | mantissa,
+        // too small for a normal double, so the leading one is shifted into the mantissa
+        exponent => ((1 << 52) | mantissa)
+            .checked_shr((1 - exponent) as u32)
+            .unwrap_or(0),
+    };
+    f64::from_bits(sign | magnitude)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::value::{f32_to_half, f64_to_quad, half_to_f32, quad_to_f64, RuntimeValue};
+    use lotl_llvm_api::types::Types;
+
+    #[test]
+    fn round_floating_points() {
+        assert_eq!(
+            RuntimeValue::float(0.1, Types::fp32()),
+            RuntimeValue::Float(0.1f32 as f64, Types::fp32())
+        );
+        assert_eq!(f32_to_half(1.0), 0x3c00);
+        assert_eq!(f32_to_half(65504.0), 0x7bff);
+        assert_eq!(f32_to_half(1e6), 0x7c00);
+        assert_eq!(half_to_f32(f32_to_half(0.1)), 0.099975586);
+        assert_eq!(half_to_f32(f32_to_half(1e-7)), 1.1920929e-7);
+        for value in [0.0, -2.5, 1e300, 5e-324, f64::INFINITY] {
+            assert_eq!(quad_to_f64(f64_to_quad(value)), value);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use lotl_error::diagnostic::DiagnosticError;
+
+pub struct UninstantiableExpression;
+
+impl DiagnosticError for UninstantiableExpression {
+    fn message(self) -> String {
+        "Only functions referred to by name can be instantiated".to_string()
+    }
+}
+
+pub struct UnknownFunction(pub String);
+
+impl DiagnosticError for UnknownFunction {
+    fn message(self) -> String {
+        format!(
+            "Cannot instantiate {:?}, as it is not a known function",
+            self.0
+        )
+    }
+}
+
+pub struct TypeArgumentCount {
+    pub name: String,
+    pub expected: usize,
+    pub found: usize,
+}
+
+impl DiagnosticError for TypeArgumentCount {
+    fn message(self) -> String {
+        format!(
+            "{:?} expects {} type arguments, but {} were given",
+            self.name, self.expected, self.found
+        )
+    }
+}
+
+pub struct DepthLimitExceeded {
+    pub name: String,
+    pub limit: usize,
+}
+
+impl DiagnosticError for DepthLimitExceeded {
+    fn message(self) -> String {
+        format!(
+            "Instantiating {:?} exceeds the depth limit of {}, it is likely infinitely recursive",
+            self.name, self.limit
+        )
+    }
+}
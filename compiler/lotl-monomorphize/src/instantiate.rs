@@ -0,0 +1,389 @@
+use crate::err::{
+    DepthLimitExceeded, TypeArgumentCount, UninstantiableExpression, UnknownFunction,
+};
+use crate::mangle::mangle;
+use lotl_ast::defs::{AstDefinition, AstDefinitionId, AstDefinitionKind};
+use lotl_ast::expr::{AstExpr, ExprId};
+use lotl_ast::graph::IdGraph;
+use lotl_ast::ids::{PureTag, Tag};
+use lotl_ast::types::AstType;
+use lotl_error::diagnostic::Diagnostic;
+use lotl_error::span::Span;
+use std::collections::{HashMap, HashSet};
+
+pub struct Monomorphizer {
+    pub(crate) definitions: IdGraph<AstDefinition>,
+    pub(crate) exprs: IdGraph<AstExpr>,
+    pub(crate) errors: Vec<Diagnostic>,
+
+    /// The mangled names of every instantiation created so far
+    instances: HashSet<String>,
+    /// The bodies left to scan for instantiations, with their instantiation depth
+    worklist: Vec<(ExprId, usize)>,
+    depth_limit: usize,
+}
+
+impl Monomorphizer {
+    pub fn new(
+        definitions: IdGraph<AstDefinition>,
+        exprs: IdGraph<AstExpr>,
+        depth_limit: usize,
+    ) -> Self {
+        Monomorphizer {
+            definitions,
+            exprs,
+            errors: Vec::new(),
+
+            instances: HashSet::new(),
+            worklist: Vec::new(),
+            depth_limit,
+        }
+    }
+
+    /// Instantiates every generic function reachable from the non-generic functions.
+    pub fn run(&mut self) {
+        let roots = self
+            .definitions
+            .values()
+            .filter_map(|definition| match &definition.kind {
+                AstDefinitionKind::Function {
+                    generics,
+                    statements: Some(statements),
+                    ..
+                } if generics.is_empty() => Some((statements.clone(), 0)),
+                _ => None,
+            });
+        self.worklist.extend(roots);
+        while let Some((body, depth)) = self.worklist.pop() {
+            self.scan(&body, depth);
+        }
+    }
+
+    /// Replaces every instantiation in the expression by a reference to the instantiated function.
+    fn scan(&mut self, id: &ExprId, depth: usize) {
+        let Some(expr) = self.exprs.get(id).cloned() else {
+            return;
+        };
+        for child in children(&expr) {
+            self.scan(&child, depth);
+        }
+        let AstExpr::Instantiation {
+            obj,
+            types,
+            span,
+            id,
+        } = expr
+        else {
+            return;
+        };
+        if let Some(name) = self.instantiate(&obj, &types, &span, depth) {
+            self.exprs.insert(AstExpr::Identifier { name, span, id });
+        }
+    }
+
+    /// Creates the instantiation of the function with the type arguments if it doesn't exist yet,
+    /// returning its mangled name.
+    fn instantiate(
+        &mut self,
+        obj: &ExprId,
+        types: &[AstType],
+        span: &Span,
+        depth: usize,
+    ) -> Option<String> {
+        let Some(path) = self.path(obj) else {
+            self.errors
+                .push(Diagnostic::new(UninstantiableExpression, span.clone()));
+            return None;
+        };
+        let name = path.join("::");
+        let Some(AstDefinition {
+            kind:
+                AstDefinitionKind::Function {
+                    parameters,
+                    generics,
+                    returns,
+                    statements,
+                },
+            annotations,
+            ..
+        }) = self.resolve(&path).cloned()
+        else {
+            self.errors
+                .push(Diagnostic::new(UnknownFunction(name), span.clone()));
+            return None;
+        };
+        if generics.len() != types.len() {
+            self.errors.push(Diagnostic::new(
+                TypeArgumentCount {
+                    name,
+                    expected: generics.len(),
+                    found: types.len(),
+                },
+                span.clone(),
+            ));
+            return None;
+        }
+
+        let mangled = mangle(&name, types);
+        if self.instances.contains(&mangled) {
+            return Some(mangled);
+        }
+        if depth >= self.depth_limit {
+            self.errors.push(Diagnostic::new(
+                DepthLimitExceeded {
+                    name,
+                    limit: self.depth_limit,
+                },
+                span.clone(),
+            ));
+            return None;
+        }
+        self.instances.insert(mangled.clone());
+
+        let substitution: HashMap<String, AstType> =
+            generics.into_iter().zip(types.iter().cloned()).collect();
+        let statements = statements.map(|statements| self.clone_expr(&statements, &substitution));
+        if let Some(statements) = &statements {
+            self.worklist.push((statements.clone(), depth + 1));
+        }
+        self.definitions
+            .register_with(&mangled, |id| AstDefinition {
+                name: mangled.clone(),
+                kind: AstDefinitionKind::Function {
+                    parameters: parameters
+                        .iter()
+                        .map(|(name, ty)| (name.clone(), substitute(ty, &substitution)))
+                        .collect(),
+                    generics: Vec::new(),
+                    returns: substitute(&returns, &substitution),
+                    statements,
+                },
+                annotations,
+                id,
+            });
+        Some(mangled)
+    }
+
+    /// Returns the path of names an expression refers to, such as `["a", "f"]` for `a::f`.
+    fn path(&self, id: &ExprId) -> Option<Vec<String>> {
+        match self.exprs.get(id)? {
+            AstExpr::Identifier { name, .. } => Some(vec![name.clone()]),
+            AstExpr::NamespaceAccess { obj, path, .. } => {
+                let mut segments = self.path(obj)?;
+                segments.push(path.clone());
+                Some(segments)
+            }
+            _ => None,
+        }
+    }
+
+    /// Finds the definition at the path, where every segment but the last names a namespace
+    /// holding the next one.
+    fn resolve(&self, path: &[String]) -> Option<&AstDefinition> {
+        let (first, rest) = path.split_first()?;
+        let mut definition = self
+            .definitions
+            .get(&AstDefinitionId::make_new_from(first))?;
+        for segment in rest {
+            let AstDefinitionKind::Namespace { members } = &definition.kind else {
+                return None;
+            };
+            let id = AstDefinitionId::make_new_from(segment);
+            if !members.contains(&id) {
+                return None;
+            }
+            definition = self.definitions.get(&id)?;
+        }
+        Some(definition)
+    }
+
+    /// Deeply copies an expression under new IDs, substituting the types inside it.
+    fn clone_expr(&mut self, id: &ExprId, substitution: &HashMap<String, AstType>) -> ExprId {
+        let Some(expr) = self.exprs.get(id).cloned() else {
+            return id.clone();
+        };
+        let id = ExprId::make_new();
+        let mut copy = |expr: &ExprId| self.clone_expr(expr, substitution);
+        let cloned = match expr {
+            AstExpr::Block { exprs, .. } => AstExpr::Block {
+                exprs: exprs.iter().map(&mut copy).collect(),
+                id: id.clone(),
+            },
+            AstExpr::If {
+                cond,
+                if_true,
+                otherwise,
+                ..
+            } => AstExpr::If {
+                cond: copy(&cond),
+                if_true: copy(&if_true),
+                otherwise: copy(&otherwise),
+                id: id.clone(),
+            },
+            AstExpr::For {
+                index_var,
+                iterable,
+                body,
+                ..
+            } => AstExpr::For {
+                index_var,
+                iterable: copy(&iterable),
+                body: copy(&body),
+                id: id.clone(),
+            },
+            AstExpr::While { cond, body, .. } => AstExpr::While {
+                cond: copy(&cond),
+                body: copy(&body),
+                id: id.clone(),
+            },
+            AstExpr::Storage {
+                ptr,
+                type_hint,
+                value,
+                ..
+            } => AstExpr::Storage {
+                ptr: copy(&ptr),
+                type_hint: type_hint.map(|ty| substitute(&ty, substitution)),
+                value: copy(&value),
+                id: id.clone(),
+            },
+            AstExpr::Returns { expr, .. } => AstExpr::Returns {
+                expr: copy(&expr),
+                id: id.clone(),
+            },
+            AstExpr::Identifier { name, span, .. } => AstExpr::Identifier {
+                name,
+                span,
+                id: id.clone(),
+            },
+            AstExpr::Numeric { number, span, .. } => AstExpr::Numeric {
+                number,
+                span,
+                id: id.clone(),
+            },
+            AstExpr::BinaryOperation {
+                op,
+                lhs,
+                rhs,
+                op_span,
+                ..
+            } => AstExpr::BinaryOperation {
+                op,
+                lhs: copy(&lhs),
+                rhs: copy(&rhs),
+                op_span,
+                id: id.clone(),
+            },
+            AstExpr::UnaryOperation {
+                op, expr, op_span, ..
+            } => AstExpr::UnaryOperation {
+                op,
+                expr: copy(&expr),
+                op_span,
+                id: id.clone(),
+            },
+            AstExpr::Invocation {
+                obj, parameters, ..
+            } => AstExpr::Invocation {
+                obj: copy(&obj),
+                parameters: parameters.iter().map(&mut copy).collect(),
+                id: id.clone(),
+            },
+            AstExpr::FieldAccess { obj, field, .. } => AstExpr::FieldAccess {
+                obj: copy(&obj),
+                field,
+                id: id.clone(),
+            },
+            AstExpr::NamespaceAccess { obj, path, .. } => AstExpr::NamespaceAccess {
+                obj: copy(&obj),
+                path,
+                id: id.clone(),
+            },
+            AstExpr::Subscript { obj, index, .. } => AstExpr::Subscript {
+                obj: copy(&obj),
+                index: copy(&index),
+                id: id.clone(),
+            },
+            AstExpr::Instantiation {
+                obj, types, span, ..
+            } => AstExpr::Instantiation {
+                obj: copy(&obj),
+                types: types
+                    .iter()
+                    .map(|ty| substitute(ty, substitution))
+                    .collect(),
+                span,
+                id: id.clone(),
+            },
+            AstExpr::InlineAsm {
+                template,
+                constraints,
+                operands,
+                flags,
+                span,
+                ..
+            } => AstExpr::InlineAsm {
+                template,
+                constraints,
+                operands: operands.iter().map(&mut copy).collect(),
+                flags,
+                span,
+                id: id.clone(),
+            },
+        };
+        self.exprs.insert(cloned);
+        id
+    }
+}
+
+/// Returns the expressions directly nested inside the expression.
+fn children(expr: &AstExpr) -> Vec<ExprId> {
+    match expr {
+        AstExpr::Block { exprs, .. } => exprs.clone(),
+        AstExpr::If {
+            cond,
+            if_true,
+            otherwise,
+            ..
+        } => vec![cond.clone(), if_true.clone(), otherwise.clone()],
+        AstExpr::For { iterable, body, .. } => vec![iterable.clone(), body.clone()],
+        AstExpr::While { cond, body, .. } => vec![cond.clone(), body.clone()],
+        AstExpr::Storage { ptr, value, .. } => vec![ptr.clone(), value.clone()],
+        AstExpr::Returns { expr, .. } => vec![expr.clone()],
+        AstExpr::Identifier { .. } | AstExpr::Numeric { .. } => Vec::new(),
+        AstExpr::BinaryOperation { lhs, rhs, .. } => vec![lhs.clone(), rhs.clone()],
+        AstExpr::UnaryOperation { expr, .. } => vec![expr.clone()],
+        AstExpr::Invocation {
+            obj, parameters, ..
+        } => {
+            let mut children = vec![obj.clone()];
+            children.extend(parameters.iter().cloned());
+            children
+        }
+        AstExpr::FieldAccess { obj, .. }
+        | AstExpr::NamespaceAccess { obj, .. }
+        | AstExpr::Instantiation { obj, .. } => vec![obj.clone()],
+        AstExpr::Subscript { obj, index, .. } => vec![obj.clone(), index.clone()],
+        AstExpr::InlineAsm { operands, .. } => operands.clone(),
+    }
+}
+
+/// Replaces the generics of a type by their type arguments.
+///
+/// Types written inside function bodies are parsed without knowing the function's generics,
+/// so unresolved names matching a generic are replaced as well.
+fn substitute(ty: &AstType, substitution: &HashMap<String, AstType>) -> AstType {
+    match ty {
+        AstType::TypeVar(name) | AstType::Unresolved(name) if substitution.contains_key(name) => {
+            substitution[name].clone()
+        }
+        AstType::Applied(name, arguments) => AstType::Applied(
+            name.clone(),
+            arguments
+                .iter()
+                .map(|ty| substitute(ty, substitution))
+                .collect(),
+        ),
+        ty => ty.clone(),
+    }
+}
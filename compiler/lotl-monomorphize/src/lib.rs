@@ -0,0 +1,165 @@
+#![deny(missing_docs)]
+
+//! This crate instantiates Lotl's generic functions.
+//! Every instantiation such as `id::[i32]` reachable from a non-generic function
+//! is replaced by a reference to a copy of the function with its generics substituted,
+//! named by mangling the function's name with its type arguments.
+
+mod err;
+mod instantiate;
+mod mangle;
+
+pub use crate::mangle::mangle;
+
+use crate::instantiate::Monomorphizer;
+use lotl_error::results::Results;
+use lotl_parser::ParseResults;
+
+/// The default number of nested instantiations before reporting infinite recursion.
+pub const DEFAULT_DEPTH_LIMIT: usize = 64;
+
+/// Instantiates the generic functions of parsed definitions, with the default depth limit.
+pub fn monomorphize(results: ParseResults) -> Results<ParseResults> {
+    monomorphize_with_limit(results, DEFAULT_DEPTH_LIMIT)
+}
+
+/// Instantiates the generic functions of parsed definitions.
+/// Instantiations nested deeper than the depth limit, such as a generic function
+/// instantiating itself with an ever-growing type, are reported instead of instantiated.
+pub fn monomorphize_with_limit(results: ParseResults, depth_limit: usize) -> Results<ParseResults> {
+    let mut monomorphizer = Monomorphizer::new(results.definitions, results.exprs, depth_limit);
+    monomorphizer.run();
+    let results = ParseResults {
+        definitions: monomorphizer.definitions,
+        exprs: monomorphizer.exprs,
+    };
+    Results::new(results, monomorphizer.errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mangle, monomorphize, monomorphize_with_limit};
+    use lotl_ast::defs::{AstDefinition, AstDefinitionId, AstDefinitionKind};
+    use lotl_ast::ids::Tag;
+    use lotl_ast::types::AstType;
+    use lotl_error::file::SourceFile;
+    use lotl_lexer::lex;
+    use lotl_parser::{ParseResults, parse};
+
+    fn function<'a>(results: &'a ParseResults, name: &str) -> Option<&'a AstDefinition> {
+        results
+            .definitions
+            .get(&AstDefinitionId::make_new_from(&name.to_string()))
+    }
+
+    #[test]
+    fn mangled_names() {
+        assert_eq!(mangle("id", &[AstType::Int32]), "_L2idIiE");
+        assert_eq!(
+            mangle(
+                "pair",
+                &[
+                    AstType::Applied("Box".to_string(), vec![AstType::Int32]),
+                    AstType::Float64
+                ]
+            ),
+            "_L4pairI3BoxIiEdE"
+        );
+        assert_eq!(mangle("a::f", &[AstType::Int32]), "_L1a1fIiE");
+        assert_ne!(
+            mangle("f", &[AstType::Unresolved("T".to_string())]),
+            mangle("f", &[AstType::TypeVar("T".to_string())])
+        );
+    }
+
+    #[test]
+    fn instantiate_generic_function() {
+        let source = SourceFile::new(
+            "example.lotl",
+            "func id[T](x: T) -> T { return x; } \
+             func main() -> i32 { id::[i32](1); id::[f64](2.0); id::[i32](3); }",
+        );
+        let ast = lex(source).bind(parse).bind(monomorphize);
+        assert_eq!(ast.diagnostics.len(), 0);
+        assert_eq!(ast.output.definitions.values().count(), 4);
+
+        let AstDefinitionKind::Function {
+            parameters,
+            generics,
+            returns,
+            statements,
+        } = &function(&ast.output, "_L2idIiE").unwrap().kind
+        else {
+            panic!("expected a function");
+        };
+        assert_eq!(parameters, &vec![("x".to_string(), AstType::Int32)]);
+        assert!(generics.is_empty());
+        assert_eq!(returns, &AstType::Int32);
+        assert!(statements.is_some());
+        assert!(function(&ast.output, "_L2idIdE").is_some());
+    }
+
+    #[test]
+    fn instantiate_nested_generic_functions() {
+        let source = SourceFile::new(
+            "example.lotl",
+            "func id[T](x: T) -> T { return x; } \
+             func wrap[T](x: T) -> Box[T] { id::[Box[T]](x); wrap::[T](x); } \
+             func main() -> i32 { wrap::[i64](1); }",
+        );
+        let ast = lex(source).bind(parse).bind(monomorphize);
+        assert_eq!(ast.diagnostics.len(), 0);
+
+        let wrap = function(&ast.output, "_L4wrapIlE").unwrap();
+        let AstDefinitionKind::Function { returns, .. } = &wrap.kind else {
+            panic!("expected a function");
+        };
+        let boxed = AstType::Applied("Box".to_string(), vec![AstType::Int64]);
+        assert_eq!(returns, &boxed);
+        assert!(function(&ast.output, "_L2idI3BoxIlEE").is_some());
+        assert_eq!(ast.output.definitions.values().count(), 5);
+    }
+
+    #[test]
+    fn report_infinite_instantiation() {
+        let source = SourceFile::new(
+            "example.lotl",
+            "func grow[T](x: T) -> T { grow::[Box[T]](x); } \
+             func main() -> i32 { grow::[i32](1); }",
+        );
+        let ast = lex(source)
+            .bind(parse)
+            .bind(|results| monomorphize_with_limit(results, 8));
+        assert_eq!(ast.diagnostics.len(), 1);
+        assert!(ast.diagnostics[0].main.0.contains("depth limit of 8"));
+        assert_eq!(ast.output.definitions.values().count(), 2 + 8);
+    }
+
+    #[test]
+    fn report_bad_instantiations() {
+        let source = SourceFile::new(
+            "example.lotl",
+            "func id[T](x: T) -> T { return x; } \
+             func main() -> i32 { id::[i32, i64](1); main::[i32](); missing::[i32](); }",
+        );
+        let ast = lex(source).bind(parse).bind(monomorphize);
+        assert_eq!(ast.diagnostics.len(), 3);
+        assert_eq!(ast.output.definitions.values().count(), 2);
+    }
+
+    #[test]
+    fn instantiate_namespaced_functions() {
+        let source = SourceFile::new(
+            "example.lotl",
+            "namespace a { func g[T](x: T) -> T { return x; } } \
+             func f[T](x: T) -> T { return x; } \
+             func main() -> i32 { a::g::[i32](1); a::f::[i32](1); f::[i32](1); }",
+        );
+        let ast = lex(source).bind(parse).bind(monomorphize);
+        assert_eq!(ast.diagnostics.len(), 1);
+        assert!(ast.diagnostics[0].main.0.contains("\"a::f\""));
+        assert!(function(&ast.output, "_L1a1gIiE").is_some());
+        assert!(function(&ast.output, "_L1fIiE").is_some());
+        assert!(function(&ast.output, "_L1a1fIiE").is_none());
+    }
+}
@@ -0,0 +1,48 @@
+use lotl_ast::types::AstType;
+
+/// Mangles the symbol name of a generic function instantiated with the type arguments.
+///
+/// Names are written with their length before them, and type arguments are listed between
+/// `I` and `E`, so `pair[Box[i32], f64]` becomes `_L4pairI3BoxIiEdE`.
+/// The segments of a namespaced name are written one after the other, so `a::f[i32]`
+/// becomes `_L1a1fIiE`.
+pub fn mangle(name: &str, types: &[AstType]) -> String {
+    let mut string = "_L".to_string();
+    for segment in name.split("::") {
+        append_name(&mut string, segment);
+    }
+    append_arguments(&mut string, types);
+    string
+}
+
+fn append_name(string: &mut String, name: &str) {
+    string.push_str(&name.len().to_string());
+    string.push_str(name);
+}
+
+fn append_arguments(string: &mut String, types: &[AstType]) {
+    string.push('I');
+    for ty in types {
+        append_type(string, ty);
+    }
+    string.push('E');
+}
+
+fn append_type(string: &mut String, ty: &AstType) {
+    match ty {
+        AstType::Int32 => string.push('i'),
+        AstType::Int64 => string.push('l'),
+        AstType::Float32 => string.push('f'),
+        AstType::Float64 => string.push('d'),
+        AstType::Void => string.push('v'),
+        AstType::TypeVar(name) => {
+            string.push('T');
+            append_name(string, name);
+        }
+        AstType::Unresolved(name) => append_name(string, name),
+        AstType::Applied(name, arguments) => {
+            append_name(string, name);
+            append_arguments(string, arguments);
+        }
+    }
+}
@@ -3,6 +3,7 @@ use crate::expect_kind;
 use crate::parser::Parser;
 use lotl_ast::defs::{AstDefinition, AstDefinitionId, AstDefinitionKind};
 use lotl_ast::expr::AstExpr;
+use lotl_ast::types::AstType;
 use lotl_error::diagnostic::Diagnostic;
 use lotl_token::{TokenKind, TokenStream};
 
@@ -94,10 +95,16 @@ impl Parser {
         }
 
         // parse the function's parameters
+        let mut parameters = Vec::new();
         let param_tok = self.peek();
-        if let TokenKind::Parenthesis(_param_toks) = &param_tok.kind {
+        if let TokenKind::Parenthesis(param_toks) = &param_tok.kind {
+            let param_toks = param_toks.clone();
             self.next();
-            // todo: parse parameters
+            parameters = self.parse_delimited_series(
+                param_toks,
+                TokenKind::Comma,
+                |parser: &mut Parser| parser.parse_parameter(&generics),
+            );
         } else {
             let p = TokenKind::Parenthesis(TokenStream::empty());
             self.push_err(Diagnostic::new(
@@ -137,7 +144,7 @@ impl Parser {
             name: name.clone(),
             id,
             kind: AstDefinitionKind::Function {
-                parameters: vec![],
+                parameters,
                 generics,
                 returns: return_ty,
                 statements,
@@ -161,4 +168,20 @@ impl Parser {
         };
         generic_type_name.to_string()
     }
+
+    pub fn parse_parameter(&mut self, generics: &[String]) -> (String, AstType) {
+        let name = self.parse_ident();
+        if let TokenKind::Colon = &self.peek().kind {
+            self.next();
+        } else {
+            self.push_err(Diagnostic::new(
+                ExpectedKindFoundKind {
+                    expected: &[TokenKind::Colon],
+                    found: self.peek().kind.clone(),
+                },
+                self.peek().location.clone(),
+            ));
+        }
+        (name, self.parse_generic_type(generics))
+    }
 }
@@ -30,3 +30,14 @@ impl<'a> DiagnosticError for ExpectedKindFoundKind<'a> {
         )
     }
 }
+
+pub struct UnknownAsmFlag(pub String);
+
+impl DiagnosticError for UnknownAsmFlag {
+    fn message(self) -> String {
+        format!(
+            "Unknown assembly flag {:?}, expected sideeffect, alignstack, or inteldialect",
+            self.0
+        )
+    }
+}
@@ -1,8 +1,8 @@
-use crate::errors::ExpectedKindFoundKind;
+use crate::errors::{ExpectedKindFoundKind, UnknownAsmFlag};
 use crate::parser::Parser;
-use lotl_ast::expr::{AstExpr, BinaryOperationKind, ExprId};
+use lotl_ast::expr::{AsmFlag, AstExpr, BinaryOperationKind, ExprId};
 use lotl_error::diagnostic::Diagnostic;
-use lotl_token::TokenKind;
+use lotl_token::{TokenKind, TokenStream};
 
 impl Parser {
     pub fn parse_expr(&mut self) -> ExprId {
@@ -194,6 +194,24 @@ impl Parser {
                     ));
                 }
 
+                // `::[T, ...]` instantiates a generic function
+                let type_tok = self.peek().clone();
+                if let TokenKind::Brackets(stream) = type_tok.kind {
+                    self.next();
+                    let types = self.parse_delimited_series(
+                        stream,
+                        TokenKind::Comma,
+                        Parser::parse_type,
+                    );
+                    obj = self.exprs.register(|id| AstExpr::Instantiation {
+                        obj,
+                        types,
+                        span: type_tok.location,
+                        id,
+                    });
+                    continue;
+                }
+
                 let ident = self.parse_ident();
                 obj = self.exprs.register(|id| AstExpr::NamespaceAccess {
                     obj,
@@ -237,6 +255,7 @@ impl Parser {
                 self.exprs
                     .register(|id| AstExpr::Numeric { number, span, id })
             }
+            TokenKind::Ident(name) if name == "asm" => self.parse_asm(),
             TokenKind::Ident(name) => {
                 let span = self.next().location.clone();
                 let name = name.clone();
@@ -267,4 +286,121 @@ impl Parser {
             }
         }
     }
+
+    pub fn parse_asm(&mut self) -> ExprId {
+        let span = self.next().location.clone();
+        let mut flags = Vec::new();
+        if let TokenKind::Brackets(stream) = &self.peek().kind {
+            let stream = stream.clone();
+            self.next();
+            flags = self
+                .parse_delimited_series(stream, TokenKind::Comma, Parser::parse_asm_flag)
+                .into_iter()
+                .flatten()
+                .collect();
+        }
+        let (template, constraints, operands) = match &self.peek().kind {
+            TokenKind::Parenthesis(stream) => {
+                let stream = stream.clone();
+                self.next();
+                self.parse_nested_stream(stream, Parser::parse_asm_arguments)
+            }
+            found => {
+                self.push_err(Diagnostic::new(
+                    ExpectedKindFoundKind {
+                        expected: &[TokenKind::Parenthesis(TokenStream::empty())],
+                        found: found.clone(),
+                    },
+                    self.peek().location.clone(),
+                ));
+                (String::new(), String::new(), Vec::new())
+            }
+        };
+        self.exprs.register(|id| AstExpr::InlineAsm {
+            template,
+            constraints,
+            operands,
+            flags,
+            span,
+            id,
+        })
+    }
+
+    pub fn parse_asm_flag(&mut self) -> Option<AsmFlag> {
+        let token = self.next();
+        match &token.kind {
+            TokenKind::Ident(name) if name == "sideeffect" => Some(AsmFlag::SideEffect),
+            TokenKind::Ident(name) if name == "alignstack" => Some(AsmFlag::AlignStack),
+            TokenKind::Ident(name) if name == "inteldialect" => Some(AsmFlag::IntelDialect),
+            TokenKind::Ident(name) => {
+                self.push_err(Diagnostic::new(
+                    UnknownAsmFlag(name.clone()),
+                    token.location.clone(),
+                ));
+                None
+            }
+            found => {
+                self.push_err(Diagnostic::new(
+                    ExpectedKindFoundKind {
+                        expected: &[TokenKind::Ident("".to_string())],
+                        found: found.clone(),
+                    },
+                    token.location.clone(),
+                ));
+                None
+            }
+        }
+    }
+
+    /// Parses the template and constraints of inline assembly, followed by its operands.
+    pub fn parse_asm_arguments(&mut self) -> (String, String, Vec<ExprId>) {
+        let template = self.parse_string();
+        if self.peek().kind != TokenKind::Comma {
+            self.push_err(Diagnostic::new(
+                ExpectedKindFoundKind {
+                    expected: &[TokenKind::Comma],
+                    found: self.peek().kind.clone(),
+                },
+                self.peek().location.clone(),
+            ));
+            return (template, String::new(), Vec::new());
+        }
+        self.next();
+        let constraints = self.parse_string();
+        let mut operands = Vec::new();
+        while self.peek().kind == TokenKind::Comma {
+            self.next();
+            operands.push(self.parse_expr());
+        }
+        if self.peek().kind != TokenKind::EndOfStream {
+            self.push_err(Diagnostic::new(
+                ExpectedKindFoundKind {
+                    expected: &[TokenKind::Comma],
+                    found: self.peek().kind.clone(),
+                },
+                self.peek().location.clone(),
+            ));
+        }
+        (template, constraints, operands)
+    }
+
+    pub fn parse_string(&mut self) -> String {
+        let token = self.peek();
+        match &token.kind {
+            TokenKind::StringLiteral(contents) => {
+                self.next();
+                contents.clone()
+            }
+            found => {
+                self.push_err(Diagnostic::new(
+                    ExpectedKindFoundKind {
+                        expected: &[TokenKind::StringLiteral("".to_string())],
+                        found: found.clone(),
+                    },
+                    token.location.clone(),
+                ));
+                "".to_string()
+            }
+        }
+    }
 }
@@ -73,6 +73,16 @@ mod tests {
         assert_eq!(ast.diagnostics.len(), 0);
     }
 
+    #[test]
+    fn generic_parameters_function() {
+        let source = SourceFile::new(
+            "example.lotl",
+            "func pair[T, U](x: T, y: Box[U]) -> T { pair::[i32, Box[f64]](1, y); } ",
+        );
+        let ast = lex(source).bind(parse);
+        assert_eq!(ast.diagnostics.len(), 0);
+    }
+
     #[test]
     fn bad_typeless_function() {
         let source = SourceFile::new("example.lotl", "func main() -> { }");
@@ -165,4 +175,24 @@ mod tests {
         let ast = lex(source).bind(parse);
         assert_eq!(ast.diagnostics.len(), 0);
     }
+
+    #[test]
+    fn asm_function() {
+        let source = SourceFile::new(
+            "example.lotl",
+            "func main() -> i32 { asm[sideeffect](\"syscall\", \"={rax},{rax},{rdi}\", 60, code); }",
+        );
+        let ast = lex(source).bind(parse);
+        assert_eq!(ast.diagnostics.len(), 0);
+    }
+
+    #[test]
+    fn bad_asm_function() {
+        let source = SourceFile::new(
+            "example.lotl",
+            "func main() -> i32 { asm[volatile](\"nop\"); }",
+        );
+        let ast = lex(source).bind(parse);
+        assert_eq!(ast.diagnostics.len(), 2);
+    }
 }
@@ -5,7 +5,6 @@ use lotl_error::diagnostic::Diagnostic;
 use lotl_token::{TokenKind, TokenStream};
 
 impl Parser {
-    #[allow(unused)]
     pub fn parse_type(&mut self) -> AstType {
         self.parse_generic_type(&[])
     }
@@ -22,16 +21,27 @@ impl Parser {
             ));
             return AstType::Void;
         };
+        let type_name = type_name.clone();
         self.next();
-        if generics.contains(type_name) {
-            return AstType::TypeVar(type_name.to_string());
+        if generics.contains(&type_name) {
+            return AstType::TypeVar(type_name);
+        }
+        if let TokenKind::Brackets(arguments) = &self.peek().kind {
+            let arguments = arguments.clone();
+            self.next();
+            let arguments = self.parse_delimited_series(
+                arguments,
+                TokenKind::Comma,
+                |parser: &mut Parser| parser.parse_generic_type(generics),
+            );
+            return AstType::Applied(type_name, arguments);
         }
         match type_name.as_ref() {
             "i32" => AstType::Int32,
             "i64" => AstType::Int64,
             "f32" => AstType::Float32,
             "f64" => AstType::Float64,
-            _ => AstType::Unresolved(type_name.to_string()),
+            _ => AstType::Unresolved(type_name),
         }
     }
 
@@ -44,6 +54,21 @@ impl Parser {
         output
     }
 
+    pub fn parse_nested_stream<T, F: Fn(&mut Self) -> T>(
+        &mut self,
+        stream: TokenStream,
+        func: F,
+    ) -> T {
+        let mut parser = Parser::new(stream);
+        let output = func(&mut parser);
+        for err in parser.get_errs() {
+            self.push_err(err);
+        }
+        self.definitions.extend(parser.definitions.into_values());
+        self.exprs.extend(parser.exprs.into_values());
+        output
+    }
+
     pub fn parse_delimited_series<T, F: Fn(&mut Self) -> T>(
         &mut self,
         stream: TokenStream,